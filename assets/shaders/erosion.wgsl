@@ -0,0 +1,62 @@
+// One step of thermal erosion on a density volume: loose material slides
+// down into the cell below, and down slopes steeper than the talus angle.
+// Flows are computed from both ends by the same rule, so material is moved,
+// never created or lost.
+
+struct Params {
+    rate: f32,
+    talus: f32,
+    iso: f32,
+}
+
+@group(0) @binding(0) var src: texture_storage_3d<r32float, read>;
+@group(0) @binding(1) var dst: texture_storage_3d<r32float, write>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+fn inside(p: vec3<i32>) -> bool {
+    return all(p >= vec3(0)) && all(p < vec3<i32>(textureDimensions(src)));
+}
+
+// How full a cell is, 0 to 1. Cells outside count as full, so nothing
+// flows out of the volume.
+fn solidity(p: vec3<i32>) -> f32 {
+    if !inside(p) {
+        return 1.0;
+    }
+    return clamp(params.iso - textureLoad(src, p).x + 0.5, 0.0, 1.0);
+}
+
+fn flow(a: vec3<i32>, b: vec3<i32>, slope: bool) -> f32 {
+    let sa = solidity(a);
+    let sb = solidity(b);
+    if slope {
+        return params.rate * 0.25 * max(0.0, sa - sb - params.talus);
+    }
+    return params.rate * min(sa, 1.0 - sb);
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn erode(@builtin(global_invocation_id) id: vec3<u32>) {
+    let p = vec3<i32>(id);
+    if !inside(p) {
+        return;
+    }
+    var slopes = array<vec3<i32>, 5>(
+        vec3(0, -1, 0),
+        vec3(1, -1, 0),
+        vec3(-1, -1, 0),
+        vec3(0, -1, 1),
+        vec3(0, -1, -1),
+    );
+    var gained = 0.0;
+    for (var i = 0; i < 5; i++) {
+        let d = slopes[i];
+        gained -= flow(p, p + d, i > 0);
+        if inside(p - d) {
+            gained += flow(p - d, p, i > 0);
+        }
+    }
+    // More solid is a lower density.
+    let v = textureLoad(src, p).x;
+    textureStore(dst, p, vec4(v - gained, 0.0, 0.0, 0.0));
+}
@@ -0,0 +1,46 @@
+// Moves terrain vertices along their normals by a few layered waves, scaled
+// by the vertex color's alpha (the voxel material's softness).
+
+#import bevy_pbr::{
+    forward_io::{Vertex, VertexOutput},
+    mesh_functions,
+    mesh_view_bindings::globals,
+    view_transformations::position_world_to_clip,
+}
+
+struct Sway {
+    amplitude: f32,
+    frequency: f32,
+    speed: f32,
+}
+
+@group(2) @binding(100) var<uniform> sway: Sway;
+
+fn waves(p: vec3<f32>, t: f32) -> f32 {
+    return sin(p.x + t) * cos(p.z * 1.3 - t * 0.7) + 0.5 * sin((p.x + p.y + p.z) * 2.1 + t * 1.9);
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    out.world_position = mesh_functions::mesh_position_local_to_world(world_from_local, vec4(vertex.position, 1.0));
+#ifdef VERTEX_NORMALS
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(vertex.normal, vertex.instance_index);
+#ifdef VERTEX_COLORS
+    let offset = waves(out.world_position.xyz * sway.frequency, globals.time * sway.speed);
+    out.world_position += vec4(out.world_normal * offset * sway.amplitude * vertex.color.a, 0.0);
+#endif
+#endif
+    out.position = position_world_to_clip(out.world_position.xyz);
+#ifdef VERTEX_UVS_A
+    out.uv = vertex.uv;
+#endif
+#ifdef VERTEX_COLORS
+    out.color = vertex.color;
+#endif
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+    return out;
+}
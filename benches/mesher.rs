@@ -0,0 +1,112 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use bevy::math::Vec3;
+use marchy_bevy::{create_mesh, mesh::build_buffers, terrain::Perlin, BrickMap, MeshingStrategy, VoxelGrid};
+
+/// Solid at or below this in the synthetic grids.
+const ISO: f32 = 5.0;
+const SIZES: [u32; 4] = [16, 32, 64, 128];
+const STRATEGIES: [MeshingStrategy; 4] = [
+    MeshingStrategy::Naive,
+    MeshingStrategy::Culled,
+    MeshingStrategy::Greedy,
+    MeshingStrategy::DualContour,
+];
+
+fn sphere(size: u32) -> VoxelGrid {
+    let mut vox = VoxelGrid::new(size);
+    let hsize = size as f32 / 2.0;
+    vox.map(|x, y, z, _val| {
+        let xo = x as f32 - hsize;
+        let yo = y as f32 - hsize;
+        let zo = z as f32 - hsize;
+        (xo * xo + yo * yo + zo * zo).sqrt()
+    });
+    vox
+}
+
+fn mesher(c: &mut Criterion) {
+    let vox = sphere(64);
+    let limit = 28.0;
+
+    c.bench_function("create_mesh sphere 64", |b| {
+        b.iter(|| create_mesh(&vox, limit))
+    });
+}
+
+/// Shared against unshared vertices on the 64³ sphere for each strategy:
+/// without the index buffer every index would be its own vertex.
+fn vertex_counts(c: &mut Criterion) {
+    let vox = sphere(64);
+    let limit = 28.0;
+    let mut group = c.benchmark_group("vertices 64");
+    group.sample_size(10);
+    for strategy in STRATEGIES {
+        let buffers = build_buffers(&vox, limit, strategy, |_| None);
+        println!(
+            "64^3 sphere, {strategy:?}: {} vertices shared, {} unshared",
+            buffers.positions.len(),
+            buffers.indices.len()
+        );
+        let id = BenchmarkId::new(format!("{strategy:?}").to_lowercase(), 64);
+        group.bench_with_input(id, &vox, |b, vox| {
+            b.iter(|| build_buffers(vox, limit, strategy, |_| None).positions.len())
+        });
+    }
+    group.finish();
+}
+
+/// A small ball in a big, mostly empty volume, where sparse storage only
+/// visits the few bricks around the ball.
+fn sparse(c: &mut Criterion) {
+    let limit = 10.0;
+    let mut vox = sphere(128);
+    vox.map(|_, _, _, v| v.min(limit + 1.0));
+    let bricks = BrickMap::from_grid(&vox, limit + 1.0);
+    println!("128^3 sparse ball: {} bricks allocated", bricks.brick_count());
+
+    c.bench_function("create_mesh dense 128", |b| {
+        b.iter(|| create_mesh(&vox, limit))
+    });
+    c.bench_function("create_mesh bricks 128", |b| {
+        b.iter(|| create_mesh(&bricks, limit))
+    });
+}
+
+/// Representative grids, and the worst case for face culling and merging.
+fn synthetic(shape: &str, size: u32) -> VoxelGrid {
+    let mut vox = VoxelGrid::new(size);
+    let half = size as f32 / 2.0;
+    let noise = Perlin::new(7);
+    vox.map(|x, y, z, _| {
+        let p = Vec3::new(x as f32, y as f32, z as f32);
+        match shape {
+            "solid" => 0.0,
+            "sphere" => ISO + (p - half).length() - half * 0.8,
+            // Rolling hills with overhangs, about half full.
+            "noise" => ISO + (y as f32 - half) * 0.2 + noise.sample(p * 0.08) * 4.0,
+            _ => if (x + y + z) % 2 == 0 { 0.0 } else { 10.0 },
+        }
+    });
+    vox
+}
+
+/// Every strategy over every shape at 16³ to 128³.
+fn strategies(c: &mut Criterion) {
+    for shape in ["solid", "sphere", "noise", "checkerboard"] {
+        let mut group = c.benchmark_group(shape);
+        group.sample_size(10);
+        for size in SIZES {
+            let vox = synthetic(shape, size);
+            for strategy in STRATEGIES {
+                let id = BenchmarkId::new(format!("{strategy:?}").to_lowercase(), size);
+                group.bench_with_input(id, &vox, |b, vox| {
+                    b.iter(|| build_buffers(vox, ISO, strategy, |_| None))
+                });
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, mesher, vertex_counts, sparse, strategies);
+criterion_main!(benches);
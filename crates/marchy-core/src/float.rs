@@ -0,0 +1,32 @@
+//! The `f32` math `std` provides, from `libm` for `no_std` builds.
+
+pub(crate) trait Float {
+    fn abs(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn rem_euclid(self, rhs: Self) -> Self;
+}
+
+impl Float for f32 {
+    fn abs(self) -> f32 {
+        libm::fabsf(self)
+    }
+
+    fn sqrt(self) -> f32 {
+        libm::sqrtf(self)
+    }
+
+    fn sin(self) -> f32 {
+        libm::sinf(self)
+    }
+
+    fn cos(self) -> f32 {
+        libm::cosf(self)
+    }
+
+    fn rem_euclid(self, rhs: f32) -> f32 {
+        let r = libm::fmodf(self, rhs);
+        if r < 0.0 { r + libm::fabsf(rhs) } else { r }
+    }
+}
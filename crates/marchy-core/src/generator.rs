@@ -0,0 +1,354 @@
+use alloc::{sync::Arc, vec::Vec};
+use core::fmt;
+use glam::{IVec3, UVec2, UVec3, Vec2, Vec3};
+use crate::{grid::VoxelGrid, sdf::Sdf};
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+
+/// Small deterministic RNG so a seed always produces the same world,
+/// independent of `rand`'s algorithm choices.
+pub fn splitmix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Improved Perlin gradient noise with a seeded permutation table.
+#[derive(Clone)]
+pub struct Perlin {
+    perm: [u8; 512],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut p: [u8; 256] = core::array::from_fn(|i| i as u8);
+        let mut state = seed;
+        for i in (1..256).rev() {
+            let j = (splitmix(&mut state) % (i as u64 + 1)) as usize;
+            p.swap(i, j);
+        }
+        Perlin { perm: core::array::from_fn(|i| p[i & 255]) }
+    }
+
+    /// Noise in roughly `-1..1`.
+    pub fn sample(&self, p: Vec3) -> f32 {
+        self.sample_periodic(p, UVec3::splat(256))
+    }
+
+    /// Noise that repeats every `period` lattice cells on each axis (at most
+    /// 256, the size of the permutation table).
+    pub fn sample_periodic(&self, p: Vec3, period: UVec3) -> f32 {
+        let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let grad = |hash: u8, x: f32, y: f32, z: f32| {
+            let h = hash & 15;
+            let u = if h < 8 { x } else { y };
+            let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+            (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+        };
+
+        let cell = p.floor();
+        let f = p - cell;
+        let period = period.clamp(UVec3::ONE, UVec3::splat(256)).as_ivec3();
+        let lo = cell.as_ivec3().rem_euclid(period);
+        let hi = (lo + 1).rem_euclid(period);
+        let (u, v, w) = (fade(f.x), fade(f.y), fade(f.z));
+
+        let perm = &self.perm;
+        let hash = |x: i32, y: i32, z: i32| {
+            perm[perm[perm[x as usize] as usize + y as usize] as usize + z as usize]
+        };
+        let (x0, y0, z0) = (lo.x, lo.y, lo.z);
+        let (x1, y1, z1) = (hi.x, hi.y, hi.z);
+
+        let (x, y, z) = (f.x, f.y, f.z);
+        let lerp = |t: f32, a: f32, b: f32| a + t * (b - a);
+        lerp(w,
+            lerp(v,
+                lerp(u, grad(hash(x0, y0, z0), x, y, z), grad(hash(x1, y0, z0), x - 1.0, y, z)),
+                lerp(u, grad(hash(x0, y1, z0), x, y - 1.0, z), grad(hash(x1, y1, z0), x - 1.0, y - 1.0, z))),
+            lerp(v,
+                lerp(u, grad(hash(x0, y0, z1), x, y, z - 1.0), grad(hash(x1, y0, z1), x - 1.0, y, z - 1.0)),
+                lerp(u, grad(hash(x0, y1, z1), x, y - 1.0, z - 1.0), grad(hash(x1, y1, z1), x - 1.0, y - 1.0, z - 1.0))))
+    }
+}
+
+/// Fractal Brownian motion: octaves of noise, each `lacunarity` times the
+/// frequency and `gain` times the amplitude of the last.
+#[derive(Clone, Debug)]
+pub struct Fbm {
+    pub frequency: f32,
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub gain: f32,
+}
+
+impl Default for Fbm {
+    fn default() -> Self {
+        Fbm {
+            frequency: 0.08,
+            octaves: 4,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+impl Fbm {
+    pub fn sample(&self, noise: &Perlin, p: Vec3) -> f32 {
+        self.sample_tiled(noise, p, None)
+    }
+
+    /// Like `sample`, but with `tile` set the result repeats every `tile.x`
+    /// units in X and `tile.y` in Z: the noise is wrapped around a torus.
+    /// Each octave's frequency is nudged so a whole number of lattice cells
+    /// fits in the tile.
+    pub fn sample_tiled(&self, noise: &Perlin, p: Vec3, tile: Option<UVec2>) -> f32 {
+        let mut freq = self.frequency;
+        let mut amp = 1.0;
+        let mut sum = 0.0;
+        let mut norm = 0.0;
+        for _ in 0..self.octaves {
+            sum += amp * match tile {
+                Some(tile) => {
+                    let tile = tile.as_vec2();
+                    let cells = (tile * freq).round().clamp(Vec2::ONE, Vec2::splat(256.0));
+                    let scale = Vec3::new(cells.x / tile.x, freq, cells.y / tile.y);
+                    let period = UVec3::new(cells.x as u32, 256, cells.y as u32);
+                    noise.sample_periodic(p * scale, period)
+                }
+                None => noise.sample(p * freq),
+            };
+            norm += amp;
+            freq *= self.lacunarity;
+            amp *= self.gain;
+        }
+        if norm > 0.0 { sum / norm } else { 0.0 }
+    }
+}
+
+/// Ground heights from 0 to 1, `width` by `depth` samples stored x-fastest,
+/// one per voxel column from the origin, as read from a grayscale image.
+#[derive(Clone, Default)]
+pub struct Heightmap {
+    pub width: u32,
+    pub depth: u32,
+    pub samples: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Bilinear height at world `x`, `z`, held at the edge value past the
+    /// map's sides.
+    pub fn sample(&self, x: f32, z: f32) -> f32 {
+        if self.width == 0 || self.depth == 0 {
+            return 0.0;
+        }
+        let max = Vec2::new(self.width as f32 - 1.0, self.depth as f32 - 1.0);
+        let p = Vec2::new(x, z).clamp(Vec2::ZERO, max);
+        let lo = p.floor().as_uvec2();
+        let hi = (lo + 1).min(max.as_uvec2());
+        let f = p - lo.as_vec2();
+        let at = |x: u32, z: u32| self.samples.get((z * self.width + x) as usize).copied().unwrap_or(0.0);
+        let near = at(lo.x, lo.y) + (at(hi.x, lo.y) - at(lo.x, lo.y)) * f.x;
+        let far = at(lo.x, hi.y) + (at(hi.x, hi.y) - at(lo.x, hi.y)) * f.x;
+        near + (far - near) * f.y
+    }
+}
+
+impl fmt::Debug for Heightmap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Heightmap({}x{})", self.width, self.depth)
+    }
+}
+
+/// Density generators. All positions are world voxel coordinates and all
+/// outputs are signed: negative is solid, positive is air.
+#[derive(Clone, Debug)]
+pub enum Generator {
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    /// Blobs wherever the noise rises above `threshold`.
+    Noise {
+        fbm: Fbm,
+        threshold: f32,
+    },
+    /// Rolling ground at `height` +/- `amplitude`, with caves carved where
+    /// the cave noise exceeds `cave_threshold`.
+    HeightmapCaves {
+        height: f32,
+        amplitude: f32,
+        fbm: Fbm,
+        caves: Fbm,
+        cave_threshold: f32,
+    },
+    /// Ground `amplitude` units above `height` where the map is white,
+    /// ramping from solid to air over `band` units around it, with optional
+    /// caves as in `HeightmapCaves`.
+    Heightmap {
+        map: Arc<Heightmap>,
+        height: f32,
+        amplitude: f32,
+        band: f32,
+        caves: Option<Fbm>,
+        cave_threshold: f32,
+    },
+    Sdf(Sdf),
+    /// Blobs that merge and split as they move, solid where their summed
+    /// field passes `threshold`. Animated by `TerrainConfig::time`.
+    Metaballs {
+        balls: Vec<Metaball>,
+        threshold: f32,
+    },
+}
+
+/// One blob of a `Generator::Metaballs`, bobbing `orbit` units either side
+/// of `center` on each axis.
+#[derive(Clone, Debug)]
+pub struct Metaball {
+    pub center: Vec3,
+    pub radius: f32,
+    pub orbit: Vec3,
+    /// Radians per second.
+    pub speed: f32,
+    pub phase: f32,
+}
+
+impl Metaball {
+    pub fn position(&self, time: f32) -> Vec3 {
+        let t = self.speed * time + self.phase;
+        self.center + self.orbit * Vec3::new(t.sin(), (t * 1.3).sin(), (t * 0.7).cos())
+    }
+}
+
+impl Generator {
+    /// The built-in generators by name: `sphere`, `noise`, `caves` or
+    /// `metaballs`.
+    pub fn named(name: &str) -> Option<Self> {
+        Some(match name {
+            "sphere" => Generator::default(),
+            "noise" => Generator::Noise { fbm: Fbm::default(), threshold: 0.1 },
+            "caves" => Generator::HeightmapCaves {
+                height: 2.0,
+                amplitude: 3.0,
+                fbm: Fbm::default(),
+                caves: Fbm { frequency: 0.15, octaves: 2, ..Default::default() },
+                cave_threshold: 0.3,
+            },
+            "metaballs" => Generator::metaballs(),
+            _ => return None,
+        })
+    }
+
+    /// `map` over a floor at 2, with the `caves` generator's tunnels.
+    pub fn heightmap(map: Heightmap, amplitude: f32) -> Self {
+        Generator::Heightmap {
+            map: Arc::new(map),
+            height: 2.0,
+            amplitude,
+            band: 2.0,
+            caves: Some(Fbm { frequency: 0.15, octaves: 2, ..Default::default() }),
+            cave_threshold: 0.3,
+        }
+    }
+
+    /// Four blobs drifting around the default sphere's spot.
+    pub fn metaballs() -> Self {
+        let ball = |phase: f32| Metaball {
+            center: Vec3::new(5.0, 0.0, 5.0),
+            radius: 2.5,
+            orbit: Vec3::new(3.0, 1.5, 3.0),
+            speed: 0.8,
+            phase,
+        };
+        Generator::Metaballs {
+            balls: (0..4).map(|i| ball(i as f32 * 1.6)).collect(),
+            threshold: 1.0,
+        }
+    }
+
+    /// Whether the density changes with `TerrainConfig::time`.
+    pub fn is_animated(&self) -> bool {
+        matches!(self, Generator::Metaballs { .. })
+    }
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Generator::Sphere {
+            center: Vec3::new(5.0, 0.0, 5.0),
+            radius: 5.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TerrainConfig {
+    pub generator: Generator,
+    pub seed: u64,
+    /// Repeat the density every `x` voxels in X and `y` in Z, so the world
+    /// tiles seamlessly. Mesh with `marchy_mesh::Boundary::Periodic` to match.
+    pub tile: Option<UVec2>,
+    /// Seconds into an animated generator's motion.
+    pub time: f32,
+}
+
+impl TerrainConfig {
+    /// A sampler for the configured generator. Building it sets up the noise
+    /// tables, so make one per fill rather than per voxel.
+    pub fn sampler(&self) -> impl Fn(Vec3) -> f32 + '_ {
+        let noise = Perlin::new(self.seed);
+        let caves = Perlin::new(self.seed ^ 0xcafe);
+        let (tile, time) = (self.tile, self.time);
+        move |p| {
+            // Shapes without noise repeat by wrapping the position.
+            let wrapped = match tile {
+                Some(t) => {
+                    let t = t.as_vec2();
+                    Vec3::new(p.x.rem_euclid(t.x), p.y, p.z.rem_euclid(t.y))
+                }
+                None => p,
+            };
+            match &self.generator {
+                Generator::Sphere { center, radius } => wrapped.distance(*center) - radius,
+                Generator::Noise { fbm, threshold } => threshold - fbm.sample_tiled(&noise, p, tile),
+                Generator::HeightmapCaves { height, amplitude, fbm, caves: cave_fbm, cave_threshold } => {
+                    let ground = height + amplitude * fbm.sample_tiled(&noise, Vec3::new(p.x, 0.0, p.z), tile);
+                    let surface = p.y - ground;
+                    let cave = cave_fbm.sample_tiled(&caves, p, tile) - cave_threshold;
+                    surface.max(cave)
+                }
+                Generator::Heightmap { map, height, amplitude, band, caves: cave_fbm, cave_threshold } => {
+                    let ground = height + amplitude * map.sample(p.x, p.z);
+                    let surface = ((p.y - ground) / band.max(1e-3)).clamp(-1.0, 1.0);
+                    match cave_fbm {
+                        Some(fbm) => surface.max(fbm.sample_tiled(&caves, p, tile) - cave_threshold),
+                        None => surface,
+                    }
+                }
+                Generator::Sdf(sdf) => sdf.sample(wrapped),
+                Generator::Metaballs { balls, threshold } => {
+                    threshold - metaball_field(wrapped, balls.iter().map(|b| (b.position(time), b.radius)))
+                }
+            }
+        }
+    }
+}
+
+/// The classic metaball field at `p`: each `(center, radius)` ball adds
+/// `radius² / distance²`, so it's 1 on a lone ball's surface.
+pub fn metaball_field(p: Vec3, balls: impl Iterator<Item = (Vec3, f32)>) -> f32 {
+    balls.map(|(c, r)| r * r / c.distance_squared(p).max(1e-4)).sum()
+}
+
+/// Fills a grid whose first cell sits at world voxel `origin`. Stored values
+/// are offset by `iso` so the generator's zero crossing is the surface.
+pub fn generate(config: &TerrainConfig, grid: &mut VoxelGrid, origin: IVec3, iso: f32) {
+    let sample = config.sampler();
+    grid.map(|x, y, z, _val| {
+        let p = origin + IVec3::new(x as i32, y as i32, z as i32);
+        iso + sample(p.as_vec3())
+    });
+}
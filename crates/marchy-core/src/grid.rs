@@ -0,0 +1,597 @@
+use crate::sdf::Sdf;
+use alloc::{vec, vec::Vec};
+use core::fmt;
+use glam::{IVec3, UVec3, Vec3};
+#[cfg(feature = "std")]
+use crate::codec;
+#[cfg(feature = "std")]
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+#[cfg(feature = "std")]
+const MAGIC: &[u8; 4] = b"VOXG";
+#[cfg(feature = "std")]
+const RAW: u8 = 0;
+#[cfg(feature = "std")]
+const RLE: u8 = 1;
+/// Lossy; see `codec`.
+#[cfg(feature = "std")]
+const DCT: u8 = 2;
+/// Set on the format byte when the grid isn't a cube; three dimensions then
+/// follow instead of one size.
+#[cfg(feature = "std")]
+const DIMS: u8 = 0x80;
+/// Most cells a grid may have, far past any real one: a header asking for
+/// more is corrupt, and `with_dims` refuses to allocate them.
+const MAX_CELLS: usize = 1 << 28;
+
+/// Cells in a `dims` grid, counted in `usize` so the product can't wrap,
+/// or `None` past `MAX_CELLS`.
+fn cell_count(dims: UVec3) -> Option<usize> {
+    (dims.x as usize)
+        .checked_mul(dims.y as usize)
+        .and_then(|n| n.checked_mul(dims.z as usize))
+        .filter(|&n| n <= MAX_CELLS)
+}
+
+/// A cell coordinate outside the grid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutOfBounds(pub UVec3);
+
+impl fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cell {} is outside the grid", self.0)
+    }
+}
+
+impl core::error::Error for OutOfBounds {}
+
+/// A `width * height * depth` box of cells, stored x-fastest then y then z.
+/// Defaults to `f32` densities, but any cell type works (material ids,
+/// packed structs, ...).
+#[derive(Clone)]
+pub struct VoxelGrid<T = f32> {
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub data: Vec<T>
+}
+
+impl<T: Clone + Default> VoxelGrid<T> {
+    /// A `size`-cubed grid of default cells.
+    pub fn new(size: u32) -> Self {
+        Self::with_dims(UVec3::splat(size))
+    }
+
+    /// A `dims`-sized grid of default cells. Panics past `MAX_CELLS`.
+    pub fn with_dims(dims: UVec3) -> Self {
+        let len = cell_count(dims).unwrap_or_else(|| panic!("a {dims} grid is too large"));
+        VoxelGrid {
+            width: dims.x,
+            height: dims.y,
+            depth: dims.z,
+            data: vec![T::default(); len]
+        }
+    }
+}
+
+impl<T> VoxelGrid<T> {
+    pub fn dims(&self) -> UVec3 {
+        UVec3::new(self.width, self.height, self.depth)
+    }
+
+    pub fn index(&self, x: u32, y: u32, z: u32) -> Option<usize> {
+        let inside = x < self.width && y < self.height && z < self.depth;
+        inside.then(|| ((z * self.height + y) * self.width + x) as usize)
+    }
+
+    pub fn get(&self, x: u32, y: u32, z: u32) -> Option<&T> {
+        self.index(x, y, z).map(|i| &self.data[i])
+    }
+
+    pub fn get_mut(&mut self, x: u32, y: u32, z: u32) -> Option<&mut T> {
+        self.index(x, y, z).map(|i| &mut self.data[i])
+    }
+
+    pub fn write(&mut self, x: u32, y: u32, z: u32, val: T) -> Result<(), OutOfBounds> {
+        let cell = self.get_mut(x, y, z).ok_or(OutOfBounds(UVec3::new(x, y, z)))?;
+        *cell = val;
+        Ok(())
+    }
+
+    /// Every cell with its coordinate, in storage order.
+    pub fn iter(&self) -> impl Iterator<Item = (UVec3, &T)> {
+        let (w, h) = (self.width, self.height);
+        self.data.iter().enumerate().map(move |(i, val)| (cell(w, h, i), val))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (UVec3, &mut T)> {
+        let (w, h) = (self.width, self.height);
+        self.data.iter_mut().enumerate().map(move |(i, val)| (cell(w, h, i), val))
+    }
+
+    /// `min..max` clamped to the grid, as rows: each one's first index in
+    /// `data` and coordinate, and the length they share.
+    fn rows(&self, min: UVec3, max: UVec3) -> (impl Iterator<Item = (usize, UVec3)> + use<T>, usize) {
+        let max = max.min(self.dims());
+        let min = min.min(max);
+        let (w, h) = (self.width as usize, self.height as usize);
+        let rows = (min.z..max.z)
+            .flat_map(move |z| (min.y..max.y).map(move |y| UVec3::new(min.x, y, z)))
+            .map(move |c| ((c.z as usize * h + c.y as usize) * w + c.x as usize, c));
+        (rows, (max.x - min.x) as usize)
+    }
+
+    /// The cells from `min` up to but not including `max`, clamped to the
+    /// grid, so edits can visit just the box they touch.
+    pub fn iter_region(&self, min: UVec3, max: UVec3) -> impl Iterator<Item = (UVec3, &T)> {
+        let (rows, len) = self.rows(min, max);
+        rows.flat_map(move |(start, c)| {
+            self.data[start..start + len].iter().zip(c.x..).map(move |(val, x)| (c.with_x(x), val))
+        })
+    }
+
+    pub fn iter_region_mut(&mut self, min: UVec3, max: UVec3) -> impl Iterator<Item = (UVec3, &mut T)> {
+        let (rows, len) = self.rows(min, max);
+        // Rows come in storage order, so each splits off the front of what
+        // the last one left.
+        let (mut rest, mut offset) = (&mut self.data[..], 0);
+        rows.flat_map(move |(start, c)| {
+            let (_, tail) = core::mem::take(&mut rest).split_at_mut(start - offset);
+            let (row, tail) = tail.split_at_mut(len);
+            (rest, offset) = (tail, start + len);
+            row.iter_mut().zip(c.x..).map(move |(val, x)| (c.with_x(x), val))
+        })
+    }
+}
+
+/// `iter` and `iter_mut` on rayon's thread pool, for whole-grid passes
+/// like filling from a generator.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+impl<T> VoxelGrid<T> {
+    pub fn par_iter(&self) -> impl rayon::iter::IndexedParallelIterator<Item = (UVec3, &T)>
+    where T: Sync {
+        use rayon::prelude::*;
+        let (w, h) = (self.width, self.height);
+        self.data.par_iter().enumerate().map(move |(i, val)| (cell(w, h, i), val))
+    }
+
+    pub fn par_iter_mut(&mut self) -> impl rayon::iter::IndexedParallelIterator<Item = (UVec3, &mut T)>
+    where T: Send {
+        use rayon::prelude::*;
+        let (w, h) = (self.width, self.height);
+        self.data.par_iter_mut().enumerate().map(move |(i, val)| (cell(w, h, i), val))
+    }
+}
+
+/// The coordinate of cell `i` in a grid `width` by `height` across.
+fn cell(width: u32, height: u32, i: usize) -> UVec3 {
+    let i = i as u32;
+    UVec3::new(i % width, (i / width) % height, i / (width * height))
+}
+
+impl<T: Copy> VoxelGrid<T> {
+    pub fn read(&self, x: u32, y: u32, z: u32) -> Option<T> {
+        self.get(x, y, z).copied()
+    }
+
+    pub fn map<F>(&mut self, mut func: F)
+    where F: FnMut(u32, u32, u32, T) -> T {
+        for (c, val) in self.iter_mut() {
+            *val = func(c.x, c.y, c.z, *val);
+        }
+    }
+
+    pub fn each<F>(&self, mut func: F)
+    where F: FnMut(u32, u32, u32, T) {
+        for (c, &val) in self.iter() {
+            func(c.x, c.y, c.z, val);
+        }
+    }
+}
+
+impl VoxelGrid {
+    /// A grid `stride` times coarser, each cell the average of the
+    /// `stride`-cubed block it covers. Partial blocks at the far edges
+    /// average what they have.
+    pub fn downsample(&self, stride: u32) -> VoxelGrid {
+        let dims = (self.dims() + stride - 1) / stride;
+        // No bigger than this grid, so within `MAX_CELLS`.
+        let mut sums = vec![(0.0, 0u32); cell_count(dims).unwrap_or_default()];
+        self.each(|x, y, z, val| {
+            let (cx, cy, cz) = (x / stride, y / stride, z / stride);
+            let sum = &mut sums[((cz * dims.y + cy) * dims.x + cx) as usize];
+            sum.0 += val;
+            sum.1 += 1;
+        });
+        VoxelGrid {
+            width: dims.x,
+            height: dims.y,
+            depth: dims.z,
+            data: sums.into_iter().map(|(sum, n)| sum / n as f32).collect(),
+        }
+    }
+
+    /// Samples an SDF at every cell, in grid-local cell coordinates. Values
+    /// are raw distances, so the surface sits at an iso level of 0.
+    pub fn fill_sdf(&mut self, sdf: &Sdf) {
+        self.map(|x, y, z, _val| sdf.sample(Vec3::new(x as f32, y as f32, z as f32)));
+    }
+}
+
+/// Reading and writing grids, which needs `std`.
+#[cfg(feature = "std")]
+impl VoxelGrid {
+    /// Writes `VOXG`, a format byte, the size (or for non-cubes, the three
+    /// dimensions), then the cells as little-endian f32s. With `compress`,
+    /// cells are stored as (run length, value) pairs, which shrinks flat
+    /// regions like all-air chunks to almost nothing.
+    pub fn write_to<W: Write>(&self, w: &mut W, compress: bool) -> io::Result<()> {
+        self.write_header(w, if compress { RLE } else { RAW })?;
+        if !compress {
+            for v in &self.data {
+                w.write_all(&v.to_le_bytes())?;
+            }
+            return Ok(());
+        }
+
+        let mut cells = self.data.iter().peekable();
+        while let Some(&v) = cells.next() {
+            let mut run = 1u32;
+            while cells.next_if(|&&n| n.to_bits() == v.to_bits()).is_some() {
+                run += 1;
+            }
+            w.write_all(&run.to_le_bytes())?;
+            w.write_all(&v.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Like `write_to`, but with the cells through the lossy `codec` at
+    /// `quality` (1 to 100). Experimental: densities come back close, not
+    /// equal, so edges of the surface can shift.
+    pub fn write_lossy<W: Write>(&self, w: &mut W, quality: u8) -> io::Result<()> {
+        self.write_header(w, DCT)?;
+        codec::encode(self, quality, w)
+    }
+
+    fn write_header<W: Write>(&self, w: &mut W, format: u8) -> io::Result<()> {
+        let cube = self.width == self.height && self.height == self.depth;
+        w.write_all(MAGIC)?;
+        w.write_all(&[if cube { format } else { format | DIMS }])?;
+        let dims: &[u32] = if cube { &[self.width] } else { &[self.width, self.height, self.depth] };
+        for d in dims {
+            w.write_all(&d.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<Self> {
+        let bad = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(bad("not a voxel grid"));
+        }
+        let mut format = [0; 1];
+        r.read_exact(&mut format)?;
+        let dims = if format[0] & DIMS != 0 {
+            UVec3::new(read_u32(r)?, read_u32(r)?, read_u32(r)?)
+        } else {
+            UVec3::splat(read_u32(r)?)
+        };
+        let len = cell_count(dims).ok_or_else(|| bad("grid too large"))?;
+
+        // Grown as cells arrive, not reserved up front from the header.
+        let mut data = vec![];
+        match format[0] & !DIMS {
+            RAW => {
+                for _ in 0..len {
+                    data.push(f32::from_bits(read_u32(r)?));
+                }
+            }
+            RLE => {
+                while data.len() < len {
+                    let run = read_u32(r)? as usize;
+                    let v = f32::from_bits(read_u32(r)?);
+                    if data.len() + run > len {
+                        return Err(bad("run overflows grid"));
+                    }
+                    data.extend(core::iter::repeat_n(v, run));
+                }
+            }
+            DCT => data = codec::decode(r, dims)?,
+            _ => return Err(bad("unknown grid format")),
+        }
+        Ok(VoxelGrid { width: dims.x, height: dims.y, depth: dims.z, data })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write_to(&mut w, true)?;
+        w.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+/// Where a voxel ray stopped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoxelHit {
+    pub cell: IVec3,
+    /// Outward normal of the face the ray entered through; zero if the ray
+    /// started inside a solid cell.
+    pub normal: IVec3,
+    /// Along the ray, in cells.
+    pub distance: f32,
+    pub point: Vec3,
+    /// Density at `point`, interpolated between cell centers.
+    pub density: f32,
+}
+
+/// Amanatides–Woo traversal: visits, in order, every cell (spanning
+/// `c .. c + 1`) the ray passes through until `hit` accepts one or
+/// `max_dist` runs out. Returns the cell, entry normal and distance.
+pub fn dda(
+    origin: Vec3,
+    dir: Vec3,
+    max_dist: f32,
+    hit: impl FnMut(IVec3) -> bool,
+) -> Option<(IVec3, IVec3, f32)> {
+    dda_skipping(origin, dir, max_dist, hit, |_| None)
+}
+
+/// `dda`, where `empty` may answer a cell with a half-open box of cells
+/// around it that can't hit (say from an `Occupancy`), which the ray then
+/// crosses in one step.
+pub fn dda_skipping(
+    origin: Vec3,
+    dir: Vec3,
+    max_dist: f32,
+    mut hit: impl FnMut(IVec3) -> bool,
+    mut empty: impl FnMut(IVec3) -> Option<(IVec3, IVec3)>,
+) -> Option<(IVec3, IVec3, f32)> {
+    let dir = dir.try_normalize()?;
+    let mut cell = origin.floor().as_ivec3();
+    let step = IVec3::from_array(dir.to_array().map(|d| if d > 0.0 { 1 } else if d < 0.0 { -1 } else { 0 }));
+    let delta = dir.abs().recip();
+    // Along the ray to where it next leaves `cell` on each axis.
+    let next = |cell: IVec3| Vec3::from_array(core::array::from_fn(|i| {
+        let edge = cell[i] as f32 + if step[i] > 0 { 1.0 } else { 0.0 };
+        if step[i] == 0 { f32::INFINITY } else { (edge - origin[i]) / dir[i] }
+    }));
+    let mut t_max = next(cell);
+    let mut normal = IVec3::ZERO;
+    let mut t = 0.0;
+    loop {
+        if hit(cell) {
+            return Some((cell, normal, t));
+        }
+        if let Some((lo, hi)) = empty(cell) {
+            // Out through whichever of the box's faces comes first.
+            let exit = Vec3::from_array(core::array::from_fn(|i| match step[i] {
+                0 => f32::INFINITY,
+                s if s > 0 => (hi[i] as f32 - origin[i]) / dir[i],
+                _ => (lo[i] as f32 - origin[i]) / dir[i],
+            }));
+            let axis = if exit.x < exit.y && exit.x < exit.z {
+                0
+            } else if exit.y < exit.z {
+                1
+            } else {
+                2
+            };
+            t = exit[axis];
+            if t > max_dist {
+                return None;
+            }
+            cell = (origin + dir * t).floor().as_ivec3().clamp(lo, hi - 1);
+            cell[axis] = if step[axis] > 0 { hi[axis] } else { lo[axis] - 1 };
+            t_max = next(cell);
+            normal = IVec3::ZERO;
+            normal[axis] = -step[axis];
+            continue;
+        }
+        let axis = if t_max.x < t_max.y && t_max.x < t_max.z {
+            0
+        } else if t_max.y < t_max.z {
+            1
+        } else {
+            2
+        };
+        t = t_max[axis];
+        if t > max_dist {
+            return None;
+        }
+        cell[axis] += step[axis];
+        t_max[axis] += delta[axis];
+        normal = IVec3::ZERO;
+        normal[axis] = -step[axis];
+    }
+}
+
+/// Density at `p` interpolated between the centers (`c + 0.5`) of the eight
+/// nearest cells. Missing cells take the nearest present one's value.
+pub fn trilinear(p: Vec3, read: impl Fn(IVec3) -> Option<f32>) -> Option<f32> {
+    let g = p - 0.5;
+    let base = g.floor();
+    let t = g - base;
+    let base = base.as_ivec3();
+    let samples: [Option<f32>; 8] = core::array::from_fn(|i| {
+        let i = i as i32;
+        read(base + IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1))
+    });
+    let fallback = read(p.floor().as_ivec3()).or_else(|| samples.iter().flatten().next().copied())?;
+    let mut sum = 0.0;
+    for (i, s) in samples.iter().enumerate() {
+        let o = IVec3::new(i as i32 & 1, (i as i32 >> 1) & 1, (i as i32 >> 2) & 1);
+        let w = Vec3::select(o.cmpeq(IVec3::ONE), t, 1.0 - t);
+        sum += w.x * w.y * w.z * s.unwrap_or(fallback);
+    }
+    Some(sum)
+}
+
+impl VoxelGrid {
+    /// Casts a ray through the grid, in cell units with cell `c` spanning
+    /// `c .. c + 1`, to the first solid (`<= iso`) cell. For picking, digging
+    /// and line-of-sight checks that need the cell rather than the mesh.
+    pub fn raycast(&self, origin: Vec3, dir: Vec3, max_dist: f32, iso: f32) -> Option<VoxelHit> {
+        let read = |c: IVec3| {
+            if c.cmplt(IVec3::ZERO).any() {
+                return None;
+            }
+            self.read(c.x as u32, c.y as u32, c.z as u32)
+        };
+        let (cell, normal, distance) = dda(origin, dir, max_dist, |c| read(c).is_some_and(|v| v <= iso))?;
+        let point = origin + dir.normalize() * distance;
+        let density = trilinear(point, read)?;
+        Some(VoxelHit { cell, normal, distance, point, density })
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::occupancy::Occupancy;
+
+    #[test]
+    fn oversized_grids_are_refused() {
+        assert_eq!(cell_count(UVec3::new(64, 32, 16)), Some(64 * 32 * 16));
+        assert_eq!(cell_count(UVec3::splat(1 << 10)), None);
+        // Wraps to zero in `u32`.
+        assert_eq!(cell_count(UVec3::new(1 << 16, 1 << 16, 1)), None);
+        let grid = std::panic::catch_unwind(|| VoxelGrid::<u8>::with_dims(UVec3::new(1 << 16, 1 << 16, 1)));
+        assert!(grid.is_err());
+    }
+
+    #[test]
+    fn regions_visit_exactly_their_cells() {
+        let mut grid = VoxelGrid::<u32>::with_dims(UVec3::new(5, 4, 3));
+        for (i, (_, v)) in grid.iter_mut().enumerate() {
+            *v = i as u32;
+        }
+        // Clamped to the grid on z.
+        let (min, max) = (UVec3::new(1, 1, 1), UVec3::new(4, 3, 9));
+        let hi = max.min(grid.dims());
+        let inside = |p: UVec3| p.cmpge(min).all() && p.cmplt(hi).all();
+        let expected: Vec<(UVec3, u32)> = grid.iter().filter(|(p, _)| inside(*p)).map(|(p, &v)| (p, v)).collect();
+        assert_eq!(expected.len(), 3 * 2 * 2);
+        let visited: Vec<(UVec3, u32)> = grid.iter_region(min, max).map(|(p, &v)| (p, v)).collect();
+        assert_eq!(visited, expected);
+
+        let mut touched = vec![];
+        for (p, v) in grid.iter_region_mut(min, max) {
+            touched.push(p);
+            *v += 1000;
+        }
+        assert_eq!(touched, expected.iter().map(|&(p, _)| p).collect::<Vec<_>>());
+        for (i, (p, &v)) in grid.iter().enumerate() {
+            assert_eq!(v, i as u32 + if inside(p) { 1000 } else { 0 }, "at {p}");
+        }
+        assert_eq!(grid.iter_region_mut(UVec3::new(2, 0, 0), UVec3::new(2, 4, 3)).count(), 0);
+        assert_eq!(grid.iter_region(UVec3::splat(6), UVec3::splat(9)).count(), 0);
+    }
+
+    /// Air, with solid cells `y <= 2`.
+    fn floor() -> VoxelGrid {
+        let mut grid = VoxelGrid::new(16);
+        grid.map(|_, y, _, _| if y <= 2 { -1.0 } else { 1.0 });
+        grid
+    }
+
+    #[test]
+    fn axis_aligned_ray_hits_the_top_face() {
+        let hit = floor().raycast(Vec3::new(4.5, 10.5, 4.5), Vec3::NEG_Y, 100.0, 0.0).unwrap();
+        assert_eq!(hit.cell, IVec3::new(4, 2, 4));
+        assert_eq!(hit.normal, IVec3::Y);
+        assert_eq!(hit.distance, 7.5);
+        assert_eq!(hit.point, Vec3::new(4.5, 3.0, 4.5));
+    }
+
+    #[test]
+    fn diagonal_ray_visits_each_cell_it_crosses() {
+        let mut visited = vec![];
+        let target = IVec3::new(3, 2, 0);
+        let (cell, normal, distance) = dda(Vec3::splat(0.5), Vec3::new(2.0, 1.0, 0.0), 10.0, |c| {
+            visited.push(c);
+            c == target
+        })
+        .unwrap();
+        let path = [(0, 0), (1, 0), (1, 1), (2, 1), (3, 1), (3, 2)].map(|(x, y)| IVec3::new(x, y, 0));
+        assert_eq!(visited, path);
+        assert_eq!((cell, normal), (target, IVec3::NEG_Y));
+        assert!((distance - 1.5 * 5f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn ray_starting_inside_solid_hits_at_once() {
+        let hit = floor().raycast(Vec3::new(4.5, 1.5, 4.5), Vec3::new(1.0, 2.0, 3.0), 100.0, 0.0).unwrap();
+        assert_eq!(hit.cell, IVec3::new(4, 1, 4));
+        assert_eq!(hit.normal, IVec3::ZERO);
+        assert_eq!(hit.distance, 0.0);
+    }
+
+    #[test]
+    fn hit_exactly_at_max_dist_counts() {
+        let grid = floor();
+        let origin = Vec3::new(4.5, 10.5, 4.5);
+        assert!(grid.raycast(origin, Vec3::NEG_Y, 7.5, 0.0).is_some());
+        assert!(grid.raycast(origin, Vec3::NEG_Y, 7.4, 0.0).is_none());
+    }
+
+    #[test]
+    fn skipping_empty_space_agrees_with_plain_dda() {
+        let mut grid = VoxelGrid::new(32);
+        grid.map(|x, y, z, _| {
+            let p = UVec3::new(x, y, z).as_vec3();
+            let ball = p.distance(Vec3::new(20.0, 12.0, 16.0)) - 5.0;
+            let pillar = if p.x >= 6.0 && p.x < 9.0 && p.z >= 22.0 && p.z < 25.0 { -1.0 } else { 1.0 };
+            ball.min(pillar)
+        });
+        let occupancy = Occupancy::build(&grid, 0.0);
+        let solid = |c: IVec3| {
+            c.cmpge(IVec3::ZERO).all() && grid.read(c.x as u32, c.y as u32, c.z as u32).is_some_and(|v| v <= 0.0)
+        };
+        let empty = |c: IVec3| {
+            let inside = c.cmpge(IVec3::ZERO).all() && c.cmplt(IVec3::splat(32)).all();
+            let (lo, hi) = occupancy.empty_box(c.as_uvec3()).filter(|_| inside)?;
+            Some((lo.as_ivec3(), hi.as_ivec3()))
+        };
+        // A fixed spread of rays from all over the grid, in odd directions
+        // so none runs along a cell edge.
+        let mut seed = 12345u32;
+        let mut rand = || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1 << 24) as f32
+        };
+        let mut hits = 0;
+        for _ in 0..200 {
+            let origin = Vec3::new(rand(), rand(), rand()) * 31.0 + 0.5;
+            let dir = Vec3::new(rand(), rand(), rand()) * 2.0 - 1.0 + Vec3::new(0.013, 0.007, 0.011);
+            let plain = dda(origin, dir, 60.0, solid);
+            let skipped = dda_skipping(origin, dir, 60.0, solid, empty);
+            match (plain, skipped) {
+                (Some((c, n, t)), Some((sc, sn, st))) => {
+                    assert_eq!((c, n), (sc, sn), "from {origin} along {dir}");
+                    assert!((t - st).abs() < 1e-3, "from {origin} along {dir}: {t} vs {st}");
+                    hits += 1;
+                }
+                (None, None) => {}
+                _ => panic!("from {origin} along {dir}: {plain:?} vs {skipped:?}"),
+            }
+        }
+        assert!(hits > 0);
+    }
+}
@@ -0,0 +1,28 @@
+//! The engine-free core of marchy: density grids and sparse storage, the
+//! occupancy pyramid, signed distance fields, the terrain generators and
+//! the grid codecs. Depends only on `glam` and `hashbrown` (and `rayon` for
+//! `parallel`), so it can be used without Bevy.
+//!
+//! Without the default `std` feature it is `no_std` (with `alloc`): grid
+//! files and the codecs go, and the `libm` feature supplies float math.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod codec;
+#[cfg(not(feature = "std"))]
+mod float;
+pub mod generator;
+pub mod grid;
+pub mod occupancy;
+pub mod sdf;
+pub mod source;
+pub mod storage;
+pub mod transform;
+
+pub use glam;
+pub use grid::VoxelGrid;
+pub use storage::{BrickMap, VoxelStorage};
+pub use transform::GridTransform;
@@ -0,0 +1,257 @@
+use alloc::{vec, vec::Vec};
+use glam::{IVec3, UVec3};
+use crate::storage::{VoxelStorage, BRICK};
+
+/// What a node of an `Occupancy` pyramid covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fill {
+    Empty,
+    Solid,
+    Mixed,
+}
+
+#[derive(Clone, Debug)]
+struct Level {
+    dims: UVec3,
+    /// One bit per node: it has at least one solid cell.
+    solid: Vec<u64>,
+    /// One bit per node: it has at least one empty cell.
+    empty: Vec<u64>,
+}
+
+impl Level {
+    fn new(dims: UVec3) -> Self {
+        let words = (dims.element_product() as usize).div_ceil(64);
+        Level { dims, solid: vec![0; words], empty: vec![0; words] }
+    }
+
+    fn index(&self, p: UVec3) -> usize {
+        ((p.z * self.dims.y + p.y) * self.dims.x + p.x) as usize
+    }
+
+    fn bits(&self, i: usize) -> (bool, bool) {
+        let (word, bit) = (i / 64, 1 << (i % 64));
+        (self.solid[word] & bit != 0, self.empty[word] & bit != 0)
+    }
+
+    /// Returns whether the node changed.
+    fn put(&mut self, i: usize, (solid, empty): (bool, bool)) -> bool {
+        let changed = self.bits(i) != (solid, empty);
+        let (word, bit) = (i / 64, 1 << (i % 64));
+        self.solid[word] = if solid { self.solid[word] | bit } else { self.solid[word] & !bit };
+        self.empty[word] = if empty { self.empty[word] | bit } else { self.empty[word] & !bit };
+        changed
+    }
+}
+
+/// A mip pyramid of occupancy bits over a density field, solid being
+/// `<= iso`. Level 0 is the cells; each level up halves every axis
+/// (rounding up), so asking whether any box of `2^level` cells is all
+/// empty, all solid or mixed is one lookup. Meshing, raycasts and anything
+/// else sweeping the field can jump over the uniform parts.
+#[derive(Clone, Debug)]
+pub struct Occupancy {
+    iso: f32,
+    levels: Vec<Level>,
+}
+
+impl Occupancy {
+    pub fn build<S: VoxelStorage<f32> + ?Sized>(storage: &S, iso: f32) -> Self {
+        let dims = storage.dims();
+        let mut base = Level::new(dims);
+        for z in 0..dims.z {
+            for y in 0..dims.y {
+                for x in 0..dims.x {
+                    let p = UVec3::new(x, y, z);
+                    let solid = storage.get(p).is_some_and(|v| v <= iso);
+                    base.put(base.index(p), (solid, !solid));
+                }
+            }
+        }
+        let mut occupancy = Occupancy { iso, levels: vec![base] };
+        while occupancy.levels.last().is_some_and(|l| l.dims.max_element() > 1) {
+            let below = occupancy.levels.len() - 1;
+            let dims = (occupancy.levels[below].dims + 1) / 2;
+            occupancy.levels.push(Level::new(dims));
+            for z in 0..dims.z {
+                for y in 0..dims.y {
+                    for x in 0..dims.x {
+                        occupancy.refresh(below + 1, UVec3::new(x, y, z));
+                    }
+                }
+            }
+        }
+        occupancy
+    }
+
+    /// The density a cell counts as solid at.
+    pub fn iso(&self) -> f32 {
+        self.iso
+    }
+
+    pub fn dims(&self) -> UVec3 {
+        self.levels[0].dims
+    }
+
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Recomputes a node from its children; returns whether it changed.
+    fn refresh(&mut self, level: usize, node: UVec3) -> bool {
+        let (below, above) = self.levels.split_at_mut(level);
+        let (below, above) = (&below[level - 1], &mut above[0]);
+        let (mut solid, mut empty) = (false, false);
+        for i in 0..8 {
+            let child = node * 2 + UVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1);
+            if child.cmplt(below.dims).all() {
+                let (s, e) = below.bits(below.index(child));
+                solid |= s;
+                empty |= e;
+            }
+        }
+        above.put(above.index(node), (solid, empty))
+    }
+
+    /// Keeps the pyramid in step with a cell's new density.
+    pub fn set(&mut self, p: UVec3, val: f32) {
+        if p.cmpge(self.dims()).any() {
+            return;
+        }
+        let solid = val <= self.iso;
+        let base = &mut self.levels[0];
+        if !base.put(base.index(p), (solid, !solid)) {
+            return;
+        }
+        for level in 1..self.levels.len() {
+            if !self.refresh(level, p >> level as u32) {
+                break;
+            }
+        }
+    }
+
+    /// The node at `level` covering cells `node << level ..`. Past the edge
+    /// counts as empty.
+    pub fn state(&self, level: usize, node: UVec3) -> Fill {
+        let Some(l) = self.levels.get(level).filter(|l| node.cmplt(l.dims).all()) else {
+            return Fill::Empty;
+        };
+        match l.bits(l.index(node)) {
+            (true, true) => Fill::Mixed,
+            (true, false) => Fill::Solid,
+            _ => Fill::Empty,
+        }
+    }
+
+    /// The biggest all-empty node holding `p`, as a half-open box of cells.
+    pub fn empty_box(&self, p: UVec3) -> Option<(UVec3, UVec3)> {
+        let level = (0..self.levels.len())
+            .rev()
+            .find(|&l| self.state(l, p >> l as u32) == Fill::Empty)?;
+        let node = p >> level as u32;
+        Some((node << level as u32, ((node + 1) << level as u32).min(self.dims())))
+    }
+
+    /// `BRICK`-sized boxes covering every solid cell, as for
+    /// `VoxelStorage::regions`. With `exposed_only`, solid bricks entirely
+    /// surrounded by solid bricks are left out too, since none of their
+    /// faces can show.
+    pub fn regions(&self, exposed_only: bool) -> Vec<(UVec3, UVec3)> {
+        let level = (BRICK.trailing_zeros() as usize).min(self.levels.len() - 1);
+        let Some(l) = self.levels.get(level) else {
+            return vec![];
+        };
+        let mut out = vec![];
+        for z in 0..l.dims.z {
+            for y in 0..l.dims.y {
+                for x in 0..l.dims.x {
+                    let node = UVec3::new(x, y, z);
+                    let buried = || {
+                        let n = node.as_ivec3();
+                        IVec3::AXES.iter().flat_map(|&a| [n + a, n - a]).all(|n| {
+                            n.cmpge(IVec3::ZERO).all()
+                                && self.state(level, n.as_uvec3()) == Fill::Solid
+                        })
+                    };
+                    match self.state(level, node) {
+                        Fill::Empty => continue,
+                        Fill::Solid if exposed_only && buried() => continue,
+                        _ => {}
+                    }
+                    out.push((node << level as u32, ((node + 1) << level as u32).min(self.dims())));
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VoxelGrid;
+
+    /// Every node of every level alike.
+    fn assert_same(a: &Occupancy, b: &Occupancy) {
+        assert_eq!(a.levels(), b.levels());
+        for level in 0..a.levels() {
+            let dims = a.levels[level].dims;
+            for z in 0..dims.z {
+                for y in 0..dims.y {
+                    for x in 0..dims.x {
+                        let node = UVec3::new(x, y, z);
+                        assert_eq!(a.state(level, node), b.state(level, node), "level {level} node {node}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn set_propagates_up_and_clears_when_a_brick_empties() {
+        let mut grid = VoxelGrid::new(16);
+        grid.data.fill(1.0);
+        let mut occupancy = Occupancy::build(&grid, 0.0);
+        assert_eq!(occupancy.levels(), 5);
+        assert_eq!(occupancy.state(4, UVec3::ZERO), Fill::Empty);
+
+        let p = UVec3::new(9, 2, 3);
+        occupancy.set(p, -1.0);
+        grid.write(p.x, p.y, p.z, -1.0).unwrap();
+        assert_eq!(occupancy.state(0, p), Fill::Solid);
+        for level in 1..5 {
+            assert_eq!(occupancy.state(level, p >> level as u32), Fill::Mixed, "level {level}");
+        }
+        assert_eq!(occupancy.state(3, UVec3::ZERO), Fill::Empty);
+        assert_eq!(occupancy.regions(false), [(UVec3::new(8, 0, 0), UVec3::new(16, 8, 8))]);
+        assert_eq!(occupancy.empty_box(UVec3::ZERO), Some((UVec3::ZERO, UVec3::splat(8))));
+        assert_eq!(occupancy.empty_box(p), None);
+        assert_same(&occupancy, &Occupancy::build(&grid, 0.0));
+
+        occupancy.set(p, 1.0);
+        grid.write(p.x, p.y, p.z, 1.0).unwrap();
+        for level in 0..5 {
+            assert_eq!(occupancy.state(level, p >> level as u32), Fill::Empty, "level {level}");
+        }
+        assert!(occupancy.regions(false).is_empty());
+        assert_eq!(occupancy.empty_box(p), Some((UVec3::ZERO, UVec3::splat(16))));
+        assert_same(&occupancy, &Occupancy::build(&grid, 0.0));
+    }
+
+    #[test]
+    fn solid_bricks_turn_mixed_when_dug() {
+        let mut grid = VoxelGrid::new(16);
+        grid.map(|x, y, z, _| if x < 8 && y < 8 && z < 8 { -1.0 } else { 1.0 });
+        let mut occupancy = Occupancy::build(&grid, 0.0);
+        assert_eq!(occupancy.state(3, UVec3::ZERO), Fill::Solid);
+        assert_eq!(occupancy.state(4, UVec3::ZERO), Fill::Mixed);
+
+        occupancy.set(UVec3::new(4, 4, 4), 1.0);
+        assert_eq!(occupancy.state(2, UVec3::ONE), Fill::Mixed);
+        assert_eq!(occupancy.state(3, UVec3::ZERO), Fill::Mixed);
+        // Past the edge reads as empty, and out of range sets do nothing.
+        assert_eq!(occupancy.state(3, UVec3::splat(2)), Fill::Empty);
+        occupancy.set(UVec3::splat(16), -1.0);
+        assert_eq!(occupancy.state(4, UVec3::ZERO), Fill::Mixed);
+    }
+}
@@ -0,0 +1,148 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use glam::Vec3;
+#[cfg(not(feature = "std"))]
+use crate::float::Float;
+
+/// A signed distance field: negative inside, positive outside. Primitives
+/// compose with the CSG combinators into a tree that can be sampled
+/// anywhere, e.g. into a grid with `VoxelGrid::fill_sdf`, or meshed straight
+/// from the field as a `DensitySource`.
+#[derive(Clone, Debug)]
+pub enum Sdf {
+    Sphere { center: Vec3, radius: f32 },
+    Cuboid { center: Vec3, half_size: Vec3 },
+    /// Torus around the Y axis.
+    Torus { center: Vec3, major: f32, minor: f32 },
+    Capsule { a: Vec3, b: Vec3, radius: f32 },
+    /// Half-space: solid on the side opposite `normal`.
+    Plane { normal: Vec3, offset: f32 },
+    /// A `radius`-thick tube along a polyline, e.g. from `catmull_rom`.
+    Tube { path: Vec<Vec3>, radius: f32 },
+    /// The bottom half of a pipe along a polyline: a `wall`-thick shell at
+    /// `radius`, open above the path. The ends are closed bowls.
+    HalfPipe { path: Vec<Vec3>, radius: f32, wall: f32 },
+    Union(Box<Sdf>, Box<Sdf>),
+    Subtract(Box<Sdf>, Box<Sdf>),
+    Intersect(Box<Sdf>, Box<Sdf>),
+    SmoothUnion(Box<Sdf>, Box<Sdf>, f32),
+}
+
+impl Sdf {
+    pub fn sphere(center: Vec3, radius: f32) -> Self {
+        Sdf::Sphere { center, radius }
+    }
+
+    pub fn cuboid(center: Vec3, half_size: Vec3) -> Self {
+        Sdf::Cuboid { center, half_size }
+    }
+
+    pub fn torus(center: Vec3, major: f32, minor: f32) -> Self {
+        Sdf::Torus { center, major, minor }
+    }
+
+    pub fn capsule(a: Vec3, b: Vec3, radius: f32) -> Self {
+        Sdf::Capsule { a, b, radius }
+    }
+
+    pub fn plane(normal: Vec3, offset: f32) -> Self {
+        Sdf::Plane { normal: normal.normalize(), offset }
+    }
+
+    pub fn tube(path: Vec<Vec3>, radius: f32) -> Self {
+        Sdf::Tube { path, radius }
+    }
+
+    pub fn half_pipe(path: Vec<Vec3>, radius: f32, wall: f32) -> Self {
+        Sdf::HalfPipe { path, radius, wall }
+    }
+
+    pub fn union(self, other: Sdf) -> Self {
+        Sdf::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn subtract(self, other: Sdf) -> Self {
+        Sdf::Subtract(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersect(self, other: Sdf) -> Self {
+        Sdf::Intersect(Box::new(self), Box::new(other))
+    }
+
+    /// Union that blends the two surfaces over roughly `k` units.
+    pub fn smooth_union(self, other: Sdf, k: f32) -> Self {
+        Sdf::SmoothUnion(Box::new(self), Box::new(other), k)
+    }
+
+    pub fn sample(&self, p: Vec3) -> f32 {
+        match self {
+            Sdf::Sphere { center, radius } => p.distance(*center) - radius,
+            Sdf::Cuboid { center, half_size } => {
+                let q = (p - *center).abs() - *half_size;
+                q.max(Vec3::ZERO).length() + q.max_element().min(0.0)
+            }
+            Sdf::Torus { center, major, minor } => {
+                let d = p - *center;
+                let ring = Vec3::new(d.x, 0.0, d.z).length() - major;
+                (ring * ring + d.y * d.y).sqrt() - minor
+            }
+            Sdf::Capsule { a, b, radius } => {
+                let pa = p - *a;
+                let ba = *b - *a;
+                let h = (pa.dot(ba) / ba.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+                (pa - ba * h).length() - radius
+            }
+            Sdf::Plane { normal, offset } => p.dot(*normal) - offset,
+            Sdf::Tube { path, radius } => p.distance(nearest_on_path(path, p)) - radius,
+            Sdf::HalfPipe { path, radius, wall } => {
+                let d = p - nearest_on_path(path, p);
+                let shell = (d.length() - radius).abs() - wall / 2.0;
+                shell.max(d.y)
+            }
+            Sdf::Union(a, b) => a.sample(p).min(b.sample(p)),
+            Sdf::Subtract(a, b) => a.sample(p).max(-b.sample(p)),
+            Sdf::Intersect(a, b) => a.sample(p).max(b.sample(p)),
+            Sdf::SmoothUnion(a, b, k) => {
+                let (da, db) = (a.sample(p), b.sample(p));
+                let h = (0.5 + 0.5 * (db - da) / k.max(f32::EPSILON)).clamp(0.0, 1.0);
+                db + (da - db) * h - k * h * (1.0 - h)
+            }
+        }
+    }
+}
+
+/// The closest point to `p` on a polyline.
+fn nearest_on_path(path: &[Vec3], p: Vec3) -> Vec3 {
+    let segment = |a: Vec3, b: Vec3| {
+        let ba = b - a;
+        let h = ((p - a).dot(ba) / ba.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+        a + ba * h
+    };
+    let nearest = path.windows(2).map(|w| segment(w[0], w[1]));
+    nearest
+        .chain(path.first().copied())
+        .min_by(|a, b| a.distance_squared(p).total_cmp(&b.distance_squared(p)))
+        .unwrap_or(p)
+}
+
+/// A Catmull-Rom spline through `points`, as a polyline with `steps`
+/// points per span. The curve passes through every point.
+pub fn catmull_rom(points: &[Vec3], steps: usize) -> Vec<Vec3> {
+    if points.len() < 2 {
+        return points.to_vec();
+    }
+    let at = |i: isize| points[i.clamp(0, points.len() as isize - 1) as usize];
+    let mut out = vec![];
+    for i in 0..points.len() as isize - 1 {
+        let (p0, p1, p2, p3) = (at(i - 1), at(i), at(i + 1), at(i + 2));
+        for s in 0..steps.max(1) {
+            let t = s as f32 / steps.max(1) as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            out.push(0.5 * (2.0 * p1
+                + (p2 - p0) * t
+                + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+                + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3));
+        }
+    }
+    out.push(points[points.len() - 1]);
+    out
+}
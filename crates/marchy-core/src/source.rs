@@ -0,0 +1,56 @@
+use glam::{IVec3, Vec3};
+use crate::{
+    generator::Perlin,
+    grid::{trilinear, VoxelGrid},
+    sdf::Sdf,
+};
+
+/// Anything densities can be read from at any point, not just at stored
+/// cells: grids, `Sdf` scenes, noise and plain closures alike. Wrap one in
+/// `marchy_mesh::Resampled` to mesh it at whatever resolution suits.
+pub trait DensitySource: Sync {
+    fn sample(&self, x: f32, y: f32, z: f32) -> f32;
+
+    /// The `(min, max)` box the source is defined over, or `None` if it
+    /// goes on forever.
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        None
+    }
+}
+
+/// In grid-local cell coordinates like `fill_sdf`, cell `c` sampled at
+/// `c`, interpolated in between and clamped past the edges.
+impl DensitySource for VoxelGrid {
+    fn sample(&self, x: f32, y: f32, z: f32) -> f32 {
+        let last = self.dims().as_ivec3() - 1;
+        let read = |c: IVec3| {
+            let c = c.clamp(IVec3::ZERO, last).as_uvec3();
+            self.read(c.x, c.y, c.z)
+        };
+        trilinear(Vec3::new(x, y, z) + 0.5, read).unwrap_or(f32::INFINITY)
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((Vec3::ZERO, (self.dims() - 1).as_vec3()))
+    }
+}
+
+/// Raw distances, so the surface sits at an iso level of 0.
+impl DensitySource for Sdf {
+    fn sample(&self, x: f32, y: f32, z: f32) -> f32 {
+        Sdf::sample(self, Vec3::new(x, y, z))
+    }
+}
+
+impl DensitySource for Perlin {
+    fn sample(&self, x: f32, y: f32, z: f32) -> f32 {
+        Perlin::sample(self, Vec3::new(x, y, z))
+    }
+}
+
+/// Closures of a position, like `TerrainConfig::sampler`.
+impl<F: Fn(Vec3) -> f32 + Sync> DensitySource for F {
+    fn sample(&self, x: f32, y: f32, z: f32) -> f32 {
+        self(Vec3::new(x, y, z))
+    }
+}
@@ -0,0 +1,226 @@
+use alloc::{boxed::Box, vec, vec::Vec};
+use glam::UVec3;
+use hashbrown::HashMap;
+use crate::grid::{OutOfBounds, VoxelGrid};
+
+/// Cells per brick edge in a `BrickMap`.
+pub const BRICK: u32 = 8;
+const BRICK_CELLS: usize = (BRICK * BRICK * BRICK) as usize;
+
+/// Somewhere to keep a box of voxels. Dense grids suit small, busy volumes;
+/// `BrickMap` suits large, mostly empty ones, since it only stores the parts
+/// that differ from a background value.
+pub trait VoxelStorage<T: Copy> {
+    fn dims(&self) -> UVec3;
+
+    fn get(&self, p: UVec3) -> Option<T>;
+
+    fn set(&mut self, p: UVec3, val: T) -> Result<(), OutOfBounds>;
+
+    /// The value of every cell outside `regions`, if there is one.
+    fn background(&self) -> Option<T> {
+        None
+    }
+
+    /// Half-open `(min, max)` boxes that together cover every cell that may
+    /// differ from `background`. Callers looking for non-background cells
+    /// only need to visit these.
+    fn regions(&self) -> Vec<(UVec3, UVec3)> {
+        vec![(UVec3::ZERO, self.dims())]
+    }
+
+    /// Approximate bytes used by the cells.
+    fn memory(&self) -> usize;
+}
+
+impl<T: Copy> VoxelStorage<T> for VoxelGrid<T> {
+    fn dims(&self) -> UVec3 {
+        VoxelGrid::dims(self)
+    }
+
+    fn get(&self, p: UVec3) -> Option<T> {
+        self.read(p.x, p.y, p.z)
+    }
+
+    fn set(&mut self, p: UVec3, val: T) -> Result<(), OutOfBounds> {
+        self.write(p.x, p.y, p.z, val)
+    }
+
+    fn memory(&self) -> usize {
+        self.data.len() * size_of::<T>()
+    }
+}
+
+/// Sparse storage: the volume is split into `BRICK`-cubed bricks, and only
+/// bricks holding something other than `background` are allocated.
+#[derive(Clone)]
+pub struct BrickMap<T> {
+    dims: UVec3,
+    pub background: T,
+    bricks: HashMap<UVec3, Box<[T; BRICK_CELLS]>>,
+}
+
+impl<T: Copy + PartialEq> BrickMap<T> {
+    pub fn new(dims: UVec3, background: T) -> Self {
+        BrickMap { dims, background, bricks: HashMap::new() }
+    }
+
+    fn split(p: UVec3) -> (UVec3, usize) {
+        let local = p % BRICK;
+        (p / BRICK, ((local.z * BRICK + local.y) * BRICK + local.x) as usize)
+    }
+
+    pub fn brick_count(&self) -> usize {
+        self.bricks.len()
+    }
+
+    /// Frees bricks that have gone back to being all background.
+    pub fn compact(&mut self) {
+        let bg = self.background;
+        self.bricks.retain(|_, cells| cells.iter().any(|&c| c != bg));
+    }
+
+    pub fn from_grid(grid: &VoxelGrid<T>, background: T) -> Self {
+        let mut map = BrickMap::new(grid.dims(), background);
+        grid.each(|x, y, z, val| {
+            // In bounds by construction.
+            let _ = map.set(UVec3::new(x, y, z), val);
+        });
+        map
+    }
+}
+
+impl<T: Copy + PartialEq> VoxelStorage<T> for BrickMap<T> {
+    fn dims(&self) -> UVec3 {
+        self.dims
+    }
+
+    fn get(&self, p: UVec3) -> Option<T> {
+        if p.cmpge(self.dims).any() {
+            return None;
+        }
+        let (brick, i) = Self::split(p);
+        Some(self.bricks.get(&brick).map_or(self.background, |cells| cells[i]))
+    }
+
+    fn set(&mut self, p: UVec3, val: T) -> Result<(), OutOfBounds> {
+        if p.cmpge(self.dims).any() {
+            return Err(OutOfBounds(p));
+        }
+        let (brick, i) = Self::split(p);
+        match self.bricks.get_mut(&brick) {
+            Some(cells) => cells[i] = val,
+            None if val == self.background => {}
+            None => {
+                let mut cells = Box::new([self.background; BRICK_CELLS]);
+                cells[i] = val;
+                self.bricks.insert(brick, cells);
+            }
+        }
+        Ok(())
+    }
+
+    fn background(&self) -> Option<T> {
+        Some(self.background)
+    }
+
+    fn regions(&self) -> Vec<(UVec3, UVec3)> {
+        self.bricks
+            .keys()
+            .map(|&b| (b * BRICK, ((b + 1) * BRICK).min(self.dims)))
+            .collect()
+    }
+
+    fn memory(&self) -> usize {
+        self.bricks.len() * BRICK_CELLS * size_of::<T>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Not a whole number of bricks on any axis.
+    const DIMS: UVec3 = UVec3::new(20, 9, 13);
+
+    fn pattern(p: UVec3) -> f32 {
+        if (p.x * 7 + p.y * 3 + p.z * 5) % 11 == 0 { -(p.x as f32) - 1.0 } else { 1.0 }
+    }
+
+    fn fill<S: VoxelStorage<f32>>(storage: &mut S) {
+        for z in 0..DIMS.z {
+            for y in 0..DIMS.y {
+                for x in 0..DIMS.x {
+                    let p = UVec3::new(x, y, z);
+                    storage.set(p, pattern(p)).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cells_round_trip_across_brick_edges() {
+        let mut map = BrickMap::new(DIMS, 1.0);
+        let cells = [(7, 0, 0), (8, 0, 0), (7, 7, 7), (8, 8, 8), (19, 8, 12)].map(|(x, y, z)| UVec3::new(x, y, z));
+        for (i, &p) in cells.iter().enumerate() {
+            map.set(p, -(i as f32)).unwrap();
+        }
+        for (i, &p) in cells.iter().enumerate() {
+            assert_eq!(map.get(p), Some(-(i as f32)));
+        }
+        // (7, 0, 0) and (7, 7, 7) share the first brick.
+        assert_eq!(map.brick_count(), 4);
+        assert_eq!(map.set(DIMS, 0.0), Err(OutOfBounds(DIMS)));
+        assert_eq!(map.get(DIMS), None);
+    }
+
+    #[test]
+    fn empty_bricks_read_as_background() {
+        let mut map = BrickMap::new(DIMS, 1.0);
+        // Background writes allocate nothing.
+        map.set(UVec3::new(3, 3, 3), 1.0).unwrap();
+        assert_eq!(map.brick_count(), 0);
+        assert_eq!(map.get(UVec3::new(3, 3, 3)), Some(1.0));
+        assert_eq!(map.get(UVec3::new(19, 8, 12)), Some(1.0));
+
+        map.set(UVec3::new(3, 3, 3), -1.0).unwrap();
+        map.set(UVec3::new(3, 3, 3), 1.0).unwrap();
+        assert_eq!(map.brick_count(), 1);
+        map.compact();
+        assert_eq!(map.brick_count(), 0);
+        assert!(map.regions().is_empty());
+    }
+
+    #[test]
+    fn bricks_match_a_dense_grid() {
+        let mut grid = VoxelGrid::with_dims(DIMS);
+        let mut map = BrickMap::new(DIMS, 1.0);
+        fill(&mut grid);
+        fill(&mut map);
+        let (grid, map): (&dyn VoxelStorage<f32>, &dyn VoxelStorage<f32>) = (&grid, &map);
+        assert_eq!(grid.dims(), map.dims());
+        for z in 0..DIMS.z {
+            for y in 0..DIMS.y {
+                for x in 0..DIMS.x {
+                    let p = UVec3::new(x, y, z);
+                    assert_eq!(grid.get(p), map.get(p), "at {p}");
+                }
+            }
+        }
+        // Everything off the background lies in some region.
+        let covered = |p: UVec3| map.regions().iter().any(|&(lo, hi)| p.cmpge(lo).all() && p.cmplt(hi).all());
+        let mut off = 0;
+        for z in 0..DIMS.z {
+            for y in 0..DIMS.y {
+                for x in 0..DIMS.x {
+                    let p = UVec3::new(x, y, z);
+                    if map.get(p) != map.background() {
+                        assert!(covered(p), "{p} is outside every region");
+                        off += 1;
+                    }
+                }
+            }
+        }
+        assert!(off > 0);
+    }
+}
@@ -0,0 +1,113 @@
+use glam::{BVec3, IVec3, Quat, Vec3};
+
+/// Where a voxel grid sits in the world. In voxel space cell `c` spans
+/// `c .. c + 1`; a voxel-space point `v` is at `origin + rotation * (v *
+/// cell_size)` in the world.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridTransform {
+    /// Where voxel space's origin, the corner of cell zero, lands.
+    pub origin: Vec3,
+    /// World units per cell.
+    pub cell_size: f32,
+    pub rotation: Quat,
+}
+
+impl Default for GridTransform {
+    fn default() -> Self {
+        GridTransform { origin: Vec3::ZERO, cell_size: 1.0, rotation: Quat::IDENTITY }
+    }
+}
+
+impl GridTransform {
+    /// Unit cells with chunk zero's mesh centered on the world origin, so
+    /// cell `x` spans `x - size/2 - 1 .. x - size/2`: the layout chunk
+    /// meshes have always used.
+    pub fn centered(chunk_size: u32) -> Self {
+        GridTransform { origin: Vec3::splat(-(chunk_size as f32 / 2.0 + 1.0)), ..Default::default() }
+    }
+
+    /// The same layout with cells `cell_size` across, grown about the world
+    /// origin.
+    pub fn scaled(self, cell_size: f32) -> Self {
+        GridTransform { origin: self.origin * cell_size / self.cell_size, cell_size, ..self }
+    }
+
+    pub fn to_world(&self, v: Vec3) -> Vec3 {
+        self.origin + self.rotation * (v * self.cell_size)
+    }
+
+    pub fn to_voxel(&self, p: Vec3) -> Vec3 {
+        self.rotation.inverse() * (p - self.origin) / self.cell_size
+    }
+
+    /// A world direction in voxel space, still of unit length if it was.
+    pub fn dir_to_voxel(&self, dir: Vec3) -> Vec3 {
+        self.rotation.inverse() * dir
+    }
+
+    pub fn dir_to_world(&self, dir: Vec3) -> Vec3 {
+        self.rotation * dir
+    }
+
+    pub fn cell_center(&self, cell: IVec3) -> Vec3 {
+        self.to_world(cell.as_vec3() + 0.5)
+    }
+
+    /// The cell containing a world point.
+    pub fn cell_at(&self, p: Vec3) -> IVec3 {
+        self.to_voxel(p).floor().as_ivec3()
+    }
+
+    /// The world-space box around voxel space's `min..max`.
+    pub fn world_bounds(&self, min: Vec3, max: Vec3) -> (Vec3, Vec3) {
+        (0..8)
+            .map(|i| self.to_world(Vec3::select(BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0), max, min)))
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(lo, hi), p| (lo.min(p), hi.max(p)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tilted() -> GridTransform {
+        GridTransform {
+            origin: Vec3::new(3.0, -2.0, 7.5),
+            cell_size: 0.25,
+            rotation: Quat::from_euler(glam::EulerRot::YXZ, 0.7, -0.3, 0.2),
+        }
+    }
+
+    #[test]
+    fn points_and_directions_round_trip() {
+        for t in [GridTransform::default(), GridTransform::centered(32).scaled(0.5), tilted()] {
+            for v in [Vec3::ZERO, Vec3::new(1.5, -4.0, 9.25), Vec3::splat(31.9)] {
+                assert!(t.to_voxel(t.to_world(v)).abs_diff_eq(v, 1e-4), "{t:?} moved {v}");
+            }
+            let dir = Vec3::new(0.3, -0.8, 0.52).normalize();
+            let back = t.dir_to_world(t.dir_to_voxel(dir));
+            assert!(back.abs_diff_eq(dir, 1e-5));
+            assert!((t.dir_to_voxel(dir).length() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn cells_contain_their_centers() {
+        let t = tilted();
+        for cell in [IVec3::ZERO, IVec3::new(-3, 5, 12), IVec3::splat(-1)] {
+            assert_eq!(t.cell_at(t.cell_center(cell)), cell);
+        }
+    }
+
+    #[test]
+    fn centered_grids_scale_about_the_world_origin() {
+        let t = GridTransform::centered(32);
+        // Chunk zero's middle, one cell in, is the world origin.
+        assert_eq!(t.to_world(Vec3::splat(17.0)), Vec3::ZERO);
+        let half = t.scaled(0.5);
+        assert_eq!(half.to_world(Vec3::splat(17.0)), Vec3::ZERO);
+        assert_eq!(half.to_world(Vec3::splat(19.0)), Vec3::ONE);
+        let (lo, hi) = half.world_bounds(Vec3::ZERO, Vec3::splat(32.0));
+        assert_eq!((lo, hi), (Vec3::splat(-8.5), Vec3::splat(7.5)));
+    }
+}
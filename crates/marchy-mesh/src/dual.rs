@@ -0,0 +1,155 @@
+use glam::{IVec3, Mat3, Vec3};
+use std::collections::HashMap;
+use crate::mesh::MeshBuffers;
+
+/// Densities further than this from the iso level are clamped, so infinite
+/// boundary values don't poison the interpolation.
+const CLAMP: f32 = 64.0;
+/// Pull toward the mass point, keeping the QEF stable on flat patches.
+const BIAS: f32 = 0.05;
+
+/// Dual contouring over a `dims`-sized grid of cells. Samples sit at cell
+/// centers; each dual cell (the cube between eight samples) that the surface
+/// crosses gets one vertex, placed by minimizing the distance to the tangent
+/// planes at its edge crossings. That keeps sharp box edges and corners
+/// sharp, where averaging the crossings (surface nets) would round them.
+///
+/// `sample` returns the density of any cell, including just outside the
+/// grid; `None` is air. Quads are emitted for sign changes along edges that
+/// start inside the grid, and given flat normals so edges shade crisply.
+/// Positions are centered like the block meshers': cell `x` is centered on
+/// `x - dims/2 - 0.5`.
+pub fn dual_contour<F>(dims: IVec3, limit: f32, sample: F) -> MeshBuffers
+where F: Fn(IVec3) -> Option<f32> {
+    let f = |p: IVec3| sample(p).map_or(CLAMP, |v| (v - limit).clamp(-CLAMP, CLAMP));
+    let origin = -dims.as_vec3() / 2.0 - 0.5;
+    let pos = |p: IVec3| p.as_vec3() + origin;
+    let gradient = |p: IVec3| {
+        Vec3::new(
+            f(p + IVec3::X) - f(p - IVec3::X),
+            f(p + IVec3::Y) - f(p - IVec3::Y),
+            f(p + IVec3::Z) - f(p - IVec3::Z),
+        ).normalize_or_zero()
+    };
+
+    let mut vertices: HashMap<IVec3, Vec3> = HashMap::new();
+    let mut vertex = |cell: IVec3| {
+        *vertices.entry(cell).or_insert_with(|| place_vertex(cell, &f, &pos, &gradient))
+    };
+
+    let mut out = MeshBuffers::default();
+    for z in 0..dims.z {
+        for y in 0..dims.y {
+            for x in 0..dims.x {
+                let c = IVec3::new(x, y, z);
+                let fc = f(c);
+                for (axis, along) in IVec3::AXES.into_iter().enumerate() {
+                    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                    let next = f(c + along);
+                    if (fc <= 0.0) == (next <= 0.0) {
+                        continue;
+                    }
+                    let (eu, ev) = (IVec3::AXES[u], IVec3::AXES[v]);
+                    // Counter-clockwise seen from +axis; flipped when the
+                    // solid side is ahead.
+                    let mut quad = [c - eu - ev, c - ev, c, c - eu].map(&mut vertex);
+                    if next <= 0.0 {
+                        quad.reverse();
+                    }
+                    push_quad(&mut out, quad);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// The QEF-minimizing point for the dual cell whose min corner sample is at
+/// `cell`, clamped to the cell.
+fn place_vertex(
+    cell: IVec3,
+    f: &impl Fn(IVec3) -> f32,
+    pos: &impl Fn(IVec3) -> Vec3,
+    gradient: &impl Fn(IVec3) -> Vec3,
+) -> Vec3 {
+    let mut points = vec![];
+    let mut normals = vec![];
+    for i in 0..8 {
+        let a = cell + IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1);
+        for axis in IVec3::AXES {
+            let b = a + axis;
+            // Only edges inside this dual cell.
+            if (b - cell).max_element() > 1 {
+                continue;
+            }
+            let (fa, fb) = (f(a), f(b));
+            if (fa <= 0.0) == (fb <= 0.0) {
+                continue;
+            }
+            let t = fa / (fa - fb);
+            points.push(pos(a).lerp(pos(b), t));
+            normals.push(gradient(a).lerp(gradient(b), t).normalize_or_zero());
+        }
+    }
+    if points.is_empty() {
+        return pos(cell) + 0.5;
+    }
+    let mass = points.iter().sum::<Vec3>() / points.len() as f32;
+
+    // Least squares on sum((n . (x - p))^2), solved relative to the mass
+    // point with a small bias toward it.
+    let mut ata = Mat3::from_diagonal(Vec3::splat(BIAS));
+    let mut atb = Vec3::ZERO;
+    for (p, n) in points.iter().zip(&normals) {
+        ata += Mat3::from_cols(*n * n.x, *n * n.y, *n * n.z);
+        atb += *n * n.dot(*p - mass);
+    }
+    let x = if ata.determinant().abs() > 1e-6 { mass + ata.inverse() * atb } else { mass };
+    x.clamp(pos(cell), pos(cell + IVec3::ONE))
+}
+
+fn push_quad(out: &mut MeshBuffers, quad: [Vec3; 4]) {
+    let [a, b, c, d] = quad;
+    let normal = (b - a).cross(c - a).try_normalize()
+        .or((c - a).cross(d - a).try_normalize())
+        .unwrap_or(Vec3::Y);
+    let base = out.positions.len() as u32;
+    for p in quad {
+        out.positions.push(p.to_array());
+        out.normals.push(normal.to_array());
+    }
+    out.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sphere_is_closed_and_near_its_surface() {
+        let dims = IVec3::splat(16);
+        let radius = 5.0;
+        // Cell `c` is centered on `c - dims/2 - 0.5` in mesh space.
+        let center = dims.as_vec3() / 2.0 + 0.5;
+        let mesh = dual_contour(dims, 0.0, |c| Some((c.as_vec3() - center).length() - radius));
+        assert!(!mesh.is_empty());
+        for p in &mesh.positions {
+            let d = Vec3::from(*p).length() - radius;
+            assert!(d.abs() <= 1.0, "vertex {p:?} is {d} from the surface");
+        }
+
+        // Closed and consistently wound: every directed edge is matched by
+        // its reverse.
+        let key = |i: u32| mesh.positions[i as usize].map(f32::to_bits);
+        let mut edges: HashMap<([u32; 3], [u32; 3]), i32> = HashMap::new();
+        for tri in mesh.indices.chunks(3) {
+            for k in 0..3 {
+                let (a, b) = (key(tri[k]), key(tri[(k + 1) % 3]));
+                *edges.entry((a, b)).or_default() += 1;
+            }
+        }
+        for (&(a, b), &n) in &edges {
+            assert_eq!(edges.get(&(b, a)).copied().unwrap_or(0), n, "open edge {a:?} -> {b:?}");
+        }
+    }
+}
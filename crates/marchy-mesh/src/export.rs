@@ -0,0 +1,135 @@
+use glam::{Affine3A, Vec3, Vec3A};
+use std::io::{self, Write};
+use crate::mesh::MeshBuffers;
+
+impl MeshBuffers {
+    /// Appends another mesh, moved by `transform`.
+    pub fn append(&mut self, other: &MeshBuffers, transform: &Affine3A) {
+        let base = self.positions.len() as u32;
+        self.positions.extend(
+            other.positions.iter().map(|&p| transform.transform_point3(p.into()).to_array())
+        );
+        // Through the inverse transpose, so non-uniform scale keeps them
+        // perpendicular.
+        let normal = transform.matrix3.inverse().transpose();
+        self.normals.extend(
+            other.normals.iter().map(|&n| (normal * Vec3A::from(n)).normalize_or_zero().to_array())
+        );
+        if !self.colors.is_empty() || !other.colors.is_empty() {
+            // Uncolored parts are white.
+            self.colors.resize(base as usize, [1.0; 4]);
+            self.colors.extend(&other.colors);
+            self.colors.resize(self.positions.len(), [1.0; 4]);
+        }
+        self.indices.extend(other.indices.iter().map(|i| i + base));
+    }
+
+    pub fn write_obj(&self, w: &mut impl Write) -> io::Result<()> {
+        writeln!(w, "# marchy export")?;
+        for [x, y, z] in &self.positions {
+            writeln!(w, "v {x} {y} {z}")?;
+        }
+        for [x, y, z] in &self.normals {
+            writeln!(w, "vn {x} {y} {z}")?;
+        }
+        let normals = self.normals.len() == self.positions.len();
+        for tri in self.indices.chunks_exact(3) {
+            let [a, b, c] = [tri[0] + 1, tri[1] + 1, tri[2] + 1];
+            if normals {
+                writeln!(w, "f {a}//{a} {b}//{b} {c}//{c}")?;
+            } else {
+                writeln!(w, "f {a} {b} {c}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A self-contained `.gltf`: one mesh, with its buffer embedded as a
+    /// base64 data URI.
+    pub fn write_gltf(&self, w: &mut impl Write) -> io::Result<()> {
+        let normals = self.normals.len() == self.positions.len();
+        let mut buf = vec![];
+        for p in &self.positions {
+            buf.extend(p.iter().flat_map(|v| v.to_le_bytes()));
+        }
+        let normal_offset = buf.len();
+        if normals {
+            for n in &self.normals {
+                buf.extend(n.iter().flat_map(|v| v.to_le_bytes()));
+            }
+        }
+        let index_offset = buf.len();
+        buf.extend(self.indices.iter().flat_map(|i| i.to_le_bytes()));
+
+        let (min, max) = self.positions.iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(lo, hi), &p| (lo.min(p.into()), hi.max(p.into())),
+        );
+        let (min, max) = if self.positions.is_empty() { (Vec3::ZERO, Vec3::ZERO) } else { (min, max) };
+        let vec3 = |v: Vec3| format!("[{},{},{}]", v.x, v.y, v.z);
+        let count = self.positions.len();
+
+        let mut views = vec![format!(
+            r#"{{"buffer":0,"byteOffset":0,"byteLength":{normal_offset},"target":34962}}"#
+        )];
+        let mut accessors = vec![format!(
+            r#"{{"bufferView":0,"componentType":5126,"count":{count},"type":"VEC3","min":{},"max":{}}}"#,
+            vec3(min),
+            vec3(max),
+        )];
+        let mut attributes = String::from(r#""POSITION":0"#);
+        if normals {
+            views.push(format!(
+                r#"{{"buffer":0,"byteOffset":{normal_offset},"byteLength":{},"target":34962}}"#,
+                index_offset - normal_offset,
+            ));
+            accessors.push(format!(
+                r#"{{"bufferView":1,"componentType":5126,"count":{count},"type":"VEC3"}}"#
+            ));
+            attributes.push_str(r#","NORMAL":1"#);
+        }
+        views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{index_offset},"byteLength":{},"target":34963}}"#,
+            buf.len() - index_offset,
+        ));
+        accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":5125,"count":{},"type":"SCALAR"}}"#,
+            views.len() - 1,
+            self.indices.len(),
+        ));
+
+        write!(
+            w,
+            concat!(
+                r#"{{"asset":{{"version":"2.0","generator":"marchy"}},"#,
+                r#""scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"#,
+                r#""meshes":[{{"primitives":[{{"attributes":{{{}}},"indices":{}}}]}}],"#,
+                r#""buffers":[{{"byteLength":{},"uri":"data:application/octet-stream;base64,{}"}}],"#,
+                r#""bufferViews":[{}],"accessors":[{}]}}"#,
+            ),
+            attributes,
+            accessors.len() - 1,
+            buf.len(),
+            base64(&buf),
+            views.join(","),
+            accessors.join(","),
+        )
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from_be_bytes([0, b[0], b[1], b[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[((n >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,14 @@
+//! Meshing for `marchy_core` density fields: the block meshers (naive,
+//! culled, greedy), dual contouring, and sampling any `DensitySource` into
+//! something they can march. Output is plain `MeshBuffers`, with OBJ and
+//! glTF writers and decimation.
+
+pub mod dual;
+pub mod export;
+pub mod mesh;
+pub mod simplify;
+pub mod source;
+
+pub use mesh::{build_buffers, Boundary, MeshBuffers, MeshingStrategy};
+pub use simplify::Simplify;
+pub use source::{DensitySource, Resampled};
@@ -0,0 +1,518 @@
+use glam::{IVec3, UVec3, Vec3, Vec4};
+use marchy_core::{occupancy::Occupancy, storage::VoxelStorage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::dual::dual_contour;
+
+/// Cube faces as four corners (counter-clockwise seen from outside), offset
+/// from a cell's max corner: voxel `(x, y, z)` spans `x-1..x` on each axis.
+const FACES: [(IVec3, [[f32; 3]; 4]); 6] = [
+    // Right
+    (IVec3::X, [[0.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, -1.0, -1.0], [0.0, 0.0, -1.0]]),
+    // Left
+    (IVec3::NEG_X, [[-1.0, 0.0, -1.0], [-1.0, -1.0, -1.0], [-1.0, -1.0, 0.0], [-1.0, 0.0, 0.0]]),
+    // Top
+    (IVec3::Y, [[-1.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, -1.0], [-1.0, 0.0, -1.0]]),
+    // Bottom
+    (IVec3::NEG_Y, [[-1.0, -1.0, -1.0], [0.0, -1.0, -1.0], [0.0, -1.0, 0.0], [-1.0, -1.0, 0.0]]),
+    // Front
+    (IVec3::Z, [[-1.0, 0.0, 0.0], [-1.0, -1.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, 0.0]]),
+    // Back
+    (IVec3::NEG_Z, [[0.0, 0.0, -1.0], [0.0, -1.0, -1.0], [-1.0, -1.0, -1.0], [-1.0, 0.0, -1.0]]),
+];
+
+/// Collects triangles, reusing an existing vertex whenever a position repeats.
+#[derive(Default)]
+struct MeshBuilder {
+    verts: Vec<[f32; 3]>,
+    indices: Vec<u32>,
+    lookup: HashMap<[u32; 3], u32>,
+}
+
+impl MeshBuilder {
+    fn vertex(&mut self, pos: [f32; 3]) -> u32 {
+        let key = pos.map(f32::to_bits);
+        *self.lookup.entry(key).or_insert_with(|| {
+            self.verts.push(pos);
+            self.verts.len() as u32 - 1
+        })
+    }
+
+    fn quad(&mut self, corners: [[f32; 3]; 4]) {
+        let [a, b, c, d] = corners.map(|p| self.vertex(p));
+        self.indices.extend_from_slice(&[a, b, c, a, c, d]);
+    }
+
+    /// Concatenates builders in order, re-sharing vertices along their seams.
+    fn merge(parts: Vec<MeshBuilder>) -> MeshBuilder {
+        let mut iter = parts.into_iter();
+        let mut out = iter.next().unwrap_or_default();
+        for part in iter {
+            let remap: Vec<u32> = part.verts.iter().map(|&p| out.vertex(p)).collect();
+            out.indices.extend(part.indices.iter().map(|&i| remap[i as usize]));
+        }
+        out
+    }
+
+    fn build(self) -> MeshBuffers {
+        MeshBuffers { positions: self.verts, indices: self.indices, ..Default::default() }
+    }
+}
+
+/// Plain indexed triangle-list buffers: what meshers produce, independent of
+/// any engine's mesh type.
+#[derive(Clone, Debug, Default)]
+pub struct MeshBuffers {
+    pub positions: Vec<[f32; 3]>,
+    /// One per position, or empty to have them computed.
+    pub normals: Vec<[f32; 3]>,
+    /// Linear RGBA, one per position, or empty for none.
+    pub colors: Vec<[f32; 4]>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshBuffers {
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Replaces the normals with the gradient of the density field, for
+    /// shading that follows the isosurface rather than the faces. Positions
+    /// are centered on a `dims` grid as the meshers emit them; `density`
+    /// gives any cell, including just outside the grid. Vertices where the
+    /// gradient vanishes keep the face normal.
+    pub fn smooth_normals(&mut self, dims: UVec3, density: impl Fn(IVec3) -> f32) {
+        if self.normals.len() != self.positions.len() {
+            self.normals = self.face_normals();
+        }
+        // Trilinear density at a point in cell coordinates (cell centers on
+        // the integers).
+        let trilinear = |g: Vec3| {
+            let c = g.floor();
+            let t = g - c;
+            let c = c.as_ivec3();
+            let mut sum = 0.0;
+            for i in 0..8 {
+                let o = IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1);
+                let w = Vec3::select(o.cmpeq(IVec3::ONE), t, 1.0 - t);
+                sum += w.x * w.y * w.z * density(c + o);
+            }
+            sum
+        };
+        let origin = dims.as_vec3() / 2.0 + 0.5;
+        for (p, n) in self.positions.iter().zip(&mut self.normals) {
+            let g = Vec3::from(*p) + origin;
+            let grad = Vec3::new(
+                trilinear(g + Vec3::X) - trilinear(g - Vec3::X),
+                trilinear(g + Vec3::Y) - trilinear(g - Vec3::Y),
+                trilinear(g + Vec3::Z) - trilinear(g - Vec3::Z),
+            );
+            if let Some(grad) = grad.try_normalize() {
+                *n = grad.to_array();
+            }
+        }
+    }
+
+    /// Face-averaged normals, as `Mesh::compute_normals` would give.
+    fn face_normals(&self) -> Vec<[f32; 3]> {
+        let mut normals = vec![Vec3::ZERO; self.positions.len()];
+        for tri in self.indices.chunks_exact(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| Vec3::from(self.positions[i as usize]));
+            let n = (b - a).cross(c - a);
+            for &i in tri {
+                normals[i as usize] += n;
+            }
+        }
+        normals.into_iter().map(|n| n.normalize_or_zero().to_array()).collect()
+    }
+
+    /// Colors each vertex by averaging `color` over the (up to eight) cells
+    /// around it; cells giving `None`, such as air, are skipped. Positions
+    /// are centered on a `dims` grid as the meshers emit them.
+    pub fn paint(&mut self, dims: UVec3, color: impl Fn(IVec3) -> Option<[f32; 4]>) {
+        let origin = dims.as_vec3() / 2.0 + 0.5;
+        self.colors = self.positions.iter().map(|p| {
+            // Corners of block meshes sit on half-integers, between cells.
+            let base = (Vec3::from(*p) + origin - 0.5).floor().as_ivec3();
+            let (mut sum, mut n) = (Vec4::ZERO, 0.0);
+            for i in 0..8 {
+                if let Some(c) = color(base + IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1)) {
+                    sum += Vec4::from(c);
+                    n += 1.0;
+                }
+            }
+            if n > 0.0 { (sum / n).to_array() } else { [1.0; 4] }
+        }).collect();
+    }
+
+    /// Darkens vertex colors by ambient occlusion: the fraction of solid
+    /// cells in the `2 * radius` cube around each vertex. Half solid is a
+    /// flat surface and stays lit; crevices and cave corners darken, by up
+    /// to `strength` when fully enclosed. Larger radii catch broader
+    /// occluders at the cost of more samples.
+    pub fn bake_ao(&mut self, dims: UVec3, radius: u32, strength: f32, solid: impl Fn(IVec3) -> bool) {
+        if radius == 0 {
+            return;
+        }
+        self.colors.resize(self.positions.len(), [1.0; 4]);
+        let origin = dims.as_vec3() / 2.0 + 0.5;
+        let r = radius as i32;
+        let total = (2 * r).pow(3) as f32;
+        for (p, color) in self.positions.iter().zip(&mut self.colors) {
+            let base = (Vec3::from(*p) + origin - 0.5).floor().as_ivec3();
+            let mut count = 0;
+            for z in 1 - r..=r {
+                for y in 1 - r..=r {
+                    for x in 1 - r..=r {
+                        count += solid(base + IVec3::new(x, y, z)) as u32;
+                    }
+                }
+            }
+            let occlusion = ((count as f32 / total - 0.5) * 2.0).clamp(0.0, 1.0);
+            let light = 1.0 - strength * occlusion;
+            for c in &mut color[..3] {
+                *c *= light;
+            }
+        }
+    }
+
+    /// Scales positions by `scale` then shifts them by `offset`.
+    pub fn transform(&mut self, scale: f32, offset: Vec3) {
+        for p in &mut self.positions {
+            *p = (Vec3::from(*p) * scale + offset).to_array();
+        }
+    }
+
+    /// Hangs a `depth`-tall curtain below every open edge lying on one of
+    /// the `faces` of the box `lo..hi`, hiding the gap until the mesh on
+    /// the other side shows up.
+    pub fn add_skirts(&mut self, faces: &[IVec3], lo: Vec3, hi: Vec3, depth: f32) {
+        let key = |i: u32| self.positions[i as usize].map(f32::to_bits);
+        let mut edges: HashMap<([u32; 3], [u32; 3]), (u32, u32, u32)> = HashMap::new();
+        for tri in self.indices.chunks_exact(3) {
+            for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+                let (ka, kb) = (key(a), key(b));
+                let edge = if ka < kb { (ka, kb) } else { (kb, ka) };
+                edges.entry(edge).or_insert((0, a, b)).0 += 1;
+            }
+        }
+        let on_face = |p: Vec3, face: IVec3| {
+            let axis = face.abs().max_position();
+            let plane = if face[axis] > 0 { hi[axis] } else { lo[axis] };
+            (p[axis] - plane).abs() < 1e-3
+        };
+        for (count, a, b) in edges.into_values() {
+            let (pa, pb) = (Vec3::from(self.positions[a as usize]), Vec3::from(self.positions[b as usize]));
+            if count != 1 || !faces.iter().any(|&f| on_face(pa, f) && on_face(pb, f)) {
+                continue;
+            }
+            let base = self.positions.len() as u32;
+            for i in [a, b] {
+                let p = Vec3::from(self.positions[i as usize]) - Vec3::Y * depth;
+                self.positions.push(p.to_array());
+                if !self.normals.is_empty() {
+                    self.normals.push(self.normals[i as usize]);
+                }
+                if !self.colors.is_empty() {
+                    self.colors.push(self.colors[i as usize]);
+                }
+            }
+            // Wound like the neighboring triangle that would share the edge.
+            self.indices.extend_from_slice(&[b, a, base, b, base, base + 1]);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MeshingStrategy {
+    /// Six faces per solid voxel.
+    Naive,
+    /// Skips faces shared by two solid voxels.
+    #[default]
+    Culled,
+    /// Culled, then merges coplanar neighboring faces into larger quads.
+    Greedy,
+    /// A smooth surface from dual contouring, keeping sharp edges sharp.
+    DualContour,
+}
+
+/// What the mesher sees past the grid's edges, where no neighbor chunk
+/// supplies values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Boundary {
+    /// Air: solid cells on the edge get capped with faces.
+    #[default]
+    Open,
+    /// Solid wall: the surface is left open where it meets the edge.
+    Solid,
+    /// Wraps to the opposite edge, so the mesh tiles seamlessly.
+    Periodic,
+}
+
+impl Boundary {
+    /// The density for a cell outside `vox`, or `None` for air.
+    pub fn resolve<S: VoxelStorage<f32> + ?Sized>(self, vox: &S, p: IVec3) -> Option<f32> {
+        match self {
+            Boundary::Open => None,
+            Boundary::Solid => Some(f32::NEG_INFINITY),
+            Boundary::Periodic => vox.get(p.rem_euclid(vox.dims().as_ivec3()).as_uvec3()),
+        }
+    }
+}
+
+/// Meshes the solid (`<= limit`) cells of `vox`. `outside` supplies values for
+/// cells just past the grid edge (e.g. from a neighboring chunk); `None`
+/// counts as empty, so boundary faces are kept. With sparse storage whose
+/// background is empty, only its occupied regions are visited (greedy
+/// meshing still sweeps the whole volume).
+///
+/// With the `parallel` feature, Z slabs (or for greedy, face directions) are
+/// meshed on rayon's pool and merged in order, so the output is identical to
+/// a single-threaded build.
+pub fn build_buffers<S, F>(vox: &S, limit: f32, strategy: MeshingStrategy, outside: F) -> MeshBuffers
+where S: VoxelStorage<f32> + Sync + ?Sized, F: Fn(IVec3) -> Option<f32> + Sync {
+    build_buffers_occupied(vox, limit, strategy, outside, None, None)
+}
+
+/// `build_buffers`, with an `Occupancy` of `vox` at `limit` letting naive
+/// and culled meshing skip empty bricks (and, when culled, buried solid
+/// ones) even in dense storage. Given each cell's `material` id, greedy
+/// meshing only merges faces of the same material.
+pub fn build_buffers_occupied<S, F>(
+    vox: &S,
+    limit: f32,
+    strategy: MeshingStrategy,
+    outside: F,
+    occupancy: Option<&Occupancy>,
+    material: Option<&(dyn Fn(IVec3) -> u8 + Sync)>,
+) -> MeshBuffers
+where S: VoxelStorage<f32> + Sync + ?Sized, F: Fn(IVec3) -> Option<f32> + Sync {
+    let dims = vox.dims().as_ivec3();
+    let o = -(vox.dims().as_vec3() / 2.0);
+
+    let value = |p: IVec3| {
+        let inside = p.cmpge(IVec3::ZERO).all() && p.cmplt(dims).all();
+        if inside { vox.get(p.as_uvec3()) } else { outside(p) }
+    };
+    let solid = |p: IVec3| value(p).is_some_and(|v| v <= limit);
+
+    if strategy == MeshingStrategy::DualContour {
+        return dual_contour(dims, limit, value);
+    }
+
+    if strategy == MeshingStrategy::Greedy {
+        let material = |p: IVec3| material.map_or(0, |m| m(p));
+        let parts = par_map(&FACES, |&(normal, corners)| greedy(normal, corners, dims, o, &solid, &material));
+        return MeshBuilder::merge(parts).build();
+    }
+
+    let occupancy = occupancy.filter(|o| o.iso() == limit && o.dims() == vox.dims());
+    let regions = match (occupancy, vox.background()) {
+        (Some(o), _) => o.regions(strategy == MeshingStrategy::Culled),
+        (None, Some(bg)) if bg > limit => vox.regions(),
+        _ => vec![(UVec3::ZERO, vox.dims())],
+    };
+    let slabs: Vec<_> = regions
+        .into_iter()
+        .flat_map(|(min, max)| (min.z..max.z).map(move |z| (min.with_z(z), max.with_z(z + 1))))
+        .collect();
+
+    let parts = par_map(&slabs, |&(min, max)| {
+        let mut builder = MeshBuilder::default();
+        for z in min.z..max.z {
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    let cell = IVec3::new(x as i32, y as i32, z as i32);
+                    if !solid(cell) {
+                        continue;
+                    }
+                    for (normal, corners) in FACES {
+                        if strategy == MeshingStrategy::Culled && solid(cell + normal) {
+                            continue;
+                        }
+                        face(&mut builder, corners, cell, cell, o);
+                    }
+                }
+            }
+        }
+        builder
+    });
+
+    MeshBuilder::merge(parts).build()
+}
+
+// The browser has no threads to give rayon, so wasm builds mesh in order
+// even with `parallel` on.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+fn par_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    use rayon::prelude::*;
+    items.par_iter().map(f).collect()
+}
+
+#[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+fn par_map<T, R>(items: &[T], f: impl Fn(&T) -> R) -> Vec<R> {
+    items.iter().map(f).collect()
+}
+
+/// Emits one face covering the cells `start..=end` (equal along the normal).
+/// Corner offsets of -1 land on the min edge of `start`, 0 on the max edge of
+/// `end`, with `o` shifting the grid so it is centered on the origin.
+fn face(builder: &mut MeshBuilder, corners: [[f32; 3]; 4], start: IVec3, end: IVec3, o: Vec3) {
+    builder.quad(corners.map(|c| {
+        std::array::from_fn(|i| {
+            if c[i] < 0.0 { start[i] as f32 + o[i] - 1.0 } else { end[i] as f32 + o[i] }
+        })
+    }));
+}
+
+/// Greedy quads for the faces pointing along `normal`, each covering faces
+/// of one `material`.
+fn greedy<S, M>(normal: IVec3, corners: [[f32; 3]; 4], dims: IVec3, o: Vec3, solid: S, material: M) -> MeshBuilder
+where S: Fn(IVec3) -> bool, M: Fn(IVec3) -> u8 {
+    let mut builder = MeshBuilder::default();
+    let axis = if normal.x != 0 { 0 } else if normal.y != 0 { 1 } else { 2 };
+    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+    let (n, nu, nv) = (dims[axis], dims[u], dims[v]);
+    let idx = |i: i32, j: i32| (j * nu + i) as usize;
+    // The material of each exposed face in the slice.
+    let mut mask = vec![None; (nu * nv) as usize];
+    let cell_at = |d: i32, i: i32, j: i32| {
+        let mut c = IVec3::ZERO;
+        c[axis] = d;
+        c[u] = i;
+        c[v] = j;
+        c
+    };
+
+    for d in 0..n {
+        for j in 0..nv {
+            for i in 0..nu {
+                let cell = cell_at(d, i, j);
+                mask[idx(i, j)] = (solid(cell) && !solid(cell + normal)).then(|| material(cell));
+            }
+        }
+
+        for j in 0..nv {
+            let mut i = 0;
+            while i < nu {
+                let m = mask[idx(i, j)];
+                if m.is_none() {
+                    i += 1;
+                    continue;
+                }
+                let mut w = 1;
+                while i + w < nu && mask[idx(i + w, j)] == m {
+                    w += 1;
+                }
+                let mut h = 1;
+                while j + h < nv && (0..w).all(|k| mask[idx(i + k, j + h)] == m) {
+                    h += 1;
+                }
+                for jj in j..j + h {
+                    for k in i..i + w {
+                        mask[idx(k, jj)] = None;
+                    }
+                }
+                face(&mut builder, corners, cell_at(d, i, j), cell_at(d, i + w - 1, j + h - 1), o);
+                i += w;
+            }
+        }
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use marchy_core::VoxelGrid;
+
+    /// A solid `dims` box.
+    fn solid_box(dims: UVec3) -> VoxelGrid {
+        let mut grid = VoxelGrid::with_dims(dims);
+        grid.data.fill(-1.0);
+        grid
+    }
+
+    #[test]
+    fn culling_drops_faces_between_solid_cells() {
+        let faces = |grid: &VoxelGrid, strategy| build_buffers(grid, 0.0, strategy, |_| None).indices.len() / 6;
+        let pair = solid_box(UVec3::new(2, 1, 1));
+        assert_eq!(faces(&pair, MeshingStrategy::Naive), 12);
+        assert_eq!(faces(&pair, MeshingStrategy::Culled), 10);
+
+        // A buried cell shows nothing; its neighbors' outer faces remain.
+        let block = solid_box(UVec3::splat(3));
+        assert_eq!(faces(&block, MeshingStrategy::Naive), 27 * 6);
+        assert_eq!(faces(&block, MeshingStrategy::Culled), 6 * 9);
+        // Faces toward air outside the grid are kept, toward solid dropped.
+        let walled = build_buffers(&pair, 0.0, MeshingStrategy::Culled, |p| (p.x < 0).then_some(-1.0));
+        assert_eq!(walled.indices.len() / 6, 9);
+    }
+
+    #[test]
+    fn boundaries_decide_the_faces_on_the_grid_edge() {
+        let culled = |grid: &VoxelGrid, boundary: Boundary| {
+            build_buffers(grid, 0.0, MeshingStrategy::Culled, |p| boundary.resolve(grid, p)).indices.len() / 6
+        };
+        let cube = solid_box(UVec3::splat(2));
+        assert_eq!(culled(&cube, Boundary::Open), 6 * 4);
+        assert_eq!(culled(&cube, Boundary::Solid), 0);
+
+        // Solid below y = 2 of 4: wrapping, the sides meet solid and the
+        // bottom meets the air at the top.
+        let mut slab = VoxelGrid::new(4);
+        slab.map(|_, y, _, _| if y < 2 { -1.0 } else { 1.0 });
+        assert_eq!(Boundary::Periodic.resolve(&slab, IVec3::new(-1, 0, 5)), Some(-1.0));
+        assert_eq!(Boundary::Periodic.resolve(&slab, IVec3::new(0, -1, 0)), Some(1.0));
+        assert_eq!(culled(&slab, Boundary::Periodic), 2 * 16);
+        assert_eq!(culled(&slab, Boundary::Open), 2 * 16 + 4 * 8);
+    }
+
+    #[test]
+    fn shared_corners_are_emitted_once() {
+        let mut builder = MeshBuilder::default();
+        builder.quad([[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]);
+        builder.quad([[1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [2.0, 1.0, 0.0], [1.0, 1.0, 0.0]]);
+        let buffers = builder.build();
+        assert_eq!(buffers.positions.len(), 6);
+        assert_eq!(buffers.indices, [0, 1, 2, 0, 2, 3, 1, 4, 5, 1, 5, 2]);
+        // Both quads point at the one copy of the edge they share.
+        assert_eq!(buffers.positions[1], [1.0, 0.0, 0.0]);
+        assert_eq!(buffers.positions[2], [1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn merged_parts_share_their_seams() {
+        let mut a = MeshBuilder::default();
+        a.quad([[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]);
+        let mut b = MeshBuilder::default();
+        b.quad([[1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [2.0, 1.0, 0.0], [1.0, 1.0, 0.0]]);
+        let buffers = MeshBuilder::merge(vec![a, b]).build();
+        assert_eq!(buffers.positions.len(), 6);
+        assert_eq!(&buffers.indices[6..], [1, 4, 5, 1, 5, 2]);
+    }
+
+    #[test]
+    fn a_cube_has_eight_corners() {
+        let buffers = build_buffers(&solid_box(UVec3::ONE), 0.0, MeshingStrategy::Naive, |_| None);
+        assert_eq!(buffers.positions.len(), 8);
+        assert_eq!(buffers.indices.len(), 36);
+        // Every corner is one vertex, used by each of its three faces.
+        for corner in 0..8 {
+            assert!(buffers.indices.iter().filter(|&&i| i == corner).count() >= 3);
+        }
+    }
+
+    #[test]
+    fn greedy_keeps_materials_apart() {
+        let grid = solid_box(UVec3::new(2, 1, 1));
+        let same = build_buffers(&grid, 0.0, MeshingStrategy::Greedy, |_| None);
+        // One quad per side of the box.
+        assert_eq!(same.indices.len(), 6 * 6);
+        let halves: &(dyn Fn(IVec3) -> u8 + Sync) = &|p| p.x as u8;
+        let split = build_buffers_occupied(&grid, 0.0, MeshingStrategy::Greedy, |_| None, None, Some(halves));
+        // The four sides running along x split where the material changes.
+        assert_eq!(split.indices.len(), (2 + 4 * 2) * 6);
+    }
+}
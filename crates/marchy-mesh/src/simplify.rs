@@ -0,0 +1,148 @@
+use glam::{IVec3, Vec3, Vec4};
+use std::collections::{HashMap, HashSet};
+use crate::mesh::MeshBuffers;
+
+/// How far `MeshBuffers::simplify` goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Simplify {
+    /// Merge vertices in cells this many mesh units across, which is about
+    /// as far as any of them moves.
+    Error(f32),
+    /// Coarsen until at most this many triangles are left (or the cells
+    /// reach 64 units).
+    Budget(usize),
+}
+
+impl MeshBuffers {
+    /// A decimated copy. Vertices `pinned` says to keep stay where they
+    /// are, so chunk meshes can hold their seams with their neighbors.
+    pub fn simplify(&self, target: Simplify, pinned: impl Fn(Vec3) -> bool) -> MeshBuffers {
+        match target {
+            Simplify::Error(cell) => self.cluster(cell, &pinned),
+            Simplify::Budget(budget) => {
+                let (mut out, mut cell) = (self.clone(), 1.0);
+                while out.indices.len() / 3 > budget && cell <= 64.0 {
+                    out = self.cluster(cell, &pinned);
+                    cell *= 2.0;
+                }
+                out
+            }
+        }
+    }
+
+    /// Vertex clustering: every vertex not `pinned` moves to the mean of the
+    /// others in its `cell`-sized grid cell, taking their mean normal and
+    /// color, and triangles that collapse are dropped. Merging can't tell
+    /// flat-shaded faces apart, so they come out smooth.
+    pub fn cluster(&self, cell: f32, pinned: impl Fn(Vec3) -> bool) -> MeshBuffers {
+        let cell = cell.max(1e-3);
+        let n = self.positions.len();
+        let (has_normals, has_colors) = (self.normals.len() == n, self.colors.len() == n);
+        let mut clusters: HashMap<IVec3, u32> = HashMap::new();
+        // Per output vertex: summed position, normal and color, and count.
+        let mut sums: Vec<(Vec3, Vec3, Vec4, f32)> = vec![];
+        let remap: Vec<u32> = (0..n)
+            .map(|i| {
+                let p = Vec3::from(self.positions[i]);
+                let normal = if has_normals { Vec3::from(self.normals[i]) } else { Vec3::ZERO };
+                let color = if has_colors { Vec4::from(self.colors[i]) } else { Vec4::ZERO };
+                let next = sums.len() as u32;
+                let out = if pinned(p) {
+                    next
+                } else {
+                    *clusters.entry((p / cell).floor().as_ivec3()).or_insert(next)
+                };
+                if out == next {
+                    sums.push((Vec3::ZERO, Vec3::ZERO, Vec4::ZERO, 0.0));
+                }
+                let sum = &mut sums[out as usize];
+                *sum = (sum.0 + p, sum.1 + normal, sum.2 + color, sum.3 + 1.0);
+                out
+            })
+            .collect();
+
+        let mut out = MeshBuffers {
+            positions: sums.iter().map(|s| (s.0 / s.3).to_array()).collect(),
+            ..Default::default()
+        };
+        if has_normals {
+            out.normals = sums.iter().map(|s| s.1.normalize_or(Vec3::Y).to_array()).collect();
+        }
+        if has_colors {
+            out.colors = sums.iter().map(|s| (s.2 / s.3).to_array()).collect();
+        }
+        let mut seen = HashSet::new();
+        for tri in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| remap[tri[i] as usize]);
+            if a == b || b == c || a == c {
+                continue;
+            }
+            // Rotated to start at the smallest index, so duplicates match
+            // whichever corner they start from.
+            let key = match a.min(b).min(c) {
+                m if m == a => [a, b, c],
+                m if m == b => [b, c, a],
+                _ => [c, a, b],
+            };
+            if seen.insert(key) {
+                out.indices.extend(key);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{build_buffers, MeshingStrategy};
+    use marchy_core::VoxelGrid;
+
+    fn sphere() -> MeshBuffers {
+        let mut grid = VoxelGrid::new(24);
+        grid.map(|x, y, z, _| Vec3::new(x as f32, y as f32, z as f32).distance(Vec3::splat(12.0)) - 9.0);
+        build_buffers(&grid, 0.0, MeshingStrategy::Culled, |_| None)
+    }
+
+    fn triangles(mesh: &MeshBuffers) -> usize {
+        mesh.indices.len() / 3
+    }
+
+    #[test]
+    fn clustering_coarsens_without_moving_far() {
+        let mesh = sphere();
+        let cell = 4.0;
+        let coarse = mesh.simplify(Simplify::Error(cell), |_| false);
+        assert!(triangles(&coarse) * 4 < triangles(&mesh), "{} of {}", triangles(&coarse), triangles(&mesh));
+        assert!(coarse.indices.iter().all(|&i| (i as usize) < coarse.positions.len()));
+        // Each vertex is a mean of originals in one cell, so near them.
+        for p in &coarse.positions {
+            let p = Vec3::from(*p);
+            let near = mesh.positions.iter().any(|&q| p.distance(Vec3::from(q)) <= cell * 3f32.sqrt());
+            assert!(near, "{p} is far from the original mesh");
+        }
+    }
+
+    #[test]
+    fn pinned_vertices_stay_put() {
+        let mesh = sphere();
+        let pinned = |p: Vec3| p.x > 0.0;
+        let coarse = mesh.simplify(Simplify::Error(4.0), pinned);
+        let kept: HashSet<[u32; 3]> = coarse.positions.iter().map(|p| p.map(f32::to_bits)).collect();
+        for p in mesh.positions.iter().filter(|&&p| pinned(Vec3::from(p))) {
+            assert!(kept.contains(&p.map(f32::to_bits)), "pinned {p:?} moved");
+        }
+        let all = mesh.simplify(Simplify::Error(4.0), |_| true);
+        assert_eq!(all.positions, mesh.positions);
+        assert_eq!(triangles(&all), triangles(&mesh));
+    }
+
+    #[test]
+    fn budgets_are_met() {
+        let mesh = sphere();
+        let budget = triangles(&mesh) / 10;
+        let coarse = mesh.simplify(Simplify::Budget(budget), |_| false);
+        assert!(triangles(&coarse) <= budget);
+        assert!(triangles(&coarse) > 0);
+    }
+}
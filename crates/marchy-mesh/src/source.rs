@@ -0,0 +1,66 @@
+use glam::{UVec3, Vec3};
+use marchy_core::{grid::OutOfBounds, storage::VoxelStorage};
+pub use marchy_core::source::DensitySource;
+use crate::mesh::{build_buffers, MeshBuffers, MeshingStrategy};
+
+/// A source read as a grid: cell `p` samples it at `min + p / resolution`,
+/// so `resolution` cells per unit, up to and including `max`. Any mesher
+/// or `create_mesh` can march it like stored voxels, and an analytic field
+/// can be meshed finer than a chunk stores it. Read-only: `set` always
+/// fails.
+pub struct Resampled<'a, S: ?Sized> {
+    pub source: &'a S,
+    pub min: Vec3,
+    pub resolution: f32,
+    dims: UVec3,
+}
+
+impl<'a, S: DensitySource + ?Sized> Resampled<'a, S> {
+    pub fn new(source: &'a S, min: Vec3, max: Vec3, resolution: f32) -> Self {
+        let dims = ((max - min).max(Vec3::ZERO) * resolution).floor().as_uvec3() + 1;
+        Resampled { source, min, resolution, dims }
+    }
+
+    /// Over the source's own bounds, if it has any.
+    pub fn covering(source: &'a S, resolution: f32) -> Option<Self> {
+        let (min, max) = source.bounds()?;
+        Some(Self::new(source, min, max, resolution))
+    }
+
+    /// Where cell `p` samples the source.
+    pub fn point(&self, p: UVec3) -> Vec3 {
+        self.min + p.as_vec3() / self.resolution
+    }
+
+    /// Meshes the cells at or below `iso`, with positions moved from the
+    /// centered chunk space meshers output into the source's own.
+    pub fn mesh(&self, iso: f32, strategy: MeshingStrategy) -> MeshBuffers {
+        let mut buffers = build_buffers(self, iso, strategy, |_| None);
+        // Cell `c` is centered on `c - dims/2 - 0.5` in mesh space.
+        let offset = self.min + (self.dims.as_vec3() / 2.0 + 0.5) / self.resolution;
+        buffers.transform(1.0 / self.resolution, offset);
+        buffers
+    }
+}
+
+impl<S: DensitySource + ?Sized> VoxelStorage<f32> for Resampled<'_, S> {
+    fn dims(&self) -> UVec3 {
+        self.dims
+    }
+
+    fn get(&self, p: UVec3) -> Option<f32> {
+        if p.cmpge(self.dims).any() {
+            return None;
+        }
+        let q = self.point(p);
+        Some(self.source.sample(q.x, q.y, q.z))
+    }
+
+    fn set(&mut self, p: UVec3, _val: f32) -> Result<(), OutOfBounds> {
+        Err(OutOfBounds(p))
+    }
+
+    fn memory(&self) -> usize {
+        0
+    }
+}
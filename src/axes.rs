@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+
+pub fn add_axes(
+    mut cmds: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let w = 0.01;
+    cmds.spawn((
+        Mesh3d(meshes.add(Cuboid::default())),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            emissive: LinearRgba::rgb(1.0, 0.0, 0.0),
+            ..default()
+        })),
+        Transform::from_xyz(0.0, 0.0, 0.0)
+            .with_scale(Vec3::new(100.0, w, w))
+    ));
+    cmds.spawn((
+        Mesh3d(meshes.add(Cuboid::default())),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            emissive: LinearRgba::rgb(0.0, 0.3, 1.0),
+            ..default()
+        })),
+        Transform::from_xyz(0.0, 0.0, 0.0)
+            .with_scale(Vec3::new(w, w, 100.0))
+    ));
+    cmds.spawn((
+        Mesh3d(meshes.add(Cuboid::default())),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            emissive: LinearRgba::rgb(0.0, 1.0, 0.0),
+            ..default()
+        })),
+        Transform::from_xyz(0.0, 0.0, 0.0)
+            .with_scale(Vec3::new(w, 100.0, w))
+    ));
+}
@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use avian3d::prelude::*;
+use crate::{
+    bounds::Respawn,
+    edit::{EditGate, TerrainCursor},
+    editor::Editable,
+    ids::StableId,
+    impact::{Lifetime, Touching},
+    projectile::{BallKind, BodyType, Projectile, ProjectileKinds},
+    tint::{self, BallMaterial, Tint},
+    MarchySettings,
+};
+
+#[derive(Debug, Event)]
+pub struct BallSpawn {
+    pub pos: Vec3,
+    pub kind: BallKind,
+    /// Overrides the kind's color.
+    pub color: Option<Color>,
+    /// The identity of a prop being loaded back in.
+    pub id: Option<StableId>,
+    /// What happens when it leaves the world; `None` despawns it.
+    pub respawn: Option<Respawn>,
+    /// Launch velocity of a dynamic ball; zero just drops it.
+    pub velocity: Vec3,
+    /// Rotation, and scale on top of the kind's size, of a prop being
+    /// loaded back in; `None` is upright at the kind's size.
+    pub pose: Option<(Quat, Vec3)>,
+}
+
+/// Every ball shares one mesh and one material, each carrying its color in
+/// a `MeshTag`, so Bevy can batch them into instanced draws instead of one
+/// draw call per ball.
+#[derive(Resource, Default)]
+pub struct BallAssets {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<BallMaterial>,
+}
+
+pub fn init_ball_assets(
+    mut cmds: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<BallMaterial>>,
+) {
+    // White vertex colors for `Tint` to multiply.
+    let mut mesh = Sphere::new(0.5).mesh().build();
+    let count = mesh.count_vertices();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![[1.0f32; 4]; count]);
+    cmds.insert_resource(BallAssets {
+        mesh: meshes.add(mesh),
+        material: materials.add(BallMaterial { base: default(), extension: Tint::default() }),
+    });
+}
+
+pub fn ball_spawn(
+    trigger: Trigger<BallSpawn>,
+    mut cmds: Commands,
+    assets: Res<BallAssets>,
+    kinds: Res<ProjectileKinds>,
+    settings: Res<MarchySettings>,
+    gate: Res<EditGate>,
+) {
+    let ev = trigger.event();
+    // Props with ids come from level and scene loads, which stay local.
+    if ev.id.is_none() && !gate.applies() {
+        return;
+    }
+    let id = kinds.id(&ev.kind).unwrap_or_else(|| {
+        warn!("unknown projectile kind {:?}", ev.kind.name());
+        0
+    });
+    let kind = kinds.get(id);
+    let color = ev.color
+        .or(kind.color.map(|[r, g, b]| Color::srgb(r, g, b)))
+        .unwrap_or(settings.ball_color);
+
+    // The shared mesh and collider are radius 0.5; scale covers other sizes.
+    let mut ball = cmds.spawn((
+        Projectile::new(id),
+        match kind.body {
+            BodyType::Dynamic => RigidBody::Dynamic,
+            BodyType::Static => RigidBody::Static,
+        },
+        Collider::sphere(0.5),
+        ColliderDensity(kind.density),
+        Restitution::new(kind.restitution)
+            .with_combine_rule(CoefficientCombine::Max),
+        CollidingEntities::default(),
+        Touching::default(),
+        Mesh3d(assets.mesh.clone()),
+        MeshMaterial3d(assets.material.clone()),
+        tint::tag(color),
+        Transform {
+            translation: ev.pos,
+            rotation: ev.pose.map_or(Quat::IDENTITY, |(r, _)| r),
+            scale: Vec3::splat(kind.radius / 0.5) * ev.pose.map_or(Vec3::ONE, |(_, s)| s),
+        },
+    ));
+    if kind.body == BodyType::Static {
+        ball.insert(Editable { radius: kind.radius });
+    } else if ev.velocity != Vec3::ZERO {
+        // Thrown balls sweep their collider so they can't tunnel through
+        // thin terrain.
+        ball.insert((LinearVelocity(ev.velocity), SweptCcd::default()));
+    }
+    if let Some(id) = ev.id {
+        ball.insert(id);
+    }
+    if let Some(respawn) = ev.respawn {
+        ball.insert(respawn);
+    }
+    if let Some(secs) = kind.lifetime {
+        ball.insert(Lifetime(Timer::from_seconds(secs, TimerMode::Once)));
+    }
+}
+
+/// F drops a ball a few units above the terrain under the cursor.
+pub fn drop_ball(mut cmds: Commands, keys: Res<ButtonInput<KeyCode>>, cursor: TerrainCursor) {
+    // Ctrl+F is the spawner's.
+    if !keys.just_pressed(KeyCode::KeyF) || keys.pressed(KeyCode::ControlLeft) {
+        return;
+    }
+    if let Some((pos, _)) = cursor.hit() {
+        cmds.trigger(BallSpawn {
+            pos: pos + Vec3::Y * 4.0,
+            kind: BallKind::Ball,
+            color: None,
+            id: None,
+            respawn: None,
+            velocity: Vec3::ZERO,
+            pose: None,
+        });
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+use avian3d::prelude::*;
+use crate::{chunk::ChunkMap, vehicle::Vehicle, MarchySettings};
+
+/// Limits of the playable world.
+#[derive(Clone, Debug)]
+pub struct WorldBounds {
+    /// Height of a solid floor under everything; `None` for no floor. It's a
+    /// half-space, so nothing can tunnel through it however fast it falls.
+    pub floor: Option<f32>,
+    /// Bodies below this height are out of bounds.
+    pub kill_y: f32,
+    /// Bodies this far outside the loaded chunks in X or Z are out of bounds.
+    pub margin: f32,
+    /// Invisible walls around the loaded chunks, rebuilt as the map changes.
+    pub walls: bool,
+}
+
+impl Default for WorldBounds {
+    fn default() -> Self {
+        WorldBounds {
+            floor: Some(-5.0),
+            kill_y: -30.0,
+            margin: 20.0,
+            walls: false,
+        }
+    }
+}
+
+/// What happens to a body that leaves the world. Dynamic bodies without
+/// one are despawned.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub enum Respawn {
+    #[default]
+    Despawn,
+    /// Put back at this point, at rest.
+    At(Vec3),
+    /// Left to fall forever.
+    Ignore,
+}
+
+/// A dynamic body left the world. `respawn` applies its `Respawn` rule.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct OutOfBounds {
+    pub entity: Entity,
+    pub pos: Vec3,
+}
+
+#[derive(Component)]
+pub struct BoundaryWall;
+
+pub fn spawn_floor(
+    mut cmds: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    settings: Res<MarchySettings>,
+) {
+    let Some(y) = settings.bounds.floor else {
+        return;
+    };
+    cmds.spawn((
+        Name::new("floor"),
+        RigidBody::Static,
+        Collider::half_space(Vec3::Y),
+        Mesh3d(meshes.add(Cylinder::new(20.0, 0.1))),
+        MeshMaterial3d(materials.add(Color::BLACK)),
+        Transform::from_xyz(0.0, y, 0.0),
+    ));
+}
+
+/// Keeps a wall on each side of the loaded chunks while `walls` is on.
+pub fn update_walls(
+    mut cmds: Commands,
+    walls: Query<Entity, With<BoundaryWall>>,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+    mut built: Local<Option<(Vec3, Vec3)>>,
+) {
+    let extent = if settings.bounds.walls { chunks.world_extent() } else { None };
+    if extent == *built {
+        return;
+    }
+    *built = extent;
+    for wall in &walls {
+        cmds.entity(wall).despawn();
+    }
+    let Some((min, max)) = extent else {
+        return;
+    };
+    let bottom = settings.bounds.floor.unwrap_or(settings.bounds.kill_y).min(min.y);
+    let (height, mid_y) = (max.y + 20.0 - bottom, (max.y + 20.0 + bottom) / 2.0);
+    let center = (min + max) / 2.0;
+    let span = max - min + 2.0;
+    for (pos, size) in [
+        (Vec3::new(min.x - 0.5, mid_y, center.z), Vec3::new(1.0, height, span.z)),
+        (Vec3::new(max.x + 0.5, mid_y, center.z), Vec3::new(1.0, height, span.z)),
+        (Vec3::new(center.x, mid_y, min.z - 0.5), Vec3::new(span.x, height, 1.0)),
+        (Vec3::new(center.x, mid_y, max.z + 0.5), Vec3::new(span.x, height, 1.0)),
+    ] {
+        cmds.spawn((
+            Name::new("boundary wall"),
+            BoundaryWall,
+            RigidBody::Static,
+            Collider::cuboid(size.x, size.y, size.z),
+            Transform::from_translation(pos),
+        ));
+    }
+}
+
+/// Fires `OutOfBounds` for dynamic bodies below the kill plane or too far
+/// outside the loaded chunks.
+pub fn kill_plane(
+    mut cmds: Commands,
+    bodies: Query<(Entity, &RigidBody, &GlobalTransform)>,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+) {
+    let bounds = &settings.bounds;
+    let area = chunks.world_extent().map(|(min, max)| {
+        let margin = Vec3::new(bounds.margin, f32::INFINITY, bounds.margin);
+        (min - margin, max + margin)
+    });
+    for (entity, body, transform) in &bodies {
+        if !body.is_dynamic() {
+            continue;
+        }
+        let pos = transform.translation();
+        let outside = area.is_some_and(|(min, max)| pos.cmplt(min).any() || pos.cmpgt(max).any());
+        if pos.y < bounds.kill_y || outside {
+            cmds.trigger(OutOfBounds { entity, pos });
+        }
+    }
+}
+
+/// Applies the body's `Respawn` rule. A vehicle's wheels are moved with it,
+/// so the joints don't fling it apart.
+pub fn respawn(
+    trigger: Trigger<OutOfBounds>,
+    mut cmds: Commands,
+    mut bodies: Query<(
+        Option<&Respawn>,
+        Option<&Vehicle>,
+        &mut Transform,
+        &mut LinearVelocity,
+        &mut AngularVelocity,
+    )>,
+) {
+    let OutOfBounds { entity, pos } = *trigger.event();
+    let Ok((rule, vehicle, t, ..)) = bodies.get(entity) else {
+        return;
+    };
+    match rule.copied().unwrap_or_default() {
+        Respawn::Despawn => {
+            debug!(target: "physics", "{entity} left the world at {pos}");
+            cmds.entity(entity).despawn();
+        }
+        Respawn::At(spawn) => {
+            debug!(target: "physics", "{entity} left the world at {pos}, back to {spawn}");
+            let delta = spawn - t.translation;
+            let mut parts = vec![entity];
+            parts.extend(vehicle.map(|v| v.wheels).into_iter().flatten());
+            for part in parts {
+                if let Ok((_, _, mut t, mut vel, mut spin)) = bodies.get_mut(part) {
+                    t.translation += delta;
+                    vel.0 = Vec3::ZERO;
+                    spin.0 = Vec3::ZERO;
+                }
+            }
+        }
+        Respawn::Ignore => {}
+    }
+}
@@ -0,0 +1,197 @@
+use bevy::{
+    image::{CompressedImageFormats, ImageSampler, ImageType},
+    prelude::*,
+    render::render_asset::RenderAssetUsages,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use crate::{
+    level::{read_manifest, LevelManifest, LoadLevel, THUMBNAIL},
+    MarchySettings,
+};
+
+#[derive(Component)]
+pub struct LevelBrowser;
+
+#[derive(Component, Clone)]
+pub enum BrowserAction {
+    Load(PathBuf),
+    Duplicate(PathBuf),
+    Delete(PathBuf),
+}
+
+#[derive(Resource, Default)]
+pub struct BrowserState {
+    pub open: bool,
+    /// Rescan the levels directory on the next frame.
+    dirty: bool,
+}
+
+/// Every level directory under `root` with a readable manifest, by name.
+pub fn scan_levels(root: &Path) -> Vec<(PathBuf, LevelManifest)> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return vec![];
+    };
+    let mut levels: Vec<_> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_dir())
+        .filter_map(|p| read_manifest(&p).ok().map(|m| (p, m)))
+        .collect();
+    levels.sort_by(|a, b| a.0.cmp(&b.0));
+    levels
+}
+
+/// Copies a level directory to the first free `<name>-copy`, `<name>-copy2`…
+/// beside it.
+pub fn duplicate_level(dir: &Path) -> io::Result<PathBuf> {
+    let name = dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let target = (1..)
+        .map(|i| {
+            let suffix = if i == 1 { String::new() } else { i.to_string() };
+            dir.with_file_name(format!("{name}-copy{suffix}"))
+        })
+        .find(|p| !p.exists())
+        .expect("some copy name is free");
+    fs::create_dir_all(&target)?;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            fs::copy(entry.path(), target.join(entry.file_name()))?;
+        }
+    }
+    Ok(target)
+}
+
+fn thumbnail(dir: &Path, images: &mut Assets<Image>) -> Option<Handle<Image>> {
+    let bytes = fs::read(dir.join(THUMBNAIL)).ok()?;
+    let image = Image::from_buffer(
+        &bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::RENDER_WORLD,
+    ).ok()?;
+    Some(images.add(image))
+}
+
+pub fn spawn_browser(mut cmds: Commands) {
+    cmds.spawn((
+        LevelBrowser,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(8.0),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(6.0)),
+            row_gap: Val::Px(6.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        Visibility::Hidden,
+    ));
+}
+
+fn button(parent: &mut ChildSpawnerCommands, label: &str, action: BrowserAction) {
+    parent.spawn((
+        Button,
+        action,
+        Text::new(label),
+        TextFont { font_size: 13.0, ..default() },
+    ));
+}
+
+/// F12 toggles the level browser: one card per level in `levels_dir`, with
+/// its thumbnail, name, author and description, and load, duplicate and
+/// delete buttons.
+pub fn rebuild_browser(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut state: ResMut<BrowserState>,
+    mut panel: Query<(Entity, &mut Visibility), With<LevelBrowser>>,
+    settings: Res<MarchySettings>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Ok((root, mut vis)) = panel.single_mut() else {
+        return;
+    };
+    // Shift+F12 is the turntable capture.
+    if keys.just_pressed(KeyCode::F12) && !keys.pressed(KeyCode::ShiftLeft) {
+        state.open = !state.open;
+        *vis = if state.open { Visibility::Visible } else { Visibility::Hidden };
+        state.dirty = state.open;
+    }
+    if !state.dirty {
+        return;
+    }
+    state.dirty = false;
+
+    let levels = scan_levels(&settings.levels_dir);
+    cmds.entity(root).despawn_related::<Children>();
+    cmds.entity(root).with_children(|parent| {
+        if levels.is_empty() {
+            parent.spawn((
+                Text::new(format!("no levels in {}", settings.levels_dir.display())),
+                TextFont { font_size: 13.0, ..default() },
+            ));
+        }
+        for (dir, manifest) in levels {
+            let thumb = thumbnail(&dir, &mut images);
+            parent
+                .spawn(Node { column_gap: Val::Px(8.0), ..default() })
+                .with_children(|card| {
+                    let size = Node { width: Val::Px(128.0), height: Val::Px(72.0), ..default() };
+                    match thumb {
+                        Some(image) => card.spawn((ImageNode::new(image), size)),
+                        None => card.spawn((BackgroundColor(Color::srgb(0.2, 0.2, 0.2)), size)),
+                    };
+                    card.spawn(Node { flex_direction: FlexDirection::Column, ..default() })
+                        .with_children(|info| {
+                            let meta = &manifest.meta;
+                            let mut text = meta.name.clone();
+                            if !meta.author.is_empty() {
+                                text += &format!(" by {}", meta.author);
+                            }
+                            if !meta.description.is_empty() {
+                                text += &format!("\n{}", meta.description);
+                            }
+                            info.spawn((Text::new(text), TextFont { font_size: 14.0, ..default() }));
+                            info.spawn(Node { column_gap: Val::Px(6.0), ..default() })
+                                .with_children(|row| {
+                                    button(row, "[load]", BrowserAction::Load(dir.clone()));
+                                    button(row, "[duplicate]", BrowserAction::Duplicate(dir.clone()));
+                                    button(row, "[delete]", BrowserAction::Delete(dir.clone()));
+                                });
+                        });
+                });
+        }
+    });
+}
+
+pub fn browser_actions(
+    mut cmds: Commands,
+    pressed: Query<(&Interaction, &BrowserAction), Changed<Interaction>>,
+    mut state: ResMut<BrowserState>,
+) {
+    for (interaction, action) in &pressed {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match action {
+            BrowserAction::Load(dir) => {
+                cmds.trigger(LoadLevel { dir: dir.clone() });
+            }
+            BrowserAction::Duplicate(dir) => match duplicate_level(dir) {
+                Ok(copy) => info!("duplicated {} to {}", dir.display(), copy.display()),
+                Err(e) => error!("failed to duplicate {}: {e}", dir.display()),
+            },
+            BrowserAction::Delete(dir) => match fs::remove_dir_all(dir) {
+                Ok(()) => info!("deleted level {}", dir.display()),
+                Err(e) => error!("failed to delete {}: {e}", dir.display()),
+            },
+        }
+        state.dirty = true;
+    }
+}
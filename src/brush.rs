@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+use crate::{chunk::ChunkMap, materials::VoxelMaterial};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BrushShape {
+    #[default]
+    Sphere,
+    /// Axis-aligned, `radius` from the center to each face.
+    Cube,
+    /// Upright, `radius` wide and `2 * radius` tall.
+    Cylinder,
+}
+
+/// How edit strength fades from the brush's center to its edge.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Falloff {
+    /// Full strength everywhere inside.
+    #[default]
+    Hard,
+    Linear,
+    /// Smoothstep: soft at the rim, flat in the middle.
+    Smooth,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BrushMode {
+    Add,
+    #[default]
+    Subtract,
+    /// Changes the material of solid cells without moving the surface.
+    Paint,
+}
+
+/// The terrain editing tool's shape and behavior.
+#[derive(Resource, Clone, Debug)]
+pub struct Brush {
+    pub shape: BrushShape,
+    pub radius: f32,
+    pub falloff: Falloff,
+    pub mode: BrushMode,
+    /// What `Paint` paints.
+    pub material: VoxelMaterial,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Brush {
+            shape: default(),
+            radius: 1.5,
+            falloff: default(),
+            mode: default(),
+            material: VoxelMaterial::Stone,
+        }
+    }
+}
+
+impl Brush {
+    /// Signed distance from the brush surface, for a point relative to its
+    /// center.
+    pub fn distance(&self, p: Vec3) -> f32 {
+        let r = self.radius;
+        match self.shape {
+            BrushShape::Sphere => p.length() - r,
+            BrushShape::Cube => {
+                let q = p.abs() - r;
+                q.max(Vec3::ZERO).length() + q.max_element().min(0.0)
+            }
+            BrushShape::Cylinder => {
+                let d = Vec2::new(p.xz().length() - r, p.y.abs() - r);
+                d.max(Vec2::ZERO).length() + d.max_element().min(0.0)
+            }
+        }
+    }
+
+    /// Edit strength, 0 to 1, at signed distance `d`.
+    pub fn weight(&self, d: f32) -> f32 {
+        let depth = (-d / self.radius.max(f32::EPSILON)).clamp(0.0, 1.0);
+        match self.falloff {
+            Falloff::Hard => 1.0,
+            Falloff::Linear => depth,
+            Falloff::Smooth => depth * depth * (3.0 - 2.0 * depth),
+        }
+    }
+
+    /// Half the size of the box the brush can touch, including the
+    /// one-voxel blend band.
+    fn reach(&self) -> f32 {
+        match self.shape {
+            BrushShape::Sphere => self.radius,
+            BrushShape::Cube => self.radius * 3f32.sqrt(),
+            BrushShape::Cylinder => self.radius * 2f32.sqrt(),
+        }
+    }
+}
+
+/// Applies `brush` at `center`, moving each cell at most `step` density
+/// units (divided by its hardness) toward the brush's shape, weighted by the
+/// falloff. Materials harder than `strength` can't be dug. Returns the
+/// materials of cells that crossed the surface, like `dig_sphere`.
+pub fn apply_brush(
+    chunks: &mut ChunkMap,
+    brush: &Brush,
+    center: Vec3,
+    iso: f32,
+    step: f32,
+    strength: f32,
+) -> Vec<VoxelMaterial> {
+    let reach = Vec3::splat(brush.reach() + 1.0);
+    let (min, max) = (chunks.voxel_at(center - reach), chunks.voxel_at(center + reach));
+    let mut crossed = vec![];
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let pos = IVec3::new(x, y, z);
+                let Some(old) = chunks.read(pos) else {
+                    continue;
+                };
+                let d = brush.distance(chunks.voxel_center(pos) - center);
+                if brush.mode == BrushMode::Paint {
+                    if d <= 0.0 && old <= iso {
+                        chunks.write_material(pos, brush.material);
+                    }
+                    continue;
+                }
+                let fill = brush.mode == BrushMode::Add;
+                let full = if fill { old.min(iso + d) } else { old.max(iso - d) };
+                let target = old + (full - old) * brush.weight(d);
+                if target == old {
+                    continue;
+                }
+                let mat = chunks.read_material(pos).unwrap_or_default();
+                let new = if fill {
+                    (old - step).max(target)
+                } else {
+                    if mat.hardness() > strength {
+                        continue;
+                    }
+                    (old + step / mat.hardness()).min(target)
+                };
+                chunks.write(pos, new);
+                if fill && old > iso && new <= iso {
+                    chunks.write_material(pos, VoxelMaterial::Dirt);
+                    crossed.push(VoxelMaterial::Dirt);
+                } else if !fill && old <= iso && new > iso {
+                    crossed.push(mat);
+                }
+            }
+        }
+    }
+    crossed
+}
+
+/// [ and ] resize the brush, \ cycles its shape, ' its falloff and ; its
+/// mode. 1-6 pick the paint material.
+pub fn brush_keys(keys: Res<ButtonInput<KeyCode>>, mut brush: ResMut<Brush>) {
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        brush.radius = (brush.radius - 0.5).max(0.5);
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        brush.radius = (brush.radius + 0.5).min(16.0);
+    }
+    if keys.just_pressed(KeyCode::Backslash) {
+        brush.shape = match brush.shape {
+            BrushShape::Sphere => BrushShape::Cube,
+            BrushShape::Cube => BrushShape::Cylinder,
+            BrushShape::Cylinder => BrushShape::Sphere,
+        };
+    }
+    if keys.just_pressed(KeyCode::Quote) {
+        brush.falloff = match brush.falloff {
+            Falloff::Hard => Falloff::Linear,
+            Falloff::Linear => Falloff::Smooth,
+            Falloff::Smooth => Falloff::Hard,
+        };
+    }
+    if keys.just_pressed(KeyCode::Semicolon) {
+        brush.mode = match brush.mode {
+            BrushMode::Subtract => BrushMode::Add,
+            BrushMode::Add => BrushMode::Paint,
+            BrushMode::Paint => BrushMode::Subtract,
+        };
+    }
+    let digits = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+    ];
+    for (key, mat) in digits.into_iter().zip(VoxelMaterial::ALL) {
+        if keys.just_pressed(key) {
+            brush.material = mat;
+        }
+    }
+    if brush.is_changed() {
+        info!("brush: {:?} r={} {:?} {:?} {:?}", brush.shape, brush.radius, brush.falloff, brush.mode, brush.material);
+    }
+}
@@ -0,0 +1,161 @@
+use bevy::{
+    input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CamMode {
+    /// Middle-drag rotates, Shift + middle-drag pans, scroll zooms.
+    #[default]
+    Orbit,
+    /// WASD moves, Q/E lowers and raises, middle-drag looks, scroll changes speed.
+    Fly,
+    /// Slow automatic orbit around `target`.
+    Cinematic,
+    /// Placed by the player controller; ignores camera input.
+    FirstPerson,
+}
+
+#[derive(Component, Clone)]
+pub struct Cam {
+    pub r: f32,
+    /// Point the camera orbits and looks at.
+    pub target: Vec3,
+    pub mode: CamMode,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub fly_speed: f32,
+}
+
+impl Default for Cam {
+    fn default() -> Self {
+        Cam {
+            r: 20.0,
+            target: Vec3::ZERO,
+            mode: CamMode::Orbit,
+            yaw: 0.0,
+            pitch: -0.15,
+            fly_speed: 8.0,
+        }
+    }
+}
+
+impl Cam {
+    pub fn rotation(&self) -> Quat {
+        Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.0)
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation() * Vec3::NEG_Z
+    }
+}
+
+/// C cycles orbit, fly and cinematic modes.
+pub fn cam_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    motion: Res<AccumulatedMouseMotion>,
+    scroll: Res<AccumulatedMouseScroll>,
+    // Real time, so the camera still moves while `TimeControl` has paused.
+    time: Res<Time<Real>>,
+    mut cams: Query<(&mut Cam, &Transform)>,
+) {
+    let dt = time.delta_secs();
+    for (mut cam, t) in &mut cams {
+        if cam.mode == CamMode::FirstPerson {
+            continue;
+        }
+        if keys.just_pressed(KeyCode::KeyC) {
+            cam.mode = match cam.mode {
+                CamMode::Orbit => CamMode::Fly,
+                CamMode::Fly => CamMode::Cinematic,
+                CamMode::Cinematic | CamMode::FirstPerson => {
+                    // Pick up orbiting from wherever the cinematic left off.
+                    let (yaw, pitch, _) = t.rotation.to_euler(EulerRot::YXZ);
+                    (cam.yaw, cam.pitch) = (yaw, pitch);
+                    CamMode::Orbit
+                }
+            };
+            info!("camera: {:?}", cam.mode);
+        }
+
+        let drag = mouse.pressed(MouseButton::Middle);
+        let delta = if drag { motion.delta } else { Vec2::ZERO };
+        let shift = keys.pressed(KeyCode::ShiftLeft);
+        match cam.mode {
+            CamMode::Orbit if shift => {
+                let pan = t.rotation * Vec3::new(-delta.x, delta.y, 0.0) * cam.r * 0.002;
+                cam.target += pan;
+            }
+            CamMode::Orbit | CamMode::Fly => {
+                let eye = cam.target - cam.forward() * cam.r;
+                cam.yaw -= delta.x * 0.005;
+                cam.pitch = (cam.pitch - delta.y * 0.005).clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+                if cam.mode == CamMode::Fly {
+                    // Look around from where the camera is, not around the target.
+                    cam.target = eye + cam.forward() * cam.r;
+                }
+            }
+            CamMode::Cinematic | CamMode::FirstPerson => {}
+        }
+
+        match cam.mode {
+            CamMode::Orbit => {
+                cam.r = (cam.r * (1.0 - scroll.delta.y * 0.1)).clamp(1.0, 200.0);
+            }
+            CamMode::Fly => {
+                cam.fly_speed = (cam.fly_speed * (1.0 + scroll.delta.y * 0.1)).clamp(0.5, 100.0);
+                let rot = cam.rotation();
+                let mut dir = Vec3::ZERO;
+                for (key, d) in [
+                    (KeyCode::KeyW, Vec3::NEG_Z),
+                    (KeyCode::KeyS, Vec3::Z),
+                    (KeyCode::KeyA, Vec3::NEG_X),
+                    (KeyCode::KeyD, Vec3::X),
+                ] {
+                    if keys.pressed(key) {
+                        dir += rot * d;
+                    }
+                }
+                if keys.pressed(KeyCode::KeyE) {
+                    dir += Vec3::Y;
+                }
+                if keys.pressed(KeyCode::KeyQ) {
+                    dir -= Vec3::Y;
+                }
+                let speed = if shift { cam.fly_speed * 3.0 } else { cam.fly_speed };
+                // The target rides along in front, so switching back to orbit
+                // circles what's being looked at.
+                cam.target += dir.normalize_or_zero() * speed * dt;
+            }
+            CamMode::Cinematic | CamMode::FirstPerson => {}
+        }
+    }
+}
+
+/// Places the camera for its mode: around `target` when orbiting, at
+/// `target - forward * r` when flying, on a timed circle when cinematic.
+pub fn cam_follow(
+    mut cams: Query<(&mut Transform, &Cam)>,
+    time: Res<Time>
+) {
+    let elapsed = time.elapsed_secs() * 0.1;
+    for (mut t, cam) in cams.iter_mut() {
+        match cam.mode {
+            CamMode::Orbit | CamMode::Fly => {
+                t.rotation = cam.rotation();
+                t.translation = cam.target - cam.forward() * cam.r;
+            }
+            CamMode::Cinematic => {
+                t.translation.x = cam.target.x + elapsed.sin() * cam.r;
+                t.translation.z = cam.target.z + elapsed.cos() * cam.r;
+                t.translation.y = cam.target.y + elapsed.sin() * 5.0;
+                t.look_at(cam.target, Dir3::Y);
+            }
+            CamMode::FirstPerson => {}
+        }
+    }
+}
@@ -0,0 +1,951 @@
+use bevy::{
+    platform::time::Instant,
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+use avian3d::prelude::*;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::Duration,
+};
+use crate::{
+    grid::{dda_skipping, trilinear, VoxelHit},
+    materials::VoxelMaterial,
+    mesh::{BevyMesh, Boundary, Simplify},
+    mesher::{DensityView, MeshOptions, Meshers},
+    occupancy::Occupancy,
+    streaming::ChunkFade,
+    transform::GridTransform,
+    MarchyMaterials,
+    MarchySettings,
+    VoxelGrid,
+};
+
+/// How far below the surface's edge a skirt hangs, in mesh cells.
+const SKIRT_DEPTH: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkCoord(pub IVec3);
+
+impl ChunkCoord {
+    pub fn neighbors(self) -> [ChunkCoord; 6] {
+        [
+            IVec3::X, IVec3::NEG_X,
+            IVec3::Y, IVec3::NEG_Y,
+            IVec3::Z, IVec3::NEG_Z,
+        ].map(|d| ChunkCoord(self.0 + d))
+    }
+}
+
+/// A chunk's identity: its coordinate plus how many times a chunk has been
+/// created there, so references to one that was since unloaded and
+/// generated afresh can tell. Saves keep the generation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkId {
+    pub coord: ChunkCoord,
+    pub generation: u32,
+}
+
+pub struct Chunk {
+    pub grid: VoxelGrid,
+    /// `VoxelMaterial` ids, one per cell.
+    pub materials: Vec<u8>,
+    pub entity: Option<Entity>,
+    pub stats: ChunkStats,
+    /// Level of detail: meshed at `1 << lod` cells per mesh cell.
+    pub lod: u32,
+    /// Faces the current mesh hangs skirts from, towards neighbors that
+    /// hadn't meshed yet.
+    pub skirts: Vec<IVec3>,
+    pub generation: u32,
+    /// Rebuilt whenever the chunk is queued for a remesh and kept current
+    /// by `ChunkMap::write` in between; `None` until its first remesh.
+    pub occupancy: Option<Occupancy>,
+    /// Overrides `MarchySettings::chunk_collider` for this chunk.
+    pub collider: Option<ChunkCollider>,
+}
+
+/// Debug counters, updated whenever a new mesh for the chunk lands.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChunkStats {
+    pub triangles: usize,
+    pub remeshes: u32,
+    pub vertices: usize,
+    /// `Time::elapsed_secs` of the last remesh, which follows every edit.
+    pub last_remesh: f32,
+}
+
+impl Chunk {
+    /// Bytes held by the voxel and material arrays.
+    pub fn memory(&self) -> usize {
+        self.grid.data.len() * size_of::<f32>() + self.materials.len()
+    }
+}
+
+/// A box of world voxels, `min` inclusive and `max` exclusive, whose
+/// densities changed. `ChunkMap` records one per chunk for every kind of
+/// write and `publish_dirty_regions` triggers them each frame; the
+/// `mark_region_dirty` observer then queues the meshes they affect. Trigger
+/// one yourself after changing voxels some other way.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegionDirty {
+    pub min: IVec3,
+    pub max: IVec3,
+}
+
+/// Marks a mesh entity as the rendered surface of a chunk.
+#[derive(Component)]
+pub struct ChunkMesh(pub ChunkCoord);
+
+/// The voxel world: fixed-size `VoxelGrid` chunks keyed by chunk coordinate,
+/// plus a queue of chunks whose data changed and need a new mesh.
+#[derive(Resource)]
+pub struct ChunkMap {
+    pub chunk_size: u32,
+    /// Where voxels are in the world. Everything converting between the
+    /// two goes through it (or `voxel_center`, `voxel_at` and
+    /// `chunk_transform`), so it's the only place cells are laid out.
+    pub transform: GridTransform,
+    chunks: HashMap<ChunkCoord, Chunk>,
+    dirty: VecDeque<ChunkCoord>,
+    queued: HashSet<ChunkCoord>,
+    /// Changed voxels since the last `publish_dirty_regions`, as one
+    /// bounding box per chunk.
+    regions: HashMap<ChunkCoord, (IVec3, IVec3)>,
+    /// Latest generation created at each coordinate, kept after unloading.
+    generations: HashMap<ChunkCoord, u32>,
+    /// Bumped by every change to the voxels, and by chunks coming and going.
+    edits: u64,
+    /// Time spent filling chunk grids from the generator since the
+    /// diagnostics last drained it.
+    pub filled: Vec<Duration>,
+}
+
+impl ChunkMap {
+    pub fn new(chunk_size: u32) -> Self {
+        ChunkMap {
+            chunk_size,
+            transform: GridTransform::centered(chunk_size),
+            chunks: HashMap::new(),
+            dirty: VecDeque::new(),
+            queued: HashSet::new(),
+            regions: HashMap::new(),
+            generations: HashMap::new(),
+            edits: 0,
+            filled: vec![],
+        }
+    }
+
+    pub fn with_transform(mut self, transform: GridTransform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn insert(&mut self, coord: ChunkCoord, grid: VoxelGrid) {
+        assert_eq!(grid.dims(), UVec3::splat(self.chunk_size), "chunk grid size mismatch");
+        // Replacing a loaded chunk's voxels keeps its identity.
+        let (entity, stats, generation, collider) = match self.chunks.remove(&coord) {
+            Some(c) => (c.entity, c.stats, c.generation, c.collider),
+            None => {
+                let generation = self.generations.entry(coord).and_modify(|g| *g += 1).or_insert(0);
+                (None, default(), *generation, None)
+            }
+        };
+        let materials = vec![0; grid.data.len()];
+        self.chunks.insert(coord, Chunk {
+            grid,
+            materials,
+            entity,
+            stats,
+            lod: 0,
+            skirts: vec![],
+            generation,
+            occupancy: None,
+            collider,
+        });
+        self.record_chunk(coord);
+    }
+
+    /// Removes a chunk. Its mesh entity, if any, is left for the caller.
+    pub fn remove(&mut self, coord: ChunkCoord) -> Option<Chunk> {
+        for n in coord.neighbors() {
+            if self.chunks.contains_key(&n) {
+                self.mark_dirty(n);
+            }
+        }
+        self.edits += 1;
+        self.chunks.remove(&coord)
+    }
+
+    pub fn get(&self, coord: ChunkCoord) -> Option<&Chunk> {
+        self.chunks.get(&coord)
+    }
+
+    /// Mutable access to a chunk's voxels. The whole chunk is recorded as
+    /// dirty.
+    pub fn grid_mut(&mut self, coord: ChunkCoord) -> Option<&mut VoxelGrid> {
+        if self.chunks.contains_key(&coord) {
+            self.record_chunk(coord);
+        }
+        self.chunks.get_mut(&coord).map(|c| &mut c.grid)
+    }
+
+    /// The loaded chunk's identity: its coordinate and generation.
+    pub fn id(&self, coord: ChunkCoord) -> Option<ChunkId> {
+        self.chunks.get(&coord).map(|c| ChunkId { coord, generation: c.generation })
+    }
+
+    /// Gives a loaded chunk the generation it was saved with.
+    pub fn restore_generation(&mut self, coord: ChunkCoord, generation: u32) {
+        if let Some(chunk) = self.chunks.get_mut(&coord) {
+            chunk.generation = generation;
+            let latest = self.generations.entry(coord).or_insert(generation);
+            *latest = (*latest).max(generation);
+        }
+    }
+
+    /// Mutable access to a whole chunk. The whole chunk is recorded as
+    /// dirty.
+    pub fn chunk_mut(&mut self, coord: ChunkCoord) -> Option<&mut Chunk> {
+        if self.chunks.contains_key(&coord) {
+            self.record_chunk(coord);
+        }
+        self.chunks.get_mut(&coord)
+    }
+
+    pub fn neighbor(&self, coord: ChunkCoord, dir: IVec3) -> Option<&Chunk> {
+        self.chunks.get(&ChunkCoord(coord.0 + dir))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ChunkCoord, &Chunk)> {
+        self.chunks.iter()
+    }
+
+    /// Changes a chunk's level of detail. It and its neighbors are remeshed,
+    /// since the seams between them depend on both levels.
+    pub fn set_lod(&mut self, coord: ChunkCoord, lod: u32) {
+        let Some(chunk) = self.chunks.get_mut(&coord) else {
+            return;
+        };
+        if chunk.lod == lod {
+            return;
+        }
+        chunk.lod = lod;
+        self.mark_dirty(coord);
+        for n in coord.neighbors() {
+            if self.chunks.contains_key(&n) {
+                self.mark_dirty(n);
+            }
+        }
+    }
+
+    /// Gives a chunk its own collider shape, or with `None` the default
+    /// again, and rebuilds it.
+    pub fn set_collider(&mut self, coord: ChunkCoord, collider: Option<ChunkCollider>) {
+        let Some(chunk) = self.chunks.get_mut(&coord) else {
+            return;
+        };
+        if chunk.collider != collider {
+            chunk.collider = collider;
+            self.mark_dirty(coord);
+        }
+    }
+
+    pub fn mark_dirty(&mut self, coord: ChunkCoord) {
+        if self.queued.insert(coord) {
+            self.dirty.push_back(coord);
+        }
+    }
+
+    /// Grows `coord`'s dirty region to take in `min..max`.
+    fn record(&mut self, coord: ChunkCoord, min: IVec3, max: IVec3) {
+        self.edits += 1;
+        self.regions
+            .entry(coord)
+            .and_modify(|(lo, hi)| (*lo, *hi) = (lo.min(min), hi.max(max)))
+            .or_insert((min, max));
+    }
+
+    fn record_chunk(&mut self, coord: ChunkCoord) {
+        let origin = coord.0 * self.chunk_size as i32;
+        self.record(coord, origin, origin + self.chunk_size as i32);
+    }
+
+    /// The regions recorded since the last call.
+    pub fn take_regions(&mut self) -> Vec<RegionDirty> {
+        self.regions.drain().map(|(_, (min, max))| RegionDirty { min, max }).collect()
+    }
+
+    /// Queues every loaded chunk `region` overlaps, and the neighbors across
+    /// any chunk face it touches, since their meshes read those cells too.
+    pub fn mark_region(&mut self, region: RegionDirty) {
+        let size = self.chunk_size as i32;
+        if region.max.cmple(region.min).any() {
+            return;
+        }
+        let (lo, hi) = (region.min.div_euclid(IVec3::splat(size)), (region.max - 1).div_euclid(IVec3::splat(size)));
+        let mut touched = vec![];
+        for z in lo.z..=hi.z {
+            for y in lo.y..=hi.y {
+                for x in lo.x..=hi.x {
+                    let coord = ChunkCoord(IVec3::new(x, y, z));
+                    touched.push(coord);
+                    let origin = coord.0 * size;
+                    for dir in [IVec3::X, IVec3::Y, IVec3::Z] {
+                        if region.min.dot(dir) <= origin.dot(dir) {
+                            touched.push(ChunkCoord(coord.0 - dir));
+                        }
+                        if region.max.dot(dir) >= origin.dot(dir) + size {
+                            touched.push(ChunkCoord(coord.0 + dir));
+                        }
+                    }
+                }
+            }
+        }
+        for coord in touched {
+            if self.chunks.contains_key(&coord) {
+                self.mark_dirty(coord);
+            }
+        }
+    }
+
+    /// A count that changes whenever the world does, for telling whether
+    /// anything was edited since it was last read. Unlike change detection
+    /// on the resource, remeshing and bookkeeping don't move it.
+    pub fn edits(&self) -> u64 {
+        self.edits
+    }
+
+    /// Chunks waiting to be queued for a remesh.
+    pub fn dirty_count(&self) -> usize {
+        self.dirty.len()
+    }
+
+    pub fn pop_dirty(&mut self) -> Option<ChunkCoord> {
+        let coord = self.dirty.pop_front()?;
+        self.queued.remove(&coord);
+        Some(coord)
+    }
+
+    /// Splits a world voxel position into its chunk and the local cell inside it.
+    pub fn locate(&self, pos: IVec3) -> (ChunkCoord, UVec3) {
+        let size = IVec3::splat(self.chunk_size as i32);
+        (ChunkCoord(pos.div_euclid(size)), pos.rem_euclid(size).as_uvec3())
+    }
+
+    /// World-space center of a voxel.
+    pub fn voxel_center(&self, pos: IVec3) -> Vec3 {
+        self.transform.cell_center(pos)
+    }
+
+    /// The voxel containing a world-space point.
+    pub fn voxel_at(&self, point: Vec3) -> IVec3 {
+        self.transform.cell_at(point)
+    }
+
+    /// The transform of a mesh built from a grid of `dims` cells whose first
+    /// cell is world voxel `corner`. Meshers put cell `x` at `x - dims/2 - 1
+    /// .. x - dims/2` along each axis, so the mesh's origin is the grid's
+    /// middle, one cell in.
+    pub fn grid_to_world(&self, corner: IVec3, dims: UVec3) -> Transform {
+        let t = &self.transform;
+        Transform {
+            translation: t.to_world(corner.as_vec3() + dims.as_vec3() / 2.0 + 1.0),
+            rotation: t.rotation,
+            scale: Vec3::splat(t.cell_size),
+        }
+    }
+
+    /// Where a chunk's mesh entity goes.
+    pub fn chunk_transform(&self, coord: ChunkCoord) -> Transform {
+        self.grid_to_world(coord.0 * self.chunk_size as i32, UVec3::splat(self.chunk_size))
+    }
+
+    /// World-space box around a chunk's mesh, with a cell of slack for the
+    /// border it's meshed with.
+    pub fn chunk_bounds(&self, coord: ChunkCoord) -> (Vec3, Vec3) {
+        let corner = (coord.0 * self.chunk_size as i32).as_vec3();
+        self.transform.world_bounds(corner - 1.0, corner + self.chunk_size as f32 + 2.0)
+    }
+
+    /// Casts a world-space ray through the voxels of every loaded chunk to
+    /// the first solid cell. `cell` is a world voxel position; `point` and
+    /// `distance` are in world units, and `normal` in voxel space.
+    pub fn raycast(&self, origin: Vec3, dir: Vec3, max_dist: f32, iso: f32) -> Option<VoxelHit> {
+        let cell_size = self.transform.cell_size;
+        let (start, dir) = (self.transform.to_voxel(origin), self.transform.dir_to_voxel(dir.normalize()));
+        let read = |c: IVec3| self.read(c);
+        let size = IVec3::splat(self.chunk_size as i32);
+        // Whole missing chunks, and empty parts of loaded ones, in one step.
+        let empty = |c: IVec3| {
+            let (coord, local) = self.locate(c);
+            let origin = coord.0 * size;
+            let Some(chunk) = self.chunks.get(&coord) else {
+                return Some((origin, origin + size));
+            };
+            let (lo, hi) = chunk.occupancy.as_ref().filter(|o| o.iso() == iso)?.empty_box(local)?;
+            Some((origin + lo.as_ivec3(), origin + hi.as_ivec3()))
+        };
+        let (cell, normal, distance) =
+            dda_skipping(start, dir, max_dist / cell_size, |c| read(c).is_some_and(|v| v <= iso), empty)?;
+        let point = start + dir * distance;
+        let density = trilinear(point, read)?;
+        Some(VoxelHit { cell, normal, distance: distance * cell_size, point: self.transform.to_world(point), density })
+    }
+
+    /// Reads a voxel by world voxel position, crossing chunk borders.
+    pub fn read(&self, pos: IVec3) -> Option<f32> {
+        let (coord, local) = self.locate(pos);
+        self.chunks
+            .get(&coord)
+            .and_then(|c| c.grid.read(local.x, local.y, local.z))
+    }
+
+    pub fn read_material(&self, pos: IVec3) -> Option<VoxelMaterial> {
+        let (coord, local) = self.locate(pos);
+        let size = self.chunk_size;
+        let idx = local.z * size * size + local.y * size + local.x;
+        self.chunks
+            .get(&coord)
+            .map(|c| VoxelMaterial::from_id(c.materials[idx as usize]))
+    }
+
+    /// Sets a voxel's material, recording it as dirty when that changes it.
+    pub fn write_material(&mut self, pos: IVec3, mat: VoxelMaterial) {
+        let (coord, local) = self.locate(pos);
+        let size = self.chunk_size;
+        let idx = local.z * size * size + local.y * size + local.x;
+        let Some(chunk) = self.chunks.get_mut(&coord) else {
+            return;
+        };
+        if chunk.materials[idx as usize] == mat.id() {
+            return;
+        }
+        chunk.materials[idx as usize] = mat.id();
+        self.record(coord, pos, pos + 1);
+    }
+
+    /// The world voxel box covered by loaded chunks, as min (inclusive) and
+    /// max (exclusive) corners.
+    pub fn extent(&self) -> Option<(IVec3, IVec3)> {
+        let size = self.chunk_size as i32;
+        let min = self.chunks.keys().map(|c| c.0).reduce(IVec3::min)?;
+        let max = self.chunks.keys().map(|c| c.0).reduce(IVec3::max)?;
+        Some((min * size, (max + 1) * size))
+    }
+
+    /// `extent` in world space.
+    pub fn world_extent(&self) -> Option<(Vec3, Vec3)> {
+        let (min, max) = self.extent()?;
+        Some(self.transform.world_bounds(min.as_vec3(), max.as_vec3()))
+    }
+
+    /// Copies the cells just outside a chunk's faces (in chunk-local
+    /// coordinates) so the chunk can be meshed without the rest of the map.
+    /// With `Boundary::Periodic`, cells past the edge of the map wrap around
+    /// to its other side.
+    pub fn border(&self, coord: ChunkCoord, boundary: Boundary) -> HashMap<IVec3, f32> {
+        let size = self.chunk_size as i32;
+        let origin = coord.0 * size;
+        let wrap = match (boundary, self.extent()) {
+            (Boundary::Periodic, Some((min, max))) => Some((min, max - min)),
+            _ => None,
+        };
+        let mut border = HashMap::new();
+        for z in -1..=size {
+            for y in -1..=size {
+                for x in -1..=size {
+                    let p = IVec3::new(x, y, z);
+                    let out = p.cmplt(IVec3::ZERO) | p.cmpge(IVec3::splat(size));
+                    if out.bitmask().count_ones() != 1 {
+                        continue;
+                    }
+                    let val = self.read(origin + p).or_else(|| {
+                        let (min, span) = wrap?;
+                        self.read(min + (origin + p - min).rem_euclid(span))
+                    });
+                    if let Some(val) = val {
+                        border.insert(p, val);
+                    }
+                }
+            }
+        }
+        border
+    }
+
+    /// Writes a voxel by world voxel position, recording it as dirty. Border
+    /// voxels end up dirtying the neighboring chunk too, since its mesh
+    /// depends on them.
+    pub fn write(&mut self, pos: IVec3, val: f32) -> bool {
+        let (coord, local) = self.locate(pos);
+        let Some(chunk) = self.chunks.get_mut(&coord) else {
+            return false;
+        };
+        if chunk.grid.write(local.x, local.y, local.z, val).is_err() {
+            return false;
+        }
+        if let Some(occupancy) = &mut chunk.occupancy {
+            occupancy.set(local, val);
+        }
+        self.record(coord, pos, pos + 1);
+        true
+    }
+}
+
+/// Triggers a `RegionDirty` for each region `ChunkMap` recorded since last
+/// frame, just before `queue_remesh`.
+pub fn publish_dirty_regions(mut cmds: Commands, mut chunks: ResMut<ChunkMap>) {
+    // Left untouched otherwise, as `queue_remesh` and `record_mesh_timings`
+    // leave it on idle frames, so `ChunkMap` change detection still means
+    // something happened; whether any voxel did is `ChunkMap::edits`.
+    if chunks.regions.is_empty() {
+        return;
+    }
+    for region in chunks.take_regions() {
+        cmds.trigger(region);
+    }
+}
+
+/// The remesh scheduler's side of `RegionDirty`.
+pub fn mark_region_dirty(trigger: Trigger<RegionDirty>, mut chunks: ResMut<ChunkMap>) {
+    chunks.mark_region(*trigger.event());
+}
+
+/// The physics shape built for each chunk mesh.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChunkCollider {
+    /// Exact, but slow for dynamic bodies to collide against in bulk.
+    #[default]
+    Trimesh,
+    /// A trimesh of the surface through `MarchySettings::simplify` (or
+    /// `DECIMATED` when that's unset): fewer triangles, slightly off.
+    Decimated,
+    /// A cuboid per run of solid cells open to the air: blocky, but cheap
+    /// to build and to collide against.
+    Boxes,
+    /// Convex pieces approximating the surface. Slower to build, faster to
+    /// simulate against, and holes narrower than a piece close up.
+    Convex,
+}
+
+impl ChunkCollider {
+    pub const ALL: [ChunkCollider; 4] =
+        [ChunkCollider::Trimesh, ChunkCollider::Decimated, ChunkCollider::Boxes, ChunkCollider::Convex];
+
+    /// Decimation for `Decimated` when `MarchySettings::simplify` is unset.
+    pub const DECIMATED: Simplify = Simplify::Error(2.0);
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChunkCollider::Trimesh => "trimesh",
+            ChunkCollider::Decimated => "decimated",
+            ChunkCollider::Boxes => "boxes",
+            ChunkCollider::Convex => "convex",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        Self::ALL[(Self::ALL.iter().position(|&c| c == self).unwrap_or(0) + 1) % Self::ALL.len()]
+    }
+}
+
+/// CPU time of one meshing job, split between the surface and its collider.
+#[derive(Clone, Copy, Debug)]
+pub struct MeshTiming {
+    pub mesh: Duration,
+    pub collider: Duration,
+    /// What the collider was built as.
+    pub shape: ChunkCollider,
+}
+
+struct MeshResult {
+    mesh: Mesh,
+    collider: Option<Collider>,
+    timing: MeshTiming,
+}
+
+/// Meshing jobs running on the `AsyncComputeTaskPool`. Re-dirtying a chunk
+/// replaces (and so cancels) its in-flight job.
+#[derive(Resource, Default)]
+pub struct MeshTasks {
+    tasks: HashMap<ChunkCoord, Task<MeshResult>>,
+    /// Chunks holding or touching an awake dynamic body, whose colliders
+    /// are queued and applied first.
+    pub priority: HashSet<ChunkCoord>,
+    /// Timings of jobs applied since the diagnostics last drained it.
+    pub finished: Vec<MeshTiming>,
+}
+
+impl MeshTasks {
+    pub fn pending(&self) -> usize {
+        self.tasks.len()
+    }
+}
+
+/// Marks the chunks containing or next to an awake dynamic body as
+/// `MeshTasks::priority`.
+pub fn prioritize_near_bodies(
+    bodies: Query<(&RigidBody, &GlobalTransform), Without<Sleeping>>,
+    chunks: Res<ChunkMap>,
+    mut tasks: ResMut<MeshTasks>,
+) {
+    tasks.priority.clear();
+    for (body, transform) in &bodies {
+        if !body.is_dynamic() {
+            continue;
+        }
+        let (coord, _) = chunks.locate(chunks.voxel_at(transform.translation()));
+        tasks.priority.insert(coord);
+        tasks.priority.extend(coord.neighbors());
+    }
+}
+
+/// Hands every dirty chunk to a background meshing task, using the mesher
+/// named by `MarchySettings::mesher`; priority chunks are spawned first.
+/// Each task gets its own copy of the chunk and of the neighboring border
+/// cells, and builds the chunk's collider as well as its mesh.
+///
+/// With a nonzero `collider_downsample` the collider comes from a second,
+/// coarser mesh of the same chunk that only the physics sees.
+///
+/// Chunks with a nonzero `lod` are downsampled before meshing and the mesh
+/// scaled back up. Where two neighbors differ in detail their surfaces don't
+/// line up, so both treat the shared face as open air: each gets capped
+/// there, and the caps act as skirts hiding the crack.
+///
+/// Side faces towards a neighbor that has never been meshed (or, while
+/// streaming, isn't loaded yet) get short skirts hanging from the surface's
+/// edge, so the sky doesn't show through the ground while it generates.
+pub fn queue_remesh(
+    mut chunks: ResMut<ChunkMap>,
+    mut tasks: ResMut<MeshTasks>,
+    meshers: Res<Meshers>,
+    settings: Res<MarchySettings>,
+    mut warned: Local<bool>,
+) {
+    // Dirty chunks wait until the setting names a mesher that exists.
+    let Some(mesher) = meshers.get(&settings.mesher) else {
+        if !*warned {
+            let names: Vec<_> = meshers.names().collect();
+            warn!(target: "meshing", "unknown mesher {:?}, have {names:?}", settings.mesher);
+            *warned = true;
+        }
+        return;
+    };
+    *warned = false;
+    // Only touched mutably with work to do, so idle frames don't flag it changed.
+    if chunks.dirty_count() == 0 {
+        return;
+    }
+    let pool = AsyncComputeTaskPool::get();
+    let mut dirty: Vec<_> = std::iter::from_fn(|| chunks.pop_dirty()).collect();
+    dirty.sort_by_key(|c| !tasks.priority.contains(c));
+    let streaming = settings.streaming.is_some();
+    for coord in dirty {
+        let Some(chunk) = chunks.chunks.get_mut(&coord) else {
+            continue;
+        };
+        // Every way of changing a chunk's voxels ends up here.
+        chunk.occupancy = Some(Occupancy::build(&chunk.grid, settings.iso_level));
+        let lod = chunk.lod;
+        let shape = chunk.collider.unwrap_or(settings.chunk_collider);
+        let stride = 1 << lod;
+        let grid = if lod == 0 { chunk.grid.clone() } else { chunk.grid.downsample(stride) };
+        let occupancy = chunk.occupancy.clone().filter(|_| lod == 0);
+        let materials = chunk.materials.clone();
+        let collision_stride = stride << settings.collider_downsample;
+        let collision_grid = (collision_stride != stride).then(|| chunk.grid.downsample(collision_stride));
+        let border = chunks.border(coord, settings.boundary);
+        let seams: Vec<IVec3> = coord.neighbors()
+            .into_iter()
+            .filter(|n| chunks.get(*n).is_some_and(|c| c.lod != lod))
+            .map(|n| n.0 - coord.0)
+            .collect();
+        let skirts: Vec<IVec3> = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z]
+            .into_iter()
+            .filter(|&d| match chunks.get(ChunkCoord(coord.0 + d)) {
+                Some(n) => n.stats.remeshes == 0,
+                None => streaming,
+            })
+            .collect();
+        if let Some(chunk) = chunks.chunks.get_mut(&coord) {
+            chunk.skirts = skirts.clone();
+        }
+        let size = chunks.chunk_size as i32;
+        let options = MeshOptions {
+            iso: settings.iso_level,
+            boundary: settings.boundary,
+            smooth_normals: settings.smooth_normals,
+        };
+        let (ao_radius, ao_strength) = (settings.ao_radius, settings.ao_strength);
+        let simplify = settings.simplify;
+        let mesher = mesher.clone();
+
+        let task = pool.spawn(async move {
+            let start = Instant::now();
+            let coarse = grid.dims().as_ivec3();
+            let outside = |p: IVec3| border_sample(&border, &seams, coarse, stride, size, p);
+            // Each cell's material, read at full resolution.
+            let last = grid.dims() - 1;
+            let material = |c: IVec3| {
+                let f = c.clamp(IVec3::ZERO, last.as_ivec3()).as_uvec3() * stride;
+                materials[((f.z * size as u32 + f.y) * size as u32 + f.x) as usize]
+            };
+            let view = DensityView {
+                storage: &grid,
+                outside: &outside,
+                occupancy: occupancy.as_ref(),
+                materials: Some(&material),
+            };
+            let mut buffers = mesher.mesh(&view, &options);
+            buffers.paint(grid.dims(), |c| {
+                let p = c.clamp(IVec3::ZERO, last.as_ivec3()).as_uvec3();
+                let solid = grid.read(p.x, p.y, p.z)? <= options.iso;
+                solid.then(|| VoxelMaterial::from_id(material(c)).vertex_color())
+            });
+            buffers.bake_ao(grid.dims(), ao_radius, ao_strength, |p| {
+                view.sample(p, options.boundary).is_some_and(|v| v <= options.iso)
+            });
+            // The chunk's faces, with voxel `x` spanning `x - size/2 - 1..x - size/2`.
+            let half = Vec3::splat(size as f32 / 2.0);
+            // Vertices past the chunk's outermost cell centers are shared
+            // with a neighbor's mesh, so decimation leaves them be.
+            let (lo, hi) = (-half - 0.5, half - 1.5);
+            let seam = |p: Vec3| p.cmple(lo + 1e-3).any() || p.cmpge(hi - 1e-3).any();
+            if lod > 0 {
+                buffers.transform(stride as f32, Vec3::splat(stride as f32 - 1.0));
+                if let Some(simplify) = simplify {
+                    buffers = buffers.simplify(simplify, seam);
+                }
+            }
+            let collider_start = Instant::now();
+            let meshed = shape != ChunkCollider::Boxes;
+            let coarse_collision = collision_grid.as_ref().filter(|_| meshed).map(|grid| {
+                let coarse = grid.dims().as_ivec3();
+                let outside = |p: IVec3| border_sample(&border, &seams, coarse, collision_stride, size, p);
+                let view = DensityView { storage: grid, outside: &outside, occupancy: None, materials: None };
+                let mut buffers = mesher.mesh(&view, &options);
+                buffers.transform(collision_stride as f32, Vec3::splat(collision_stride as f32 - 1.0));
+                buffers
+            });
+            let decimated = (shape == ChunkCollider::Decimated).then(|| {
+                let target = simplify.unwrap_or(ChunkCollider::DECIMATED);
+                coarse_collision.as_ref().unwrap_or(&buffers).simplify(target, seam)
+            });
+            // Skirts are only for show, so keep them out of the collider.
+            let solid = decimated
+                .or(coarse_collision)
+                .or_else(|| (meshed && !skirts.is_empty()).then(|| buffers.clone()))
+                .map(BevyMesh::into_mesh);
+            let mut collider_time = collider_start.elapsed();
+            buffers.add_skirts(&skirts, -half - 1.0, half - 1.0, SKIRT_DEPTH * stride as f32);
+            let mesh = buffers.into_mesh();
+            let solid = solid.as_ref().unwrap_or(&mesh);
+            let mesh_time = start.elapsed() - collider_time;
+            let collider_start = Instant::now();
+            // Built on the task so only this chunk's shape is redone.
+            let collider = match shape {
+                ChunkCollider::Boxes => match &collision_grid {
+                    Some(grid) => voxel_boxes(grid, collision_stride, options.iso),
+                    None => voxel_boxes(&grid, stride, options.iso),
+                },
+                _ if solid.count_vertices() == 0 => None,
+                ChunkCollider::Trimesh | ChunkCollider::Decimated => Collider::trimesh_from_mesh(solid),
+                ChunkCollider::Convex => Collider::convex_decomposition_from_mesh(solid),
+            };
+            collider_time += collider_start.elapsed();
+            let timing = MeshTiming { mesh: mesh_time, collider: collider_time, shape };
+            MeshResult { mesh, collider, timing }
+        });
+        tasks.tasks.insert(coord, task);
+    }
+}
+
+/// The density just outside a chunk meshed at `stride` cells per mesh cell
+/// (as a `coarse` grid), from the full-resolution `border` cells one past
+/// its faces; `None` across `seams`.
+fn border_sample(
+    border: &HashMap<IVec3, f32>,
+    seams: &[IVec3],
+    coarse: IVec3,
+    stride: u32,
+    size: i32,
+    p: IVec3,
+) -> Option<f32> {
+    let dir = p.div_euclid(coarse).clamp(IVec3::NEG_ONE, IVec3::ONE);
+    if seams.contains(&dir) {
+        return None;
+    }
+    let full = IVec3::select(dir.cmplt(IVec3::ZERO), IVec3::NEG_ONE, p * stride as i32);
+    let full = IVec3::select(dir.cmpgt(IVec3::ZERO), IVec3::splat(size), full);
+    border.get(&full).copied()
+}
+
+/// Swaps finished meshes and colliders onto their chunk entities, at most
+/// `remesh_budget` per frame so a burst of edits doesn't cause a spike.
+/// Priority chunks don't count against the budget.
+///
+/// A chunk meshing for the first time gets its entity right away, with a box
+/// around its solid cells as a stand-in collider, so bodies don't fall
+/// through terrain that's still being built.
+pub fn apply_remesh(
+    mut cmds: Commands,
+    mut chunks: ResMut<ChunkMap>,
+    mut tasks: ResMut<MeshTasks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mats: Res<MarchyMaterials>,
+    settings: Res<MarchySettings>,
+    time: Res<Time>,
+) {
+    let size = chunks.chunk_size;
+    let MeshTasks { tasks: running, priority, .. } = &mut *tasks;
+    for coord in running.keys() {
+        let at = chunks.chunk_transform(*coord);
+        let Some(chunk) = chunks.chunks.get_mut(coord) else {
+            continue;
+        };
+        if chunk.entity.is_none() {
+            let entity = spawn_chunk(&mut cmds, *coord, at, &mats);
+            if let Some(placeholder) = placeholder_collider(&chunk.grid, settings.iso_level) {
+                cmds.entity(entity).insert(placeholder);
+            }
+            chunk.entity = Some(entity);
+        }
+    }
+
+    let mut done = vec![];
+    let mut budget = settings.remesh_budget;
+    let (urgent, rest): (Vec<_>, Vec<_>) = running
+        .iter_mut()
+        .partition(|(coord, _)| priority.contains(*coord));
+    for (coord, task) in urgent.into_iter().chain(rest) {
+        let urgent = priority.contains(coord);
+        if !urgent && budget == 0 {
+            break;
+        }
+        if let Some(result) = block_on(future::poll_once(task)) {
+            done.push((*coord, result));
+            if !urgent {
+                budget -= 1;
+            }
+        }
+    }
+
+    for (coord, MeshResult { mesh, collider, timing }) in done {
+        tasks.tasks.remove(&coord);
+        tasks.finished.push(timing);
+        debug!(target: "meshing", "remeshed {:?} in {:?} (+{:?} collider)", coord.0, timing.mesh, timing.collider);
+        let at = chunks.chunk_transform(coord);
+        let Some(chunk) = chunks.chunks.get_mut(&coord) else {
+            continue;
+        };
+        let first = chunk.stats.remeshes == 0;
+        chunk.stats.triangles = mesh.indices().map_or(0, |i| i.len() / 3);
+        chunk.stats.vertices = mesh.count_vertices();
+        chunk.stats.remeshes += 1;
+        chunk.stats.last_remesh = time.elapsed_secs();
+        let entity = *chunk.entity.get_or_insert_with(|| spawn_chunk(&mut cmds, coord, at, &mats));
+        if first && settings.streaming.as_ref().is_some_and(|s| s.fade) {
+            cmds.entity(entity).insert(ChunkFade::rising(at, size));
+        }
+        if first {
+            // Neighbors skirting the gap this chunk just filled can drop them.
+            for n in coord.neighbors() {
+                if chunks.get(n).is_some_and(|c| c.skirts.contains(&(coord.0 - n.0))) {
+                    chunks.mark_dirty(n);
+                }
+            }
+        }
+
+        // No collider doesn't mean nothing to see: the shape can fail to
+        // build, and skirts are left out of it. The stand-in box keeps
+        // bodies up where it does fail.
+        let mut entity = cmds.entity(entity);
+        if mesh.count_vertices() == 0 {
+            entity.remove::<Mesh3d>();
+        } else {
+            entity.insert(Mesh3d(meshes.add(mesh)));
+        }
+        match collider.or_else(|| placeholder_collider(&chunk.grid, settings.iso_level)) {
+            Some(collider) => entity.insert(collider),
+            None => entity.remove::<Collider>(),
+        };
+    }
+}
+
+fn spawn_chunk(cmds: &mut Commands, coord: ChunkCoord, at: Transform, mats: &MarchyMaterials) -> Entity {
+    cmds.spawn((
+        Name::new(format!("chunk {}", coord.0)),
+        ChunkMesh(coord),
+        MeshMaterial3d(mats.terrain.clone()),
+        RigidBody::Static,
+        at,
+        CollidingEntities::default()
+    )).id()
+}
+
+/// A cuboid per run along X of solid cells with a face open to the air
+/// (cells past the grid's edge count as air), in the chunk entity's space
+/// for a grid meshed at `stride`. Buried cells can't be reached, so they're
+/// left out.
+fn voxel_boxes(grid: &VoxelGrid, stride: u32, iso: f32) -> Option<Collider> {
+    let dims = grid.dims().as_ivec3();
+    let solid = |c: IVec3| {
+        c.cmpge(IVec3::ZERO).all() && c.cmplt(dims).all()
+            && grid.read(c.x as u32, c.y as u32, c.z as u32).is_some_and(|v| v <= iso)
+    };
+    let faces = [IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y, IVec3::Z, IVec3::NEG_Z];
+    let exposed = |c: IVec3| solid(c) && faces.iter().any(|&d| !solid(c + d));
+    // Cell `x` spans `x - size/2 - 1 .. x - size/2`, then scaled up like
+    // the mesh of a downsampled chunk.
+    let offset = dims.as_vec3() / 2.0 + 1.0;
+    let s = stride as f32;
+    let to_chunk = |p: Vec3| (p - offset) * s + (s - 1.0);
+    let mut boxes = vec![];
+    for z in 0..dims.z {
+        for y in 0..dims.y {
+            let mut start = None;
+            for x in 0..=dims.x {
+                let open = x < dims.x && exposed(IVec3::new(x, y, z));
+                match (open, start) {
+                    (true, None) => start = Some(x),
+                    (false, Some(x0)) => {
+                        let min = to_chunk(Vec3::new(x0 as f32, y as f32, z as f32));
+                        let max = to_chunk(Vec3::new(x as f32, y as f32 + 1.0, z as f32 + 1.0));
+                        let size = max - min;
+                        boxes.push(((min + max) / 2.0, Quat::IDENTITY, Collider::cuboid(size.x, size.y, size.z)));
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    (!boxes.is_empty()).then(|| Collider::compound(boxes))
+}
+
+/// A box around a chunk's solid cells, in the chunk entity's space.
+fn placeholder_collider(grid: &VoxelGrid, iso: f32) -> Option<Collider> {
+    let dims = grid.dims();
+    let mut bounds: Option<(UVec3, UVec3)> = None;
+    for z in 0..dims.z {
+        for y in 0..dims.y {
+            for x in 0..dims.x {
+                if grid.read(x, y, z).is_some_and(|v| v <= iso) {
+                    let c = UVec3::new(x, y, z);
+                    bounds = Some(bounds.map_or((c, c), |(lo, hi)| (lo.min(c), hi.max(c))));
+                }
+            }
+        }
+    }
+    let (lo, hi) = bounds?;
+    // Cell `x` spans `x - size/2 - 1 .. x - size/2`.
+    let offset = dims.as_vec3() / 2.0 + 1.0;
+    let (min, max) = (lo.as_vec3() - offset, (hi + 1).as_vec3() - offset);
+    let size = max - min;
+    Some(Collider::compound(vec![(
+        (min + max) / 2.0,
+        Quat::IDENTITY,
+        Collider::cuboid(size.x, size.y, size.z),
+    )]))
+}
@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+use std::{collections::HashMap, io};
+use crate::{
+    chunk::{ChunkCoord, ChunkMap},
+    MarchySettings,
+    VoxelGrid,
+};
+
+/// Flipped cells drawn before the rest of the diff is skipped.
+const MAX_POINTS: usize = 20_000;
+/// Chunks re-encoded per frame while the diff is open.
+const DIFF_BUDGET: usize = 4;
+
+/// How a grid fares through the lossy codec against lossless RLE.
+#[derive(Clone, Debug, Default)]
+pub struct CodecReport {
+    pub lossless_bytes: usize,
+    pub lossy_bytes: usize,
+    pub max_error: f32,
+    pub rms_error: f32,
+    /// Cells on the other side of the iso level after decoding, and
+    /// whether they were solid before.
+    pub flipped: Vec<(UVec3, bool)>,
+}
+
+pub fn compare(grid: &VoxelGrid, quality: u8, iso: f32) -> io::Result<CodecReport> {
+    let (mut lossless, mut lossy) = (vec![], vec![]);
+    grid.write_to(&mut lossless, true)?;
+    grid.write_lossy(&mut lossy, quality)?;
+    let decoded = VoxelGrid::read_from(&mut lossy.as_slice())?;
+    let mut report = CodecReport { lossless_bytes: lossless.len(), lossy_bytes: lossy.len(), ..default() };
+    let mut sum = 0.0;
+    grid.each(|x, y, z, before| {
+        let after = decoded.read(x, y, z).unwrap_or_default();
+        let error = (after - before).abs();
+        report.max_error = report.max_error.max(error);
+        sum += error * error;
+        if (before <= iso) != (after <= iso) {
+            report.flipped.push((UVec3::new(x, y, z), before <= iso));
+        }
+    });
+    report.rms_error = (sum / grid.data.len().max(1) as f32).sqrt();
+    Ok(report)
+}
+
+/// Debug view of what the lossy codec would lose: every cell whose side of
+/// the surface it flips, red where solid turns to air and green where air
+/// turns solid, with the totals logged.
+#[derive(Resource)]
+pub struct CodecDiff {
+    pub enabled: bool,
+    pub quality: u8,
+    /// Per chunk, the remesh and quality its report was made at.
+    reports: HashMap<ChunkCoord, (u32, u8, CodecReport)>,
+    logged: bool,
+}
+
+impl Default for CodecDiff {
+    fn default() -> Self {
+        CodecDiff { enabled: false, quality: 50, reports: HashMap::new(), logged: false }
+    }
+}
+
+/// Ctrl+F8 toggles the codec diff. While it's on, Ctrl+Minus and
+/// Ctrl+Equal lower and raise the quality.
+pub fn codec_diff_keys(keys: Res<ButtonInput<KeyCode>>, mut diff: ResMut<CodecDiff>) {
+    if !keys.pressed(KeyCode::ControlLeft) {
+        return;
+    }
+    if keys.just_pressed(KeyCode::F8) {
+        diff.enabled = !diff.enabled;
+        diff.logged = false;
+        info!("codec diff: {} (quality {})", diff.enabled, diff.quality);
+    }
+    if !diff.enabled {
+        return;
+    }
+    let quality = diff.quality;
+    if keys.just_pressed(KeyCode::Minus) {
+        diff.quality = quality.saturating_sub(10).max(1);
+    }
+    if keys.just_pressed(KeyCode::Equal) {
+        diff.quality = (quality + 10).min(100);
+    }
+    if diff.quality != quality {
+        diff.logged = false;
+        info!("codec diff quality: {}", diff.quality);
+    }
+}
+
+pub fn draw_codec_diff(
+    mut diff: ResMut<CodecDiff>,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+    mut gizmos: Gizmos,
+) {
+    if !diff.enabled {
+        return;
+    }
+    let quality = diff.quality;
+    diff.reports.retain(|coord, _| chunks.get(*coord).is_some());
+    let stale: Vec<ChunkCoord> = chunks
+        .iter()
+        .filter(|(coord, chunk)| {
+            !diff.reports.get(*coord).is_some_and(|(r, q, _)| *r == chunk.stats.remeshes && *q == quality)
+        })
+        .map(|(coord, _)| *coord)
+        .take(DIFF_BUDGET)
+        .collect();
+    for coord in &stale {
+        let Some(chunk) = chunks.get(*coord) else {
+            continue;
+        };
+        match compare(&chunk.grid, quality, settings.iso_level) {
+            Ok(report) => {
+                diff.reports.insert(*coord, (chunk.stats.remeshes, quality, report));
+            }
+            Err(e) => warn!("codec diff failed for {:?}: {e}", coord.0),
+        }
+        diff.logged = false;
+    }
+
+    if stale.is_empty() && !diff.logged {
+        diff.logged = true;
+        let reports = || diff.reports.values().map(|(_, _, r)| r);
+        let lossless: usize = reports().map(|r| r.lossless_bytes).sum();
+        let lossy: usize = reports().map(|r| r.lossy_bytes).sum();
+        let flipped: usize = reports().map(|r| r.flipped.len()).sum();
+        let max_error = reports().map(|r| r.max_error).fold(0.0, f32::max);
+        let rms = reports().map(|r| r.rms_error).sum::<f32>() / diff.reports.len().max(1) as f32;
+        info!(
+            "codec diff q{quality}: {lossless} -> {lossy} bytes ({:.1}x), max error {max_error:.3}, \
+            mean rms {rms:.3}, {flipped} cells flipped",
+            lossless as f32 / lossy.max(1) as f32,
+        );
+    }
+
+    let size = chunks.chunk_size as i32;
+    let mut drawn = 0;
+    for (coord, (_, _, report)) in &diff.reports {
+        for &(local, was_solid) in &report.flipped {
+            if drawn == MAX_POINTS {
+                return;
+            }
+            drawn += 1;
+            let color = if was_solid { Color::srgb(1.0, 0.2, 0.2) } else { Color::srgb(0.2, 1.0, 0.3) };
+            let center = chunks.voxel_center(coord.0 * size + local.as_ivec3());
+            gizmos.sphere(center, 0.12, color).resolution(4);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::{Path, PathBuf}, str::FromStr};
+use crate::{
+    culling::ChunkCulling,
+    fluid::FluidLayer,
+    quality::Quality,
+    scatter::ScatterSettings,
+    scene::read_scene,
+    streaming::Streaming,
+    transform::GridTransform,
+    MarchySettings,
+};
+
+const DEFAULT_PATH: &str = "marchy.toml";
+
+/// Startup knobs for experimenting without a rebuild, read from
+/// `marchy.toml` (or `--config <path>`) and then overridden by flags:
+/// `--grid-size`, `--voxel-size`, `--iso`, `--seed`, `--generator`, `--mesher`,
+/// `--heightmap`, `--physics on|off`, `--balls`, `--camera-radius`, `--stream`,
+/// `--scatter`, `--water-level`, `--lossy-saves`, `--quality`, `--day-length`,
+/// `--time-of-day`, `--draw-distance` and `--rest-colliders`. `--scene <dir>`
+/// starts from a saved scene's config (ahead of any other) and loads the
+/// scene once the app is up.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarchyConfig {
+    pub grid_size: u32,
+    /// World units per voxel.
+    pub voxel_size: f32,
+    pub iso_level: f32,
+    /// Seeds the terrain and `SeededRng`.
+    pub seed: u64,
+    /// `shape` (the app's test shape), or a `Generator::named` generator.
+    pub generator: String,
+    /// A grayscale image to build the ground from instead of `generator`.
+    pub heightmap: Option<PathBuf>,
+    /// A backend registered in `Meshers`.
+    pub mesher: String,
+    pub physics: bool,
+    /// Balls dropped on the terrain at startup.
+    pub balls: usize,
+    pub camera_radius: f32,
+    /// Stream endless terrain around the viewer.
+    pub stream: bool,
+    /// Strew rocks over the terrain.
+    pub scatter: bool,
+    /// Flood every open cell below this height with water.
+    pub water_level: Option<f32>,
+    /// Save densities lossily at this quality (1 to 100); experimental.
+    pub lossy_saves: Option<u8>,
+    /// A `Quality` preset by name, or `auto` to benchmark and pick one.
+    pub quality: String,
+    /// Seconds per day/night cycle; 0 holds the sun still.
+    pub day_length: f32,
+    /// Time of day at startup, 0 to 1 with noon at 0.5.
+    pub time_of_day: f32,
+    /// Hide chunks off screen or farther than this.
+    pub draw_distance: Option<f32>,
+    /// Rest the colliders of chunks with nothing moving within this; turns
+    /// on the same culling as `draw_distance`.
+    pub rest_colliders: Option<f32>,
+    /// A scene to load after startup; only ever set by `--scene`.
+    #[serde(skip)]
+    pub scene: Option<PathBuf>,
+}
+
+impl Default for MarchyConfig {
+    fn default() -> Self {
+        let settings = MarchySettings::default();
+        MarchyConfig {
+            grid_size: settings.grid_size,
+            voxel_size: 1.0,
+            iso_level: settings.iso_level,
+            seed: settings.terrain.seed,
+            generator: "shape".into(),
+            heightmap: None,
+            mesher: settings.mesher,
+            physics: true,
+            balls: 30,
+            camera_radius: 20.0,
+            stream: false,
+            scatter: false,
+            water_level: None,
+            lossy_saves: None,
+            quality: "auto".into(),
+            day_length: settings.day.length,
+            time_of_day: settings.day.start,
+            draw_distance: None,
+            rest_colliders: None,
+            scene: None,
+        }
+    }
+}
+
+impl MarchyConfig {
+    /// The config file, if there is one, with any flags in `args` applied
+    /// on top. Flags it doesn't know are left for others (like `Scenario`).
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let args: Vec<String> = args.into_iter().collect();
+        let flag = |name: &str| args.windows(2).find(|w| w[0] == name).map(|w| PathBuf::from(&w[1]));
+        let (scene, path) = (flag("--scene"), flag("--config"));
+        let mut config = match (&scene, &path) {
+            (Some(dir), _) => Self::read_scene(dir)?,
+            (None, Some(path)) => Self::read(path)?,
+            (None, None) if fs::exists(DEFAULT_PATH).unwrap_or(false) => Self::read(&PathBuf::from(DEFAULT_PATH))?,
+            (None, None) => Self::default(),
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--grid-size" => config.grid_size = parse(&arg, value()?)?,
+                "--voxel-size" => config.voxel_size = parse(&arg, value()?)?,
+                "--iso" => config.iso_level = parse(&arg, value()?)?,
+                "--seed" => config.seed = parse(&arg, value()?)?,
+                "--generator" => config.generator = value()?,
+                "--heightmap" => config.heightmap = Some(PathBuf::from(value()?)),
+                "--mesher" => config.mesher = value()?,
+                "--physics" => {
+                    config.physics = match value()?.as_str() {
+                        "on" => true,
+                        "off" => false,
+                        v => return Err(format!("bad --physics {v:?} (on, off)")),
+                    }
+                }
+                "--balls" => config.balls = parse(&arg, value()?)?,
+                "--camera-radius" => config.camera_radius = parse(&arg, value()?)?,
+                "--stream" => config.stream = true,
+                "--scatter" => config.scatter = true,
+                "--water-level" => config.water_level = Some(parse(&arg, value()?)?),
+                "--lossy-saves" => config.lossy_saves = Some(parse(&arg, value()?)?),
+                "--quality" => config.quality = value()?,
+                "--day-length" => config.day_length = parse(&arg, value()?)?,
+                "--time-of-day" => config.time_of_day = parse(&arg, value()?)?,
+                "--draw-distance" => config.draw_distance = Some(parse(&arg, value()?)?),
+                "--rest-colliders" => config.rest_colliders = Some(parse(&arg, value()?)?),
+                _ => {}
+            }
+        }
+        if config.voxel_size <= 0.0 || !config.voxel_size.is_finite() {
+            return Err(format!("bad --voxel-size {}: must be positive", config.voxel_size));
+        }
+        if config.day_length < 0.0 || !config.day_length.is_finite() {
+            return Err(format!("bad --day-length {}: must be 0 or more", config.day_length));
+        }
+        if config.quality != "auto" && Quality::named(&config.quality).is_none() {
+            return Err(format!("unknown quality {:?} (auto, low, medium, high, ultra)", config.quality));
+        }
+        Ok(config)
+    }
+
+    fn read_scene(dir: &Path) -> Result<Self, String> {
+        let scene = read_scene(dir).map_err(|e| format!("can't read scene {}: {e}", dir.display()))?;
+        Ok(MarchyConfig { scene: Some(dir.to_path_buf()), ..scene.config })
+    }
+
+    fn read(path: &PathBuf) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("can't read {}: {e}", path.display()))?;
+        toml::from_str(&text).map_err(|e| format!("bad config {}: {e}", path.display()))
+    }
+
+    /// Copies everything but the generator, balls and camera (which are
+    /// the app's to set up) into the settings.
+    pub fn apply(&self, settings: &mut MarchySettings) {
+        settings.grid_size = self.grid_size;
+        settings.grid_transform = (self.voxel_size != 1.0)
+            .then(|| GridTransform::centered(self.grid_size).scaled(self.voxel_size));
+        settings.iso_level = self.iso_level;
+        settings.terrain.seed = self.seed;
+        settings.rng_seed = self.seed;
+        settings.mesher = self.mesher.clone();
+        settings.physics.enabled = self.physics;
+        settings.streaming = self.stream.then(Streaming::default);
+        settings.scatter = self.scatter.then(ScatterSettings::default);
+        settings.fluids = self.water_level.into_iter().map(FluidLayer::water).collect();
+        settings.lossy_saves = self.lossy_saves;
+        settings.day.length = self.day_length;
+        settings.day.start = self.time_of_day.rem_euclid(1.0);
+        settings.culling = (self.draw_distance.is_some() || self.rest_colliders.is_some()).then(|| ChunkCulling {
+            draw_distance: self.draw_distance,
+            frustum: true,
+            collider_radius: self.rest_colliders,
+        });
+        let quality = Quality::named(&self.quality).unwrap_or_else(Quality::detect);
+        quality.apply(settings);
+    }
+}
+
+fn parse<T: FromStr>(arg: &str, value: String) -> Result<T, String>
+where T::Err: std::fmt::Display {
+    value.parse().map_err(|e| format!("bad {arg} {value:?}: {e}"))
+}
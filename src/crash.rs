@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use std::{
+    backtrace::Backtrace,
+    fs,
+    panic,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use crate::{chunk::ChunkMap, save::write_world, MarchySettings};
+
+/// Seconds between world snapshots while the world keeps changing.
+const SNAPSHOT_INTERVAL: f32 = 5.0;
+
+/// What the panic hook writes out: kept up to date by systems, since the
+/// hook itself can't reach the ECS.
+#[derive(Default)]
+struct CrashState {
+    dir: PathBuf,
+    settings: String,
+    /// The latest world snapshot in the F5 save format.
+    world: Vec<u8>,
+}
+
+#[derive(Resource)]
+pub struct CrashGuard {
+    state: Arc<Mutex<CrashState>>,
+    since_snapshot: f32,
+    /// `ChunkMap::edits` as of the last world snapshot.
+    snapshot_edits: u64,
+}
+
+/// Installs a panic hook that writes `crash-<time>.txt` (panic message,
+/// backtrace, settings and seed) and `crash-<time>.mwld` (the last world
+/// snapshot, loadable with F9 after renaming) to `crash_dir`, then hands
+/// over to the previous hook. Does nothing on the web.
+pub struct CrashPlugin;
+
+impl Plugin for CrashPlugin {
+    fn build(&self, app: &mut App) {
+        // A browser has no clock for `SystemTime` or disk to write to, and a
+        // panic in the hook would swallow the original message.
+        if cfg!(target_arch = "wasm32") {
+            return;
+        }
+        let guard = CrashGuard {
+            state: default(),
+            since_snapshot: 0.0,
+            snapshot_edits: 0,
+        };
+        let state = guard.state.clone();
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            write_crash_report(&state, &info.to_string());
+            previous(info);
+        }));
+        app.insert_resource(guard)
+            .add_systems(Last, snapshot_for_crash);
+    }
+}
+
+fn write_crash_report(state: &Mutex<CrashState>, message: &str) {
+    // A panic while the snapshot was being taken leaves it poisoned, but the
+    // last complete snapshot is still worth writing.
+    let state = state.lock().unwrap_or_else(|e| e.into_inner());
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    if fs::create_dir_all(&state.dir).is_err() {
+        return;
+    }
+    let report = format!(
+        "{message}\n\n{}\n\nbacktrace:\n{}\n",
+        state.settings,
+        Backtrace::force_capture(),
+    );
+    let base = state.dir.join(format!("crash-{stamp}"));
+    let report_ok = fs::write(base.with_extension("txt"), report).is_ok();
+    let world_ok = state.world.is_empty() || fs::write(base.with_extension("mwld"), &state.world).is_ok();
+    eprintln!(
+        "crash report {}{}",
+        base.display(),
+        if report_ok && world_ok { " written" } else { " could not be fully written" },
+    );
+}
+
+fn describe(settings: &MarchySettings) -> String {
+    format!(
+        "seed: {}\nrng seed: {}\ngenerator: {:?}\ntile: {:?}\ngrid_size: {}\niso_level: {}\nmesher: {}\nboundary: {:?}",
+        settings.terrain.seed,
+        settings.rng_seed,
+        settings.terrain.generator,
+        settings.terrain.tile,
+        settings.grid_size,
+        settings.iso_level,
+        settings.mesher,
+        settings.boundary,
+    )
+}
+
+/// Keeps the crash state current: settings whenever they change, and the
+/// world every few seconds while it's being edited.
+pub fn snapshot_for_crash(
+    mut guard: ResMut<CrashGuard>,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+    time: Res<Time>,
+) {
+    if settings.is_changed() {
+        let mut state = guard.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.dir = settings.crash_dir.clone();
+        state.settings = describe(&settings);
+    }
+    guard.since_snapshot += time.delta_secs();
+    if chunks.edits() == guard.snapshot_edits || guard.since_snapshot < SNAPSHOT_INTERVAL {
+        return;
+    }
+    guard.since_snapshot = 0.0;
+    guard.snapshot_edits = chunks.edits();
+    let mut world = vec![];
+    if write_world(&chunks, &mut world, None).is_ok() {
+        guard.state.lock().unwrap_or_else(|e| e.into_inner()).world = world;
+    }
+}
@@ -0,0 +1,108 @@
+use avian3d::prelude::*;
+use bevy::{math::Affine3A, prelude::*, render::primitives::{Aabb, Frustum}};
+use crate::{
+    camera::Cam,
+    chunk::{ChunkMap, ChunkMesh},
+    shells::IsoShell,
+    MarchySettings,
+};
+
+/// Which chunks are drawn and simulated. A chunk is hidden when it's
+/// wholly outside the camera's view or farther than the draw distance;
+/// its collider can also rest while nothing moving is near it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkCulling {
+    /// Chunks with no point this close to the camera are hidden; `None`
+    /// draws out to the far plane.
+    pub draw_distance: Option<f32>,
+    /// Hide chunks entirely off screen.
+    pub frustum: bool,
+    /// Disable the colliders of chunks with no dynamic body (or the camera)
+    /// within this distance; `None` keeps them all. The cursor can't pick
+    /// terrain whose collider is resting.
+    pub collider_radius: Option<f32>,
+}
+
+impl Default for ChunkCulling {
+    fn default() -> Self {
+        ChunkCulling { draw_distance: Some(150.0), frustum: true, collider_radius: None }
+    }
+}
+
+/// Counts from this frame's culling, for the diagnostics overlay.
+#[derive(Resource, Default)]
+pub struct Culling {
+    pub hidden: usize,
+    pub resting: usize,
+}
+
+/// How far `p` is from the box `min..max`.
+fn box_distance(p: Vec3, (min, max): (Vec3, Vec3)) -> f32 {
+    p.clamp(min, max).distance(p)
+}
+
+/// Shows or hides every chunk mesh. Runs once the camera's frustum is
+/// current and before visibility propagates, so nothing pops in a frame
+/// late. This also hides the terrain under its iso shells, and the shells
+/// of culled chunks.
+pub fn cull_chunks(
+    settings: Res<MarchySettings>,
+    chunks: Res<ChunkMap>,
+    cams: Query<(&GlobalTransform, &Frustum), With<Cam>>,
+    mut culling: ResMut<Culling>,
+    mut terrain: Query<(&ChunkMesh, &mut Visibility, Option<&Children>)>,
+    mut shells: Query<&mut Visibility, (With<IsoShell>, Without<ChunkMesh>)>,
+) {
+    let view = settings.culling.as_ref().zip(cams.single().ok());
+    culling.hidden = 0;
+    for (chunk, mut vis, children) in &mut terrain {
+        let culled = view.is_some_and(|(config, (cam, frustum))| {
+            let bounds = chunks.chunk_bounds(chunk.0);
+            let far = config.draw_distance.is_some_and(|d| box_distance(cam.translation(), bounds) > d);
+            let aabb = Aabb::from_min_max(bounds.0, bounds.1);
+            far || (config.frustum && !frustum.intersects_obb(&aabb, &Affine3A::IDENTITY, true, false))
+        });
+        culling.hidden += culled as usize;
+        let hide = culled || settings.iso_shells.is_some();
+        vis.set_if_neq(if hide { Visibility::Hidden } else { Visibility::Inherited });
+        for &child in children.into_iter().flatten() {
+            if let Ok(mut shell) = shells.get_mut(child) {
+                shell.set_if_neq(if culled { Visibility::Hidden } else { Visibility::Visible });
+            }
+        }
+    }
+}
+
+/// Puts chunk colliders with nothing moving near them to rest, and wakes
+/// them as soon as something comes within `collider_radius`. Runs after
+/// the frame's spawns land, so a new ball never meets a resting chunk.
+pub fn rest_far_colliders(
+    mut cmds: Commands,
+    settings: Res<MarchySettings>,
+    chunks: Res<ChunkMap>,
+    bodies: Query<(&RigidBody, &GlobalTransform)>,
+    cams: Query<&GlobalTransform, With<Cam>>,
+    terrain: Query<(Entity, &ChunkMesh, Has<ColliderDisabled>)>,
+    mut culling: ResMut<Culling>,
+) {
+    let radius = settings.culling.as_ref().and_then(|c| c.collider_radius);
+    let movers: Vec<Vec3> = bodies
+        .iter()
+        .filter(|(body, _)| body.is_dynamic())
+        .map(|(_, t)| t.translation())
+        .chain(cams.iter().map(|t| t.translation()))
+        .collect();
+    culling.resting = 0;
+    for (entity, chunk, resting) in &terrain {
+        let rest = radius.is_some_and(|r| {
+            let bounds = chunks.chunk_bounds(chunk.0);
+            !movers.iter().any(|&p| box_distance(p, bounds) <= r)
+        });
+        culling.resting += rest as usize;
+        if rest && !resting {
+            cmds.entity(entity).insert(ColliderDisabled);
+        } else if !rest && resting {
+            cmds.entity(entity).remove::<ColliderDisabled>();
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use bevy::{
+    pbr::{CascadeShadowConfig, CascadeShadowConfigBuilder, NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::{mesh::VertexAttributeValues, render_resource::Face},
+};
+use std::f32::consts::TAU;
+use crate::{camera::Cam, MarchySettings};
+
+/// Sky dome radius, inside the camera's far plane.
+const SKY_RADIUS: f32 = 800.0;
+
+/// The sun's trip across the sky. Time of day runs 0 to 1: sunrise at
+/// 0.25, noon at 0.5, sunset at 0.75. It follows game time, so pausing
+/// stops the sun too.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DayCycle {
+    /// Seconds for a whole day; 0 holds the sun at `start`.
+    pub length: f32,
+    /// Time of day at startup.
+    pub start: f32,
+    /// Sun height at noon, in degrees above the horizon.
+    pub peak: f32,
+    /// Sun illuminance at noon, in lux.
+    pub noon_lux: f32,
+    /// Ambient brightness at noon; nights get a fifth of it.
+    pub ambient: f32,
+    /// Paint a sky gradient around the camera rather than the clear color.
+    pub sky: bool,
+}
+
+impl Default for DayCycle {
+    fn default() -> Self {
+        DayCycle {
+            length: 0.0,
+            start: 0.375,
+            peak: 60.0,
+            noon_lux: light_consts::lux::OVERCAST_DAY,
+            ambient: 100.0,
+            sky: true,
+        }
+    }
+}
+
+impl DayCycle {
+    /// Unit vector toward the sun. It rises in the east (+x), and peaks
+    /// over the south (+z), where the camera starts out looking from.
+    pub fn sun_dir(&self, time: f32) -> Vec3 {
+        let a = (time - 0.25) * TAU;
+        let peak = self.peak.clamp(1.0, 89.0).to_radians();
+        Vec3::new(a.cos(), a.sin() * peak.sin(), a.sin() * peak.cos())
+    }
+}
+
+/// How the sun's shadow map is split over distance. More, tighter cascades
+/// keep shadows on big terrain from shimmering as the camera moves.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SunShadows {
+    pub cascades: usize,
+    /// Far edge of the first, sharpest cascade.
+    pub first_far: f32,
+    /// Nothing casts shadows past this.
+    pub max_distance: f32,
+    /// How much neighboring cascades blend into each other, 0 to 1.
+    pub overlap: f32,
+}
+
+impl Default for SunShadows {
+    fn default() -> Self {
+        SunShadows { cascades: 4, first_far: 12.0, max_distance: 150.0, overlap: 0.3 }
+    }
+}
+
+impl SunShadows {
+    pub fn config(&self) -> CascadeShadowConfig {
+        CascadeShadowConfigBuilder {
+            num_cascades: self.cascades.max(1),
+            // The builder wants each bound past the one before.
+            first_cascade_far_bound: self.first_far.clamp(0.5, self.max_distance * 0.5),
+            maximum_distance: self.max_distance.max(1.0),
+            overlap_proportion: self.overlap,
+            ..default()
+        }
+        .build()
+    }
+}
+
+#[derive(Component)]
+pub struct Sun;
+
+#[derive(Component)]
+pub struct Sky;
+
+/// Where the day is up to.
+#[derive(Resource, Default)]
+pub struct Daylight {
+    /// Game seconds since startup.
+    elapsed: f32,
+    /// The time the sky was last painted at.
+    painted: Option<f32>,
+}
+
+impl Daylight {
+    pub fn time_of_day(&self, day: &DayCycle) -> f32 {
+        let turns = if day.length > 0.0 { self.elapsed / day.length } else { 0.0 };
+        (day.start + turns).rem_euclid(1.0)
+    }
+}
+
+/// How far the sun is over the horizon, as a 0 to 1 ramp from `lo` to `hi`.
+fn ramp(lo: f32, hi: f32, h: f32) -> f32 {
+    let t = ((h - lo) / (hi - lo)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Linear sky color in direction `n` with the sun toward `sun`.
+fn sky_color(n: Vec3, sun: Vec3) -> Vec3 {
+    let (day, dusk) = (ramp(-0.1, 0.3, sun.y), 1.0 - ramp(0.0, 0.3, sun.y.abs()));
+    let zenith = Vec3::new(0.004, 0.005, 0.02).lerp(Vec3::new(0.05, 0.15, 0.6), day);
+    let horizon = Vec3::new(0.01, 0.012, 0.03).lerp(Vec3::new(0.45, 0.6, 0.85), day);
+    let horizon = horizon.lerp(Vec3::new(0.9, 0.35, 0.1), dusk * 0.8);
+    let up = n.y.max(0.0).sqrt();
+    // Below the horizon fades toward a dim ground.
+    let color = if n.y >= 0.0 { horizon.lerp(zenith, up) } else { horizon * (1.0 + n.y * 0.7) };
+    color + Vec3::new(1.0, 0.6, 0.3) * n.dot(sun).max(0.0).powi(12) * (0.2 + dusk) * day.max(dusk)
+}
+
+pub fn spawn_sun(
+    mut cmds: Commands,
+    settings: Res<MarchySettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    cmds.spawn((
+        Name::new("sun"),
+        Sun,
+        DirectionalLight {
+            illuminance: settings.day.noon_lux,
+            shadows_enabled: settings.shadow_map.is_some(),
+            ..default()
+        },
+        settings.sun_shadows.config(),
+    ));
+    let mut dome = Sphere::new(SKY_RADIUS).mesh().uv(32, 18);
+    let count = dome.count_vertices();
+    dome.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![[0.0f32; 4]; count]);
+    cmds.spawn((
+        Name::new("sky"),
+        Sky,
+        Mesh3d(meshes.add(dome)),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            unlit: true,
+            fog_enabled: false,
+            // Seen from inside.
+            cull_mode: Some(Face::Front),
+            ..default()
+        })),
+        NotShadowCaster,
+        NotShadowReceiver,
+        Visibility::Hidden,
+    ));
+}
+
+/// Moves the sun and sets its light, the ambient light and the sky for the
+/// time of day. With the sun down a dim bluish moon shines from opposite.
+pub fn update_daylight(
+    time: Res<Time>,
+    settings: Res<MarchySettings>,
+    mut daylight: ResMut<Daylight>,
+    ambient: Option<ResMut<AmbientLight>>,
+    mut suns: Query<(&mut DirectionalLight, &mut Transform), With<Sun>>,
+    mut sky: Query<(&Mesh3d, &mut Visibility), With<Sky>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let day = &settings.day;
+    daylight.elapsed += time.delta_secs();
+    let now = daylight.time_of_day(day);
+    let sun = day.sun_dir(now);
+    let (lit, low) = (ramp(-0.02, 0.25, sun.y), ramp(0.0, 0.4, sun.y));
+    for (mut light, mut transform) in &mut suns {
+        let (toward, lux, color) = if sun.y > -0.02 {
+            let warm = Vec3::new(1.0, 0.55, 0.3).lerp(Vec3::ONE, low);
+            (sun, day.noon_lux * lit, warm)
+        } else {
+            (-sun, day.noon_lux * 0.02, Vec3::new(0.6, 0.7, 1.0))
+        };
+        light.illuminance = lux;
+        light.color = Color::linear_rgb(color.x, color.y, color.z);
+        *transform = Transform::IDENTITY.looking_to(-toward, Vec3::Y);
+    }
+    if let Some(mut ambient) = ambient {
+        ambient.brightness = day.ambient * (0.2 + 0.8 * lit);
+    }
+
+    let Ok((dome, mut vis)) = sky.single_mut() else {
+        return;
+    };
+    vis.set_if_neq(if day.sky { Visibility::Visible } else { Visibility::Hidden });
+    // Repainted a game minute at a time at most.
+    if !day.sky || daylight.painted.is_some_and(|t| (t - now).abs() < 1.0 / 1440.0) {
+        return;
+    }
+    let Some(mesh) = meshes.get_mut(&dome.0) else {
+        return;
+    };
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return;
+    };
+    let colors: Vec<[f32; 4]> = positions
+        .iter()
+        .map(|&p| sky_color(Vec3::from(p) / SKY_RADIUS, sun).extend(1.0).to_array())
+        .collect();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    daylight.painted = Some(now);
+}
+
+/// Keeps the dome centered on the camera, so it can't be flown out of.
+pub fn follow_sky(cams: Query<&Transform, (With<Cam>, Without<Sky>)>, mut sky: Query<&mut Transform, With<Sky>>) {
+    if let (Ok(cam), Ok(mut dome)) = (cams.single(), sky.single_mut()) {
+        dome.translation = cam.translation;
+    }
+}
+
+pub fn apply_sun_shadows(mut cmds: Commands, settings: Res<MarchySettings>, suns: Query<Entity, With<Sun>>) {
+    for sun in &suns {
+        cmds.entity(sun).insert(settings.sun_shadows.config());
+    }
+}
@@ -0,0 +1,158 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use std::f32::consts::TAU;
+use crate::{edit::TerrainCursor, terrain::Perlin, MarchySettings};
+
+/// Oldest decals are removed past this many.
+const MAX_DECALS: usize = 64;
+const TEXTURE_SIZE: u32 = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecalKind {
+    Scorch,
+    Crack,
+}
+
+/// Stamps a decal. With `snap`, it is moved onto the terrain surface found
+/// by casting from `pos` along `-normal`, taking that surface's normal.
+#[derive(Debug, Event)]
+pub struct DecalSpawn {
+    pub pos: Vec3,
+    pub normal: Vec3,
+    pub radius: f32,
+    pub kind: DecalKind,
+    pub snap: bool,
+}
+
+/// A textured quad lying on the terrain that fades out over `life`.
+#[derive(Component)]
+pub struct Decal {
+    pub life: Timer,
+    material: Handle<StandardMaterial>,
+}
+
+#[derive(Resource)]
+pub struct DecalAssets {
+    pub quad: Handle<Mesh>,
+    pub scorch: Handle<Image>,
+    pub crack: Handle<Image>,
+}
+
+/// Procedural RGBA texture: `alpha(u, v)` over `-1..1` squared, black color.
+fn decal_texture(alpha: impl Fn(Vec2) -> f32) -> Image {
+    let mut data = Vec::with_capacity((TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize);
+    for y in 0..TEXTURE_SIZE {
+        for x in 0..TEXTURE_SIZE {
+            let uv = Vec2::new(x as f32, y as f32) / (TEXTURE_SIZE - 1) as f32 * 2.0 - 1.0;
+            let a = alpha(uv).clamp(0.0, 1.0);
+            data.extend([10, 8, 6, (a * 255.0) as u8]);
+        }
+    }
+    Image::new(
+        Extent3d { width: TEXTURE_SIZE, height: TEXTURE_SIZE, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+pub fn init_decal_assets(
+    mut cmds: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let noise = Perlin::new(7);
+
+    // Soft dark blotch with a ragged edge.
+    let scorch = decal_texture(|uv| {
+        let edge = 0.75 + 0.2 * noise.sample(uv.extend(0.0) * 4.0);
+        (1.0 - uv.length() / edge) * 2.0 * (0.7 + 0.3 * noise.sample(uv.extend(1.0) * 9.0))
+    });
+
+    // A few wobbly spokes radiating from the center.
+    let crack = decal_texture(|uv| {
+        let r = uv.length();
+        let angle = uv.y.atan2(uv.x) + 0.3 * noise.sample(uv.extend(2.0) * 3.0);
+        let spoke = (angle / TAU * 7.0).fract();
+        let width = 0.06 * (1.0 - r);
+        let d = (spoke - 0.5).abs() * TAU / 7.0 * r;
+        if r > 0.95 { 0.0 } else { 1.0 - d / width.max(0.001) }
+    });
+
+    cmds.insert_resource(DecalAssets {
+        quad: meshes.add(Rectangle::new(1.0, 1.0)),
+        scorch: images.add(scorch),
+        crack: images.add(crack),
+    });
+}
+
+pub fn decal_spawn(
+    trigger: Trigger<DecalSpawn>,
+    mut cmds: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    assets: Res<DecalAssets>,
+    cursor: TerrainCursor,
+    decals: Query<(Entity, &Decal)>,
+    settings: Res<MarchySettings>,
+) {
+    let ev = trigger.event();
+    let normal = ev.normal.try_normalize().unwrap_or(Vec3::Y);
+    let (pos, normal) = match Dir3::new(-normal) {
+        Ok(down) if ev.snap => cursor
+            .cast(ev.pos + normal * 0.5, down, ev.radius + 1.5)
+            .unwrap_or((ev.pos, normal)),
+        _ => (ev.pos, normal),
+    };
+
+    let full = decals.iter().count() >= MAX_DECALS;
+    let oldest = decals
+        .iter()
+        .filter(|_| full)
+        .max_by(|a, b| a.1.life.elapsed_secs().total_cmp(&b.1.life.elapsed_secs()));
+    if let Some((oldest, _)) = oldest {
+        cmds.entity(oldest).despawn();
+    }
+
+    let material = materials.add(StandardMaterial {
+        base_color_texture: Some(match ev.kind {
+            DecalKind::Scorch => assets.scorch.clone(),
+            DecalKind::Crack => assets.crack.clone(),
+        }),
+        alpha_mode: AlphaMode::Blend,
+        perceptual_roughness: 1.0,
+        depth_bias: 10.0,
+        ..default()
+    });
+    cmds.spawn((
+        Name::new("decal"),
+        Decal {
+            life: Timer::from_seconds(settings.decal_life, TimerMode::Once),
+            material: material.clone(),
+        },
+        Mesh3d(assets.quad.clone()),
+        MeshMaterial3d(material),
+        Transform::from_translation(pos + normal * 0.02)
+            .with_rotation(Quat::from_rotation_arc(Vec3::Z, normal))
+            .with_scale(Vec3::splat(ev.radius * 2.0)),
+    ));
+}
+
+pub fn fade_decals(
+    mut cmds: Commands,
+    mut decals: Query<(Entity, &mut Decal)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    time: Res<Time>,
+) {
+    for (entity, mut decal) in &mut decals {
+        decal.life.tick(time.delta());
+        if decal.life.finished() {
+            cmds.entity(entity).despawn();
+        } else if let Some(mat) = materials.get_mut(&decal.material) {
+            mat.base_color.set_alpha(1.0 - decal.life.fraction());
+        }
+    }
+}
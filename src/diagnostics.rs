@@ -0,0 +1,213 @@
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticPath, Diagnostics, DiagnosticsStore, FrameTimeDiagnosticsPlugin,
+        RegisterDiagnostic,
+    },
+    prelude::*,
+    render::diagnostic::RenderDiagnosticsPlugin,
+};
+use crate::{
+    chunk::{ChunkMap, MeshTasks},
+    culling::Culling,
+    terrain::remesh_all,
+    timecontrol::TimeControl,
+    MarchySettings,
+};
+
+/// CPU milliseconds spent filling one chunk's grid from the generator.
+pub const GRID_FILL: DiagnosticPath = DiagnosticPath::const_new("marchy/grid_fill");
+/// CPU milliseconds spent building one chunk mesh.
+pub const MESH_CPU: DiagnosticPath = DiagnosticPath::const_new("marchy/mesh_cpu");
+/// CPU milliseconds spent building one chunk collider.
+pub const COLLIDER_CPU: DiagnosticPath = DiagnosticPath::const_new("marchy/collider_cpu");
+/// `COLLIDER_CPU` by shape, in `ChunkCollider::ALL` order.
+pub const COLLIDER_SHAPES: [DiagnosticPath; 4] = [
+    DiagnosticPath::const_new("marchy/collider_cpu/trimesh"),
+    DiagnosticPath::const_new("marchy/collider_cpu/decimated"),
+    DiagnosticPath::const_new("marchy/collider_cpu/boxes"),
+    DiagnosticPath::const_new("marchy/collider_cpu/convex"),
+];
+/// Totals over every loaded chunk's current mesh, for comparing meshers.
+pub const VERTICES: DiagnosticPath = DiagnosticPath::const_new("marchy/vertices");
+pub const TRIANGLES: DiagnosticPath = DiagnosticPath::const_new("marchy/triangles");
+pub const CHUNKS: DiagnosticPath = DiagnosticPath::const_new("marchy/chunks");
+/// Chunk meshes applied per frame.
+pub const MESHES_APPLIED: DiagnosticPath = DiagnosticPath::const_new("marchy/meshes_applied");
+pub const MESH_TASKS: DiagnosticPath = DiagnosticPath::const_new("marchy/mesh_tasks");
+
+#[derive(Resource, Default)]
+pub struct DiagnosticsOverlay {
+    pub open: bool,
+}
+
+#[derive(Component)]
+pub struct DiagnosticsText;
+
+/// Frame time, Marchy's own CPU timings, and the per-pass render timings
+/// from `RenderDiagnosticsPlugin`. GPU times (`*/elapsed_gpu`) come from
+/// timestamp queries, so they only appear on adapters that support them;
+/// any pass or compute mesher that records a span on the render context's
+/// diagnostic recorder shows up here without further wiring.
+pub struct MarchyDiagnosticsPlugin;
+
+impl Plugin for MarchyDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin::default());
+        }
+        if !app.is_plugin_added::<RenderDiagnosticsPlugin>() {
+            app.add_plugins(RenderDiagnosticsPlugin);
+        }
+        for path in COLLIDER_SHAPES {
+            app.register_diagnostic(Diagnostic::new(path).with_suffix("ms"));
+        }
+        app.register_diagnostic(Diagnostic::new(GRID_FILL).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(MESH_CPU).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(COLLIDER_CPU).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(VERTICES).with_smoothing_factor(0.0))
+            .register_diagnostic(Diagnostic::new(TRIANGLES).with_smoothing_factor(0.0))
+            .register_diagnostic(Diagnostic::new(CHUNKS).with_smoothing_factor(0.0))
+            .register_diagnostic(Diagnostic::new(MESHES_APPLIED))
+            .register_diagnostic(Diagnostic::new(MESH_TASKS))
+            .init_resource::<DiagnosticsOverlay>()
+            .add_systems(Startup, spawn_overlay)
+            .add_systems(Update, (
+                record_mesh_timings,
+                (toggle_overlay, cycle_chunk_collider, update_overlay).chain(),
+            ));
+    }
+}
+
+fn spawn_overlay(mut cmds: Commands) {
+    cmds.spawn((
+        DiagnosticsText,
+        Text::new(""),
+        TextFont { font_size: 13.0, ..default() },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        },
+    ));
+}
+
+pub fn record_mesh_timings(
+    mut tasks: ResMut<MeshTasks>,
+    mut chunks: ResMut<ChunkMap>,
+    mut diagnostics: Diagnostics,
+) {
+    let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+    if !chunks.filled.is_empty() {
+        for elapsed in chunks.filled.drain(..) {
+            diagnostics.add_measurement(&GRID_FILL, || ms(elapsed));
+        }
+    }
+    let applied = tasks.finished.len();
+    for timing in tasks.finished.drain(..) {
+        diagnostics.add_measurement(&MESH_CPU, || ms(timing.mesh));
+        diagnostics.add_measurement(&COLLIDER_CPU, || ms(timing.collider));
+        diagnostics.add_measurement(&COLLIDER_SHAPES[timing.shape as usize], || ms(timing.collider));
+    }
+    diagnostics.add_measurement(&MESHES_APPLIED, || applied as f64);
+    diagnostics.add_measurement(&MESH_TASKS, || tasks.pending() as f64);
+
+    let (vertices, triangles) = chunks
+        .iter()
+        .fold((0, 0), |(v, t), (_, c)| (v + c.stats.vertices, t + c.stats.triangles));
+    diagnostics.add_measurement(&VERTICES, || vertices as f64);
+    diagnostics.add_measurement(&TRIANGLES, || triangles as f64);
+    diagnostics.add_measurement(&CHUNKS, || chunks.iter().count() as f64);
+}
+
+/// F8 toggles the diagnostics overlay (Shift+F8 is the session stats,
+/// Ctrl+F8 the codec diff).
+pub fn toggle_overlay(keys: Res<ButtonInput<KeyCode>>, mut overlay: ResMut<DiagnosticsOverlay>) {
+    let modified = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ControlLeft, KeyCode::AltLeft]);
+    if keys.just_pressed(KeyCode::F8) && !modified {
+        overlay.open = !overlay.open;
+    }
+}
+
+/// Alt+F8 switches every chunk without a shape of its own to the next
+/// `ChunkCollider` and rebuilds them, to compare the shapes' costs here.
+pub fn cycle_chunk_collider(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<MarchySettings>,
+    mut chunks: ResMut<ChunkMap>,
+) {
+    if !(keys.pressed(KeyCode::AltLeft) && keys.just_pressed(KeyCode::F8)) {
+        return;
+    }
+    settings.chunk_collider = settings.chunk_collider.next();
+    remesh_all(&mut chunks);
+    info!(target: "physics", "chunk colliders: {}", settings.chunk_collider.name());
+}
+
+pub fn update_overlay(
+    overlay: Res<DiagnosticsOverlay>,
+    store: Res<DiagnosticsStore>,
+    settings: Res<MarchySettings>,
+    time: Res<TimeControl>,
+    culling: Res<Culling>,
+    mut text: Query<&mut Text, With<DiagnosticsText>>,
+) {
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    if !overlay.open {
+        text.0.clear();
+        return;
+    }
+
+    let line = |d: &Diagnostic| {
+        let value = d.smoothed().unwrap_or(0.0);
+        format!("{:<48} {value:>8.2} {}\n", d.path().as_str(), d.suffix)
+    };
+    let mut out = format!(
+        "time: {}\nmesher: {}\ncolliders: {}\nculled: {} chunks hidden, {} colliders resting\ncpu\n",
+        time.describe(),
+        settings.mesher,
+        settings.chunk_collider.name(),
+        culling.hidden,
+        culling.resting,
+    );
+    for path in [
+        &FrameTimeDiagnosticsPlugin::FPS,
+        &FrameTimeDiagnosticsPlugin::FRAME_TIME,
+        &GRID_FILL,
+        &MESH_CPU,
+        &COLLIDER_CPU,
+        &COLLIDER_SHAPES[0],
+        &COLLIDER_SHAPES[1],
+        &COLLIDER_SHAPES[2],
+        &COLLIDER_SHAPES[3],
+        &MESHES_APPLIED,
+        &MESH_TASKS,
+        &CHUNKS,
+        &VERTICES,
+        &TRIANGLES,
+    ] {
+        // Shapes never built are left out.
+        if let Some(d) = store.get(path).filter(|d| d.measurement().is_some()) {
+            out.push_str(&line(d));
+        }
+    }
+
+    // Render passes, CPU and GPU side by side, sorted so each pass's pair
+    // sits together.
+    let mut passes: Vec<_> = store
+        .iter()
+        .filter(|d| d.path().as_str().starts_with("render/"))
+        .collect();
+    passes.sort_by_key(|d| d.path().as_str());
+    if passes.is_empty() {
+        out.push_str("\nrender: no timings (timestamp queries unsupported?)\n");
+    } else {
+        out.push_str("\nrender\n");
+        for d in passes {
+            out.push_str(&line(d));
+        }
+    }
+    text.0 = out;
+}
@@ -0,0 +1,375 @@
+use bevy::{ecs::system::SystemParam, prelude::*, window::PrimaryWindow};
+use std::f32::consts::TAU;
+use avian3d::prelude::*;
+use crate::{
+    brush::{apply_brush, Brush, BrushMode},
+    chunk::{ChunkMap, ChunkMesh},
+    editor::cursor_ray,
+    game::Game,
+    layers::Layer,
+    materials::VoxelMaterial,
+    preview::{AreaPreview, PreviewShape},
+    touch::TouchPointer,
+    MarchySettings,
+};
+
+/// The voxel changes a sphere edit would make, as `(pos, old, new)`. Cells are
+/// combined with the sphere's distance relative to the iso level, so the
+/// edit has a smooth edge rather than snapping whole cells.
+pub fn sphere_edits(
+    chunks: &ChunkMap,
+    center: Vec3,
+    radius: f32,
+    iso: f32,
+    fill: bool
+) -> Vec<(IVec3, f32, f32)> {
+    // World units, like `center` and `radius`; densities are per cell.
+    let cell = chunks.transform.cell_size;
+    let min = chunks.voxel_at(center - Vec3::splat(radius + cell));
+    let max = chunks.voxel_at(center + Vec3::splat(radius + cell));
+    let mut edits = vec![];
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let pos = IVec3::new(x, y, z);
+                let Some(val) = chunks.read(pos) else {
+                    continue;
+                };
+                let d = (radius - chunks.voxel_center(pos).distance(center)) / cell;
+                let new = if fill { val.min(iso - d) } else { val.max(iso + d) };
+                if new != val {
+                    edits.push((pos, val, new));
+                }
+            }
+        }
+    }
+    edits
+}
+
+/// Carves (or with `fill`, adds) a sphere into the voxel field.
+pub fn apply_sphere(chunks: &mut ChunkMap, center: Vec3, radius: f32, iso: f32, fill: bool) {
+    for (pos, _, new) in sphere_edits(chunks, center, radius, iso, fill) {
+        chunks.write(pos, new);
+    }
+}
+
+/// Moves cells toward a sphere edit by at most `step` density units, so
+/// holding the tool digs gradually. Harder materials dig proportionally
+/// slower, and ones harder than `strength` not at all. Filled cells become
+/// dirt. Returns the materials of cells that crossed the surface, i.e. were
+/// fully mined (or filled).
+pub fn dig_sphere(
+    chunks: &mut ChunkMap,
+    center: Vec3,
+    radius: f32,
+    iso: f32,
+    fill: bool,
+    step: f32,
+    strength: f32,
+) -> Vec<VoxelMaterial> {
+    let mut crossed = vec![];
+    for (pos, old, target) in sphere_edits(chunks, center, radius, iso, fill) {
+        let mat = chunks.read_material(pos).unwrap_or_default();
+        let new = if fill {
+            (old - step).max(target)
+        } else {
+            if mat.hardness() > strength {
+                continue;
+            }
+            (old + step / mat.hardness()).min(target)
+        };
+        chunks.write(pos, new);
+        if fill && old > iso && new <= iso {
+            chunks.write_material(pos, VoxelMaterial::Dirt);
+            crossed.push(VoxelMaterial::Dirt);
+        } else if !fill && old <= iso && new > iso {
+            crossed.push(mat);
+        }
+    }
+    crossed
+}
+
+/// How many cells a sphere edit would turn solid (positive) or empty
+/// (negative).
+pub fn sphere_volume_change(chunks: &ChunkMap, center: Vec3, radius: f32, iso: f32, fill: bool) -> i32 {
+    sphere_edits(chunks, center, radius, iso, fill)
+        .iter()
+        .map(|&(_, old, new)| (new <= iso) as i32 - (old <= iso) as i32)
+        .sum()
+}
+
+/// Raycasts against terrain chunks only, ignoring balls and props.
+#[derive(SystemParam)]
+pub struct TerrainCursor<'w, 's> {
+    windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    cams: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+    terrain: Query<'w, 's, (), With<ChunkMesh>>,
+    spatial: SpatialQuery<'w, 's>,
+    touch: Res<'w, TouchPointer>,
+}
+
+impl TerrainCursor<'_, '_> {
+    /// Hit point and surface normal along `dir`.
+    pub fn cast(&self, origin: Vec3, dir: Dir3, max_dist: f32) -> Option<(Vec3, Vec3)> {
+        let hit = self.spatial.cast_ray_predicate(
+            origin,
+            dir,
+            max_dist,
+            true,
+            &SpatialQueryFilter::from_mask(Layer::Terrain),
+            &|e| self.terrain.contains(e),
+        )?;
+        Some((origin + *dir * hit.distance, hit.normal))
+    }
+
+    /// The ray from the camera through the mouse cursor, or the finger
+    /// editing on a touch screen.
+    pub fn ray(&self) -> Option<Ray3d> {
+        let window = self.windows.single().ok()?;
+        let (camera, cam_t) = self.cams.single().ok()?;
+        match self.touch.pos {
+            Some(pos) => camera.viewport_to_world(cam_t, pos).ok(),
+            None => cursor_ray(window, camera, cam_t),
+        }
+    }
+
+    /// Where the cursor ray meets the terrain.
+    pub fn hit(&self) -> Option<(Vec3, Vec3)> {
+        let ray = self.ray()?;
+        self.cast(ray.origin, ray.direction, 100.0)
+    }
+}
+
+/// Whether edits change this world. A networked client's don't: they go
+/// to the host, and only what the host sends back (`replaying`) is applied,
+/// so every peer edits in the same order.
+#[derive(Resource, Default)]
+pub struct EditGate {
+    pub remote: bool,
+    pub replaying: bool,
+}
+
+impl EditGate {
+    pub fn applies(&self) -> bool {
+        !self.remote || self.replaying
+    }
+}
+
+/// Mouse, keyboard and brush state for the editing tools.
+#[derive(SystemParam)]
+pub struct EditInput<'w> {
+    pub mouse: Res<'w, ButtonInput<MouseButton>>,
+    pub keys: Res<'w, ButtonInput<KeyCode>>,
+    pub brush: Res<'w, Brush>,
+    pub gate: Res<'w, EditGate>,
+}
+
+/// One frame of `apply_brush` outside game mode, as an event so it can be
+/// replayed elsewhere.
+#[derive(Event, Clone, Debug)]
+pub struct BrushStroke {
+    pub brush: Brush,
+    pub center: Vec3,
+    pub step: f32,
+    pub strength: f32,
+}
+
+pub fn brush_stroke(
+    trigger: Trigger<BrushStroke>,
+    mut chunks: ResMut<ChunkMap>,
+    settings: Res<MarchySettings>,
+    gate: Res<EditGate>,
+) {
+    if gate.applies() {
+        let BrushStroke { brush, center, step, strength } = trigger.event();
+        apply_brush(&mut chunks, brush, *center, settings.iso_level, *step, *strength);
+    }
+}
+
+/// Left mouse applies the brush where the cursor ray meets the terrain;
+/// holding Ctrl swaps digging and filling. The touched chunks are remeshed
+/// (and get new colliders) in the background. In game mode the current tool
+/// sets radius and strength, wears down as it mines, and mined voxels go
+/// into the inventory.
+pub fn dig(
+    mut cmds: Commands,
+    input: EditInput,
+    cursor: TerrainCursor,
+    time: Res<Time>,
+    mut chunks: ResMut<ChunkMap>,
+    mut game: ResMut<Game>,
+    settings: Res<MarchySettings>,
+) {
+    if !input.mouse.pressed(MouseButton::Left) || input.keys.pressed(KeyCode::ShiftLeft) {
+        return;
+    }
+    let Some((point, _)) = cursor.hit() else {
+        return;
+    };
+    let mut brush = input.brush.clone();
+    if input.keys.pressed(KeyCode::ControlLeft) {
+        brush.mode = match brush.mode {
+            BrushMode::Add => BrushMode::Subtract,
+            BrushMode::Subtract => BrushMode::Add,
+            BrushMode::Paint => BrushMode::Paint,
+        };
+    }
+    let fill = brush.mode == BrushMode::Add;
+    let step = settings.dig_rate * time.delta_secs();
+
+    if !game.active {
+        cmds.trigger(BrushStroke { brush, center: point, step, strength: settings.dig_strength });
+        return;
+    }
+    // Inventories aren't shared, so a networked client can't mine.
+    if !input.gate.applies() {
+        return;
+    }
+
+    if game.tool.durability == 0 || (fill && game.inventory.get(VoxelMaterial::Dirt) == 0) {
+        return;
+    }
+    brush.radius = game.tool.radius;
+    let crossed = apply_brush(&mut chunks, &brush, point, settings.iso_level, step, game.tool.strength);
+    for mat in crossed {
+        if fill {
+            game.inventory.take(mat, 1);
+        } else {
+            game.inventory.add(mat, 1);
+            game.tool.durability = game.tool.durability.saturating_sub(1);
+        }
+    }
+}
+
+/// Blasts a smooth sphere out of the terrain. Each cell moves at most
+/// `strength` density units toward the carved shape, less in hard
+/// materials, and materials harder than `strength` hold, so weak blasts
+/// only scar stone and bedrock. Use `f32::INFINITY` to carve everything.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Carve {
+    pub center: Vec3,
+    pub radius: f32,
+    pub strength: f32,
+    pub cause: CarveCause,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CarveCause {
+    /// Someone pressed the carve key.
+    Tool,
+    /// A projectile hit something.
+    Impact,
+}
+
+/// The touched chunks are marked dirty, so they get new meshes and
+/// colliders in the background.
+pub fn carve(
+    trigger: Trigger<Carve>,
+    mut chunks: ResMut<ChunkMap>,
+    settings: Res<MarchySettings>,
+    gate: Res<EditGate>,
+) {
+    let Carve { center, radius, strength, .. } = *trigger.event();
+    if gate.applies() {
+        dig_sphere(&mut chunks, center, radius, settings.iso_level, false, strength, strength);
+    }
+}
+
+/// I blasts a crater where the cursor points.
+pub fn carve_key(mut cmds: Commands, keys: Res<ButtonInput<KeyCode>>, cursor: TerrainCursor) {
+    if !keys.just_pressed(KeyCode::KeyI) || keys.pressed(KeyCode::ShiftLeft) {
+        return;
+    }
+    if let Some((center, _)) = cursor.hit() {
+        cmds.trigger(Carve { center, radius: 2.5, strength: 6.0, cause: CarveCause::Tool });
+    }
+}
+
+/// Ring of points at `radius` around `center`, each dropped onto the terrain
+/// along `-normal` so the ring hugs the surface.
+fn surface_ring(cursor: &TerrainCursor, center: Vec3, normal: Vec3, radius: f32) -> Vec<Vec3> {
+    let (a, b) = normal.any_orthonormal_pair();
+    let down = Dir3::new(-normal).unwrap_or(Dir3::NEG_Y);
+    (0..=32)
+        .map(|i| {
+            let angle = i as f32 / 32.0 * TAU;
+            let p = center + (a * angle.cos() + b * angle.sin()) * radius;
+            cursor
+                .cast(p + normal * radius, down, radius * 2.0)
+                .map_or(p, |(hit, n)| hit + n * 0.02)
+        })
+        .collect()
+}
+
+/// Outlines the brush footprint on the terrain: the outer ring is the dig
+/// radius, the inner ring is where the edit's one-voxel blend band starts.
+pub fn brush_preview(
+    cursor: TerrainCursor,
+    keys: Res<ButtonInput<KeyCode>>,
+    brush: Res<Brush>,
+    mut gizmos: Gizmos,
+) {
+    let Some((point, normal)) = cursor.hit() else {
+        return;
+    };
+    let color = if keys.pressed(KeyCode::ControlLeft) {
+        Color::srgb(0.3, 1.0, 0.4)
+    } else {
+        Color::srgb(1.0, 0.5, 0.2)
+    };
+    let radius = brush.radius;
+    gizmos.linestrip(surface_ring(&cursor, point, normal, radius), color);
+    if radius > 1.0 {
+        gizmos.linestrip(surface_ring(&cursor, point, normal, radius - 1.0), color.with_alpha(0.4));
+    }
+    gizmos.line(point, point + normal * 0.5, color);
+}
+
+/// Outlines the voxel cell under the cursor, found by walking the voxels
+/// rather than hitting the mesh, with a dot at the exact hit point.
+pub fn highlight_voxel(
+    cursor: TerrainCursor,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+    mut gizmos: Gizmos,
+) {
+    if !settings.pick_highlight {
+        return;
+    }
+    let Some(ray) = cursor.ray() else {
+        return;
+    };
+    let Some(hit) = chunks.raycast(ray.origin, *ray.direction, 100.0, settings.iso_level) else {
+        return;
+    };
+    let color = Color::srgb(0.9, 0.9, 1.0);
+    let grid = &chunks.transform;
+    let cell = Transform {
+        translation: chunks.voxel_center(hit.cell),
+        rotation: grid.rotation,
+        scale: Vec3::splat(grid.cell_size * 1.01),
+    };
+    gizmos.cuboid(cell, color);
+    gizmos.sphere(hit.point, 0.06, color);
+    gizmos.line(hit.point, hit.point + grid.dir_to_world(hit.normal.as_vec3()) * 0.3, color);
+}
+
+/// Holding Alt previews the volume the brush would affect.
+pub fn preview_brush(
+    cursor: TerrainCursor,
+    keys: Res<ButtonInput<KeyCode>>,
+    brush: Res<Brush>,
+    mut preview: ResMut<AreaPreview>,
+) {
+    preview.shape = None;
+    if !keys.pressed(KeyCode::AltLeft) {
+        return;
+    }
+    if let Some((point, _)) = cursor.hit() {
+        preview.shape = Some(PreviewShape {
+            center: point,
+            radius: brush.radius,
+            fill: (brush.mode == BrushMode::Add) != keys.pressed(KeyCode::ControlLeft),
+        });
+    }
+}
@@ -0,0 +1,183 @@
+use bevy::{input::mouse::AccumulatedMouseMotion, prelude::*, window::PrimaryWindow};
+
+/// Entities the editor gizmo can select and transform (lights, static props).
+#[derive(Component)]
+pub struct Editable {
+    /// Pick radius around the entity's origin.
+    pub radius: f32,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Resource)]
+pub struct Selection {
+    pub entity: Option<Entity>,
+    pub mode: GizmoMode,
+    pub axis: Vec3,
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Selection {
+            entity: None,
+            mode: GizmoMode::default(),
+            axis: Vec3::X,
+        }
+    }
+}
+
+/// Cursor ray from the main camera, if the cursor is over the window.
+pub fn cursor_ray(
+    window: &Window,
+    camera: &Camera,
+    cam_t: &GlobalTransform,
+) -> Option<Ray3d> {
+    let cursor = window.cursor_position()?;
+    camera.viewport_to_world(cam_t, cursor).ok()
+}
+
+/// Shift + left click picks the closest `Editable` along the cursor ray.
+pub fn select(
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cams: Query<(&Camera, &GlobalTransform)>,
+    editables: Query<(Entity, &GlobalTransform, &Editable)>,
+    mut selection: ResMut<Selection>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        selection.entity = None;
+    }
+    if !(keys.pressed(KeyCode::ShiftLeft) && mouse.just_pressed(MouseButton::Left)) {
+        return;
+    }
+    let (Ok(window), Ok((camera, cam_t))) = (windows.single(), cams.single()) else {
+        return;
+    };
+    let Some(ray) = cursor_ray(window, camera, cam_t) else {
+        return;
+    };
+
+    let mut best: Option<(Entity, f32)> = None;
+    for (entity, t, editable) in &editables {
+        let to = t.translation() - ray.origin;
+        let along = to.dot(*ray.direction);
+        if along < 0.0 || (to - *ray.direction * along).length() > editable.radius {
+            continue;
+        }
+        if best.is_none_or(|(_, d)| along < d) {
+            best = Some((entity, along));
+        }
+    }
+    selection.entity = best.map(|(e, _)| e);
+}
+
+/// G/R/T switches translate/rotate/scale, X/Y/Z picks the axis, and dragging
+/// with the right mouse button applies the transform.
+pub fn manipulate(
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    motion: Res<AccumulatedMouseMotion>,
+    cams: Query<(&Camera, &GlobalTransform)>,
+    mut selection: ResMut<Selection>,
+    mut targets: Query<&mut Transform, With<Editable>>,
+) {
+    let Some(entity) = selection.entity else {
+        return;
+    };
+    let Ok(mut t) = targets.get_mut(entity) else {
+        selection.entity = None;
+        return;
+    };
+
+    for (key, mode) in [
+        (KeyCode::KeyG, GizmoMode::Translate),
+        (KeyCode::KeyR, GizmoMode::Rotate),
+        (KeyCode::KeyT, GizmoMode::Scale),
+    ] {
+        if keys.just_pressed(key) {
+            selection.mode = mode;
+        }
+    }
+    for (key, axis) in [
+        (KeyCode::KeyX, Vec3::X),
+        (KeyCode::KeyY, Vec3::Y),
+        (KeyCode::KeyZ, Vec3::Z),
+    ] {
+        if keys.just_pressed(key) {
+            selection.axis = axis;
+        }
+    }
+
+    let delta = motion.delta;
+    if !mouse.pressed(MouseButton::Right) || delta == Vec2::ZERO {
+        return;
+    }
+    let Ok((camera, cam_t)) = cams.single() else {
+        return;
+    };
+
+    match selection.mode {
+        GizmoMode::Translate => {
+            // Move along the axis by however far the mouse travelled along
+            // the axis' on-screen projection.
+            let (Ok(a), Ok(b)) = (
+                camera.world_to_viewport(cam_t, t.translation),
+                camera.world_to_viewport(cam_t, t.translation + selection.axis),
+            ) else {
+                return;
+            };
+            let screen_axis = b - a;
+            let len2 = screen_axis.length_squared();
+            if len2 > f32::EPSILON {
+                t.translation += selection.axis * delta.dot(screen_axis) / len2;
+            }
+        }
+        GizmoMode::Rotate => {
+            t.rotate_axis(Dir3::new_unchecked(selection.axis), delta.x * 0.01);
+        }
+        GizmoMode::Scale => {
+            let factor = (1.0 + delta.x * 0.01).max(0.01);
+            t.scale *= Vec3::ONE + selection.axis * (factor - 1.0);
+        }
+    }
+}
+
+pub fn draw_gizmo(
+    selection: Res<Selection>,
+    targets: Query<&GlobalTransform, With<Editable>>,
+    mut gizmos: Gizmos,
+) {
+    let Some(t) = selection.entity.and_then(|e| targets.get(e).ok()) else {
+        return;
+    };
+    let pos = t.translation();
+
+    for (axis, color) in [
+        (Vec3::X, Color::srgb(1.0, 0.2, 0.2)),
+        (Vec3::Y, Color::srgb(0.2, 1.0, 0.2)),
+        (Vec3::Z, Color::srgb(0.2, 0.4, 1.0)),
+    ] {
+        let color = if axis == selection.axis { Color::WHITE.mix(&color, 0.3) } else { color };
+        let end = pos + axis * 1.5;
+        match selection.mode {
+            GizmoMode::Translate => {
+                gizmos.arrow(pos, end, color);
+            }
+            GizmoMode::Rotate => {
+                let rot = Quat::from_rotation_arc(Vec3::Z, axis);
+                gizmos.circle(Isometry3d::new(pos, rot), 1.2, color);
+            }
+            GizmoMode::Scale => {
+                gizmos.line(pos, end, color);
+                gizmos.cuboid(Transform::from_translation(end).with_scale(Vec3::splat(0.15)), color);
+            }
+        }
+    }
+}
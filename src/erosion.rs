@@ -0,0 +1,291 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        gpu_readback::{Readback, ReadbackComplete},
+        render_asset::RenderAssets,
+        render_graph::{self, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
+        render_resource::{
+            binding_types::{texture_storage_3d, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::GpuImage,
+        Render, RenderApp, RenderSet,
+    },
+};
+use crate::{chunk::ChunkMap, MarchySettings};
+
+const SHADER: &str = "shaders/erosion.wgsl";
+const WORKGROUP: u32 = 4;
+
+#[derive(Clone, Debug)]
+pub struct ErosionSettings {
+    /// Share of a cell's loose material that can move per step, up to 0.5.
+    pub rate: f32,
+    /// Fill difference a slope holds without sliding.
+    pub talus: f32,
+    /// Seconds between copying the eroded volume back into the chunks.
+    pub readback_every: f32,
+}
+
+impl Default for ErosionSettings {
+    fn default() -> Self {
+        ErosionSettings { rate: 0.05, talus: 0.3, readback_every: 0.5 }
+    }
+}
+
+#[derive(Clone, Copy, ShaderType)]
+struct ErosionParams {
+    rate: f32,
+    talus: f32,
+    iso: f32,
+}
+
+/// The world's densities as a pair of 3D textures on the GPU, stepped back
+/// and forth by the erosion shader every frame while `running`. Texel `t`
+/// is world voxel `origin + t`.
+#[derive(Resource, Clone, ExtractResource)]
+pub struct GpuErosion {
+    textures: [Handle<Image>; 2],
+    /// Which texture the next step reads.
+    front: usize,
+    pub running: bool,
+    origin: IVec3,
+    dims: UVec3,
+    params: ErosionParams,
+    readback: Timer,
+}
+
+/// Runs thermal erosion on the GPU, with the results read back into the
+/// chunks every so often for remeshing. Shift+F10 starts and stops it.
+pub struct ErosionPlugin;
+
+impl Plugin for ErosionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractResourcePlugin::<GpuErosion>::default())
+            .add_systems(Update, (erosion_key, step_erosion).chain());
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(
+            Render,
+            prepare_bind_groups
+                .in_set(RenderSet::PrepareBindGroups)
+                .run_if(resource_exists::<GpuErosion>),
+        );
+        let mut graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        graph.add_node(ErosionLabel, ErosionNode);
+        graph.add_node_edge(ErosionLabel, bevy::render::graph::CameraDriverLabel);
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<ErosionPipeline>();
+        }
+    }
+}
+
+/// Shift+F10 uploads the loaded chunks and starts eroding them, or stops
+/// and reads back the final result.
+fn erosion_key(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    erosion: Option<ResMut<GpuErosion>>,
+    mut images: ResMut<Assets<Image>>,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+) {
+    if !(keys.pressed(KeyCode::ShiftLeft) && keys.just_pressed(KeyCode::F10)) {
+        return;
+    }
+    if let Some(mut erosion) = erosion.filter(|e| e.running) {
+        erosion.running = false;
+        read_back(&mut cmds, &erosion);
+        info!("erosion stopped");
+        return;
+    }
+    let Some((min, max)) = chunks.extent() else {
+        return;
+    };
+    let dims = (max - min).as_uvec3();
+    let mut data = Vec::with_capacity((dims.element_product() * 4) as usize);
+    for z in 0..dims.z as i32 {
+        for y in 0..dims.y as i32 {
+            for x in 0..dims.x as i32 {
+                // Gaps between chunks are air.
+                let v = chunks.read(min + IVec3::new(x, y, z)).unwrap_or(settings.iso_level + 64.0);
+                data.extend(v.to_le_bytes());
+            }
+        }
+    }
+    let mut image = Image::new(
+        Extent3d { width: dims.x, height: dims.y, depth_or_array_layers: dims.z },
+        TextureDimension::D3,
+        data,
+        TextureFormat::R32Float,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.texture_descriptor.usage =
+        TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC | TextureUsages::COPY_DST;
+    let erosion = &settings.erosion;
+    cmds.insert_resource(GpuErosion {
+        textures: [images.add(image.clone()), images.add(image)],
+        front: 0,
+        running: true,
+        origin: min,
+        dims,
+        params: ErosionParams {
+            rate: erosion.rate.clamp(0.0, 0.5),
+            talus: erosion.talus,
+            iso: settings.iso_level,
+        },
+        readback: Timer::from_seconds(erosion.readback_every, TimerMode::Repeating),
+    });
+    info!("eroding {dims} voxels on the GPU");
+}
+
+/// Flips the textures after each step, and reads the latest back now and
+/// then.
+fn step_erosion(mut cmds: Commands, time: Res<Time>, erosion: Option<ResMut<GpuErosion>>) {
+    let Some(mut erosion) = erosion.filter(|e| e.running) else {
+        return;
+    };
+    erosion.front ^= 1;
+    if erosion.readback.tick(time.delta()).just_finished() {
+        read_back(&mut cmds, &erosion);
+    }
+}
+
+/// Copies the most recently written texture into the chunks. Only cells that
+/// changed are written, so only their chunks get remeshed.
+fn read_back(cmds: &mut Commands, erosion: &GpuErosion) {
+    let (origin, dims) = (erosion.origin, erosion.dims);
+    // This frame's step reads `front`, so writes the other one.
+    let latest = erosion.textures[1 - erosion.front].clone();
+    cmds.spawn(Readback::texture(latest)).observe(
+        move |trigger: Trigger<ReadbackComplete>, mut cmds: Commands, mut chunks: ResMut<ChunkMap>| {
+            cmds.entity(trigger.target()).despawn();
+            let row = dims.x as usize * 4;
+            let bytes = &trigger.event().0;
+            // Rows may come back padded to the copy alignment.
+            let stride = if bytes.len() == row * (dims.y * dims.z) as usize {
+                row
+            } else {
+                row.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT as usize)
+            };
+            for z in 0..dims.z {
+                for y in 0..dims.y {
+                    let start = (z * dims.y + y) as usize * stride;
+                    let Some(line) = bytes.get(start..start + row) else {
+                        return;
+                    };
+                    for (x, b) in line.chunks_exact(4).enumerate() {
+                        let pos = origin + IVec3::new(x as i32, y as i32, z as i32);
+                        let v = f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+                        if chunks.read(pos).is_some_and(|old| (old - v).abs() > 1e-3) {
+                            chunks.write(pos, v);
+                        }
+                    }
+                }
+            }
+        },
+    );
+}
+
+#[derive(Resource)]
+struct ErosionPipeline {
+    layout: BindGroupLayout,
+    pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for ErosionPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "erosion",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    texture_storage_3d(TextureFormat::R32Float, StorageTextureAccess::ReadOnly),
+                    texture_storage_3d(TextureFormat::R32Float, StorageTextureAccess::WriteOnly),
+                    uniform_buffer::<ErosionParams>(false),
+                ),
+            ),
+        );
+        let shader = world.load_asset(SHADER);
+        let pipeline = world.resource::<PipelineCache>().queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("erosion".into()),
+            layout: vec![layout.clone()],
+            push_constant_ranges: vec![],
+            shader,
+            shader_defs: vec![],
+            entry_point: "erode".into(),
+            zero_initialize_workgroup_memory: false,
+        });
+        ErosionPipeline { layout, pipeline }
+    }
+}
+
+/// Reading texture 0 and writing 1, and the other way round.
+#[derive(Resource)]
+struct ErosionBindGroups([BindGroup; 2]);
+
+fn prepare_bind_groups(
+    mut cmds: Commands,
+    pipeline: Res<ErosionPipeline>,
+    erosion: Res<GpuErosion>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    let [a, b] = &erosion.textures;
+    let (Some(a), Some(b)) = (gpu_images.get(a), gpu_images.get(b)) else {
+        return;
+    };
+    let mut params = UniformBuffer::from(erosion.params);
+    params.write_buffer(&device, &queue);
+    let group = |from: &GpuImage, to: &GpuImage| {
+        device.create_bind_group(
+            None,
+            &pipeline.layout,
+            &BindGroupEntries::sequential((&from.texture_view, &to.texture_view, &params)),
+        )
+    };
+    cmds.insert_resource(ErosionBindGroups([group(a, b), group(b, a)]));
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct ErosionLabel;
+
+struct ErosionNode;
+
+impl render_graph::Node for ErosionNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let (Some(erosion), Some(groups)) =
+            (world.get_resource::<GpuErosion>(), world.get_resource::<ErosionBindGroups>())
+        else {
+            return Ok(());
+        };
+        if !erosion.running {
+            return Ok(());
+        }
+        let pipeline = world.resource::<ErosionPipeline>();
+        let Some(compute) = world.resource::<PipelineCache>().get_compute_pipeline(pipeline.pipeline) else {
+            return Ok(());
+        };
+        let mut pass = render_context
+            .command_encoder()
+            .begin_compute_pass(&ComputePassDescriptor::default());
+        pass.set_pipeline(compute);
+        pass.set_bind_group(0, &groups.0[erosion.front], &[]);
+        let groups = (erosion.dims + WORKGROUP - 1) / WORKGROUP;
+        pass.dispatch_workgroups(groups.x, groups.y, groups.z);
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+use crate::{
+    chunk::ChunkMesh,
+    mesh::{BevyMesh, MeshBuffers},
+    MarchySettings,
+};
+
+pub fn export_obj(mesh: &Mesh, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    MeshBuffers::from_mesh(mesh).write_obj(&mut w)?;
+    w.flush()
+}
+
+pub fn export_gltf(mesh: &Mesh, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    MeshBuffers::from_mesh(mesh).write_gltf(&mut w)?;
+    w.flush()
+}
+
+/// Every chunk's surface merged into one mesh, in world space.
+pub fn merge_chunks<'a>(
+    chunks: impl IntoIterator<Item = (&'a Mesh3d, &'a Transform)>,
+    meshes: &Assets<Mesh>,
+) -> MeshBuffers {
+    let mut data = MeshBuffers::default();
+    for (mesh, t) in chunks {
+        if let Some(mesh) = meshes.get(&mesh.0) {
+            data.append(&MeshBuffers::from_mesh(mesh), &t.compute_affine());
+        }
+    }
+    data
+}
+
+/// F7 writes every chunk's surface, in world space, to `export_path` as
+/// both `.obj` and `.gltf`. With a modifier held F7 is `timelapse_keys`.
+pub fn export_key(
+    keys: Res<ButtonInput<KeyCode>>,
+    chunks: Query<(&Mesh3d, &Transform), With<ChunkMesh>>,
+    meshes: Res<Assets<Mesh>>,
+    settings: Res<MarchySettings>,
+) {
+    let modified = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ControlLeft, KeyCode::AltLeft]);
+    if !keys.just_pressed(KeyCode::F7) || modified {
+        return;
+    }
+    let data = merge_chunks(&chunks, &meshes);
+
+    for ext in ["obj", "gltf"] {
+        let path = settings.export_path.with_extension(ext);
+        let result = File::create(&path).and_then(|f| {
+            let mut w = BufWriter::new(f);
+            match ext {
+                "obj" => data.write_obj(&mut w)?,
+                _ => data.write_gltf(&mut w)?,
+            }
+            w.flush()
+        });
+        match result {
+            Ok(()) => info!("exported {} triangles to {}", data.indices.len() / 3, path.display()),
+            Err(e) => error!("failed to export {}: {e}", path.display()),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::{pbr::NotShadowCaster, prelude::*};
+use avian3d::prelude::*;
+use std::collections::HashMap;
+use crate::{
+    chunk::{ChunkCoord, ChunkMap},
+    mesh::BevyMesh,
+    mesher::{DensityView, MeshOptions, Meshers},
+    MarchySettings,
+    VoxelGrid,
+};
+
+/// Density units a fluid reaches into the terrain, so its surface tucks
+/// under the ground rather than fighting it.
+const OVERLAP: f32 = 1.0;
+
+/// A water table or lava pool: a second surface marched from its own field
+/// and drawn translucent over the terrain, filling every open cell below
+/// `level`, caves included.
+#[derive(Clone, Debug)]
+pub struct FluidLayer {
+    pub name: String,
+    /// World height of the surface.
+    pub level: f32,
+    /// Alpha sets how see-through it is.
+    pub color: Color,
+    pub emissive: LinearRgba,
+    /// Give each chunk's fluid a sensor collider so bodies can tell they've
+    /// touched it; otherwise it has none.
+    pub sensor: bool,
+}
+
+impl FluidLayer {
+    pub fn water(level: f32) -> Self {
+        FluidLayer {
+            name: "water".into(),
+            level,
+            color: Color::srgba(0.15, 0.35, 0.6, 0.6),
+            emissive: LinearRgba::BLACK,
+            sensor: true,
+        }
+    }
+
+    pub fn lava(level: f32) -> Self {
+        FluidLayer {
+            name: "lava".into(),
+            level,
+            color: Color::srgba(1.0, 0.35, 0.05, 0.9),
+            emissive: LinearRgba::rgb(4.0, 1.0, 0.1),
+            sensor: true,
+        }
+    }
+
+    /// The layer's density at height `y` over terrain density `terrain`:
+    /// solid below the level where the terrain is open, or nearly.
+    pub fn density(&self, y: f32, terrain: f32, iso: f32) -> f32 {
+        (iso + y - self.level).max(2.0 * iso - terrain - OVERLAP)
+    }
+}
+
+/// Marks a chunk's mesh of `MarchySettings::fluids[i]`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Fluid(pub usize);
+
+/// A material per fluid layer and, per chunk, the remesh its fluids were
+/// meshed after and their entities.
+#[derive(Resource)]
+pub struct Fluids {
+    materials: Vec<Handle<StandardMaterial>>,
+    placed: HashMap<ChunkCoord, (u32, Vec<Entity>)>,
+}
+
+impl FromWorld for Fluids {
+    fn from_world(world: &mut World) -> Self {
+        let layers = world.resource::<MarchySettings>().fluids.clone();
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let materials = layers
+            .into_iter()
+            .map(|layer| materials.add(StandardMaterial {
+                base_color: layer.color,
+                emissive: layer.emissive,
+                perceptual_roughness: 0.1,
+                alpha_mode: AlphaMode::Blend,
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            }))
+            .collect();
+        Fluids { materials, placed: HashMap::new() }
+    }
+}
+
+/// Meshes each fluid layer in every chunk that reaches below it, once the
+/// chunk has a mesh and again whenever it's remeshed, as children of the
+/// chunk entity.
+pub fn mesh_fluids(
+    mut cmds: Commands,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+    meshers: Res<Meshers>,
+    mut fluids: ResMut<Fluids>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if settings.fluids.is_empty() {
+        return;
+    }
+    let Some(mesher) = meshers.get(&settings.mesher) else {
+        return;
+    };
+    let iso = settings.iso_level;
+    let options = MeshOptions { iso, boundary: settings.boundary, smooth_normals: settings.smooth_normals };
+    let size = chunks.chunk_size;
+    fluids.placed.retain(|coord, _| chunks.get(*coord).is_some_and(|c| c.entity.is_some()));
+    for (coord, chunk) in chunks.iter() {
+        let (Some(chunk_entity), remeshes) = (chunk.entity, chunk.stats.remeshes) else {
+            continue;
+        };
+        if remeshes == 0 || fluids.placed.get(coord).is_some_and(|(r, _)| *r == remeshes) {
+            continue;
+        }
+        for old in fluids.placed.remove(coord).map(|(_, e)| e).unwrap_or_default() {
+            cmds.entity(old).try_despawn();
+        }
+
+        let origin = coord.0 * size as i32;
+        let bottom = chunks.voxel_center(origin).y;
+        let border = chunks.border(*coord, settings.boundary);
+        let mut spawned = vec![];
+        for (i, layer) in settings.fluids.iter().enumerate() {
+            if bottom > layer.level {
+                continue;
+            }
+            let height = |y: i32| chunks.voxel_center(origin + IVec3::Y * y).y;
+            let mut grid = VoxelGrid::new(size);
+            let mut any = false;
+            for z in 0..size {
+                for y in 0..size {
+                    for x in 0..size {
+                        let terrain = chunk.grid.read(x, y, z).unwrap_or(iso);
+                        let val = layer.density(height(y as i32), terrain, iso);
+                        any |= val <= iso;
+                        let _ = grid.write(x, y, z, val);
+                    }
+                }
+            }
+            if !any {
+                continue;
+            }
+            let outside = |p: IVec3| Some(layer.density(height(p.y), *border.get(&p)?, iso));
+            let view = DensityView { storage: &grid, outside: &outside, occupancy: None, materials: None };
+            let buffers = mesher.mesh(&view, &options);
+            if buffers.positions.is_empty() {
+                continue;
+            }
+            let mesh = buffers.into_mesh();
+            let collider = layer.sensor.then(|| Collider::trimesh_from_mesh(&mesh)).flatten();
+            let mut entity = cmds.spawn((
+                Name::new(layer.name.clone()),
+                Fluid(i),
+                Mesh3d(meshes.add(mesh)),
+                MeshMaterial3d(fluids.materials[i].clone()),
+                Transform::default(),
+                NotShadowCaster,
+                ChildOf(chunk_entity),
+            ));
+            if let Some(collider) = collider {
+                entity.insert((collider, Sensor));
+            }
+            spawned.push(entity.id());
+        }
+        fluids.placed.insert(*coord, (remeshes, spawned));
+    }
+}
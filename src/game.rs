@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use crate::materials::VoxelMaterial;
+
+/// Mined voxels, counted per material.
+#[derive(Default, Clone, Debug)]
+pub struct Inventory {
+    counts: [u32; VoxelMaterial::ALL.len()],
+}
+
+impl Inventory {
+    pub fn get(&self, mat: VoxelMaterial) -> u32 {
+        self.counts[mat.id() as usize]
+    }
+
+    pub fn add(&mut self, mat: VoxelMaterial, n: u32) {
+        self.counts[mat.id() as usize] += n;
+    }
+
+    /// Removes `n` of a material, or nothing if there aren't enough.
+    pub fn take(&mut self, mat: VoxelMaterial, n: u32) -> bool {
+        let count = &mut self.counts[mat.id() as usize];
+        if *count < n {
+            return false;
+        }
+        *count -= n;
+        true
+    }
+
+    pub fn has_all(&self, cost: &[(VoxelMaterial, u32)]) -> bool {
+        cost.iter().all(|&(mat, n)| self.get(mat) >= n)
+    }
+}
+
+/// The digging tool used in game mode.
+#[derive(Clone, Debug)]
+pub struct Tool {
+    pub level: u32,
+    pub radius: f32,
+    /// Compared against `VoxelMaterial::hardness`.
+    pub strength: f32,
+    /// Voxels left before the tool breaks.
+    pub durability: u32,
+    pub max_durability: u32,
+}
+
+impl Default for Tool {
+    fn default() -> Self {
+        Tool {
+            level: 1,
+            radius: 1.0,
+            strength: 1.0,
+            durability: 60,
+            max_durability: 60,
+        }
+    }
+}
+
+impl Tool {
+    pub fn upgrade_cost(&self) -> [(VoxelMaterial, u32); 2] {
+        [
+            (VoxelMaterial::Dirt, 20 * self.level),
+            (VoxelMaterial::Stone, 10 * (self.level - 1)),
+        ]
+    }
+
+    pub fn repair_cost(&self) -> [(VoxelMaterial, u32); 1] {
+        [(VoxelMaterial::Dirt, 5 * self.level)]
+    }
+
+    pub fn upgrade(&mut self) {
+        self.level += 1;
+        self.radius += 0.25;
+        self.strength += 2.0;
+        self.max_durability += 40;
+        self.durability = self.max_durability;
+    }
+}
+
+/// The mini game: dig with a tool that wears out, collect what you mine, and
+/// spend it on repairs and upgrades.
+#[derive(Resource, Default)]
+pub struct Game {
+    pub active: bool,
+    pub tool: Tool,
+    pub inventory: Inventory,
+}
+
+#[derive(Component)]
+pub struct GameHud;
+
+pub fn spawn_hud(mut cmds: Commands) {
+    cmds.spawn((
+        GameHud,
+        Text::new(""),
+        TextFont { font_size: 16.0, ..default() },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Percent(40.0),
+            ..default()
+        },
+    ));
+}
+
+fn spend(inventory: &mut Inventory, cost: &[(VoxelMaterial, u32)]) -> bool {
+    if !inventory.has_all(cost) {
+        return false;
+    }
+    for &(mat, n) in cost {
+        inventory.take(mat, n);
+    }
+    true
+}
+
+/// F6 toggles game mode, U upgrades the tool and Y repairs it.
+pub fn game_input(keys: Res<ButtonInput<KeyCode>>, mut game: ResMut<Game>) {
+    if keys.just_pressed(KeyCode::F6) {
+        game.active = !game.active;
+    }
+    if !game.active {
+        return;
+    }
+    let Game { tool, inventory, .. } = &mut *game;
+    if keys.just_pressed(KeyCode::KeyU) && spend(inventory, &tool.upgrade_cost()) {
+        tool.upgrade();
+    }
+    if keys.just_pressed(KeyCode::KeyY) && spend(inventory, &tool.repair_cost()) {
+        tool.durability = tool.max_durability;
+    }
+}
+
+pub fn update_hud(game: Res<Game>, mut hud: Query<&mut Text, With<GameHud>>) {
+    let Ok(mut text) = hud.single_mut() else {
+        return;
+    };
+    if !game.active {
+        text.0.clear();
+        return;
+    }
+    let tool = &game.tool;
+    let costs = |cost: &[(VoxelMaterial, u32)]| {
+        cost.iter()
+            .filter(|(_, n)| *n > 0)
+            .map(|(mat, n)| format!("{n} {mat:?}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut out = format!(
+        "tool lv{}  radius {:.2}  strength {:.0}  durability {}/{}\n",
+        tool.level, tool.radius, tool.strength, tool.durability, tool.max_durability
+    );
+    for mat in VoxelMaterial::ALL {
+        out.push_str(&format!("{mat:?}: {}  ", game.inventory.get(mat)));
+    }
+    out.push_str(&format!(
+        "\nU upgrade ({})  Y repair ({})",
+        costs(&tool.upgrade_cost()),
+        costs(&tool.repair_cost())
+    ));
+    text.0 = out;
+}
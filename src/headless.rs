@@ -0,0 +1,164 @@
+use bevy::{app::AppExit, prelude::*};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    time::Instant,
+};
+use crate::{
+    chunk::{ChunkCoord, ChunkMap},
+    materials::{layered, VoxelMaterial},
+    mesh::MeshBuffers,
+    mesher::{DensityView, MeshOptions, Meshers},
+    save::load_world,
+    terrain,
+    MarchySettings,
+    VoxelGrid,
+};
+
+/// A windowless run that meshes one world, writes it out and quits, for
+/// batch generation from scripts.
+#[derive(Resource, Clone, Debug)]
+pub struct Headless {
+    /// A world save to mesh instead of generating a chunk.
+    pub input: Option<PathBuf>,
+    /// `.gltf` writes glTF, anything else OBJ.
+    pub out: PathBuf,
+}
+
+impl Headless {
+    /// `--headless [--input <save>] [--out <path>]`, with the generator,
+    /// mesher and grid size taken from `MarchyConfig`. `None` when the app
+    /// should open a window as usual.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut args = args.into_iter();
+        let (mut headless, mut input, mut out) = (false, None, PathBuf::from("marchy.obj"));
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--headless" => headless = true,
+                "--input" => input = Some(PathBuf::from(value()?)),
+                "--out" => out = PathBuf::from(value()?),
+                _ => {}
+            }
+        }
+        Ok(headless.then_some(Headless { input, out }))
+    }
+
+    /// Meshes the world on `MinimalPlugins` and returns how it went.
+    pub fn run(self, settings: MarchySettings) -> AppExit {
+        App::new()
+            .add_plugins(MinimalPlugins)
+            .init_resource::<Meshers>()
+            .insert_resource(settings)
+            .insert_resource(self)
+            .add_systems(Update, mesh_and_exit)
+            .run()
+    }
+}
+
+/// The chunks to mesh: the save's, or one generated at the origin.
+fn world(headless: &Headless, settings: &MarchySettings) -> io::Result<ChunkMap> {
+    let Some(input) = &headless.input else {
+        let mut grid = VoxelGrid::new(settings.grid_size);
+        terrain::generate(&settings.terrain, &mut grid, IVec3::ZERO, settings.iso_level);
+        let materials = layered(&grid, settings.iso_level);
+        let mut chunks = ChunkMap::new(settings.grid_size).with_transform(settings.grid_transform(settings.grid_size));
+        chunks.insert(ChunkCoord(IVec3::ZERO), grid);
+        if let Some(chunk) = chunks.chunk_mut(ChunkCoord(IVec3::ZERO)) {
+            chunk.materials = materials;
+        }
+        return Ok(chunks);
+    };
+    let snapshot = load_world(input)?;
+    let mut chunks = ChunkMap::new(snapshot.chunk_size).with_transform(settings.grid_transform(snapshot.chunk_size));
+    for saved in snapshot.chunks {
+        chunks.insert(saved.coord, saved.grid);
+        if let Some(chunk) = chunks.chunk_mut(saved.coord) {
+            chunk.materials = saved.materials;
+        }
+    }
+    Ok(chunks)
+}
+
+/// Every chunk through the configured mesher, painted and shaded like the
+/// live pipeline (without LOD or skirts), merged in world space.
+fn mesh_world(chunks: &ChunkMap, settings: &MarchySettings, meshers: &Meshers) -> Result<MeshBuffers, String> {
+    let mesher = meshers
+        .get(&settings.mesher)
+        .ok_or(format!("unknown mesher {:?}", settings.mesher))?;
+    let options = MeshOptions {
+        iso: settings.iso_level,
+        boundary: settings.boundary,
+        smooth_normals: settings.smooth_normals,
+    };
+    let size = chunks.chunk_size;
+    let mut data = MeshBuffers::default();
+    for (coord, chunk) in chunks.iter() {
+        let border = chunks.border(*coord, settings.boundary);
+        let outside = |p: IVec3| border.get(&p).copied();
+        let last = chunk.grid.dims() - 1;
+        let material = |c: IVec3| {
+            let c = c.clamp(IVec3::ZERO, last.as_ivec3()).as_uvec3();
+            chunk.materials[((c.z * size + c.y) * size + c.x) as usize]
+        };
+        let view = DensityView { storage: &chunk.grid, outside: &outside, occupancy: None, materials: Some(&material) };
+        let mut buffers = mesher.mesh(&view, &options);
+        buffers.paint(chunk.grid.dims(), |c| {
+            let p = c.clamp(IVec3::ZERO, last.as_ivec3()).as_uvec3();
+            let solid = chunk.grid.read(p.x, p.y, p.z)? <= options.iso;
+            solid.then(|| VoxelMaterial::from_id(material(c)).vertex_color())
+        });
+        buffers.bake_ao(chunk.grid.dims(), settings.ao_radius, settings.ao_strength, |p| {
+            view.sample(p, options.boundary).is_some_and(|v| v <= options.iso)
+        });
+        data.append(&buffers, &chunks.chunk_transform(*coord).compute_affine());
+    }
+    Ok(data)
+}
+
+fn write(data: &MeshBuffers, headless: &Headless) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(&headless.out)?);
+    match headless.out.extension().and_then(|e| e.to_str()) {
+        Some("gltf") => data.write_gltf(&mut w)?,
+        _ => data.write_obj(&mut w)?,
+    }
+    w.flush()
+}
+
+/// Does the whole job on the first frame, printing stats to stdout.
+fn mesh_and_exit(
+    headless: Res<Headless>,
+    settings: Res<MarchySettings>,
+    meshers: Res<Meshers>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let start = Instant::now();
+    let result = world(&headless, &settings)
+        .map_err(|e| format!("failed to load world: {e}"))
+        .and_then(|chunks| {
+            let loaded = start.elapsed();
+            let data = mesh_world(&chunks, &settings, &meshers)?;
+            let meshed = start.elapsed();
+            write(&data, &headless).map_err(|e| format!("failed to write {}: {e}", headless.out.display()))?;
+            println!(
+                "chunks: {}\nvertices: {}\ntriangles: {}\n\
+                generate ms: {:.2}\nmesh ms: {:.2}\nwrite ms: {:.2}\nwrote: {}",
+                chunks.iter().count(),
+                data.positions.len(),
+                data.indices.len() / 3,
+                loaded.as_secs_f64() * 1000.0,
+                (meshed - loaded).as_secs_f64() * 1000.0,
+                (start.elapsed() - meshed).as_secs_f64() * 1000.0,
+                headless.out.display(),
+            );
+            Ok(())
+        });
+    exit.write(match result {
+        Ok(()) => AppExit::Success,
+        Err(e) => {
+            eprintln!("{e}");
+            AppExit::error()
+        }
+    });
+}
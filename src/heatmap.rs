@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use crate::chunk::{Chunk, ChunkMap};
+
+/// Seconds over which `LastEdit` fades from hot to cold.
+const RECENT: f32 = 30.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeatMetric {
+    #[default]
+    Triangles,
+    Remeshes,
+    Memory,
+    LastEdit,
+}
+
+impl HeatMetric {
+    pub const ALL: [HeatMetric; 4] = [
+        HeatMetric::Triangles,
+        HeatMetric::Remeshes,
+        HeatMetric::Memory,
+        HeatMetric::LastEdit,
+    ];
+
+    /// Raw value for a chunk; higher is hotter.
+    pub fn value(self, chunk: &Chunk, now: f32) -> f32 {
+        match self {
+            HeatMetric::Triangles => chunk.stats.triangles as f32,
+            HeatMetric::Remeshes => chunk.stats.remeshes as f32,
+            HeatMetric::Memory => chunk.memory() as f32,
+            HeatMetric::LastEdit if chunk.stats.remeshes == 0 => 0.0,
+            HeatMetric::LastEdit => (1.0 - (now - chunk.stats.last_remesh) / RECENT).max(0.0),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Heatmap {
+    pub enabled: bool,
+    pub metric: HeatMetric,
+}
+
+#[derive(Component)]
+pub struct HeatmapLabel;
+
+pub fn spawn_heatmap_label(mut cmds: Commands) {
+    cmds.spawn((
+        HeatmapLabel,
+        Text::new(""),
+        TextFont { font_size: 14.0, ..default() },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        },
+    ));
+}
+
+/// F4 toggles the heatmap, Shift + F4 picks the next metric.
+pub fn heatmap_input(keys: Res<ButtonInput<KeyCode>>, mut heatmap: ResMut<Heatmap>) {
+    // Ctrl+F4 is the point cloud's.
+    if !keys.just_pressed(KeyCode::F4) || keys.pressed(KeyCode::ControlLeft) {
+        return;
+    }
+    if keys.pressed(KeyCode::ShiftLeft) {
+        let i = HeatMetric::ALL.iter().position(|&m| m == heatmap.metric).unwrap_or(0);
+        heatmap.metric = HeatMetric::ALL[(i + 1) % HeatMetric::ALL.len()];
+        heatmap.enabled = true;
+    } else {
+        heatmap.enabled = !heatmap.enabled;
+    }
+}
+
+/// Outlines every chunk, tinted blue (coldest) to red (hottest) relative to
+/// the hottest chunk for the selected metric.
+pub fn draw_heatmap(
+    heatmap: Res<Heatmap>,
+    chunks: Res<ChunkMap>,
+    time: Res<Time>,
+    mut gizmos: Gizmos,
+    mut label: Query<&mut Text, With<HeatmapLabel>>,
+) {
+    let Ok(mut text) = label.single_mut() else {
+        return;
+    };
+    if !heatmap.enabled {
+        text.0.clear();
+        return;
+    }
+
+    let now = time.elapsed_secs();
+    let values: Vec<_> = chunks
+        .iter()
+        .map(|(coord, chunk)| (*coord, heatmap.metric.value(chunk, now)))
+        .collect();
+    let max = values.iter().fold(0.0_f32, |m, (_, v)| m.max(*v));
+
+    let size = chunks.chunk_size as i32;
+    for (coord, v) in &values {
+        let heat = if max > 0.0 { v / max } else { 0.0 };
+        let mut cube = chunks.chunk_transform(*coord);
+        cube.translation = chunks.transform.to_world((coord.0 * size).as_vec3() + size as f32 / 2.0);
+        cube.scale *= size as f32 - 0.05;
+        gizmos.cuboid(cube, Color::hsl(240.0 * (1.0 - heat), 1.0, 0.5));
+    }
+    text.0 = format!(
+        "heatmap: {:?}  max {max:.0}  ({} chunks)  Shift+F4 next",
+        heatmap.metric,
+        values.len()
+    );
+}
@@ -0,0 +1,71 @@
+use bevy::{
+    ecs::{component::HookContext, world::DeferredWorld},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::editor::Editable;
+
+/// An entity's identity across saves and reloads, where `Entity` ids are
+/// reassigned. Anything that refers to placed objects (level props, lights,
+/// markers) should hold one of these and look it up in `StableIds`.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[component(on_add = register, on_remove = unregister)]
+pub struct StableId(pub u64);
+
+/// Which entity currently has each `StableId`, kept up to date by the
+/// component's hooks.
+#[derive(Resource, Default)]
+pub struct StableIds {
+    next: u64,
+    entities: HashMap<StableId, Entity>,
+}
+
+impl StableIds {
+    /// A fresh id, never handed out or registered before.
+    pub fn allocate(&mut self) -> StableId {
+        self.next += 1;
+        StableId(self.next)
+    }
+
+    pub fn get(&self, id: StableId) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+}
+
+fn register(mut world: DeferredWorld, ctx: HookContext) {
+    let Some(&id) = world.get::<StableId>(ctx.entity) else {
+        return;
+    };
+    let Some(mut ids) = world.get_resource_mut::<StableIds>() else {
+        return;
+    };
+    // Ids loaded from a save must never be handed out again.
+    ids.next = ids.next.max(id.0);
+    if let Some(old) = ids.entities.insert(id, ctx.entity) {
+        warn!("stable id {} moved from {old} to {}", id.0, ctx.entity);
+    }
+}
+
+fn unregister(mut world: DeferredWorld, ctx: HookContext) {
+    let Some(&id) = world.get::<StableId>(ctx.entity) else {
+        return;
+    };
+    let Some(mut ids) = world.get_resource_mut::<StableIds>() else {
+        return;
+    };
+    if ids.entities.get(&id) == Some(&ctx.entity) {
+        ids.entities.remove(&id);
+    }
+}
+
+/// Gives every placed (editable) object without a `StableId` a new one.
+pub fn assign_stable_ids(
+    mut cmds: Commands,
+    mut ids: ResMut<StableIds>,
+    placed: Query<Entity, (With<Editable>, Without<StableId>)>,
+) {
+    for entity in &placed {
+        cmds.entity(entity).insert(ids.allocate());
+    }
+}
@@ -0,0 +1,140 @@
+use bevy::{audio::Volume, prelude::*, render::mesh::MeshTag};
+use avian3d::prelude::*;
+use rand::Rng;
+use crate::{ball::BallAssets, projectile::Projectile, rng::SeededRng, MarchySettings};
+
+/// Impacts slower than this make no particles or sound.
+const QUIET_SPEED: f32 = 1.0;
+const PARTICLE_LIFE: f32 = 0.6;
+
+/// Fired when a ball starts touching something.
+#[derive(Debug, Event)]
+pub struct BallImpact {
+    pub ball: Entity,
+    pub other: Entity,
+    /// World-space contact point, or the ball's center if the solver had
+    /// no contact for the pair yet.
+    pub point: Vec3,
+    /// The ball's velocity relative to what it hit.
+    pub relative_velocity: Vec3,
+}
+
+/// What a ball was touching last frame, so each contact fires once.
+#[derive(Component, Default)]
+pub struct Touching(Vec<Entity>);
+
+/// Despawns its entity when it runs out.
+#[derive(Component)]
+pub struct Lifetime(pub Timer);
+
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec3,
+    life: Timer,
+    size: f32,
+}
+
+/// Fires `BallImpact` for every new contact of a projectile.
+pub fn detect_impacts(
+    mut cmds: Commands,
+    mut balls: Query<(Entity, &CollidingEntities, &mut Touching, &Transform), With<Projectile>>,
+    velocities: Query<&LinearVelocity>,
+    collisions: Collisions,
+) {
+    let velocity = |e: Entity| velocities.get(e).map_or(Vec3::ZERO, |v| v.0);
+    for (ball, colliding, mut touching, transform) in &mut balls {
+        for &other in colliding.iter().filter(|e| !touching.0.contains(e)) {
+            let point = collisions
+                .get(ball, other)
+                .and_then(|pair| pair.manifolds.iter().flat_map(|m| &m.points).next())
+                .map_or(transform.translation, |p| p.point);
+            trace!(target: "physics", "{ball} hit {other} at {point}");
+            cmds.trigger(BallImpact {
+                ball,
+                other,
+                point,
+                relative_velocity: velocity(ball) - velocity(other),
+            });
+        }
+        touching.0.clear();
+        touching.0.extend(colliding.iter());
+    }
+}
+
+/// A puff of little balls in the impacting ball's color, bigger for harder
+/// hits.
+pub fn impact_burst(
+    trigger: Trigger<BallImpact>,
+    mut cmds: Commands,
+    assets: Res<BallAssets>,
+    balls: Query<&MeshTag>,
+    settings: Res<MarchySettings>,
+    mut rng: ResMut<SeededRng>,
+) {
+    let ev = trigger.event();
+    let speed = ev.relative_velocity.length();
+    if speed < QUIET_SPEED {
+        return;
+    }
+    let Ok(&tag) = balls.get(ev.ball) else {
+        return;
+    };
+    let count = ((speed * 2.0) as usize).clamp(4, settings.max_particles.max(4));
+    for _ in 0..count {
+        let dir = Vec3::new(rng.random::<f32>() - 0.5, rng.random::<f32>(), rng.random::<f32>() - 0.5).normalize_or(Vec3::Y);
+        let size = 0.05 + rng.random::<f32>() * 0.05;
+        cmds.spawn((
+            Particle {
+                velocity: dir * speed.min(10.0) * (0.3 + rng.random::<f32>() * 0.4),
+                life: Timer::from_seconds(PARTICLE_LIFE, TimerMode::Once),
+                size,
+            },
+            Mesh3d(assets.mesh.clone()),
+            MeshMaterial3d(assets.material.clone()),
+            tag,
+            Transform::from_translation(ev.point).with_scale(Vec3::splat(size)),
+        ));
+    }
+}
+
+/// Plays `impact_sound`, if set, louder for harder hits.
+pub fn impact_sound(
+    trigger: Trigger<BallImpact>,
+    mut cmds: Commands,
+    server: Res<AssetServer>,
+    settings: Res<MarchySettings>,
+) {
+    let speed = trigger.event().relative_velocity.length();
+    let Some(path) = settings.impact_sound.clone().filter(|_| speed >= QUIET_SPEED) else {
+        return;
+    };
+    cmds.spawn((
+        AudioPlayer::new(server.load(path)),
+        PlaybackSettings::DESPAWN.with_volume(Volume::Linear((speed / 10.0).min(1.0))),
+    ));
+}
+
+pub fn update_particles(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform)>,
+) {
+    let dt = time.delta_secs();
+    for (entity, mut particle, mut transform) in &mut particles {
+        if particle.life.tick(time.delta()).finished() {
+            cmds.entity(entity).despawn();
+            continue;
+        }
+        particle.velocity += Vec3::NEG_Y * 9.81 * dt;
+        transform.translation += particle.velocity * dt;
+        transform.scale = Vec3::splat(particle.size * particle.life.fraction_remaining());
+    }
+}
+
+pub fn expire(mut cmds: Commands, time: Res<Time>, mut lifetimes: Query<(Entity, &mut Lifetime)>) {
+    for (entity, mut lifetime) in &mut lifetimes {
+        if lifetime.0.tick(time.delta()).finished() {
+            cmds.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use bevy::prelude::*;
+use avian3d::prelude::*;
+use std::collections::HashSet;
+use crate::{
+    chunk::{ChunkMap, RegionDirty},
+    materials::VoxelMaterial,
+    mesh::{BevyMesh, Boundary},
+    mesher::{DensityView, MeshOptions, Meshers},
+    MarchyMaterials,
+    MarchySettings,
+    VoxelGrid,
+};
+
+/// Solid components bigger than this are taken to be ground.
+const MAX_ISLAND: usize = 8_000;
+/// Islands smaller than this crumble to air instead of falling.
+const MIN_ISLAND: usize = 8;
+
+const FACES: [IVec3; 6] = [IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y, IVec3::Z, IVec3::NEG_Z];
+
+/// A piece of terrain that came loose and fell.
+#[derive(Component)]
+pub struct Island;
+
+/// Finds terrain left hanging by an edit and drops it: solid cells that no
+/// longer connect (face to face) to the bottom of the loaded world are cut
+/// out into a grid of their own and spawned as a dynamic body.
+#[derive(Resource)]
+pub struct Islands {
+    pub enabled: bool,
+    /// Edited boxes to check around.
+    pending: Vec<(IVec3, IVec3)>,
+}
+
+impl Default for Islands {
+    fn default() -> Self {
+        Islands { enabled: true, pending: vec![] }
+    }
+}
+
+/// Shift+I toggles island detection.
+pub fn island_key(keys: Res<ButtonInput<KeyCode>>, mut islands: ResMut<Islands>) {
+    if keys.pressed(KeyCode::ShiftLeft) && keys.just_pressed(KeyCode::KeyI) {
+        islands.enabled = !islands.enabled;
+        islands.pending.clear();
+        info!("detached islands: {}", islands.enabled);
+    }
+}
+
+/// Whole chunks are loads and regenerations rather than edits, and
+/// generated terrain is left to float if it likes.
+pub fn queue_island_check(trigger: Trigger<RegionDirty>, mut islands: ResMut<Islands>, chunks: Res<ChunkMap>) {
+    let RegionDirty { min, max } = *trigger.event();
+    if islands.enabled && max - min != IVec3::splat(chunks.chunk_size as i32) {
+        islands.pending.push((min - 1, max + 1));
+    }
+}
+
+/// Sand and water are loose already, and left to `sand`. `None` where
+/// nothing is loaded.
+fn solid(chunks: &ChunkMap, pos: IVec3, iso: f32) -> Option<bool> {
+    let loose = matches!(chunks.read_material(pos)?, VoxelMaterial::Sand | VoxelMaterial::Water);
+    Some(chunks.read(pos)? <= iso && !loose)
+}
+
+/// The solid component around `seed`, and whether it's free: not touching
+/// the bottom of the world, an unloaded cell or `anchored`, and no bigger
+/// than `MAX_ISLAND`. Stops as soon as it isn't.
+fn flood(chunks: &ChunkMap, seed: IVec3, iso: f32, floor: i32, anchored: &HashSet<IVec3>) -> (Vec<IVec3>, bool) {
+    let mut seen = HashSet::from([seed]);
+    let (mut stack, mut cells) = (vec![seed], vec![]);
+    while let Some(p) = stack.pop() {
+        cells.push(p);
+        if p.y <= floor || cells.len() > MAX_ISLAND {
+            return (cells, false);
+        }
+        for n in FACES.map(|d| p + d) {
+            if seen.contains(&n) {
+                continue;
+            }
+            match solid(chunks, n, iso) {
+                None => return (cells, false),
+                Some(true) if anchored.contains(&n) => return (cells, false),
+                Some(true) => {
+                    seen.insert(n);
+                    stack.push(n);
+                }
+                Some(false) => {}
+            }
+        }
+    }
+    (cells, true)
+}
+
+/// Moves `cells` out of the map into a grid just big enough for them and a
+/// cell of air around them, along with their materials. Returns the world
+/// voxel of the grid's first cell too.
+fn extract(chunks: &mut ChunkMap, cells: &[IVec3], iso: f32) -> (VoxelGrid, Vec<u8>, IVec3) {
+    let air = iso + 1.0;
+    let lo = cells.iter().copied().reduce(IVec3::min).unwrap_or_default() - 1;
+    let hi = cells.iter().copied().reduce(IVec3::max).unwrap_or_default() + 1;
+    let mut grid = VoxelGrid::with_dims((hi - lo + 1).as_uvec3());
+    grid.data.fill(air);
+    let mut materials = vec![0; grid.data.len()];
+    let island: HashSet<IVec3> = cells.iter().copied().collect();
+    for z in lo.z..=hi.z {
+        for y in lo.y..=hi.y {
+            for x in lo.x..=hi.x {
+                let p = IVec3::new(x, y, z);
+                let l = (p - lo).as_uvec3();
+                // Other solids in the box stay behind; the air around the
+                // island keeps its values so the surface comes out the same.
+                let val = match chunks.read(p) {
+                    Some(v) if island.contains(&p) || v > iso => v,
+                    _ => air,
+                };
+                let _ = grid.write(l.x, l.y, l.z, val);
+                if let Some(i) = grid.index(l.x, l.y, l.z).filter(|_| island.contains(&p)) {
+                    materials[i] = chunks.read_material(p).unwrap_or_default().id();
+                }
+            }
+        }
+    }
+    for &p in cells {
+        chunks.write(p, air);
+    }
+    (grid, materials, lo)
+}
+
+/// Checks around last frame's edits. Each component is flooded once; ones
+/// found anchored stop later floods that reach them early.
+pub fn detach_islands(
+    mut cmds: Commands,
+    mut islands: ResMut<Islands>,
+    mut chunks: ResMut<ChunkMap>,
+    meshers: Res<Meshers>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mats: Res<MarchyMaterials>,
+    settings: Res<MarchySettings>,
+) {
+    if islands.pending.is_empty() {
+        return;
+    }
+    let regions = std::mem::take(&mut islands.pending);
+    let Some((min, _)) = chunks.extent() else {
+        return;
+    };
+    let iso = settings.iso_level;
+    let (mut anchored, mut taken, mut found) = (HashSet::new(), HashSet::new(), vec![]);
+    for (lo, hi) in regions {
+        for z in lo.z..hi.z {
+            for y in lo.y..hi.y {
+                for x in lo.x..hi.x {
+                    let p = IVec3::new(x, y, z);
+                    if anchored.contains(&p) || taken.contains(&p) || solid(&chunks, p, iso) != Some(true) {
+                        continue;
+                    }
+                    let (cells, free) = flood(&chunks, p, iso, min.y, &anchored);
+                    if free {
+                        taken.extend(cells.iter().copied());
+                        found.push(cells);
+                    } else {
+                        anchored.extend(cells);
+                    }
+                }
+            }
+        }
+    }
+
+    let Some(mesher) = meshers.get(&settings.mesher) else {
+        return;
+    };
+    let options = MeshOptions { iso, boundary: Boundary::Open, smooth_normals: settings.smooth_normals };
+    for cells in found {
+        let (grid, materials, lo) = extract(&mut chunks, &cells, iso);
+        if cells.len() < MIN_ISLAND {
+            continue;
+        }
+        let outside = |_: IVec3| None;
+        let dims = grid.dims();
+        let material = |c: IVec3| {
+            let c = c.clamp(IVec3::ZERO, (dims - 1).as_ivec3()).as_uvec3();
+            grid.index(c.x, c.y, c.z).map_or(0, |i| materials[i])
+        };
+        let view = DensityView { storage: &grid, outside: &outside, occupancy: None, materials: Some(&material) };
+        let mut buffers = mesher.mesh(&view, &options);
+        buffers.paint(dims, |c| {
+            let p = c.clamp(IVec3::ZERO, (dims - 1).as_ivec3()).as_uvec3();
+            let solid = grid.read(p.x, p.y, p.z)? <= iso;
+            solid.then(|| VoxelMaterial::from_id(material(c)).vertex_color())
+        });
+        let mesh = buffers.into_mesh();
+        // Dynamic bodies want a convex shape; a hull is close enough for
+        // something this small.
+        let Some(collider) = Collider::convex_hull_from_mesh(&mesh) else {
+            continue;
+        };
+        // Grid cell `x` is world voxel `lo + x`.
+        let at = chunks.grid_to_world(lo, dims);
+        debug!(target: "physics", "detached an island of {} cells at {lo}", cells.len());
+        cmds.spawn((
+            Name::new("island"),
+            Island,
+            Mesh3d(meshes.add(mesh)),
+            MeshMaterial3d(mats.terrain.clone()),
+            RigidBody::Dynamic,
+            collider,
+            at,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::ChunkCoord;
+
+    const ISO: f32 = 0.0;
+
+    /// One chunk with a floor two cells deep and a 4x2x2 slab hanging
+    /// over it, the slab's first cell made of stone.
+    fn world() -> ChunkMap {
+        let mut grid = VoxelGrid::new(16);
+        grid.map(|x, y, z, _| {
+            let floor = y < 2;
+            let slab = (4..8).contains(&x) && (8..10).contains(&y) && (5..7).contains(&z);
+            if floor || slab { -1.0 } else { 1.0 }
+        });
+        let mut chunks = ChunkMap::new(16);
+        chunks.insert(ChunkCoord(IVec3::ZERO), grid);
+        chunks.write_material(IVec3::new(4, 8, 5), VoxelMaterial::Stone);
+        chunks
+    }
+
+    #[test]
+    fn floods_tell_free_from_anchored() {
+        let chunks = world();
+        let floor = chunks.extent().unwrap().0.y;
+        let (cells, free) = flood(&chunks, IVec3::new(5, 9, 6), ISO, floor, &HashSet::new());
+        assert!(free);
+        assert_eq!(cells.len(), 16);
+        let (_, free) = flood(&chunks, IVec3::new(8, 1, 8), ISO, floor, &HashSet::new());
+        assert!(!free);
+        let anchored = HashSet::from([IVec3::new(7, 8, 5)]);
+        let (_, free) = flood(&chunks, IVec3::new(4, 8, 5), ISO, floor, &anchored);
+        assert!(!free);
+    }
+
+    #[test]
+    fn sand_is_not_part_of_an_island() {
+        let mut chunks = world();
+        chunks.write_material(IVec3::new(7, 9, 6), VoxelMaterial::Sand);
+        let (cells, free) = flood(&chunks, IVec3::new(4, 8, 5), ISO, 0, &HashSet::new());
+        assert!(free);
+        assert_eq!(cells.len(), 15);
+    }
+
+    #[test]
+    fn extraction_moves_cells_and_materials() {
+        let mut chunks = world();
+        let (cells, _) = flood(&chunks, IVec3::new(4, 8, 5), ISO, 0, &HashSet::new());
+        let (grid, materials, lo) = extract(&mut chunks, &cells, ISO);
+        // A cell of air on every side, so the box isn't cubic.
+        assert_eq!(lo, IVec3::new(3, 7, 4));
+        assert_eq!(grid.dims(), UVec3::new(6, 4, 4));
+        assert_eq!(grid.data.iter().filter(|&&v| v <= ISO).count(), 16);
+        let stone = grid.index(1, 1, 1).unwrap();
+        assert_eq!(materials[stone], VoxelMaterial::Stone.id());
+        assert_eq!(materials.iter().filter(|&&m| m != 0).count(), 1);
+        for p in cells {
+            assert!(chunks.read(p).unwrap() > ISO);
+        }
+        assert!(chunks.read(IVec3::new(4, 1, 5)).unwrap() <= ISO);
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use avian3d::prelude::*;
+use crate::{
+    marble::Marble,
+    outliner::Agent,
+    projectile::Projectile,
+    vehicle::Wheel,
+    MarchySettings,
+};
+
+#[derive(PhysicsLayer, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layer {
+    /// Balls, marbles and anything else not sorted into another layer.
+    #[default]
+    Debris,
+    /// Chunks, the floor and boundary walls.
+    Terrain,
+    /// The player, vehicles and their wheels.
+    Player,
+    Sensors,
+}
+
+/// Which layers collide with which. Pairs work both ways.
+#[derive(Clone, Debug)]
+pub struct LayerSettings {
+    pub interacts: Vec<(Layer, Layer)>,
+}
+
+impl Default for LayerSettings {
+    fn default() -> Self {
+        use Layer::*;
+        LayerSettings {
+            interacts: vec![
+                (Debris, Debris),
+                (Debris, Terrain),
+                (Debris, Player),
+                (Player, Terrain),
+                (Player, Player),
+                (Sensors, Debris),
+                (Sensors, Player),
+            ],
+        }
+    }
+}
+
+impl LayerSettings {
+    pub fn layers(&self, layer: Layer) -> CollisionLayers {
+        let filters: Vec<Layer> = self
+            .interacts
+            .iter()
+            .filter_map(|&(a, b)| match (a == layer, b == layer) {
+                (true, _) => Some(b),
+                (_, true) => Some(a),
+                _ => None,
+            })
+            .collect();
+        CollisionLayers::new(layer, filters)
+    }
+}
+
+/// Puts every new collider without layers of its own on the layer its
+/// components suggest, so new kinds of entity don't collide with
+/// everything by default.
+pub fn assign_layers(
+    trigger: Trigger<OnAdd, Collider>,
+    mut cmds: Commands,
+    bodies: Query<(
+        Has<CollisionLayers>,
+        Has<Sensor>,
+        Has<Agent>,
+        Has<Wheel>,
+        Has<Projectile>,
+        Has<Marble>,
+        Option<&RigidBody>,
+    )>,
+    settings: Res<MarchySettings>,
+) {
+    let entity = trigger.target();
+    let Ok((has_layers, sensor, agent, wheel, projectile, marble, body)) = bodies.get(entity) else {
+        return;
+    };
+    if has_layers {
+        return;
+    }
+    let layer = if sensor {
+        Layer::Sensors
+    } else if agent || wheel {
+        Layer::Player
+    } else if projectile || marble {
+        Layer::Debris
+    } else if body.is_some_and(|b| b.is_static()) {
+        Layer::Terrain
+    } else {
+        Layer::Debris
+    };
+    cmds.entity(entity).insert(settings.layers.layers(layer));
+}
@@ -0,0 +1,360 @@
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::view::screenshot::{save_to_disk, Screenshot},
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::{Path, PathBuf}};
+use crate::{
+    ball::BallSpawn,
+    chunk::ChunkMap,
+    edit::TerrainCursor,
+    editor::Editable,
+    ids::StableId,
+    lights::PlacedLight,
+    mesh::Boundary,
+    projectile::{BallKind, Projectile, ProjectileKinds},
+    save::{load_world, restore_world, save_world},
+    MarchySettings,
+};
+
+/// Bumped whenever the manifest changes incompatibly. Levels from newer
+/// versions are refused rather than half-loaded.
+pub const LEVEL_VERSION: u32 = 1;
+const MANIFEST: &str = "level.toml";
+const WORLD: &str = "world.mwld";
+pub const THUMBNAIL: &str = "thumbnail.png";
+
+/// A named point for game logic to find: spawn points, goals, triggers.
+#[derive(Component, Clone, Debug)]
+pub struct Marker(pub String);
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelMeta {
+    pub name: String,
+    pub author: String,
+    pub description: String,
+}
+
+/// The settings a level needs to look the way it was built.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LevelSettings {
+    pub iso_level: f32,
+    pub mesher: String,
+    pub boundary: Boundary,
+    #[serde(default)]
+    pub smooth_normals: bool,
+    pub seed: u64,
+    pub tile: Option<[u32; 2]>,
+}
+
+impl LevelSettings {
+    fn from_settings(s: &MarchySettings) -> Self {
+        LevelSettings {
+            iso_level: s.iso_level,
+            mesher: s.mesher.clone(),
+            boundary: s.boundary,
+            smooth_normals: s.smooth_normals,
+            seed: s.terrain.seed,
+            tile: s.terrain.tile.map(|t| t.to_array()),
+        }
+    }
+
+    fn apply(&self, s: &mut MarchySettings) {
+        s.iso_level = self.iso_level;
+        s.mesher = self.mesher.clone();
+        s.boundary = self.boundary;
+        s.smooth_normals = self.smooth_normals;
+        s.terrain.seed = self.seed;
+        s.terrain.tile = self.tile.map(UVec2::from_array);
+    }
+}
+
+/// A static projectile prop.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedProp {
+    #[serde(default)]
+    pub id: Option<StableId>,
+    pub kind: BallKind,
+    pub pos: [f32; 3],
+    /// As turned and scaled with the editor gizmo; older levels have
+    /// neither.
+    #[serde(default = "upright")]
+    pub rotation: [f32; 4],
+    /// On top of the kind's own size.
+    #[serde(default = "unscaled")]
+    pub scale: [f32; 3],
+}
+
+fn upright() -> [f32; 4] {
+    Quat::IDENTITY.to_array()
+}
+
+fn unscaled() -> [f32; 3] {
+    [1.0; 3]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LightKind {
+    Point,
+    Spot,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedLight {
+    #[serde(default)]
+    pub id: Option<StableId>,
+    pub kind: LightKind,
+    pub pos: [f32; 3],
+    pub rotation: [f32; 4],
+    /// Linear RGB.
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedMarker {
+    #[serde(default)]
+    pub id: Option<StableId>,
+    pub name: String,
+    pub pos: [f32; 3],
+}
+
+/// `level.toml`: everything in a level except the voxels, which sit beside
+/// it in `world.mwld` (the F5 save format).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LevelManifest {
+    pub version: u32,
+    #[serde(default)]
+    pub meta: LevelMeta,
+    pub settings: LevelSettings,
+    #[serde(default)]
+    pub props: Vec<SavedProp>,
+    #[serde(default)]
+    pub lights: Vec<SavedLight>,
+    #[serde(default)]
+    pub markers: Vec<SavedMarker>,
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Writes a level directory: the manifest and the world voxels.
+pub fn write_level(dir: &Path, manifest: &LevelManifest, chunks: &ChunkMap, lossy: Option<u8>) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let text = toml::to_string_pretty(manifest).map_err(|e| invalid(e.to_string()))?;
+    fs::write(dir.join(MANIFEST), text)?;
+    save_world(chunks, dir.join(WORLD), lossy)
+}
+
+pub fn read_manifest(dir: &Path) -> io::Result<LevelManifest> {
+    let text = fs::read_to_string(dir.join(MANIFEST))?;
+    let manifest: LevelManifest = toml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+    if manifest.version > LEVEL_VERSION {
+        return Err(invalid(format!(
+            "level version {} is newer than supported {LEVEL_VERSION}",
+            manifest.version
+        )));
+    }
+    Ok(manifest)
+}
+
+/// What goes into a level's manifest besides its meta, read from the
+/// running app.
+#[derive(SystemParam)]
+pub struct LevelContents<'w, 's> {
+    settings: Res<'w, MarchySettings>,
+    kinds: Res<'w, ProjectileKinds>,
+    props: Query<'w, 's, (&'static Projectile, &'static Transform, Option<&'static StableId>), With<Editable>>,
+    lights: Query<
+        'w,
+        's,
+        (&'static Transform, Option<&'static PointLight>, Option<&'static SpotLight>, Option<&'static StableId>),
+        With<PlacedLight>,
+    >,
+    markers: Query<'w, 's, (&'static Marker, &'static Transform, Option<&'static StableId>)>,
+}
+
+impl LevelContents<'_, '_> {
+    /// The manifest of everything placed right now.
+    pub fn manifest(&self, meta: LevelMeta) -> LevelManifest {
+        let lights = self.lights.iter().filter_map(|(t, point, spot, id)| {
+            let (kind, color, intensity, range) = match (point, spot) {
+                (Some(p), _) => (LightKind::Point, p.color, p.intensity, p.range),
+                (_, Some(s)) => (LightKind::Spot, s.color, s.intensity, s.range),
+                _ => return None,
+            };
+            let c = color.to_linear();
+            Some(SavedLight {
+                id: id.copied(),
+                kind,
+                pos: t.translation.to_array(),
+                rotation: t.rotation.to_array(),
+                color: [c.red, c.green, c.blue],
+                intensity,
+                range,
+            })
+        });
+        LevelManifest {
+            version: LEVEL_VERSION,
+            meta,
+            settings: LevelSettings::from_settings(&self.settings),
+            props: self.props.iter().map(|(p, t, id)| {
+                let kind = self.kinds.get(p.kind);
+                SavedProp {
+                    id: id.copied(),
+                    kind: kind.name.as_str().into(),
+                    pos: t.translation.to_array(),
+                    rotation: t.rotation.to_array(),
+                    scale: (t.scale / (kind.radius / 0.5)).to_array(),
+                }
+            }).collect(),
+            lights: lights.collect(),
+            markers: self.markers.iter().map(|(m, t, id)| SavedMarker {
+                id: id.copied(),
+                name: m.0.clone(),
+                pos: t.translation.to_array(),
+            }).collect(),
+        }
+    }
+}
+
+/// Ctrl+F5 saves the world, static props, placed lights, markers and the
+/// relevant settings as a level in `level_path`.
+pub fn save_level(keys: Res<ButtonInput<KeyCode>>, chunks: Res<ChunkMap>, contents: LevelContents) {
+    if !(keys.pressed(KeyCode::ControlLeft) && keys.just_pressed(KeyCode::F5)) {
+        return;
+    }
+    let settings = &contents.settings;
+    let dir = &settings.level_path;
+    let name = dir.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let manifest = contents.manifest(LevelMeta { name, ..default() });
+    match write_level(dir, &manifest, &chunks, settings.lossy_saves) {
+        Ok(()) => info!("saved level to {}", dir.display()),
+        Err(e) => error!("failed to save level {}: {e}", dir.display()),
+    }
+}
+
+/// Ctrl+F5 also screenshots the window into the level as its thumbnail.
+pub fn capture_thumbnail(mut cmds: Commands, keys: Res<ButtonInput<KeyCode>>, settings: Res<MarchySettings>) {
+    if keys.pressed(KeyCode::ControlLeft) && keys.just_pressed(KeyCode::F5) {
+        let path = settings.level_path.join(THUMBNAIL);
+        cmds.spawn(Screenshot::primary_window()).observe(save_to_disk(path));
+    }
+}
+
+/// Replaces the world and everything placed in it with the level in `dir`.
+#[derive(Event, Clone, Debug)]
+pub struct LoadLevel {
+    pub dir: PathBuf,
+}
+
+/// Ctrl+F9 loads the level in `level_path`.
+pub fn load_level_key(mut cmds: Commands, keys: Res<ButtonInput<KeyCode>>, settings: Res<MarchySettings>) {
+    if keys.pressed(KeyCode::ControlLeft) && keys.just_pressed(KeyCode::F9) {
+        cmds.trigger(LoadLevel { dir: settings.level_path.clone() });
+    }
+}
+
+pub fn load_level(
+    trigger: Trigger<LoadLevel>,
+    mut cmds: Commands,
+    mut chunks: ResMut<ChunkMap>,
+    mut settings: ResMut<MarchySettings>,
+    placed: Query<Entity, Or<(With<PlacedLight>, With<Marker>, (With<Projectile>, With<Editable>))>>,
+) {
+    let dir = trigger.event().dir.clone();
+    let result = read_manifest(&dir).and_then(|manifest| {
+        let world = load_world(dir.join(WORLD))?;
+        restore_world(&mut cmds, &mut chunks, world)?;
+        Ok(manifest)
+    });
+    let manifest = match result {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            error!("failed to load level {}: {e}", dir.display());
+            return;
+        }
+    };
+
+    for entity in &placed {
+        cmds.entity(entity).despawn();
+    }
+    manifest.settings.apply(&mut settings);
+    // Saving again goes back where it came from.
+    settings.level_path = dir.clone();
+    for prop in manifest.props {
+        cmds.trigger(BallSpawn {
+            pos: Vec3::from(prop.pos),
+            kind: prop.kind,
+            color: None,
+            id: prop.id,
+            respawn: None,
+            velocity: Vec3::ZERO,
+            pose: Some((Quat::from_array(prop.rotation), Vec3::from(prop.scale))),
+        });
+    }
+    for light in manifest.lights {
+        let transform = Transform::from_translation(Vec3::from(light.pos))
+            .with_rotation(Quat::from_array(light.rotation));
+        let color = Color::linear_rgb(light.color[0], light.color[1], light.color[2]);
+        let base = (PlacedLight, Editable { radius: 0.5 }, Name::new("light"), transform);
+        let mut entity = match light.kind {
+            LightKind::Point => cmds.spawn((base, PointLight {
+                color,
+                intensity: light.intensity,
+                range: light.range,
+                shadows_enabled: true,
+                ..default()
+            })),
+            LightKind::Spot => cmds.spawn((base, SpotLight {
+                color,
+                intensity: light.intensity,
+                range: light.range,
+                shadows_enabled: true,
+                ..default()
+            })),
+        };
+        if let Some(id) = light.id {
+            entity.insert(id);
+        }
+    }
+    for marker in manifest.markers {
+        spawn_marker(&mut cmds, marker.name, Vec3::from(marker.pos), marker.id);
+    }
+    info!("loaded level {:?} from {}", manifest.meta.name, dir.display());
+}
+
+fn spawn_marker(cmds: &mut Commands, name: String, pos: Vec3, id: Option<StableId>) {
+    let mut marker = cmds.spawn((
+        Name::new(format!("marker {name}")),
+        Marker(name),
+        Editable { radius: 0.4 },
+        Transform::from_translation(pos),
+    ));
+    if let Some(id) = id {
+        marker.insert(id);
+    }
+}
+
+/// J drops a spawn marker on the terrain under the cursor.
+pub fn place_marker(mut cmds: Commands, keys: Res<ButtonInput<KeyCode>>, cursor: TerrainCursor) {
+    if !keys.just_pressed(KeyCode::KeyJ) {
+        return;
+    }
+    if let Some((pos, normal)) = cursor.hit() {
+        spawn_marker(&mut cmds, "spawn".into(), pos + normal * 0.5, None);
+    }
+}
+
+pub fn draw_markers(markers: Query<&GlobalTransform, With<Marker>>, mut gizmos: Gizmos) {
+    for t in &markers {
+        let p = t.translation();
+        gizmos.line(p - Vec3::Y * 0.5, p + Vec3::Y * 0.5, Color::srgb(1.0, 0.8, 0.0));
+        gizmos.sphere(p + Vec3::Y * 0.5, 0.15, Color::srgb(1.0, 0.8, 0.0));
+    }
+}
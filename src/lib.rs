@@ -0,0 +1,517 @@
+use bevy::prelude::*;
+use std::{f32::consts::TAU, path::PathBuf};
+
+// The engine-free parts live in their own crates; re-exported under their
+// old paths so the rest of this crate reads them as before.
+pub use marchy_core::{grid, occupancy, sdf, storage, transform};
+pub use marchy_mesh::{dual, source};
+
+pub mod axes;
+pub mod ball;
+pub mod bounds;
+pub mod brush;
+pub mod browser;
+pub mod camera;
+pub mod chunk;
+pub mod codec;
+pub mod config;
+pub mod crash;
+pub mod culling;
+pub mod daylight;
+pub mod decal;
+pub mod diagnostics;
+pub mod edit;
+pub mod editor;
+pub mod erosion;
+pub mod export;
+pub mod fluid;
+pub mod game;
+pub mod headless;
+pub mod heatmap;
+pub mod ids;
+pub mod impact;
+pub mod islands;
+pub mod layers;
+pub mod level;
+pub mod lights;
+pub mod lod;
+pub mod logging;
+pub mod marble;
+pub mod materials;
+pub mod mesh;
+pub mod mesher;
+pub mod metaballs;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod outliner;
+pub mod palette;
+pub mod petrify;
+pub mod physics;
+pub mod player;
+pub mod pointcloud;
+pub mod preview;
+pub mod projectile;
+pub mod quality;
+pub mod retro;
+pub mod rng;
+pub mod sand;
+pub mod save;
+pub mod scatter;
+pub mod scenario;
+pub mod scene;
+pub mod shells;
+pub mod shooter;
+pub mod slice;
+pub mod spawner;
+pub mod stats;
+pub mod streaming;
+pub mod sway;
+pub mod terrain;
+pub mod timecontrol;
+pub mod timelapse;
+pub mod tint;
+pub mod touch;
+pub mod tuning;
+pub mod turntable;
+pub mod tutorial;
+pub mod vehicle;
+pub mod voxelize;
+
+pub use chunk::{ChunkCoord, ChunkMap};
+pub use grid::VoxelGrid;
+pub use storage::{BrickMap, VoxelStorage};
+pub use transform::GridTransform;
+pub use mesh::{build_mesh, create_mesh, BevyMesh, Boundary, MeshBuffers, MeshingStrategy};
+pub use mesher::{Mesher, Meshers};
+
+#[derive(Resource, Clone)]
+pub struct MarchySettings {
+    pub grid_size: u32,
+    /// Where voxels sit in the world; `None` is `GridTransform::centered`.
+    pub grid_transform: Option<GridTransform>,
+    pub iso_level: f32,
+    /// Name of a backend registered in `Meshers`.
+    pub mesher: String,
+    /// The two meshers retro mode swaps between.
+    pub smooth_mesher: String,
+    pub blocky_mesher: String,
+    /// How meshing treats the edges of the world.
+    pub boundary: Boundary,
+    /// Shade with density-gradient normals rather than flat faces.
+    pub smooth_normals: bool,
+    /// Cells sampled around each vertex for baked ambient occlusion, out
+    /// to this distance; 0 turns it off.
+    pub ao_radius: u32,
+    /// How dark fully enclosed vertices get, 0 to 1.
+    pub ao_strength: f32,
+    /// Finished background meshes applied per frame.
+    pub remesh_budget: usize,
+    /// Shape of each chunk's collider, rebuilt with its mesh.
+    pub chunk_collider: chunk::ChunkCollider,
+    /// Halvings of resolution colliders are built at below their chunk's
+    /// mesh. 1 bakes them from a 2x-downsampled field: much faster to
+    /// rebuild while editing, slightly off from the rendered surface.
+    pub collider_downsample: u32,
+    /// Camera distances past which chunks drop a level of detail, halving
+    /// their resolution each time.
+    pub lod_distances: Vec<f32>,
+    /// Decimates the meshes of chunks past the first LOD distance, and
+    /// `ChunkCollider::Decimated` colliders; `None` keeps them as marched.
+    pub simplify: Option<mesh::Simplify>,
+    /// Starting radius of the editing `Brush`.
+    pub dig_radius: f32,
+    /// Outline the voxel cell under the cursor.
+    pub pick_highlight: bool,
+    /// Density units per second removed from (or added to) dirt.
+    pub dig_rate: f32,
+    /// Materials harder than this can't be dug.
+    pub dig_strength: f32,
+    /// Frames between re-samples of an animated terrain generator.
+    pub animate_every: u32,
+    pub terrain_color: Color,
+    pub ball_color: Color,
+    pub axes: bool,
+    pub physics: physics::PhysicsSettings,
+    /// Which kinds of collider interact.
+    pub layers: layers::LayerSettings,
+    /// The preset last applied; see `Quality::apply` for what it sets.
+    pub quality: quality::Quality,
+    /// Sun shadow map resolution; `None` turns sun shadows off.
+    pub shadow_map: Option<usize>,
+    /// How the sun's shadows are split into cascades.
+    pub sun_shadows: daylight::SunShadows,
+    /// The sun's course and the sky's colors over a day.
+    pub day: daylight::DayCycle,
+    /// Most particles in one impact burst.
+    pub max_particles: usize,
+    pub bounds: bounds::WorldBounds,
+    pub terrain: terrain::TerrainConfig,
+    pub erosion: erosion::ErosionSettings,
+    /// Vertex motion of soft terrain materials.
+    pub sway: sway::Sway,
+    /// Load and unload chunks around the viewer; `None` keeps whatever
+    /// chunks the app inserted.
+    pub streaming: Option<streaming::Streaming>,
+    pub save_path: PathBuf,
+    /// Experimental: world and level saves store densities through the
+    /// lossy `codec` at this quality (1 to 100), trading exactness for size.
+    pub lossy_saves: Option<u8>,
+    /// Where a panic writes its crash report and emergency world snapshot.
+    pub crash_dir: PathBuf,
+    /// Directory Ctrl+F5 saves a level to and Ctrl+F9 loads it from.
+    pub level_path: PathBuf,
+    /// Directory Shift+F5 saves a scene to and Shift+F9 loads it from.
+    pub scene_path: PathBuf,
+    /// Where the F12 level browser looks for levels.
+    pub levels_dir: PathBuf,
+    pub projectiles_path: PathBuf,
+    /// Onboarding steps; the tutorial is skipped if the file is missing.
+    pub tutorial_path: PathBuf,
+    /// Base path for F7 mesh exports; `.obj` and `.gltf` are appended.
+    pub export_path: PathBuf,
+    /// Seconds between timelapse frames.
+    pub timelapse_interval: f32,
+    /// Where Alt+F7 writes timelapse frames.
+    pub timelapse_dir: PathBuf,
+    /// Model F11 voxelizes into the terrain: an `.obj` on disk, or a glTF
+    /// asset.
+    pub import_path: PathBuf,
+    /// World-space size of imported models' largest extent.
+    pub import_size: f32,
+    /// Seconds an impact decal takes to fade out.
+    pub decal_life: f32,
+    /// Projectiles landing faster than this carve a dent in the terrain.
+    pub carve_speed: f32,
+    /// Asset played when a ball hits something; `None` is silent.
+    pub impact_sound: Option<PathBuf>,
+    /// Ctrl+F's continuous ball spawner.
+    pub spawner: spawner::SpawnerSettings,
+    /// What right click fires from the camera.
+    pub shooter: shooter::ShooterSettings,
+    /// Seeds `SeededRng`, and with it everything random but the terrain.
+    pub rng_seed: u64,
+    /// Rocks strewn over the terrain; `None` leaves it bare.
+    pub scatter: Option<scatter::ScatterSettings>,
+    /// Hide chunks off screen or far away, and rest idle colliders; `None`
+    /// draws and simulates every chunk.
+    pub culling: Option<culling::ChunkCulling>,
+    /// Draw these iso levels as translucent shells in place of the terrain.
+    pub iso_shells: Option<shells::IsoShells>,
+    /// Water tables and lava pools drawn over the terrain.
+    pub fluids: Vec<fluid::FluidLayer>,
+}
+
+impl Default for MarchySettings {
+    fn default() -> Self {
+        MarchySettings {
+            grid_size: 10,
+            grid_transform: None,
+            iso_level: 5.0,
+            mesher: "culled".into(),
+            smooth_mesher: "dual".into(),
+            blocky_mesher: "greedy".into(),
+            boundary: Boundary::default(),
+            smooth_normals: false,
+            ao_radius: 1,
+            ao_strength: 0.6,
+            remesh_budget: 4,
+            chunk_collider: default(),
+            collider_downsample: 0,
+            lod_distances: vec![40.0, 80.0],
+            simplify: None,
+            dig_radius: 1.5,
+            pick_highlight: true,
+            dig_rate: 8.0,
+            dig_strength: 5.0,
+            animate_every: 2,
+            terrain_color: Color::WHITE,
+            ball_color: Color::WHITE,
+            axes: true,
+            physics: default(),
+            layers: default(),
+            quality: default(),
+            shadow_map: Some(2048),
+            sun_shadows: default(),
+            day: default(),
+            max_particles: 16,
+            bounds: default(),
+            terrain: default(),
+            erosion: default(),
+            sway: default(),
+            streaming: None,
+            save_path: PathBuf::from("world.marchy"),
+            lossy_saves: None,
+            crash_dir: PathBuf::from("crash"),
+            level_path: PathBuf::from("levels/untitled"),
+            scene_path: PathBuf::from("scenes/untitled"),
+            levels_dir: PathBuf::from("levels"),
+            projectiles_path: PathBuf::from("assets/projectiles.toml"),
+            tutorial_path: PathBuf::from("assets/tutorial.toml"),
+            export_path: PathBuf::from("terrain"),
+            timelapse_interval: 1.0,
+            timelapse_dir: PathBuf::from("timelapse"),
+            import_path: PathBuf::from("model.obj"),
+            import_size: 8.0,
+            decal_life: 20.0,
+            carve_speed: 12.0,
+            impact_sound: None,
+            spawner: default(),
+            shooter: default(),
+            rng_seed: 0,
+            scatter: None,
+            culling: None,
+            iso_shells: None,
+            fluids: vec![],
+        }
+    }
+}
+
+impl MarchySettings {
+    /// The layout for a world of `chunk_size` chunks.
+    pub fn grid_transform(&self, chunk_size: u32) -> GridTransform {
+        self.grid_transform.unwrap_or_else(|| GridTransform::centered(chunk_size))
+    }
+}
+
+/// Shared material handles built from `MarchySettings` before `Startup` runs.
+#[derive(Resource)]
+pub struct MarchyMaterials {
+    pub terrain: Handle<sway::TerrainMaterial>,
+}
+
+#[derive(Default)]
+pub struct MarchyPlugin {
+    pub settings: MarchySettings,
+}
+
+impl Plugin for MarchyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            diagnostics::MarchyDiagnosticsPlugin,
+            stats::SessionStatsPlugin,
+            crash::CrashPlugin,
+            logging::LoggingPlugin,
+            erosion::ErosionPlugin,
+            MaterialPlugin::<sway::TerrainMaterial>::default(),
+            MaterialPlugin::<tint::BallMaterial>::default(),
+        ))
+            .insert_resource(self.settings.clone())
+            .insert_resource(
+                ChunkMap::new(self.settings.grid_size).with_transform(self.settings.grid_transform(self.settings.grid_size)),
+            )
+            .insert_resource(rng::SeededRng::new(self.settings.rng_seed))
+            .insert_resource(brush::Brush { radius: self.settings.dig_radius, ..default() })
+            .init_resource::<chunk::MeshTasks>()
+            .init_resource::<mesher::Meshers>()
+            .init_resource::<tuning::Tuning>()
+            .init_resource::<editor::Selection>()
+            .init_resource::<outliner::OutlinerState>()
+            .init_resource::<browser::BrowserState>()
+            .init_resource::<preview::AreaPreview>()
+            .init_resource::<game::Game>()
+            .init_resource::<projectile::ProjectileKinds>()
+            .init_resource::<heatmap::Heatmap>()
+            .init_resource::<pointcloud::PointCloud>()
+            .init_resource::<codec::CodecDiff>()
+            .init_resource::<islands::Islands>()
+            .init_resource::<sand::SandSim>()
+            .init_resource::<ids::StableIds>()
+            .init_resource::<edit::EditGate>()
+            .init_resource::<timecontrol::TimeControl>()
+            .init_resource::<scene::PendingBodies>()
+            .init_resource::<touch::TouchPointer>()
+            .init_resource::<slice::SlicePlane>()
+            .init_resource::<vehicle::ActiveVehicle>()
+            .init_resource::<timelapse::Timelapse>()
+            .init_resource::<spawner::Spawner>()
+            .init_resource::<retro::Retro>()
+            .init_resource::<palette::CommandRegistry>()
+            .init_resource::<palette::Palette>()
+            .init_resource::<scatter::Scatter>()
+            .init_resource::<shells::Shells>()
+            .init_resource::<daylight::Daylight>()
+            .init_resource::<culling::Culling>()
+            .init_resource::<fluid::Fluids>()
+            .add_systems(PreStartup, (
+                init_materials,
+                ball::init_ball_assets,
+                projectile::load_projectiles,
+                decal::init_decal_assets,
+                tutorial::load_tutorial,
+            ))
+            .add_systems(Startup, (
+                tuning::spawn_tuning_panel,
+                lights::spawn_light_panel,
+                outliner::spawn_outliner,
+                browser::spawn_browser,
+                preview::spawn_preview,
+                game::spawn_hud,
+                heatmap::spawn_heatmap_label,
+                tutorial::spawn_tutorial_panel,
+                bounds::spawn_floor,
+                palette::spawn_palette,
+                daylight::spawn_sun,
+            ))
+            .add_systems(Update, (
+                spinner,
+                (camera::cam_input, camera::cam_follow).chain(),
+                (
+                    ball::drop_ball,
+                    (spawner::spawner_key, spawner::run_spawner).chain(),
+                    impact::detect_impacts,
+                    impact::update_particles,
+                    impact::expire,
+                ),
+                projectile::projectile_impacts,
+                (decal::fade_decals, retro::crossfade),
+                (
+                    save::save_load_keys,
+                    (
+                        ids::assign_stable_ids,
+                        level::save_level,
+                        level::capture_thumbnail,
+                        level::load_level_key,
+                        level::place_marker,
+                        scene::save_scene,
+                        (scene::load_scene_key, scene::restore_bodies).chain(),
+                    ),
+                    game::game_input,
+                    (
+                        terrain::reroll_terrain,
+                        terrain::animate_terrain,
+                        metaballs::toggle_metaballs,
+                        marble::marble_run_key,
+                        retro::retro_key,
+                    ),
+                    (brush::brush_keys, edit::dig, edit::carve_key).chain(),
+                    streaming::stream_chunks,
+                    lod::update_lod,
+                    chunk::prioritize_near_bodies,
+                    chunk::publish_dirty_regions,
+                    chunk::queue_remesh,
+                    chunk::apply_remesh,
+                    scatter::scatter_props,
+                    fluid::mesh_fluids,
+                    streaming::fade_chunks,
+                ).chain(),
+                (edit::brush_preview, edit::highlight_voxel),
+                (edit::preview_brush, preview::update_preview).chain(),
+                (tuning::tuning_input, tuning::update_tuning_panel).chain(),
+                (editor::select, editor::manipulate, editor::draw_gizmo).chain(),
+                (lights::place_light, lights::tune_light, lights::update_light_panel).chain(),
+                (lights::draw_light_markers, level::draw_markers),
+                (game::update_hud, palette::update_palette),
+                (
+                    (export::export_key, petrify::petrify_key, voxelize::import_key),
+                    (timelapse::timelapse_keys, timelapse::record_timelapse, timelapse::play_timelapse),
+                ),
+                (
+                    (heatmap::heatmap_input, heatmap::draw_heatmap).chain(),
+                    (pointcloud::point_cloud_keys, pointcloud::draw_point_cloud).chain(),
+                    (codec::codec_diff_keys, codec::draw_codec_diff).chain(),
+                    (slice::slice_keys, slice::update_slice).chain(),
+                ),
+                (vehicle::spawn_vehicle_key, vehicle::drive),
+                (player::toggle_player, player::move_player).chain(),
+                (
+                    (outliner::outliner_actions, outliner::rebuild_outliner).chain(),
+                    (browser::browser_actions, browser::rebuild_browser).chain(),
+                ),
+                (
+                    (physics::apply_physics_settings, quality::apply_shadow_quality, daylight::apply_sun_shadows)
+                        .run_if(resource_changed::<MarchySettings>),
+                    bounds::update_walls,
+                    bounds::kill_plane,
+                ),
+            ))
+            .add_systems(PostStartup, scene::boot_scene)
+            .add_systems(PreUpdate, (palette::palette_input, touch::touch_input).after(bevy::input::InputSystem))
+            .add_systems(Update, (tutorial::track_tutorial, tutorial::update_tutorial_panel)
+                .chain()
+                .run_if(resource_exists::<tutorial::Tutorial>))
+            .add_systems(Update, voxelize::finish_import
+                .run_if(resource_exists::<voxelize::PendingImport>))
+            .add_systems(Update, (metaballs::move_charges, metaballs::accumulate_charges)
+                .chain()
+                .before(chunk::publish_dirty_regions)
+                .run_if(resource_exists::<metaballs::MetaballSim>))
+            .add_systems(Update, (islands::island_key, islands::detach_islands)
+                .chain()
+                .before(chunk::publish_dirty_regions))
+            .add_systems(Update, (sand::sand_key, sand::step_sand)
+                .chain()
+                .before(chunk::publish_dirty_regions))
+            .add_systems(Update, (marble::drop_marbles, marble::finish_marbles)
+                .run_if(resource_exists::<marble::MarbleRun>))
+            .add_systems(Update, turntable::turntable_key)
+            .add_systems(Update, turntable::run_turntable
+                .after(camera::cam_input)
+                .before(camera::cam_follow)
+                .run_if(resource_exists::<turntable::Turntable>))
+            .add_systems(Update, shells::build_shells)
+            .add_systems(Update, shooter::shoot)
+            .add_systems(PostUpdate, (
+                culling::cull_chunks
+                    .after(bevy::render::view::VisibilitySystems::UpdateFrusta)
+                    .before(bevy::render::view::VisibilitySystems::VisibilityPropagate),
+                culling::rest_far_colliders.after(bevy::transform::TransformSystem::TransformPropagate),
+            ))
+            .add_systems(Update, (daylight::update_daylight, daylight::follow_sky.after(camera::cam_follow)))
+            // A turntable capture owns the clock while it runs.
+            .add_systems(Update, (timecontrol::time_control_keys, timecontrol::apply_time_control)
+                .chain()
+                .run_if(not(resource_exists::<turntable::Turntable>)))
+            .add_systems(Update, (scenario::drive_scenario, scenario::finish_scenario)
+                .chain()
+                .run_if(resource_exists::<scenario::Scenario>))
+            .add_observer(ball::ball_spawn)
+            .add_observer(chunk::mark_region_dirty)
+            .add_observer(bounds::respawn)
+            .add_observer(decal::decal_spawn)
+            .add_observer(edit::carve)
+            .add_observer(edit::brush_stroke)
+            .add_observer(impact::impact_burst)
+            .add_observer(islands::queue_island_check)
+            .add_observer(sand::wake_sand)
+            .add_observer(layers::assign_layers)
+            .add_observer(impact::impact_sound)
+            .add_observer(level::load_level)
+            .add_observer(scene::load_scene)
+            .add_observer(vehicle::vehicle_spawn)
+            .add_observer(voxelize::model_edit);
+
+        if self.settings.axes {
+            app.add_systems(Startup, axes::add_axes);
+        }
+        #[cfg(feature = "net")]
+        app.add_plugins(net::NetPlugin);
+    }
+}
+
+fn init_materials(
+    mut cmds: Commands,
+    mut materials: ResMut<Assets<sway::TerrainMaterial>>,
+    settings: Res<MarchySettings>,
+) {
+    cmds.insert_resource(MarchyMaterials {
+        terrain: materials.add(sway::TerrainMaterial {
+            base: settings.terrain_color.into(),
+            extension: settings.sway.clone(),
+        }),
+    });
+}
+
+#[derive(Component)]
+pub struct Spin;
+
+fn spinner(
+    mut spinners: Query<&mut Transform, With<Spin>>,
+    time: Res<Time>
+){
+    let dt = time.delta_secs();
+    for mut t in spinners.iter_mut() {
+        t.rotate_y(TAU * dt * 0.02);
+        t.rotate_x(TAU * dt * 0.03);
+        t.rotate_z(TAU * dt * 0.01);
+    }
+}
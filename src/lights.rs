@@ -0,0 +1,175 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use avian3d::prelude::*;
+use crate::editor::{cursor_ray, Editable, Selection};
+
+/// A light placed with the light tool (as opposed to the scene's sun).
+#[derive(Component)]
+pub struct PlacedLight;
+
+#[derive(Component)]
+pub struct LightPanel;
+
+/// L drops a point light on the terrain under the cursor, Shift+L a spot
+/// light aimed at the surface. The new light becomes the selection.
+pub fn place_light(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cams: Query<(&Camera, &GlobalTransform)>,
+    spatial: SpatialQuery,
+    mut selection: ResMut<Selection>,
+) {
+    if !keys.just_pressed(KeyCode::KeyL) {
+        return;
+    }
+    let (Ok(window), Ok((camera, cam_t))) = (windows.single(), cams.single()) else {
+        return;
+    };
+    let Some(ray) = cursor_ray(window, camera, cam_t) else {
+        return;
+    };
+    let Some(hit) = spatial.cast_ray(
+        ray.origin,
+        ray.direction,
+        100.0,
+        true,
+        &SpatialQueryFilter::default()
+    ) else {
+        return;
+    };
+
+    let pos = ray.get_point(hit.distance) + hit.normal * 1.0;
+    let base = (
+        PlacedLight,
+        Editable { radius: 0.5 },
+        Name::new("light"),
+    );
+    let entity = if keys.pressed(KeyCode::ShiftLeft) {
+        let up = if hit.normal.y.abs() > 0.9 { Vec3::Z } else { Vec3::Y };
+        cmds.spawn((
+            base,
+            SpotLight {
+                intensity: 200_000.0,
+                range: 12.0,
+                shadows_enabled: true,
+                ..default()
+            },
+            Transform::from_translation(pos).looking_to(-hit.normal, up),
+        )).id()
+    } else {
+        cmds.spawn((
+            base,
+            PointLight {
+                intensity: 100_000.0,
+                range: 10.0,
+                shadows_enabled: true,
+                ..default()
+            },
+            Transform::from_translation(pos),
+        )).id()
+    };
+    selection.entity = Some(entity);
+}
+
+/// While a placed light is selected: , and . scale intensity, - and = change
+/// range, H rotates the hue and Delete removes it.
+pub fn tune_light(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut selection: ResMut<Selection>,
+    mut points: Query<&mut PointLight, With<PlacedLight>>,
+    mut spots: Query<&mut SpotLight, With<PlacedLight>>,
+) {
+    // Alt+Period, Alt+Minus and Alt+Equal are the time controls.
+    let Some(entity) = selection.entity.filter(|_| !keys.pressed(KeyCode::AltLeft)) else {
+        return;
+    };
+    if !points.contains(entity) && !spots.contains(entity) {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Delete) {
+        cmds.entity(entity).despawn();
+        selection.entity = None;
+        return;
+    }
+
+    let mut intensity = 1.0;
+    let mut range = 0.0;
+    let mut hue = 0.0;
+    if keys.just_pressed(KeyCode::Period) { intensity = 1.25; }
+    if keys.just_pressed(KeyCode::Comma) { intensity = 0.8; }
+    if keys.just_pressed(KeyCode::Equal) { range = 1.0; }
+    if keys.just_pressed(KeyCode::Minus) { range = -1.0; }
+    if keys.just_pressed(KeyCode::KeyH) { hue = 30.0; }
+    if intensity == 1.0 && range == 0.0 && hue == 0.0 {
+        return;
+    }
+
+    let shift = |color: &mut Color| {
+        *color = Hsla::from(*color).rotate_hue(hue).into();
+    };
+    if let Ok(mut light) = points.get_mut(entity) {
+        light.intensity *= intensity;
+        light.range = (light.range + range).max(1.0);
+        shift(&mut light.color);
+    }
+    if let Ok(mut light) = spots.get_mut(entity) {
+        light.intensity *= intensity;
+        light.range = (light.range + range).max(1.0);
+        shift(&mut light.color);
+    }
+}
+
+pub fn spawn_light_panel(mut cmds: Commands) {
+    cmds.spawn((
+        LightPanel,
+        Text::new(""),
+        TextFont { font_size: 14.0, ..default() },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        },
+    ));
+}
+
+pub fn update_light_panel(
+    selection: Res<Selection>,
+    points: Query<&PointLight, With<PlacedLight>>,
+    spots: Query<&SpotLight, With<PlacedLight>>,
+    mut panel: Query<&mut Text, With<LightPanel>>,
+) {
+    let Ok(mut text) = panel.single_mut() else {
+        return;
+    };
+    let info = selection.entity.and_then(|e| {
+        points.get(e).map(|l| ("point", l.color, l.intensity, l.range))
+            .or_else(|_| spots.get(e).map(|l| ("spot", l.color, l.intensity, l.range)))
+            .ok()
+    });
+    text.0 = match info {
+        Some((kind, color, intensity, range)) => {
+            let c = color.to_srgba();
+            format!(
+                "{kind} light\ncolor: {:.2} {:.2} {:.2}\nintensity: {intensity:.0} (, .)\nrange: {range:.1} (- =)\nH hue, Del remove",
+                c.red, c.green, c.blue
+            )
+        }
+        None => String::new(),
+    };
+}
+
+pub fn draw_light_markers(
+    points: Query<(&GlobalTransform, &PointLight), With<PlacedLight>>,
+    spots: Query<(&GlobalTransform, &SpotLight), With<PlacedLight>>,
+    mut gizmos: Gizmos,
+) {
+    for (t, light) in &points {
+        gizmos.sphere(t.translation(), 0.2, light.color);
+    }
+    for (t, light) in &spots {
+        gizmos.sphere(t.translation(), 0.2, light.color);
+        gizmos.arrow(t.translation(), t.translation() + t.forward() * 1.0, light.color);
+    }
+}
@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use crate::{camera::Cam, chunk::{ChunkCoord, ChunkMap}, MarchySettings};
+
+/// The level of detail for a chunk `dist` from the camera: one level per
+/// entry of `distances` it's beyond, capped so the coarse grid divides the
+/// chunk evenly and keeps at least two cells a side.
+pub fn lod_for(dist: f32, distances: &[f32], chunk_size: u32) -> u32 {
+    let mut lod = distances.iter().filter(|d| dist > **d).count() as u32;
+    while lod > 0 && (chunk_size % (1 << lod) != 0 || chunk_size >> lod < 2) {
+        lod -= 1;
+    }
+    lod
+}
+
+/// Moves chunks between detail levels as the camera moves.
+pub fn update_lod(
+    cam: Query<&GlobalTransform, With<Cam>>,
+    mut chunks: ResMut<ChunkMap>,
+    settings: Res<MarchySettings>,
+) {
+    let Ok(cam) = cam.single() else {
+        return;
+    };
+    let eye = cam.translation();
+    let size = chunks.chunk_size;
+    let changes: Vec<(ChunkCoord, u32)> = chunks
+        .iter()
+        .filter_map(|(coord, chunk)| {
+            let dist = chunks.chunk_transform(*coord).translation.distance(eye);
+            let lod = lod_for(dist, &settings.lod_distances, size);
+            (lod != chunk.lod).then_some((*coord, lod))
+        })
+        .collect();
+    for (coord, lod) in changes {
+        debug!(target: "streaming", "chunk {:?} to lod {lod}", coord.0);
+        chunks.set_lod(coord, lod);
+    }
+}
@@ -0,0 +1,154 @@
+use bevy::{
+    log::{
+        tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry},
+        BoxedLayer,
+    },
+    prelude::*,
+};
+use std::{
+    fs::{self, File},
+    io::BufRead,
+    path::{Path, PathBuf},
+    sync::{mpsc, Mutex},
+};
+
+/// Log targets for the engine's subsystems, for filters like
+/// `info,meshing=debug`.
+pub const TARGETS: [&str; 4] = ["meshing", "streaming", "physics", "net"];
+
+/// Read by `log_layer` while `LogPlugin` builds, so insert it before
+/// `DefaultPlugins` to change it.
+#[derive(Resource, Clone, Debug)]
+pub struct LogSettings {
+    /// Where each session's log file goes.
+    pub dir: PathBuf,
+    /// Previous sessions' logs kept alongside the current one.
+    pub keep: usize,
+    /// Starting filter, in `RUST_LOG` syntax. `RUST_LOG` overrides it.
+    pub filter: String,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings {
+            dir: PathBuf::from("logs"),
+            keep: 4,
+            filter: "info,wgpu=error,naga=warn".into(),
+        }
+    }
+}
+
+/// Swaps the log filter while running.
+#[derive(Resource, Clone)]
+pub struct LogControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogControl {
+    pub fn set_filter(&self, filter: &str) -> Result<(), String> {
+        let filter = EnvFilter::try_new(filter).map_err(|e| e.to_string())?;
+        self.handle.reload(filter).map_err(|e| e.to_string())
+    }
+
+    pub fn filter(&self) -> String {
+        self.handle.with_current(|f| f.to_string()).unwrap_or_default()
+    }
+}
+
+/// For `LogPlugin::custom_layer`: puts all output, the console's included,
+/// behind a filter `LogControl` can change, and copies it to this session's
+/// log file. Set the plugin's own level to `TRACE` so it lets everything
+/// through to this one.
+pub fn log_layer(app: &mut App) -> Option<BoxedLayer> {
+    let settings = app.world().get_resource::<LogSettings>().cloned().unwrap_or_default();
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&settings.filter))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, handle) = reload::Layer::new(filter);
+    app.insert_resource(LogControl { handle });
+
+    // Nothing is listening yet, so complain straight to stderr.
+    let file = rotate(&settings.dir, settings.keep)
+        .inspect_err(|e| eprintln!("no log file in {}: {e}", settings.dir.display()))
+        .ok()
+        .map(|file| fmt::layer().with_ansi(false).with_writer(Mutex::new(file)));
+    Some(Box::new(filter.and_then(file)))
+}
+
+/// Creates `marchy.log` for this session, first shifting older logs up one
+/// (`marchy.log` to `marchy.1.log` and so on) and dropping any past `keep`.
+fn rotate(dir: &Path, keep: usize) -> std::io::Result<File> {
+    fs::create_dir_all(dir)?;
+    let name = |i: usize| match i {
+        0 => dir.join("marchy.log"),
+        i => dir.join(format!("marchy.{i}.log")),
+    };
+    let _ = fs::remove_file(name(keep));
+    for i in (0..keep).rev() {
+        let _ = fs::rename(name(i), name(i + 1));
+    }
+    File::create(name(0))
+}
+
+/// A console line from somewhere other than the terminal, like the
+/// command palette.
+#[derive(Event, Clone, Debug)]
+pub struct ConsoleCommand(pub String);
+
+/// Lines typed into the terminal, read on a background thread.
+#[derive(Resource)]
+struct ConsoleInput(Mutex<mpsc::Receiver<String>>);
+
+/// Lets the terminal change the log filter. Does nothing unless `log_layer`
+/// was installed.
+pub struct LoggingPlugin;
+
+impl Plugin for LoggingPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<LogControl>() {
+            return;
+        }
+        // A browser has no terminal (or threads) to read one from.
+        if !cfg!(target_arch = "wasm32") {
+            let (tx, rx) = mpsc::channel();
+            std::thread::spawn(move || {
+                for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+                    if tx.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+            app.insert_resource(ConsoleInput(Mutex::new(rx)));
+        }
+        app.add_systems(Update, console_commands.run_if(resource_exists::<ConsoleInput>))
+            .add_observer(|trigger: Trigger<ConsoleCommand>, control: Res<LogControl>| {
+                run_console(&trigger.event().0, &control);
+            });
+    }
+}
+
+/// `log <filter>` in the terminal replaces the filter, e.g.
+/// `log info,meshing=debug,physics=trace`; a bare `log` shows the current one.
+fn console_commands(input: Res<ConsoleInput>, control: Res<LogControl>) {
+    let Ok(rx) = input.0.lock() else {
+        return;
+    };
+    for line in rx.try_iter() {
+        run_console(&line, &control);
+    }
+}
+
+fn run_console(line: &str, control: &LogControl) {
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("log"), None) => {
+            info!("log filter: {} (targets: {})", control.filter(), TARGETS.join(", "));
+        }
+        (Some("log"), Some(filter)) => match control.set_filter(filter) {
+            Ok(()) => info!("log filter: {filter}"),
+            Err(e) => warn!("bad log filter {filter:?}: {e}"),
+        },
+        (Some(cmd), _) => warn!("unknown command {cmd:?}"),
+        (None, _) => {}
+    }
+}
@@ -1,124 +1,147 @@
-use bevy::{
-    prelude::*,
-    render::{
-        mesh::{Indices, VertexAttributeValues},
-        render_asset::RenderAssetUsages,
-        render_resource::PrimitiveTopology,
-    },
-};
-use std::f32::consts::{ PI, TAU };
-use rand::random;
+use bevy::{log::{Level, LogPlugin}, platform::time::Instant, prelude::*};
+use rand::Rng;
 use avian3d::prelude::*;
+use marchy_bevy::{
+    ball::BallSpawn,
+    camera::Cam,
+    config::MarchyConfig,
+    headless::Headless,
+    logging,
+    materials::layered,
+    projectile::BallKind,
+    rng::SeededRng,
+    scenario::Scenario,
+    sdf::Sdf,
+    terrain::{self, Fbm, Generator, TerrainConfig},
+    turntable::Turntable,
+    ChunkCoord,
+    ChunkMap,
+    MarchyPlugin,
+    MarchySettings,
+    VoxelGrid,
+};
 
-#[derive(Component)]
-struct Phys {
-    pos: Vec2,
-    acc: f32,
-    max_acc: f32,
-}
-
-#[derive(Component)]
-struct Spin;
-#[derive(Component)]
-struct Cam {
-    r: f32
-}
-
-struct VoxelGrid {
-    size: u32,
-    data: Vec<f32>
-}
+fn main() {
+    let parsed = Scenario::from_args(std::env::args()).and_then(|scenario| {
+        let headless = Headless::from_args(std::env::args())?;
+        let turntable = Turntable::from_args(std::env::args())?;
+        Ok((scenario, headless, turntable, MarchyConfig::from_args(std::env::args())?))
+    });
+    let (scenario, headless, turntable, config) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
 
-impl VoxelGrid {
-    pub fn new(size: u32) -> Self {
-        VoxelGrid {
-            size,
-            data: vec![0.0; (size * size * size) as usize]
+    let generator = if let Some(path) = &config.heightmap {
+        let map = terrain::load_heightmap(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(2);
+        });
+        Generator::heightmap(map, 8.0)
+    } else {
+        match config.generator.as_str() {
+            // Streaming needs terrain that goes on forever.
+            "shape" if config.stream => Generator::HeightmapCaves {
+                height: 2.0,
+                amplitude: 6.0,
+                fbm: default(),
+                caves: Fbm { frequency: 0.15, octaves: 2, ..default() },
+                cave_threshold: 0.3,
+            },
+            "shape" => Generator::Sdf(test_shape()),
+            name => Generator::named(name).unwrap_or_else(|| {
+                eprintln!("unknown generator {name:?} (shape, sphere, noise, caves, metaballs)");
+                std::process::exit(2);
+            }),
         }
-    }
+    };
+    let mut settings = MarchySettings {
+        terrain: TerrainConfig { generator, ..default() },
+        ..default()
+    };
+    config.apply(&mut settings);
 
-    pub fn read(&self, x: u32, y: u32, z: u32) -> f32 {
-        let size = self.size;
-        let idx = z * size * size + y * size + x;
-        self.data[idx as usize]
+    if let Some(headless) = headless {
+        let code = if headless.run(settings).is_success() { 0 } else { 1 };
+        std::process::exit(code);
     }
 
-    pub fn map<F>(&mut self, mut func: F)
-    where F: FnMut(u32, u32, u32, f32) -> f32 {
-        let size = self.size;
-        for i in 0..self.data.len() {
-            let z = (i as u32 / (size * size)) % size;
-            let y = (i as u32 / size) % size;
-            let x = i as u32 % size;
-            self.data[i] = func(x, y, z, self.data[i]);
+    let mut app = App::new();
+    app
+        .add_plugins((
+            DefaultPlugins
+                .set(LogPlugin {
+                    // `log_layer` does the filtering, so it can change at runtime.
+                    level: Level::TRACE,
+                    filter: String::new(),
+                    custom_layer: logging::log_layer,
+                    ..default()
+                })
+                .set(WindowPlugin { primary_window: Some(window()), ..default() }),
+            PhysicsPlugins::default(),
+            MarchyPlugin { settings },
+        ))
+        .insert_resource(config)
+        .add_systems(Startup, setup);
+    if let Some(scenario) = scenario {
+        app.insert_resource(scenario);
+    }
+    if let Some(turntable) = turntable {
+        app.insert_resource(turntable);
+    }
+    #[cfg(feature = "net")]
+    match marchy_bevy::net::Net::from_args(std::env::args()) {
+        Ok(Some(net)) => {
+            app.insert_resource(net);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
         }
     }
+    app.run();
+}
 
-    pub fn each<F>(&mut self, mut func: F)
-    where F: FnMut(u32, u32, u32, f32) -> () {
-        let size = self.size;
-        for i in 0..self.data.len() {
-            let z = (i as u32 / (size * size)) % size;
-            let y = (i as u32 / size) % size;
-            let x = i as u32 % size;
-            func(x, y, z, self.data[i]);
-        }
+/// On the web, the canvas fills whatever element the page puts it in, and
+/// keys like F5 reach the app instead of reloading the page.
+fn window() -> Window {
+    Window {
+        title: "marchy".into(),
+        fit_canvas_to_parent: true,
+        prevent_default_event_handling: true,
+        ..default()
     }
 }
 
-fn main() {
-    App::new()
-        .add_plugins((DefaultPlugins, PhysicsPlugins::default()))
-        .add_systems(Startup, (setup,add_axes))
-        .add_systems(Update, (spinner, cam_follow, collides))
-        .add_observer(ball_spawn)
-        .run();
+/// A blob with a ring on top and a tunnel bored through it.
+fn test_shape() -> Sdf {
+    Sdf::sphere(Vec3::new(5.0, 0.0, 5.0), 4.5)
+        .smooth_union(Sdf::torus(Vec3::new(5.0, 3.5, 5.0), 3.0, 0.8), 1.5)
+        .subtract(Sdf::capsule(Vec3::new(0.0, 1.0, 5.0), Vec3::new(10.0, 1.0, 5.0), 1.2))
 }
 
 fn setup(
     mut cmds: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    settings: Res<MarchySettings>,
+    config: Res<MarchyConfig>,
+    mut chunks: ResMut<ChunkMap>,
+    mut rng: ResMut<SeededRng>,
 ) {
-    let mut vox = VoxelGrid::new(10);
-    let size = vox.size as f32;
-    let hsize = size / 2.0;
-    vox.map(|x, y, z, _val| {
-        let xo = x as f32 - hsize;
-        let yo = y as f32;
-        let zo = z as f32 - hsize;
-        (xo * xo + yo * yo + zo * zo).sqrt()
-    });
-
-    let mat = materials.add(StandardMaterial {
-        base_color: Color::linear_rgb(1.0, 0.5, 0.5),
-        ..default()
-    });
-    let mat_off = materials.add(StandardMaterial {
-        base_color: Color::linear_rgb(0.4, 0.8, 0.8),
-        ..default()
-    });
-    let sphere = meshes.add(Sphere::default());
-
-    vox.each(|x, y, z, val| {
-        let xo = x as f32 - hsize;
-        let yo = y as f32 - hsize;
-        let zo = z as f32 - hsize;
-
-        cmds.spawn((
-            MeshMaterial3d(if val < 5.0 { mat.clone() } else { mat_off.clone() }),
-            Mesh3d(sphere.clone()),
-            Transform::from_xyz(xo, yo, zo)
-                .with_scale(Vec3::splat(0.1))
-        ));
-    });
+    let start = Instant::now();
+    let mut vox = VoxelGrid::new(settings.grid_size);
+    terrain::generate(&settings.terrain, &mut vox, IVec3::ZERO, settings.iso_level);
+    chunks.filled.push(start.elapsed());
 
     cmds.spawn((
         Name::new("cam"),
         Camera3d::default(),
-        Transform::from_xyz(0.0, 3.0, 20.0)
+        Transform::from_xyz(0.0, 3.0, config.camera_radius)
             .looking_at(Vec3::new(0.0, 0.0, 0.0), Dir3::Y),
-        Cam { r: 20.0 }
+        Cam { r: config.camera_radius, target: Vec3::ZERO, ..default() }
     ));
 
     cmds.insert_resource(AmbientLight {
@@ -127,30 +150,13 @@ fn setup(
         ..default()
     });
 
-    cmds.spawn((
-        DirectionalLight {
-            illuminance: light_consts::lux::OVERCAST_DAY,
-            shadows_enabled: true,
-            ..default()
-        },
-        Transform {
-            translation: Vec3::new(0.0, 2.0, 0.0),
-            rotation: Quat::from_rotation_x(-PI / 4.),
-            ..default()
-        },
-    ));
-
-    // let limit = random::<f32>() * 4.0;
-    let limit = 5.0;
-    let mesh = create_mesh(&vox, limit);
-    cmds.spawn((
-        MeshMaterial3d(materials.add(StandardMaterial::default())),
-        RigidBody::Static,
-        Collider::trimesh_from_mesh(&mesh).unwrap(),
-        Transform::from_xyz(0.0, 0.0, 0.0),
-        Mesh3d(meshes.add(mesh)),
-        CollidingEntities::default()
-    ));
+    // let limit = rng.random::<f32>() * 4.0;
+    let coord = ChunkCoord(IVec3::ZERO);
+    let layers = layered(&vox, settings.iso_level);
+    chunks.insert(coord, vox);
+    if let Some(chunk) = chunks.chunk_mut(coord) {
+        chunk.materials = layers;
+    }
 
     for pos in [
         [-2.5, -0.5, -0.5],
@@ -159,208 +165,28 @@ fn setup(
     ] {
         cmds.trigger(BallSpawn {
             pos: Vec3::new(pos[0], pos[1], pos[2]),
-            ptype: 1
+            kind: BallKind::Static,
+            color: None,
+            id: None,
+            respawn: None,
+            velocity: Vec3::ZERO,
+            pose: None,
         });
     }
 
-    cmds.spawn((
-        RigidBody::Static,
-        Collider::cylinder(10.0, 0.1),
-        Mesh3d(meshes.add(Cylinder::new(20.0, 0.1))),
-        MeshMaterial3d(materials.add(Color::BLACK)),
-        Transform::from_xyz(0.0, -5.0, 0.0),
-    ));
-
-    for _ in 0..30 {
+    for i in 0..config.balls {
         cmds.trigger(BallSpawn {
             pos: Vec3::new(
-               random::<f32>() * 10.0 - 5.0,
-               random::<f32>() * 2.0 + 2.0,
-               random::<f32>() * 10.0 - 5.0,
+               rng.random::<f32>() * 10.0 - 5.0,
+               rng.random::<f32>() * 2.0 + 2.0,
+               rng.random::<f32>() * 10.0 - 5.0,
             ),
-            ptype: 0
+            kind: [BallKind::Ball, BallKind::Ball, BallKind::Bouncy, BallKind::Heavy, BallKind::Sticky][i % 5].clone(),
+            color: None,
+            id: None,
+            respawn: None,
+            velocity: Vec3::ZERO,
+            pose: None,
         });
     }
 }
-
-
-fn collides(query: Query<(Entity, &CollidingEntities)>) {
-    for (entity, colliding_entities) in &query {
-        /*println!(
-            "{} is colliding with the following entities: {:?}",
-            entity,
-            colliding_entities,
-        );*/
-    }
-}
-
-fn add_axes(
-    mut cmds: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    let w = 0.01;
-    cmds.spawn((
-        Mesh3d(meshes.add(Cuboid::default())),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            emissive: LinearRgba::rgb(1.0, 0.0, 0.0),
-            ..default()
-        })),
-        Transform::from_xyz(0.0, 0.0, 0.0)
-            .with_scale(Vec3::new(100.0, w, w))
-    ));
-    cmds.spawn((
-        Mesh3d(meshes.add(Cuboid::default())),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            emissive: LinearRgba::rgb(0.0, 0.3, 1.0),
-            ..default()
-        })),
-        Transform::from_xyz(0.0, 0.0, 0.0)
-            .with_scale(Vec3::new(w, w, 100.0))
-    ));
-    cmds.spawn((
-        Mesh3d(meshes.add(Cuboid::default())),
-        MeshMaterial3d(materials.add(StandardMaterial {
-            emissive: LinearRgba::rgb(0.0, 1.0, 0.0),
-            ..default()
-        })),
-        Transform::from_xyz(0.0, 0.0, 0.0)
-            .with_scale(Vec3::new(w, 100.0, w))
-    ));
-}
-
-fn spinner(
-    mut spinners: Query<&mut Transform, With<Spin>>,
-    time: Res<Time>
-){
-    let dt = time.delta_secs();
-    for mut t in spinners.iter_mut() {
-        t.rotate_y(TAU * dt * 0.02);
-        t.rotate_x(TAU * dt * 0.03);
-        t.rotate_z(TAU * dt * 0.01);
-    }
-}
-
-fn create_mesh(vox: &VoxelGrid, limit: f32) -> Mesh {
-    let size = vox.size;
-    let vol = size * size * size;
-    let xo = -(size as f32 / 2.0);
-    let yo = xo;
-    let zo = xo;
-
-    let mut verts: Vec<[f32; 3]> = vec![];
-
-    for i in 0..vol {
-        let val = vox.data[i as usize];
-        if val > limit {
-            continue;
-        }
-
-        let x = (i % size) as f32 + xo;
-        let y = ((i / size) % size) as f32 + yo;
-        let z = ((i / (size * size)) % size) as f32 + zo;
-
-        // Front
-        verts.push([x - 1.0, y, z]);
-        verts.push([x - 1.0, y - 1.0, z]);
-        verts.push([x, y - 1.0, z]);
-        verts.push([x - 1.0, y, z]);
-        verts.push([x, y - 1.0, z]);
-        verts.push([x, y, z]);
-
-        // Back
-        verts.push([x, y, z - 1.0]);
-        verts.push([x, y - 1.0, z - 1.0]);
-        verts.push([x - 1.0, y - 1.0, z - 1.0]);
-        verts.push([x, y, z - 1.0]);
-        verts.push([x - 1.0, y - 1.0, z - 1.0]);
-        verts.push([x - 1.0, y, z - 1.0]);
-
-        // Top
-        verts.push([x - 1.0, y, z]);
-        verts.push([x, y, z]);
-        verts.push([x, y, z - 1.0]);
-        verts.push([x - 1.0, y, z]);
-        verts.push([x, y, z - 1.0]);
-        verts.push([x - 1.0, y, z - 1.0]);
-
-        // Bottom
-        verts.push([x, y, z - 1.0]);
-        verts.push([x, y, z]);
-        verts.push([x - 1.0, y - 1.0, z]);
-        verts.push([x, y - 1.0, z - 1.0]);
-        verts.push([x - 1.0, y - 1.0, z]);
-        verts.push([x - 1.0, y - 1.0, z - 1.0]);
-
-        // Left
-        verts.push([x - 1.0, y, z - 1.0]);
-        verts.push([x - 1.0, y - 1.0, z - 1.0]);
-        verts.push([x - 1.0, y - 1.0, z]);
-        verts.push([x - 1.0, y, z - 1.0]);
-        verts.push([x - 1.0, y - 1.0, z]);
-        verts.push([x - 1.0, y, z]);
-
-        // Right
-        verts.push([x, y, z]);
-        verts.push([x, y - 1.0, z - 1.0]);
-        verts.push([x, y, z - 1.0]);
-        verts.push([x, y, z]);
-        verts.push([x, y - 1.0, z]);
-        verts.push([x, y - 1.0, z - 1.0]);
-    }
-
-    let len = verts.len();
-
-    let mut mesh = Mesh::new(
-        PrimitiveTopology::TriangleList,
-        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD
-    )
-    .with_inserted_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        VertexAttributeValues::Float32x3(verts)
-    )
-    // TODO: reusue verts, hey...
-    .with_inserted_indices(Indices::U32((0..=len as u32).collect()));
-
-    mesh.compute_normals();
-    mesh
-}
-
-fn cam_follow(
-    mut cams: Query<(&mut Transform, &Cam)>,
-    time: Res<Time>
-) {
-    let elapsed = time.elapsed_secs() * 0.1;
-    for (mut t, cam) in cams.iter_mut() {
-        t.translation.x = elapsed.sin() * cam.r;
-        t.translation.z = elapsed.cos() * cam.r;
-        t.translation.y = elapsed.sin() * 5.0;
-        t.look_at(Vec3::new(0.0, 0.0, 0.0), Dir3::Y);
-    }
-}
-
-#[derive(Debug, Event)]
-struct BallSpawn {
-    pos: Vec3,
-    ptype: u32,
-}
-
-fn ball_spawn(
-    trigger: Trigger<BallSpawn>,
-    mut cmds: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    let pos = trigger.event().pos;
-    let ptype = trigger.event().ptype;
-
-    cmds.spawn((
-        if ptype == 0 { RigidBody::Dynamic } else { RigidBody::Static },
-        Collider::sphere(0.5),
-        Restitution::new(0.8)
-            .with_combine_rule(CoefficientCombine::Max),
-        Mesh3d(meshes.add(Sphere::new(0.5))),
-        MeshMaterial3d(materials.add(Color::WHITE)),
-        Transform::from_translation(pos),
-    ));
-}
@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+use avian3d::prelude::*;
+use rand::Rng;
+use crate::{
+    ball::BallAssets,
+    chunk::ChunkMap,
+    materials,
+    rng::SeededRng,
+    sdf::{catmull_rom, Sdf},
+    tint,
+    MarchySettings,
+};
+
+const TRACK_RADIUS: f32 = 1.2;
+const TRACK_WALL: f32 = 0.6;
+/// Open air kept around the track where it runs through the terrain.
+const CLEARANCE: f32 = 2.0;
+const MARBLE_RADIUS: f32 = 0.3;
+
+/// A marble run carved through the terrain, with marbles dropped in at
+/// `start` every `spawn` tick and timed to the finish.
+#[derive(Resource)]
+pub struct MarbleRun {
+    pub start: Vec3,
+    pub finish: Vec3,
+    pub spawn: Timer,
+    /// Fastest run so far, in seconds.
+    pub best: Option<f32>,
+}
+
+#[derive(Component)]
+pub struct Marble {
+    /// `Time::elapsed_secs` when it was dropped in.
+    pub dropped: f32,
+}
+
+/// The sensor at the bottom of the run.
+#[derive(Component)]
+pub struct FinishLine;
+
+/// A winding path from high in one corner of `min..max` to low in the
+/// other, through random points in between.
+pub fn marble_path(min: Vec3, max: Vec3, rng: &mut impl Rng) -> Vec<Vec3> {
+    let inset = Vec3::splat(TRACK_RADIUS + CLEARANCE);
+    let (lo, hi) = (min + inset, (max - inset).max(min + inset));
+    let n = 6;
+    let points: Vec<Vec3> = (0..n)
+        .map(|i| {
+            let t = i as f32 / (n - 1) as f32;
+            let (x, z) = match i {
+                0 => (0.0, 0.0),
+                i if i == n - 1 => (1.0, 1.0),
+                _ => (rng.random(), rng.random()),
+            };
+            lo + (hi - lo) * Vec3::new(x, 1.0 - t, z)
+        })
+        .collect();
+    catmull_rom(&points, 8)
+}
+
+/// Trenches the track into every chunk and lines it with a half-pipe.
+pub fn carve_track(chunks: &mut ChunkMap, path: &[Vec3], iso: f32) {
+    let pipe = Sdf::half_pipe(path.to_vec(), TRACK_RADIUS, TRACK_WALL);
+    let clear = Sdf::tube(path.to_vec(), TRACK_RADIUS + CLEARANCE);
+    let size = chunks.chunk_size as i32;
+    let coords: Vec<_> = chunks.iter().map(|(c, _)| *c).collect();
+    for coord in coords {
+        let origin = chunks.voxel_center(coord.0 * size);
+        if let Some(chunk) = chunks.chunk_mut(coord) {
+            chunk.grid.map(|x, y, z, val| {
+                let p = origin + Vec3::new(x as f32, y as f32, z as f32);
+                iso + (val - iso).max(-clear.sample(p)).min(pipe.sample(p))
+            });
+            chunk.materials = materials::layered(&chunk.grid, iso);
+        }
+    }
+}
+
+/// F1 carves a new marble run through the loaded terrain, replacing the last
+/// one's finish line.
+pub fn marble_run_key(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    finish_lines: Query<Entity, With<FinishLine>>,
+    mut chunks: ResMut<ChunkMap>,
+    settings: Res<MarchySettings>,
+    mut rng: ResMut<SeededRng>,
+) {
+    if keys.pressed(KeyCode::ControlLeft) || !keys.just_pressed(KeyCode::F1) {
+        return;
+    }
+    let Some((min, max)) = chunks.world_extent() else {
+        return;
+    };
+    let path = marble_path(min, max, &mut *rng);
+    carve_track(&mut chunks, &path, settings.iso_level);
+    for entity in &finish_lines {
+        cmds.entity(entity).despawn();
+    }
+    let (start, finish) = (path[0], path[path.len() - 1]);
+    cmds.spawn((
+        Name::new("finish line"),
+        FinishLine,
+        Sensor,
+        RigidBody::Static,
+        Collider::sphere(TRACK_RADIUS),
+        CollidingEntities::default(),
+        Transform::from_translation(finish),
+    ));
+    cmds.insert_resource(MarbleRun {
+        start,
+        finish,
+        spawn: Timer::from_seconds(3.0, TimerMode::Repeating),
+        best: None,
+    });
+    info!("marble run from {start} to {finish}");
+}
+
+/// Drops a marble in at the start of the run every few seconds.
+pub fn drop_marbles(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut run: ResMut<MarbleRun>,
+    assets: Res<BallAssets>,
+    mut rng: ResMut<SeededRng>,
+) {
+    if !run.spawn.tick(time.delta()).just_finished() {
+        return;
+    }
+    let color = Color::hsl(rng.random::<f32>() * 360.0, 0.8, 0.6);
+    cmds.spawn((
+        Name::new("marble"),
+        Marble { dropped: time.elapsed_secs() },
+        RigidBody::Dynamic,
+        Collider::sphere(0.5),
+        Mesh3d(assets.mesh.clone()),
+        MeshMaterial3d(assets.material.clone()),
+        tint::tag(color),
+        // The shared ball mesh is radius 0.5.
+        Transform::from_translation(run.start + Vec3::Y * (TRACK_RADIUS / 2.0))
+            .with_scale(Vec3::splat(MARBLE_RADIUS / 0.5)),
+    ));
+}
+
+/// Times marbles reaching the finish line, then removes them.
+pub fn finish_marbles(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut run: ResMut<MarbleRun>,
+    finish_lines: Query<&CollidingEntities, With<FinishLine>>,
+    marbles: Query<&Marble>,
+) {
+    for colliding in &finish_lines {
+        for &entity in colliding.iter() {
+            let Ok(marble) = marbles.get(entity) else {
+                continue;
+            };
+            let secs = time.elapsed_secs() - marble.dropped;
+            if run.best.is_none_or(|best| secs < best) {
+                run.best = Some(secs);
+            }
+            info!("marble finished in {secs:.2}s (best {:.2}s)", run.best.unwrap_or(secs));
+            cmds.entity(entity).despawn();
+        }
+    }
+}
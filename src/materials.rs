@@ -0,0 +1,94 @@
+use bevy::color::{Alpha, Color, LinearRgba};
+use crate::VoxelGrid;
+
+/// What a voxel is made of. Stored per cell as a `u8` alongside the density.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum VoxelMaterial {
+    #[default]
+    Dirt = 0,
+    Sand = 1,
+    Stone = 2,
+    Bedrock = 3,
+    Grass = 4,
+    Water = 5,
+}
+
+impl VoxelMaterial {
+    pub const ALL: [VoxelMaterial; 6] = [
+        VoxelMaterial::Dirt,
+        VoxelMaterial::Sand,
+        VoxelMaterial::Stone,
+        VoxelMaterial::Bedrock,
+        VoxelMaterial::Grass,
+        VoxelMaterial::Water,
+    ];
+
+    pub fn from_id(id: u8) -> Self {
+        Self::ALL.get(id as usize).copied().unwrap_or_default()
+    }
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// How much a tool's dig rate is divided by. A tool whose strength is
+    /// below the hardness can't dig the material at all.
+    pub fn hardness(self) -> f32 {
+        match self {
+            VoxelMaterial::Water => 0.2,
+            VoxelMaterial::Sand => 0.5,
+            VoxelMaterial::Grass => 0.8,
+            VoxelMaterial::Dirt => 1.0,
+            VoxelMaterial::Stone => 3.0,
+            VoxelMaterial::Bedrock => 10.0,
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            VoxelMaterial::Dirt => Color::srgb(0.45, 0.32, 0.2),
+            VoxelMaterial::Sand => Color::srgb(0.86, 0.78, 0.55),
+            VoxelMaterial::Stone => Color::srgb(0.5, 0.5, 0.52),
+            VoxelMaterial::Bedrock => Color::srgb(0.2, 0.2, 0.24),
+            VoxelMaterial::Grass => Color::srgb(0.3, 0.6, 0.2),
+            VoxelMaterial::Water => Color::srgb(0.2, 0.4, 0.75),
+        }
+    }
+
+    /// How much the terrain shader lets the surface undulate, from 0 to 1.
+    pub fn sway(self) -> f32 {
+        match self {
+            VoxelMaterial::Grass | VoxelMaterial::Water => 1.0,
+            VoxelMaterial::Sand => 0.3,
+            _ => 0.0,
+        }
+    }
+
+    /// `color` as a linear vertex color, with `sway` in the alpha (the
+    /// terrain is opaque, so nothing else reads it).
+    pub fn vertex_color(self) -> [f32; 4] {
+        LinearRgba::from(self.color()).with_alpha(self.sway()).to_f32_array()
+    }
+}
+
+/// Assigns materials by depth below the surface: a skin of grass, dirt
+/// under it, then stone, then bedrock.
+pub fn layered(grid: &VoxelGrid, iso: f32) -> Vec<u8> {
+    grid.data
+        .iter()
+        .map(|&val| {
+            let depth = iso - val;
+            let mat = if depth < 1.0 {
+                VoxelMaterial::Grass
+            } else if depth < 2.0 {
+                VoxelMaterial::Dirt
+            } else if depth < 6.0 {
+                VoxelMaterial::Stone
+            } else {
+                VoxelMaterial::Bedrock
+            };
+            mat.id()
+        })
+        .collect()
+}
@@ -0,0 +1,73 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        render_asset::RenderAssetUsages,
+        render_resource::PrimitiveTopology,
+    },
+};
+pub use marchy_mesh::{mesh::*, simplify::Simplify};
+use crate::storage::VoxelStorage;
+
+/// Moving `MeshBuffers` in and out of Bevy's `Mesh` asset.
+pub trait BevyMesh {
+    /// Copies a mesh's positions, normals and indices. Missing normals are
+    /// left empty; unindexed meshes get sequential indices.
+    fn from_mesh(mesh: &Mesh) -> Self;
+
+    fn into_mesh(self) -> Mesh;
+}
+
+impl BevyMesh for MeshBuffers {
+    fn from_mesh(mesh: &Mesh) -> Self {
+        let read = |attr| match mesh.attribute(attr) {
+            Some(VertexAttributeValues::Float32x3(v)) => v.clone(),
+            _ => vec![],
+        };
+        let positions = read(Mesh::ATTRIBUTE_POSITION);
+        let normals = read(Mesh::ATTRIBUTE_NORMAL);
+        let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+            Some(VertexAttributeValues::Float32x4(v)) => v.clone(),
+            _ => vec![],
+        };
+        let indices = match mesh.indices() {
+            Some(Indices::U32(i)) => i.clone(),
+            Some(Indices::U16(i)) => i.iter().map(|&i| i as u32).collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        MeshBuffers { positions, normals, colors, indices }
+    }
+
+    fn into_mesh(self) -> Mesh {
+        let count = self.positions.len();
+        let has_normals = self.normals.len() == count && count > 0;
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD
+        )
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            VertexAttributeValues::Float32x3(self.positions)
+        )
+        .with_inserted_indices(Indices::U32(self.indices));
+
+        if has_normals {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        } else {
+            mesh.compute_normals();
+        }
+        if self.colors.len() == count && count > 0 {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+        }
+        mesh
+    }
+}
+
+pub fn create_mesh<S: VoxelStorage<f32> + Sync + ?Sized>(vox: &S, limit: f32) -> Mesh {
+    build_mesh(vox, limit, MeshingStrategy::default(), |_| None)
+}
+
+pub fn build_mesh<S, F>(vox: &S, limit: f32, strategy: MeshingStrategy, outside: F) -> Mesh
+where S: VoxelStorage<f32> + Sync + ?Sized, F: Fn(IVec3) -> Option<f32> + Sync {
+    build_buffers(vox, limit, strategy, outside).into_mesh()
+}
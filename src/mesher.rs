@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use std::{collections::HashMap, sync::Arc};
+use crate::{
+    mesh::{build_buffers_occupied, Boundary, MeshBuffers, MeshingStrategy},
+    occupancy::Occupancy,
+    storage::VoxelStorage,
+};
+
+/// Read-only densities for one chunk: its own cells, plus `outside` for the
+/// cells just past its faces (from neighboring chunks).
+pub struct DensityView<'a> {
+    pub storage: &'a (dyn VoxelStorage<f32> + Sync),
+    pub outside: &'a (dyn Fn(IVec3) -> Option<f32> + Sync),
+    /// Of `storage`, when one is kept, so meshers can skip uniform space.
+    pub occupancy: Option<&'a Occupancy>,
+    /// The material id of a chunk-local cell, for meshers that keep
+    /// materials apart.
+    pub materials: Option<&'a (dyn Fn(IVec3) -> u8 + Sync)>,
+}
+
+impl DensityView<'_> {
+    pub fn dims(&self) -> UVec3 {
+        self.storage.dims()
+    }
+
+    /// Density at a chunk-local cell, which may be just outside the chunk,
+    /// falling back to `boundary` where no neighbor covers it.
+    pub fn sample(&self, p: IVec3, boundary: Boundary) -> Option<f32> {
+        let inside = p.cmpge(IVec3::ZERO).all() && p.cmplt(self.dims().as_ivec3()).all();
+        if inside {
+            self.storage.get(p.as_uvec3())
+        } else {
+            (self.outside)(p).or_else(|| boundary.resolve(self.storage, p))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MeshOptions {
+    /// Cells at or below this are solid.
+    pub iso: f32,
+    /// Applies at the edges of the world, not between loaded chunks.
+    pub boundary: Boundary,
+    /// Normals from the density gradient instead of the faces.
+    pub smooth_normals: bool,
+}
+
+/// Turns a chunk's densities into triangles. Output is in chunk-local space,
+/// centered on the chunk like the built-in meshers. Implementations run on
+/// background tasks, so they must be `Send + Sync`.
+pub trait Mesher: Send + Sync {
+    fn mesh(&self, view: &DensityView, options: &MeshOptions) -> MeshBuffers;
+}
+
+/// The built-in meshers, one per `MeshingStrategy`.
+pub struct BlockMesher(pub MeshingStrategy);
+
+impl Mesher for BlockMesher {
+    fn mesh(&self, view: &DensityView, options: &MeshOptions) -> MeshBuffers {
+        let outside = |p| view.sample(p, options.boundary);
+        let mut buffers = build_buffers_occupied(view.storage, options.iso, self.0, outside, view.occupancy, view.materials);
+        if options.smooth_normals {
+            // Clamped like the dual contourer, so solid boundaries don't
+            // swamp the gradient.
+            let (iso, range) = (options.iso, 4.0);
+            buffers.smooth_normals(view.dims(), |p| {
+                outside(p).map_or(iso + range, |v| v.clamp(iso - range, iso + range))
+            });
+        }
+        buffers
+    }
+}
+
+/// Meshing backends by name; `MarchySettings::mesher` picks which one the
+/// remesh pipeline uses. Register more with `register`.
+#[derive(Resource, Clone)]
+pub struct Meshers {
+    backends: HashMap<String, Arc<dyn Mesher>>,
+}
+
+impl Default for Meshers {
+    fn default() -> Self {
+        let mut meshers = Meshers { backends: HashMap::new() };
+        meshers.register("naive", BlockMesher(MeshingStrategy::Naive));
+        meshers.register("culled", BlockMesher(MeshingStrategy::Culled));
+        meshers.register("greedy", BlockMesher(MeshingStrategy::Greedy));
+        meshers.register("dual", BlockMesher(MeshingStrategy::DualContour));
+        meshers
+    }
+}
+
+impl Meshers {
+    pub fn register(&mut self, name: &str, mesher: impl Mesher + 'static) {
+        self.backends.insert(name.to_string(), Arc::new(mesher));
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Mesher>> {
+        self.backends.get(name).cloned()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.backends.keys().map(String::as_str)
+    }
+}
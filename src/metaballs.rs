@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use rand::Rng;
+use crate::{
+    chunk::{ChunkMap, MeshTasks},
+    materials,
+    rng::SeededRng,
+    terrain::{metaball_field, regenerate_all},
+    MarchySettings,
+};
+
+/// A moving point charge of the metaball simulation.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Charge {
+    /// Where the charge's field is 1 when it's on its own.
+    pub radius: f32,
+    pub velocity: Vec3,
+}
+
+/// Present while the simulation owns the terrain.
+#[derive(Resource, Clone, Debug)]
+pub struct MetaballSim {
+    /// Solid where the summed field passes this.
+    pub threshold: f32,
+}
+
+/// Shift+M replaces the terrain with a handful of wandering charges, meshed
+/// as one blob; Shift+M again brings the generated terrain back.
+pub fn toggle_metaballs(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    charges: Query<Entity, With<Charge>>,
+    sim: Option<Res<MetaballSim>>,
+    mut chunks: ResMut<ChunkMap>,
+    settings: Res<MarchySettings>,
+    mut rng: ResMut<SeededRng>,
+) {
+    if !(keys.pressed(KeyCode::ShiftLeft) && keys.just_pressed(KeyCode::KeyM)) {
+        return;
+    }
+    if sim.is_some() {
+        for charge in &charges {
+            cmds.entity(charge).despawn();
+        }
+        cmds.remove_resource::<MetaballSim>();
+        regenerate_all(&mut chunks, &settings);
+        return;
+    }
+    let Some((min, max)) = chunks.world_extent() else {
+        return;
+    };
+    for _ in 0..6 {
+        let pos = min.lerp(max, 0.5) + (Vec3::new(rng.random(), rng.random(), rng.random()) - 0.5) * (max - min) * 0.5;
+        let velocity = (Vec3::new(rng.random(), rng.random(), rng.random()) - 0.5).normalize_or_zero() * 3.0;
+        cmds.spawn((
+            Name::new("charge"),
+            Charge { radius: 1.5 + rng.random::<f32>(), velocity },
+            Transform::from_translation(pos),
+        ));
+    }
+    cmds.insert_resource(MetaballSim { threshold: 1.0 });
+}
+
+/// Moves the charges, bouncing them off the edges of the loaded chunks.
+pub fn move_charges(
+    time: Res<Time>,
+    chunks: Res<ChunkMap>,
+    mut charges: Query<(&mut Charge, &mut Transform)>,
+) {
+    let Some((min, max)) = chunks.world_extent() else {
+        return;
+    };
+    let dt = time.delta_secs();
+    for (mut charge, mut t) in &mut charges {
+        let inset = Vec3::splat(charge.radius);
+        let (lo, hi) = (min + inset, (max - inset).max(min + inset));
+        t.translation += charge.velocity * dt;
+        let v = &mut charge.velocity;
+        *v = Vec3::select(t.translation.cmplt(lo), v.abs(), *v);
+        *v = Vec3::select(t.translation.cmpgt(hi), -v.abs(), *v);
+        t.translation = t.translation.clamp(lo, hi);
+    }
+}
+
+/// Writes the charges' field into every chunk and lets the remesh pipeline
+/// march it. Like `animate_terrain`, waits for the last meshes to land so
+/// cancelled jobs don't starve the blob of updates.
+pub fn accumulate_charges(
+    sim: Res<MetaballSim>,
+    tasks: Res<MeshTasks>,
+    charges: Query<(&Charge, &Transform)>,
+    mut chunks: ResMut<ChunkMap>,
+    settings: Res<MarchySettings>,
+) {
+    if tasks.pending() > 0 {
+        return;
+    }
+    let balls: Vec<(Vec3, f32)> = charges.iter().map(|(c, t)| (t.translation, c.radius)).collect();
+    let size = chunks.chunk_size as i32;
+    let iso = settings.iso_level;
+    let coords: Vec<_> = chunks.iter().map(|(c, _)| *c).collect();
+    for coord in coords {
+        let center = chunks.voxel_center(coord.0 * size);
+        if let Some(chunk) = chunks.chunk_mut(coord) {
+            chunk.grid.map(|x, y, z, _| {
+                let p = center + Vec3::new(x as f32, y as f32, z as f32);
+                iso + sim.threshold - metaball_field(p, balls.iter().copied())
+            });
+            chunk.materials = materials::layered(&chunk.grid, iso);
+        }
+    }
+}
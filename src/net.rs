@@ -0,0 +1,431 @@
+use bevy::prelude::*;
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+use crate::{
+    ball::BallSpawn,
+    brush::{Brush, BrushMode, BrushShape, Falloff},
+    chunk::ChunkMap,
+    edit::{BrushStroke, Carve, CarveCause, EditGate},
+    grid::read_u32,
+    materials::VoxelMaterial,
+    projectile::BallKind,
+    save::{read_world, restore_world, write_world},
+};
+
+const SHAPES: [BrushShape; 3] = [BrushShape::Sphere, BrushShape::Cube, BrushShape::Cylinder];
+const FALLOFFS: [Falloff; 3] = [Falloff::Hard, Falloff::Linear, Falloff::Smooth];
+const MODES: [BrushMode; 3] = [BrushMode::Add, BrushMode::Subtract, BrushMode::Paint];
+/// Longest message read; a whole world fits with room to spare.
+const MAX_MESSAGE: usize = 256 << 20;
+/// Longest projectile kind name read.
+const MAX_KIND: usize = 256;
+
+/// How to take part, from `Net::from_args`.
+#[derive(Clone, Debug)]
+pub enum NetRole {
+    /// Accept clients on this address.
+    Host(String),
+    /// Connect to a host at this address.
+    Join(String),
+}
+
+/// A networked session keeping every peer's terrain identical. The host
+/// owns the world: it applies brush strokes, carves and ball spawns as they
+/// happen and sends each to the clients numbered in that order. Clients
+/// start from the host's world, apply only what it sends, in order, and
+/// send their own player's edits to the host instead of applying them.
+/// Bodies are simulated on every peer and may drift apart; what they carve
+/// counts only on the host. Levels, props and simulations that change
+/// terrain by themselves (sand, erosion, metaballs) aren't synced.
+#[derive(Resource)]
+pub struct Net {
+    pub role: NetRole,
+    listener: Option<TcpListener>,
+    peers: Vec<Peer>,
+    /// The number of the next edit the host sends, or a client expects.
+    seq: u64,
+    /// A client has its world and may send edits.
+    joined: bool,
+}
+
+struct Peer {
+    stream: TcpStream,
+    inbox: Vec<u8>,
+    outbox: Vec<u8>,
+}
+
+/// What's replicated.
+#[derive(Clone, Debug)]
+pub enum NetEdit {
+    Brush(BrushStroke),
+    Carve(Carve),
+    Ball { pos: Vec3, velocity: Vec3, kind: BallKind },
+}
+
+enum Message {
+    /// Host to a new client: the world as it is, and the next edit's number.
+    World(u64, Vec<u8>),
+    /// Host to clients: an edit the host has applied.
+    Edit(u64, NetEdit),
+    /// Client to host: an edit to apply and pass on.
+    Request(NetEdit),
+}
+
+impl Net {
+    /// `--host <addr>` or `--join <addr>`. `None` to play alone.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut args = args.into_iter();
+        let mut role = None;
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--host" => role = Some(NetRole::Host(value()?)),
+                "--join" => role = Some(NetRole::Join(value()?)),
+                _ => {}
+            }
+        }
+        Ok(role.map(|role| Net { role, listener: None, peers: vec![], seq: 0, joined: false }))
+    }
+
+    fn is_host(&self) -> bool {
+        matches!(self.role, NetRole::Host(_))
+    }
+
+    fn send(&mut self, message: &Message) {
+        let mut frame = vec![];
+        if let Err(e) = encode(message, &mut frame) {
+            error!(target: "net", "failed to encode a message: {e}");
+            return;
+        }
+        for peer in &mut self.peers {
+            peer.outbox.extend((frame.len() as u32).to_le_bytes());
+            peer.outbox.extend(&frame);
+        }
+    }
+
+    /// Numbers a local edit and sends it on. Clients send their edits to
+    /// the host, once they've joined; ones from before are dropped.
+    fn share(&mut self, edit: NetEdit) {
+        if self.is_host() {
+            let seq = self.seq;
+            self.seq += 1;
+            self.send(&Message::Edit(seq, edit));
+        } else if self.joined {
+            self.send(&Message::Request(edit));
+        }
+    }
+}
+
+fn write_vec3(w: &mut impl Write, v: Vec3) -> io::Result<()> {
+    for c in v.to_array() {
+        w.write_all(&c.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_f32(r: &mut impl Read) -> io::Result<f32> {
+    read_u32(r).map(f32::from_bits)
+}
+
+fn read_vec3(r: &mut impl Read) -> io::Result<Vec3> {
+    Ok(Vec3::new(read_f32(r)?, read_f32(r)?, read_f32(r)?))
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut b = [0];
+    r.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut b = [0; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+fn pick<T: Copy>(all: &[T], i: u8) -> io::Result<T> {
+    all.get(i as usize).copied().ok_or(io::Error::new(ErrorKind::InvalidData, "bad enum in message"))
+}
+
+/// Tag byte, then the fields in order, little-endian.
+fn encode_edit(edit: &NetEdit, w: &mut impl Write) -> io::Result<()> {
+    match edit {
+        NetEdit::Brush(stroke) => {
+            let b = &stroke.brush;
+            w.write_all(&[0, b.shape as u8, b.falloff as u8, b.mode as u8, b.material.id()])?;
+            w.write_all(&b.radius.to_le_bytes())?;
+            write_vec3(w, stroke.center)?;
+            w.write_all(&stroke.step.to_le_bytes())?;
+            w.write_all(&stroke.strength.to_le_bytes())
+        }
+        NetEdit::Carve(carve) => {
+            w.write_all(&[1])?;
+            write_vec3(w, carve.center)?;
+            w.write_all(&carve.radius.to_le_bytes())?;
+            w.write_all(&carve.strength.to_le_bytes())
+        }
+        NetEdit::Ball { pos, velocity, kind } => {
+            w.write_all(&[2])?;
+            write_vec3(w, *pos)?;
+            write_vec3(w, *velocity)?;
+            let name = kind.name();
+            w.write_all(&(name.len() as u32).to_le_bytes())?;
+            w.write_all(name.as_bytes())
+        }
+    }
+}
+
+fn decode_edit(r: &mut impl Read) -> io::Result<NetEdit> {
+    Ok(match read_u8(r)? {
+        0 => {
+            let (shape, falloff, mode, material) = (read_u8(r)?, read_u8(r)?, read_u8(r)?, read_u8(r)?);
+            let brush = Brush {
+                shape: pick(&SHAPES, shape)?,
+                falloff: pick(&FALLOFFS, falloff)?,
+                mode: pick(&MODES, mode)?,
+                material: VoxelMaterial::from_id(material),
+                radius: read_f32(r)?,
+            };
+            NetEdit::Brush(BrushStroke { brush, center: read_vec3(r)?, step: read_f32(r)?, strength: read_f32(r)? })
+        }
+        1 => NetEdit::Carve(Carve {
+            center: read_vec3(r)?,
+            radius: read_f32(r)?,
+            strength: read_f32(r)?,
+            cause: CarveCause::Tool,
+        }),
+        2 => {
+            let (pos, velocity) = (read_vec3(r)?, read_vec3(r)?);
+            let len = read_u32(r)? as usize;
+            if len > MAX_KIND {
+                return Err(io::Error::new(ErrorKind::InvalidData, "kind name too long"));
+            }
+            let mut kind = vec![0; len];
+            r.read_exact(&mut kind)?;
+            let kind = String::from_utf8(kind).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+            NetEdit::Ball { pos, velocity, kind: kind.into() }
+        }
+        _ => return Err(io::Error::new(ErrorKind::InvalidData, "unknown edit")),
+    })
+}
+
+fn encode(message: &Message, w: &mut impl Write) -> io::Result<()> {
+    match message {
+        Message::World(seq, world) => {
+            w.write_all(&[0])?;
+            w.write_all(&seq.to_le_bytes())?;
+            w.write_all(world)
+        }
+        Message::Edit(seq, edit) => {
+            w.write_all(&[1])?;
+            w.write_all(&seq.to_le_bytes())?;
+            encode_edit(edit, w)
+        }
+        Message::Request(edit) => {
+            w.write_all(&[2])?;
+            encode_edit(edit, w)
+        }
+    }
+}
+
+fn decode(mut r: &[u8]) -> io::Result<Message> {
+    Ok(match read_u8(&mut r)? {
+        0 => Message::World(read_u64(&mut r)?, r.to_vec()),
+        1 => Message::Edit(read_u64(&mut r)?, decode_edit(&mut r)?),
+        2 => Message::Request(decode_edit(&mut r)?),
+        _ => return Err(io::Error::new(ErrorKind::InvalidData, "unknown message")),
+    })
+}
+
+impl Peer {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Peer { stream, inbox: vec![], outbox: vec![] })
+    }
+
+    /// Sends what it can and reads what's arrived, returning the complete
+    /// messages. An error means the connection is gone.
+    fn pump(&mut self) -> io::Result<Vec<Message>> {
+        while !self.outbox.is_empty() {
+            match self.stream.write(&self.outbox) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => drop(self.outbox.drain(..n)),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        let mut buf = [0; 64 * 1024];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.inbox.extend(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        let mut messages = vec![];
+        while let Some(len) = self.inbox.first_chunk::<4>().map(|b| u32::from_le_bytes(*b) as usize) {
+            if len > MAX_MESSAGE {
+                return Err(io::Error::new(ErrorKind::InvalidData, "message too long"));
+            }
+            if self.inbox.len() < 4 + len {
+                break;
+            }
+            messages.push(decode(&self.inbox[4..4 + len])?);
+            self.inbox.drain(..4 + len);
+        }
+        Ok(messages)
+    }
+}
+
+/// Opens the session: binds the host's port, or connects to the host and
+/// hands the world over to it.
+pub fn start_net(mut net: ResMut<Net>, mut gate: ResMut<EditGate>) {
+    let result = match net.role.clone() {
+        NetRole::Host(addr) => TcpListener::bind(&addr)
+            .and_then(|listener| listener.set_nonblocking(true).map(|()| listener))
+            .map(|listener| {
+                net.listener = Some(listener);
+                info!(target: "net", "hosting on {addr}");
+            }),
+        NetRole::Join(addr) => TcpStream::connect(&addr).and_then(Peer::new).map(|peer| {
+            net.peers.push(peer);
+            gate.remote = true;
+            info!(target: "net", "joining {addr}");
+        }),
+    };
+    if let Err(e) = result {
+        error!(target: "net", "network session failed to start: {e}");
+    }
+}
+
+/// Takes in new clients, sending each the world, then applies what the
+/// peers sent: on the host, clients' requests, which are numbered and
+/// passed on like its own edits; on a client, the host's edits, in order.
+pub fn sync_net(world: &mut World) {
+    let mut incoming = vec![];
+    world.resource_scope(|world, mut net: Mut<Net>| {
+        while let Some(Ok((stream, addr))) = net.listener.as_ref().map(TcpListener::accept) {
+            let mut snapshot = vec![];
+            let result = write_world(world.resource::<ChunkMap>(), &mut snapshot, None)
+                .and_then(|()| Peer::new(stream));
+            match result {
+                Ok(mut peer) => {
+                    let mut frame = vec![];
+                    if encode(&Message::World(net.seq, snapshot), &mut frame).is_ok() {
+                        peer.outbox.extend((frame.len() as u32).to_le_bytes());
+                        peer.outbox.extend(frame);
+                        net.peers.push(peer);
+                        info!(target: "net", "{addr} joined");
+                    }
+                }
+                Err(e) => warn!(target: "net", "{addr} couldn't join: {e}"),
+            }
+        }
+        net.peers.retain_mut(|peer| match peer.pump() {
+            Ok(messages) => {
+                incoming.extend(messages);
+                true
+            }
+            Err(e) => {
+                warn!(target: "net", "lost a peer: {e}");
+                false
+            }
+        });
+    });
+
+    for message in incoming {
+        let edit = match message {
+            Message::Request(edit) if world.resource::<Net>().is_host() => edit,
+            Message::Edit(seq, edit) if !world.resource::<Net>().is_host() => {
+                let mut net = world.resource_mut::<Net>();
+                if seq != net.seq {
+                    error!(target: "net", "edit {seq} arrived expecting {}; terrain may differ", net.seq);
+                }
+                net.seq = seq + 1;
+                edit
+            }
+            Message::World(seq, bytes) if !world.resource::<Net>().is_host() => {
+                let result = read_world(&mut bytes.as_slice()).and_then(|snapshot| {
+                    world.resource_scope(|world, mut chunks: Mut<ChunkMap>| {
+                        restore_world(&mut world.commands(), &mut chunks, snapshot)
+                    })
+                });
+                world.flush();
+                match result {
+                    Ok(()) => {
+                        let mut net = world.resource_mut::<Net>();
+                        (net.seq, net.joined) = (seq, true);
+                        info!(target: "net", "joined with the host's world");
+                    }
+                    Err(e) => error!(target: "net", "couldn't load the host's world: {e}"),
+                }
+                continue;
+            }
+            Message::Request(_) => continue,
+            // Only the host orders edits and hands out the world.
+            Message::Edit(..) | Message::World(..) => {
+                warn!(target: "net", "dropped a host-only message from a client");
+                continue;
+            }
+        };
+        world.resource_mut::<EditGate>().replaying = true;
+        match edit {
+            NetEdit::Brush(stroke) => world.trigger(stroke),
+            NetEdit::Carve(carve) => world.trigger(carve),
+            NetEdit::Ball { pos, velocity, kind } => {
+                world.trigger(BallSpawn { pos, kind, color: None, id: None, respawn: None, velocity, pose: None })
+            }
+        }
+        world.flush();
+        world.resource_mut::<EditGate>().replaying = false;
+    }
+}
+
+/// Whether a local edit goes out: everything on the host, including the
+/// clients' requests it applies, but on a client only its player's own
+/// edits, since ones replayed from the host were shared already.
+fn outgoing<'a>(net: Option<ResMut<'a, Net>>, gate: &EditGate) -> Option<ResMut<'a, Net>> {
+    net.filter(|net| net.is_host() || !gate.replaying)
+}
+
+pub fn share_stroke(trigger: Trigger<BrushStroke>, net: Option<ResMut<Net>>, gate: Res<EditGate>) {
+    if let Some(mut net) = outgoing(net, &gate) {
+        net.share(NetEdit::Brush(trigger.event().clone()));
+    }
+}
+
+/// Impact carves count only on the host, whose bodies are the real ones.
+pub fn share_carve(trigger: Trigger<Carve>, net: Option<ResMut<Net>>, gate: Res<EditGate>) {
+    let carve = *trigger.event();
+    if let Some(mut net) = outgoing(net, &gate).filter(|net| net.is_host() || carve.cause == CarveCause::Tool) {
+        net.share(NetEdit::Carve(carve));
+    }
+}
+
+/// Props spawned by a level load have ids and stay local, like the rest of
+/// the level.
+pub fn share_ball(trigger: Trigger<BallSpawn>, net: Option<ResMut<Net>>, gate: Res<EditGate>) {
+    let BallSpawn { pos, kind, id, velocity, .. } = trigger.event();
+    if let Some(mut net) = outgoing(net, &gate).filter(|_| id.is_none()) {
+        net.share(NetEdit::Ball { pos: *pos, velocity: *velocity, kind: kind.clone() });
+    }
+}
+
+/// Runs the session `Net` describes, when the app has one.
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, start_net.run_if(resource_exists::<Net>))
+            .add_systems(Update, sync_net
+                .before(crate::chunk::publish_dirty_regions)
+                .run_if(resource_exists::<Net>))
+            .add_observer(share_stroke)
+            .add_observer(share_carve)
+            .add_observer(share_ball);
+    }
+}
@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use crate::{camera::Cam, chunk::ChunkMesh, editor::{Editable, Selection}};
+
+/// Marks controllable actors (players, vehicles) for the outliner.
+#[derive(Component)]
+pub struct Agent;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Category {
+    Chunks,
+    Props,
+    Lights,
+    Agents,
+}
+
+const CATEGORIES: [(Category, &str); 4] = [
+    (Category::Chunks, "chunks"),
+    (Category::Props, "props"),
+    (Category::Lights, "lights"),
+    (Category::Agents, "agents"),
+];
+
+/// Chunks are usually too many to list, so they only get a group row.
+const MAX_ROWS: usize = 40;
+
+#[derive(Component)]
+pub struct Outliner;
+
+#[derive(Component, Clone, Copy)]
+pub enum RowAction {
+    Focus(Entity),
+    ToggleVisibility(Entity),
+    Delete(Entity),
+    ToggleGroup(Category),
+}
+
+#[derive(Resource)]
+pub struct OutlinerState {
+    pub open: bool,
+    refresh: Timer,
+}
+
+impl Default for OutlinerState {
+    fn default() -> Self {
+        OutlinerState {
+            open: false,
+            refresh: Timer::from_seconds(0.5, TimerMode::Repeating),
+        }
+    }
+}
+
+type Entry<'a> = (
+    Entity,
+    Option<&'a Name>,
+    Has<ChunkMesh>,
+    Has<Editable>,
+    Has<Agent>,
+    Has<PointLight>,
+    Has<SpotLight>,
+    Has<DirectionalLight>,
+);
+type EntryItem<'a> = (Entity, Option<&'a Name>, bool, bool, bool, bool, bool, bool);
+
+fn category(entry: &EntryItem) -> Option<Category> {
+    let (_, _, chunk, editable, agent, point, spot, dir) = *entry;
+    if chunk {
+        Some(Category::Chunks)
+    } else if agent {
+        Some(Category::Agents)
+    } else if point || spot || dir {
+        Some(Category::Lights)
+    } else if editable {
+        Some(Category::Props)
+    } else {
+        None
+    }
+}
+
+pub fn spawn_outliner(mut cmds: Commands) {
+    cmds.spawn((
+        Outliner,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(6.0)),
+            row_gap: Val::Px(2.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        Visibility::Hidden,
+    ));
+}
+
+fn button(parent: &mut ChildSpawnerCommands, label: impl Into<String>, action: RowAction) {
+    parent.spawn((
+        Button,
+        action,
+        Text::new(label),
+        TextFont { font_size: 13.0, ..default() },
+    ));
+}
+
+/// F3 toggles the outliner. While open it is rebuilt a couple of times a
+/// second so spawned and despawned entities show up.
+pub fn rebuild_outliner(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut state: ResMut<OutlinerState>,
+    mut panel: Query<(Entity, &mut Visibility), With<Outliner>>,
+    entries: Query<Entry>,
+) {
+    let Ok((root, mut vis)) = panel.single_mut() else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::F3) {
+        state.open = !state.open;
+        *vis = if state.open { Visibility::Visible } else { Visibility::Hidden };
+        state.refresh.reset();
+    } else if !state.open || !state.refresh.tick(time.delta()).just_finished() {
+        return;
+    }
+    if !state.open {
+        return;
+    }
+
+    cmds.entity(root).despawn_related::<Children>();
+    cmds.entity(root).with_children(|parent| {
+        let mut rows = 0;
+        for (cat, label) in CATEGORIES {
+            let items: Vec<_> = entries
+                .iter()
+                .filter(|e| category(e) == Some(cat))
+                .collect();
+            if items.is_empty() {
+                continue;
+            }
+            button(parent, format!("{label} ({})", items.len()), RowAction::ToggleGroup(cat));
+            if cat == Category::Chunks {
+                continue;
+            }
+
+            for (entity, name, ..) in items {
+                if rows >= MAX_ROWS {
+                    break;
+                }
+                rows += 1;
+                let name = name.map(|n| n.to_string()).unwrap_or_else(|| format!("{entity}"));
+                parent
+                    .spawn(Node { column_gap: Val::Px(6.0), ..default() })
+                    .with_children(|row| {
+                        button(row, format!("  {name}"), RowAction::Focus(entity));
+                        button(row, "[v]", RowAction::ToggleVisibility(entity));
+                        button(row, "[x]", RowAction::Delete(entity));
+                    });
+            }
+        }
+    });
+}
+
+fn toggle(vis: &mut Visibility) {
+    *vis = match *vis {
+        Visibility::Hidden => Visibility::Inherited,
+        _ => Visibility::Hidden,
+    };
+}
+
+pub fn outliner_actions(
+    mut cmds: Commands,
+    pressed: Query<(&Interaction, &RowAction), Changed<Interaction>>,
+    entries: Query<Entry>,
+    mut vis: Query<&mut Visibility, Without<Outliner>>,
+    transforms: Query<&GlobalTransform>,
+    mut cams: Query<&mut Cam>,
+    mut selection: ResMut<Selection>,
+) {
+    for (interaction, action) in &pressed {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *action {
+            RowAction::Focus(e) => {
+                selection.entity = Some(e);
+                if let Ok(t) = transforms.get(e) {
+                    for mut cam in &mut cams {
+                        cam.target = t.translation();
+                    }
+                }
+            }
+            RowAction::ToggleVisibility(e) => {
+                if let Ok(mut v) = vis.get_mut(e) {
+                    toggle(&mut v);
+                }
+            }
+            RowAction::Delete(e) => {
+                if selection.entity == Some(e) {
+                    selection.entity = None;
+                }
+                if let Ok(mut ec) = cmds.get_entity(e) {
+                    ec.despawn();
+                }
+            }
+            RowAction::ToggleGroup(cat) => {
+                for entry in &entries {
+                    if category(&entry) == Some(cat) {
+                        if let Ok(mut v) = vis.get_mut(entry.0) {
+                            toggle(&mut v);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use bevy::{
+    input::keyboard::{Key, KeyboardInput},
+    prelude::*,
+};
+use crate::logging::ConsoleCommand;
+
+/// Matches shown at once.
+const SHOWN: usize = 12;
+
+/// What running a command does.
+#[derive(Clone, Debug)]
+pub enum Action {
+    /// Presses these keys together for a frame, as if typed.
+    Keys(Vec<KeyCode>),
+    /// A line for the terminal console.
+    Console(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct PaletteCommand {
+    pub name: String,
+    pub action: Action,
+}
+
+/// Everything the Ctrl+P palette can run. Tools are key bindings, so most
+/// commands just press their keys; add more with `register`.
+#[derive(Resource)]
+pub struct CommandRegistry {
+    pub commands: Vec<PaletteCommand>,
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        use KeyCode::*;
+        let mut registry = CommandRegistry { commands: vec![] };
+        for (name, keys) in [
+            ("drop ball", &[KeyF][..]),
+            ("ball spawner", &[ControlLeft, KeyF]),
+            ("blast crater", &[KeyI]),
+            ("detached islands", &[ShiftLeft, KeyI]),
+            ("place light", &[KeyL]),
+            ("place marker", &[KeyJ]),
+            ("player", &[KeyP]),
+            ("vehicle", &[KeyV]),
+            ("camera mode", &[KeyC]),
+            ("gizmo translate", &[KeyG]),
+            ("gizmo rotate", &[KeyR]),
+            ("gizmo scale", &[KeyT]),
+            ("brush smaller", &[BracketLeft]),
+            ("brush bigger", &[BracketRight]),
+            ("brush shape", &[Backslash]),
+            ("brush falloff", &[Quote]),
+            ("brush mode", &[Semicolon]),
+            ("terrain reroll seed", &[KeyN]),
+            ("terrain next generator", &[KeyM]),
+            ("terrain tile", &[KeyK]),
+            ("terrain boundary", &[KeyB]),
+            ("terrain smooth normals", &[KeyO]),
+            ("metaballs", &[ShiftLeft, KeyM]),
+            ("marble run", &[F1]),
+            ("falling sand", &[ControlLeft, F1]),
+            ("retro mode", &[Backquote]),
+            ("erode", &[ShiftLeft, F10]),
+            ("petrify", &[F10]),
+            ("import model", &[F11]),
+            ("save world", &[F5]),
+            ("load world", &[F9]),
+            ("save level", &[ControlLeft, F5]),
+            ("load level", &[ControlLeft, F9]),
+            ("save scene", &[ShiftLeft, F5]),
+            ("load scene", &[ShiftLeft, F9]),
+            ("export mesh", &[F7]),
+            ("timelapse record", &[ShiftLeft, F7]),
+            ("timelapse play", &[ControlLeft, F7]),
+            ("game mode", &[F6]),
+            ("debug tuning panel", &[F2]),
+            ("debug outliner", &[F3]),
+            ("debug heatmap", &[F4]),
+            ("debug point cloud", &[ControlLeft, F4]),
+            ("debug slice plane", &[End]),
+            ("debug diagnostics", &[F8]),
+            ("debug session stats", &[ShiftLeft, F8]),
+            ("debug codec diff", &[ControlLeft, F8]),
+            ("debug cycle chunk colliders", &[AltLeft, F8]),
+            ("time pause", &[AltLeft, KeyP]),
+            ("time step", &[AltLeft, Period]),
+            ("time slower", &[AltLeft, Minus]),
+            ("time faster", &[AltLeft, Equal]),
+            ("level browser", &[F12]),
+            ("turntable capture", &[ShiftLeft, F12]),
+            ("tutorial", &[Tab]),
+        ] {
+            registry.register(name, Action::Keys(keys.to_vec()));
+        }
+        registry.register("log show filter", Action::Console("log".into()));
+        registry.register("log meshing debug", Action::Console("log info,meshing=debug".into()));
+        registry.register("log physics trace", Action::Console("log info,physics=trace".into()));
+        registry
+    }
+}
+
+impl CommandRegistry {
+    pub fn register(&mut self, name: &str, action: Action) {
+        self.commands.push(PaletteCommand { name: name.into(), action });
+    }
+
+    /// Indices of the commands matching `query`, best first.
+    pub fn search(&self, query: &str) -> Vec<usize> {
+        let mut hits: Vec<(i32, usize)> = self
+            .commands
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| Some((fuzzy_score(query, &c.name)?, i)))
+            .collect();
+        hits.sort_by_key(|&(score, i)| (-score, i));
+        hits.into_iter().map(|(_, i)| i).collect()
+    }
+}
+
+/// How well `query` matches `name` as a case-insensitive subsequence, or
+/// `None` if it doesn't. Runs of letters and letters starting words count
+/// extra; gaps and long names count against.
+pub fn fuzzy_score(query: &str, name: &str) -> Option<i32> {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut score, mut next, mut last) = (0, 0, None);
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (next..name.len()).find(|&j| name[j] == q)?;
+        score += 1;
+        if found > 0 && last == Some(found - 1) {
+            score += 5;
+        }
+        if found == 0 || !name[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - next).min(4) as i32;
+        last = Some(found);
+        next = found + 1;
+    }
+    Some(score * 8 - name.len() as i32)
+}
+
+/// `Ctrl+F` style, for showing a command's binding.
+pub fn chord_name(keys: &[KeyCode]) -> String {
+    let names: Vec<String> = keys
+        .iter()
+        .map(|k| match k {
+            KeyCode::ControlLeft | KeyCode::ControlRight => "Ctrl".into(),
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => "Shift".into(),
+            KeyCode::AltLeft | KeyCode::AltRight => "Alt".into(),
+            k => {
+                let name = format!("{k:?}");
+                name.strip_prefix("Key").or(name.strip_prefix("Digit")).unwrap_or(name.as_str()).to_string()
+            }
+        })
+        .collect();
+    names.join("+")
+}
+
+#[derive(Resource, Default)]
+pub struct Palette {
+    pub open: bool,
+    pub query: String,
+    pub selected: usize,
+    /// Keys pressed on a command's behalf, let go the next frame.
+    held: Vec<KeyCode>,
+}
+
+#[derive(Component)]
+pub struct PalettePanel;
+
+pub fn spawn_palette(mut cmds: Commands) {
+    cmds.spawn((
+        PalettePanel,
+        Text::new(""),
+        TextFont { font_size: 15.0, ..default() },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            left: Val::Percent(35.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+        Visibility::Hidden,
+    ));
+}
+
+/// Ctrl+P opens the palette. While it's open, typing filters the
+/// commands, up/down picks one, Enter runs it and Escape closes; no other
+/// system sees the keys. Runs in `PreUpdate`, after input is read, so the
+/// keys a command presses reach this frame's tools.
+pub fn palette_input(
+    mut cmds: Commands,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut typed: EventReader<KeyboardInput>,
+    mut palette: ResMut<Palette>,
+    registry: Res<CommandRegistry>,
+) {
+    for key in std::mem::take(&mut palette.held) {
+        keys.release(key);
+    }
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !palette.open {
+        typed.clear();
+        if ctrl && keys.just_pressed(KeyCode::KeyP) {
+            palette.open = true;
+            palette.query.clear();
+            palette.selected = 0;
+            keys.reset_all();
+        }
+        return;
+    }
+
+    for ev in typed.read() {
+        if !ev.state.is_pressed() || ctrl {
+            continue;
+        }
+        match &ev.logical_key {
+            Key::Character(s) => palette.query.push_str(s),
+            Key::Space => palette.query.push(' '),
+            Key::Backspace => {
+                palette.query.pop();
+            }
+            _ => continue,
+        }
+        palette.selected = 0;
+    }
+    let hits = registry.search(&palette.query);
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        palette.selected = (palette.selected + 1).min(hits.len().min(SHOWN).saturating_sub(1));
+    }
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        palette.selected = palette.selected.saturating_sub(1);
+    }
+    let run = keys.just_pressed(KeyCode::Enter);
+    if run || keys.just_pressed(KeyCode::Escape) || (ctrl && keys.just_pressed(KeyCode::KeyP)) {
+        palette.open = false;
+    }
+    keys.reset_all();
+
+    let Some(command) = hits.get(palette.selected).map(|&i| &registry.commands[i]).filter(|_| run) else {
+        return;
+    };
+    info!("palette: {}", command.name);
+    match &command.action {
+        Action::Keys(chord) => {
+            for &key in chord {
+                keys.press(key);
+            }
+            palette.held = chord.clone();
+        }
+        Action::Console(line) => cmds.trigger(ConsoleCommand(line.clone())),
+    }
+}
+
+pub fn update_palette(
+    palette: Res<Palette>,
+    registry: Res<CommandRegistry>,
+    mut panel: Query<(&mut Text, &mut Visibility), With<PalettePanel>>,
+) {
+    let Ok((mut text, mut vis)) = panel.single_mut() else {
+        return;
+    };
+    *vis = if palette.open { Visibility::Visible } else { Visibility::Hidden };
+    if !palette.open {
+        return;
+    }
+
+    let mut out = format!("> {}_\n", palette.query);
+    for (row, i) in registry.search(&palette.query).into_iter().take(SHOWN).enumerate() {
+        let command = &registry.commands[i];
+        let cursor = if row == palette.selected { ">" } else { " " };
+        let binding = match &command.action {
+            Action::Keys(chord) => chord_name(chord),
+            Action::Console(_) => "console".into(),
+        };
+        out.push_str(&format!("{cursor} {}  [{binding}]\n", command.name));
+    }
+    text.0 = out;
+}
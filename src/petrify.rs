@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use avian3d::prelude::*;
+use crate::{
+    chunk::ChunkMap,
+    materials::VoxelMaterial,
+    player::Player,
+    sdf::Sdf,
+    vehicle::{Vehicle, Wheel},
+    MarchySettings,
+};
+
+/// A body's collider as an SDF in its own space, plus where it sits.
+struct Shape {
+    sdf: Sdf,
+    /// Bounding sphere radius around the body's origin.
+    reach: f32,
+    translation: Vec3,
+    rotation: Quat,
+}
+
+impl Shape {
+    /// Spheres, boxes and capsules; other collider shapes aren't supported.
+    fn from_collider(collider: &Collider, transform: &GlobalTransform) -> Option<Self> {
+        let shape = collider.shape_scaled();
+        let (sdf, reach) = if let Some(ball) = shape.as_ball() {
+            (Sdf::sphere(Vec3::ZERO, ball.radius), ball.radius)
+        } else if let Some(cuboid) = shape.as_cuboid() {
+            let he = cuboid.half_extents;
+            let he = Vec3::new(he.x, he.y, he.z);
+            (Sdf::cuboid(Vec3::ZERO, he), he.length())
+        } else if let Some(capsule) = shape.as_capsule() {
+            let (a, b) = (capsule.segment.a, capsule.segment.b);
+            let (a, b) = (Vec3::new(a.x, a.y, a.z), Vec3::new(b.x, b.y, b.z));
+            let reach = a.length().max(b.length()) + capsule.radius;
+            (Sdf::capsule(a, b, capsule.radius), reach)
+        } else {
+            return None;
+        };
+        let (_, rotation, translation) = transform.to_scale_rotation_translation();
+        Some(Shape { sdf, reach, translation, rotation })
+    }
+
+    fn sample(&self, p: Vec3) -> f32 {
+        self.sdf.sample(self.rotation.inverse() * (p - self.translation))
+    }
+}
+
+/// Fills a body's shape into the voxel field. Cells it turns solid become
+/// stone. Returns how many cells changed.
+fn fill_shape(chunks: &mut ChunkMap, shape: &Shape, iso: f32) -> usize {
+    let min = chunks.voxel_at(shape.translation - Vec3::splat(shape.reach + 1.0));
+    let max = chunks.voxel_at(shape.translation + Vec3::splat(shape.reach + 1.0));
+    let mut changed = 0;
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let pos = IVec3::new(x, y, z);
+                let Some(val) = chunks.read(pos) else {
+                    continue;
+                };
+                let new = val.min(iso + shape.sample(chunks.voxel_center(pos)));
+                if new == val {
+                    continue;
+                }
+                chunks.write(pos, new);
+                if val > iso && new <= iso {
+                    chunks.write_material(pos, VoxelMaterial::Stone);
+                }
+                changed += 1;
+            }
+        }
+    }
+    changed
+}
+
+/// F10 petrifies the scene: every dynamic body (except the player and
+/// vehicles) is voxelized into the terrain where it lies, then despawned, so
+/// a physics pile becomes permanent, diggable ground.
+pub fn petrify_key(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    bodies: Query<
+        (Entity, &RigidBody, &Collider, &GlobalTransform),
+        (Without<Player>, Without<Vehicle>, Without<Wheel>),
+    >,
+    mut chunks: ResMut<ChunkMap>,
+    settings: Res<MarchySettings>,
+) {
+    if !keys.just_pressed(KeyCode::F10) || keys.pressed(KeyCode::ShiftLeft) {
+        return;
+    }
+    let (mut count, mut skipped) = (0, 0);
+    for (entity, body, collider, transform) in &bodies {
+        if *body != RigidBody::Dynamic {
+            continue;
+        }
+        let Some(shape) = Shape::from_collider(collider, transform) else {
+            skipped += 1;
+            continue;
+        };
+        fill_shape(&mut chunks, &shape, settings.iso_level);
+        cmds.entity(entity).despawn();
+        count += 1;
+    }
+    info!("petrified {count} bodies ({skipped} unsupported shapes left alone)");
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use avian3d::prelude::*;
+use crate::MarchySettings;
+
+/// avian3d solver knobs, so heavy stacked-ball scenes can be tuned at runtime.
+#[derive(Clone)]
+pub struct PhysicsSettings {
+    /// False pauses the simulation.
+    pub enabled: bool,
+    pub substeps: u32,
+    pub restitution_iterations: usize,
+    pub gravity: Vec3,
+    pub sleep_linear: f32,
+    pub sleep_angular: f32,
+    pub deactivation_time: f32,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        PhysicsSettings {
+            enabled: true,
+            substeps: 6,
+            restitution_iterations: 1,
+            gravity: Vec3::NEG_Y * 9.81,
+            sleep_linear: 0.15,
+            sleep_angular: 0.15,
+            deactivation_time: 1.0,
+        }
+    }
+}
+
+pub fn apply_physics_settings(
+    settings: Res<MarchySettings>,
+    mut substeps: ResMut<SubstepCount>,
+    mut solver: ResMut<SolverConfig>,
+    mut gravity: ResMut<Gravity>,
+    mut sleep: ResMut<SleepingThreshold>,
+    mut deactivation: ResMut<DeactivationTime>,
+    mut time: ResMut<Time<Physics>>,
+) {
+    let p = &settings.physics;
+    if p.enabled {
+        time.unpause();
+    } else {
+        time.pause();
+    }
+    substeps.0 = p.substeps;
+    solver.restitution_iterations = p.restitution_iterations;
+    gravity.0 = p.gravity;
+    sleep.linear = p.sleep_linear;
+    sleep.angular = p.sleep_angular;
+    deactivation.0 = p.deactivation_time;
+}
@@ -0,0 +1,162 @@
+use bevy::{
+    input::mouse::AccumulatedMouseMotion,
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use avian3d::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+use crate::{
+    bounds::Respawn,
+    camera::{Cam, CamMode},
+    chunk::ChunkMap,
+    outliner::Agent,
+    MarchySettings,
+};
+
+/// Capsule dimensions: radius and the length of the straight section.
+pub const RADIUS: f32 = 0.35;
+pub const LENGTH: f32 = 1.0;
+/// Eye height above the capsule's center.
+const EYE: f32 = 0.6;
+
+#[derive(Component)]
+pub struct Player {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub speed: f32,
+    pub jump: f32,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Player { yaw: 0.0, pitch: 0.0, speed: 5.0, jump: 5.0 }
+    }
+}
+
+/// Scans the voxel column at `(x, z)` from the top of the loaded world down
+/// and returns where a player standing on the first solid cell would have
+/// its center. Everything above that cell is open, so the spot is clear.
+pub fn find_spawn(chunks: &ChunkMap, x: f32, z: f32, iso: f32) -> Option<Vec3> {
+    let size = chunks.chunk_size as i32;
+    let (lo, hi) = chunks
+        .iter()
+        .fold((i32::MAX, i32::MIN), |(lo, hi), (c, _)| (lo.min(c.0.y), hi.max(c.0.y)));
+    if lo > hi {
+        return None;
+    }
+    let column = chunks.voxel_at(Vec3::new(x, 0.0, z));
+    (lo * size..(hi + 1) * size).rev().find_map(|y| {
+        let pos = IVec3::new(column.x, y, column.z);
+        let solid = chunks.read(pos)? <= iso;
+        solid.then(|| chunks.voxel_center(pos) + Vec3::Y * (0.5 + LENGTH / 2.0 + RADIUS + 0.1))
+    })
+}
+
+fn grab_cursor(window: &mut Window, grab: bool) {
+    window.cursor_options.grab_mode = if grab { CursorGrabMode::Locked } else { CursorGrabMode::None };
+    window.cursor_options.visible = !grab;
+}
+
+/// P drops a player onto the terrain under the camera's target and looks
+/// through its eyes; P again removes it and goes back to orbiting.
+pub fn toggle_player(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    players: Query<Entity, With<Player>>,
+    mut cams: Query<&mut Cam>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+) {
+    // Alt+P is the time control's pause.
+    if !keys.just_pressed(KeyCode::KeyP) || keys.pressed(KeyCode::AltLeft) {
+        return;
+    }
+    let Ok(mut cam) = cams.single_mut() else {
+        return;
+    };
+
+    if let Ok(player) = players.single() {
+        cmds.entity(player).despawn();
+        cam.mode = CamMode::Orbit;
+        if let Ok(mut window) = windows.single_mut() {
+            grab_cursor(&mut window, false);
+        }
+        return;
+    }
+
+    let Some(pos) = find_spawn(&chunks, cam.target.x, cam.target.z, settings.iso_level) else {
+        warn!("no ground under {} to spawn a player on", cam.target);
+        return;
+    };
+    cmds.spawn((
+        Name::new("player"),
+        Player { yaw: cam.yaw, ..default() },
+        Agent,
+        RigidBody::Dynamic,
+        Collider::capsule(RADIUS, LENGTH),
+        LockedAxes::ROTATION_LOCKED,
+        Friction::ZERO.with_combine_rule(CoefficientCombine::Min),
+        Restitution::ZERO.with_combine_rule(CoefficientCombine::Min),
+        Transform::from_translation(pos),
+        Respawn::At(pos),
+    ));
+    cam.mode = CamMode::FirstPerson;
+    if let Ok(mut window) = windows.single_mut() {
+        grab_cursor(&mut window, true);
+    }
+}
+
+/// Mouse looks, WASD walks relative to where the player faces, Space jumps
+/// when standing on something. The camera sits at the player's eyes.
+pub fn move_player(
+    keys: Res<ButtonInput<KeyCode>>,
+    motion: Res<AccumulatedMouseMotion>,
+    spatial: SpatialQuery,
+    mut players: Query<(Entity, &mut Player, &mut LinearVelocity, &Transform)>,
+    mut cams: Query<(&mut Transform, &mut Cam), Without<Player>>,
+) {
+    let Ok((entity, mut player, mut vel, t)) = players.single_mut() else {
+        return;
+    };
+    player.yaw -= motion.delta.x * 0.003;
+    player.pitch = (player.pitch - motion.delta.y * 0.003).clamp(-FRAC_PI_2 + 0.01, FRAC_PI_2 - 0.01);
+
+    let facing = Quat::from_rotation_y(player.yaw);
+    let mut dir = Vec3::ZERO;
+    for (key, d) in [
+        (KeyCode::KeyW, Vec3::NEG_Z),
+        (KeyCode::KeyS, Vec3::Z),
+        (KeyCode::KeyA, Vec3::NEG_X),
+        (KeyCode::KeyD, Vec3::X),
+    ] {
+        if keys.pressed(key) {
+            dir += facing * d;
+        }
+    }
+    let walk = dir.normalize_or_zero() * player.speed;
+    vel.x = walk.x;
+    vel.z = walk.z;
+
+    let grounded = spatial
+        .cast_ray(
+            t.translation,
+            Dir3::NEG_Y,
+            LENGTH / 2.0 + RADIUS + 0.1,
+            true,
+            &SpatialQueryFilter::from_excluded_entities([entity]),
+        )
+        .is_some();
+    if grounded && keys.just_pressed(KeyCode::Space) {
+        vel.y = player.jump;
+    }
+
+    if let Ok((mut cam_t, mut cam)) = cams.single_mut() {
+        cam_t.translation = t.translation + Vec3::Y * EYE;
+        cam_t.rotation = Quat::from_euler(EulerRot::YXZ, player.yaw, player.pitch, 0.0);
+        // Keep the orbit pivot just ahead, so leaving first person is seamless.
+        cam.yaw = player.yaw;
+        cam.pitch = player.pitch;
+        cam.target = cam_t.translation + cam.forward() * cam.r;
+    }
+}
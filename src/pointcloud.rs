@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+use crate::{chunk::ChunkMap, MarchySettings};
+
+/// Points drawn before the rest of a view is skipped.
+const MAX_POINTS: usize = 20_000;
+
+/// Which voxels the point cloud shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Region {
+    /// One plane of voxels across the world, at `PointCloud::slice`.
+    #[default]
+    SliceY,
+    SliceX,
+    SliceZ,
+    /// Every voxel, up to `MAX_POINTS`.
+    All,
+}
+
+impl Region {
+    pub const ALL: [Region; 4] = [Region::SliceY, Region::SliceX, Region::SliceZ, Region::All];
+
+    fn axis(self) -> Option<usize> {
+        match self {
+            Region::SliceX => Some(0),
+            Region::SliceY => Some(1),
+            Region::SliceZ => Some(2),
+            Region::All => None,
+        }
+    }
+}
+
+/// Debug view of the raw density samples as gizmo points: solid voxels
+/// red, air blue, brighter the nearer they are to the iso level.
+#[derive(Resource, Default)]
+pub struct PointCloud {
+    pub enabled: bool,
+    pub region: Region,
+    /// World voxel coordinate of the slice along its axis.
+    pub slice: i32,
+}
+
+/// Ctrl+F4 toggles the point cloud. While it's on, Home picks the next
+/// region and Page Up/Down move the slice.
+pub fn point_cloud_keys(keys: Res<ButtonInput<KeyCode>>, mut cloud: ResMut<PointCloud>, chunks: Res<ChunkMap>) {
+    if keys.pressed(KeyCode::ControlLeft) && keys.just_pressed(KeyCode::F4) {
+        cloud.enabled = !cloud.enabled;
+        if cloud.enabled && cloud.slice == 0 {
+            // Start in the middle of the world rather than on its edge.
+            if let Some((min, max)) = chunks.extent() {
+                cloud.slice = (min + max).y / 2;
+            }
+        }
+        info!("point cloud: {} ({:?} at {})", cloud.enabled, cloud.region, cloud.slice);
+    }
+    // With Shift they're the slice plane's.
+    if !cloud.enabled || keys.pressed(KeyCode::ShiftLeft) {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Home) {
+        let i = Region::ALL.iter().position(|&r| r == cloud.region).unwrap_or(0);
+        cloud.region = Region::ALL[(i + 1) % Region::ALL.len()];
+        info!("point cloud region: {:?}", cloud.region);
+    }
+    if keys.just_pressed(KeyCode::PageUp) {
+        cloud.slice += 1;
+    }
+    if keys.just_pressed(KeyCode::PageDown) {
+        cloud.slice -= 1;
+    }
+}
+
+pub fn draw_point_cloud(
+    cloud: Res<PointCloud>,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+    mut gizmos: Gizmos,
+) {
+    if !cloud.enabled {
+        return;
+    }
+    let Some((mut min, mut max)) = chunks.extent() else {
+        return;
+    };
+    if let Some(axis) = cloud.region.axis() {
+        min[axis] = cloud.slice;
+        max[axis] = cloud.slice + 1;
+    }
+
+    let iso = settings.iso_level;
+    let mut drawn = 0;
+    for z in min.z..max.z {
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let pos = IVec3::new(x, y, z);
+                let Some(val) = chunks.read(pos) else {
+                    continue;
+                };
+                if drawn == MAX_POINTS {
+                    return;
+                }
+                drawn += 1;
+                let hue = if val <= iso { 0.0 } else { 210.0 };
+                let near = 1.0 - ((val - iso).abs() / 4.0).min(1.0);
+                let color = Color::hsl(hue, 1.0, 0.2 + 0.5 * near);
+                gizmos.sphere(chunks.voxel_center(pos), 0.06, color).resolution(4);
+            }
+        }
+    }
+}
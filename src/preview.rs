@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use crate::{chunk::ChunkMap, edit::sphere_volume_change, MarchySettings};
+
+/// A spherical volume a destructive action is about to affect.
+#[derive(Clone, Copy, Debug)]
+pub struct PreviewShape {
+    pub center: Vec3,
+    pub radius: f32,
+    pub fill: bool,
+}
+
+/// Tools set `shape` each frame they want their area of effect shown;
+/// leaving it `None` hides the preview.
+#[derive(Resource, Default)]
+pub struct AreaPreview {
+    pub shape: Option<PreviewShape>,
+}
+
+#[derive(Component)]
+pub struct PreviewShell;
+
+#[derive(Component)]
+pub struct PreviewLabel;
+
+#[derive(Resource)]
+pub struct PreviewMaterials {
+    carve: Handle<StandardMaterial>,
+    fill: Handle<StandardMaterial>,
+}
+
+pub fn spawn_preview(
+    mut cmds: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let shell = |color: Color| StandardMaterial {
+        base_color: color,
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        cull_mode: None,
+        ..default()
+    };
+    let mats = PreviewMaterials {
+        carve: materials.add(shell(Color::srgba(1.0, 0.4, 0.1, 0.25))),
+        fill: materials.add(shell(Color::srgba(0.2, 1.0, 0.4, 0.25))),
+    };
+
+    cmds.spawn((
+        PreviewShell,
+        Mesh3d(meshes.add(Sphere::new(1.0).mesh().ico(3).unwrap())),
+        MeshMaterial3d(mats.carve.clone()),
+        Transform::default(),
+        Visibility::Hidden,
+    ));
+    cmds.spawn((
+        PreviewLabel,
+        Text::new(""),
+        TextFont { font_size: 14.0, ..default() },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        },
+    ));
+    cmds.insert_resource(mats);
+}
+
+pub fn update_preview(
+    preview: Res<AreaPreview>,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+    mats: Res<PreviewMaterials>,
+    mut shell: Query<(&mut Transform, &mut Visibility, &mut MeshMaterial3d<StandardMaterial>), With<PreviewShell>>,
+    mut label: Query<&mut Text, With<PreviewLabel>>,
+) {
+    let (Ok((mut t, mut vis, mut mat)), Ok(mut text)) = (shell.single_mut(), label.single_mut()) else {
+        return;
+    };
+    let Some(shape) = preview.shape else {
+        *vis = Visibility::Hidden;
+        text.0.clear();
+        return;
+    };
+
+    *vis = Visibility::Visible;
+    t.translation = shape.center;
+    t.scale = Vec3::splat(shape.radius);
+    mat.0 = if shape.fill { mats.fill.clone() } else { mats.carve.clone() };
+
+    let change = sphere_volume_change(&chunks, shape.center, shape.radius, settings.iso_level, shape.fill);
+    text.0 = if change >= 0 {
+        format!("+{change} voxels")
+    } else {
+        format!("{change} voxels")
+    };
+}
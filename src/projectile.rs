@@ -0,0 +1,335 @@
+use bevy::prelude::*;
+use avian3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, fs, path::Path};
+use crate::{
+    decal::{DecalKind, DecalSpawn},
+    edit::{Carve, CarveCause},
+    MarchySettings,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyType {
+    #[default]
+    Dynamic,
+    Static,
+}
+
+/// One entry of the projectile registry, usually read from
+/// `assets/projectiles.toml`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ProjectileKind {
+    pub name: String,
+    pub body: BodyType,
+    pub radius: f32,
+    pub restitution: f32,
+    pub density: f32,
+    /// sRGB; `None` uses `MarchySettings::ball_color`.
+    pub color: Option<[f32; 3]>,
+    /// Names of behaviors run when the projectile first touches something.
+    pub on_impact: Vec<String>,
+    /// Radius of the crater the `explode` behavior carves.
+    pub blast_radius: f32,
+    /// Seconds before the projectile despawns; `None` keeps it around.
+    pub lifetime: Option<f32>,
+}
+
+impl Default for ProjectileKind {
+    fn default() -> Self {
+        ProjectileKind {
+            name: "ball".into(),
+            body: BodyType::Dynamic,
+            radius: 0.5,
+            restitution: 0.8,
+            density: 1.0,
+            color: None,
+            on_impact: vec![],
+            blast_radius: 2.0,
+            lifetime: None,
+        }
+    }
+}
+
+/// Which `ProjectileKind` a ball is. The built-in kinds have variants of
+/// their own; `Named` covers ones added in `assets/projectiles.toml`.
+/// Saved and sent as the kind's name.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum BallKind {
+    #[default]
+    Ball,
+    /// A fixed prop the editor can move.
+    Static,
+    Bouncy,
+    /// Dense and slow to bounce, and cracks what it lands on.
+    Heavy,
+    /// Stops dead where it first touches.
+    Sticky,
+    /// Blows a crater where it lands.
+    Explosive,
+    Named(String),
+}
+
+impl BallKind {
+    pub const BUILT_IN: [BallKind; 6] = [
+        BallKind::Ball,
+        BallKind::Static,
+        BallKind::Bouncy,
+        BallKind::Heavy,
+        BallKind::Sticky,
+        BallKind::Explosive,
+    ];
+
+    pub fn name(&self) -> &str {
+        match self {
+            BallKind::Ball => "ball",
+            BallKind::Static => "static",
+            BallKind::Bouncy => "bouncy",
+            BallKind::Heavy => "heavy",
+            BallKind::Sticky => "sticky",
+            BallKind::Explosive => "explosive",
+            BallKind::Named(name) => name,
+        }
+    }
+
+    /// A built-in kind's settings before the config file has its say.
+    /// `Named` kinds get a plain ball's.
+    pub fn defaults(&self) -> ProjectileKind {
+        let base = ProjectileKind { name: self.name().into(), ..default() };
+        match self {
+            BallKind::Ball | BallKind::Named(_) => base,
+            BallKind::Static => ProjectileKind { body: BodyType::Static, color: Some([1.0, 0.9, 0.5]), ..base },
+            BallKind::Bouncy => ProjectileKind {
+                restitution: 1.0,
+                lifetime: Some(60.0),
+                color: Some([0.4, 1.0, 0.5]),
+                ..base
+            },
+            BallKind::Heavy => ProjectileKind {
+                radius: 0.7,
+                density: 10.0,
+                restitution: 0.1,
+                color: Some([0.3, 0.3, 0.35]),
+                on_impact: vec!["crack".into()],
+                ..base
+            },
+            BallKind::Sticky => ProjectileKind {
+                restitution: 0.0,
+                color: Some([0.8, 0.3, 1.0]),
+                on_impact: vec!["stick".into()],
+                ..base
+            },
+            BallKind::Explosive => ProjectileKind {
+                color: Some([1.0, 0.2, 0.1]),
+                on_impact: vec!["explode".into()],
+                ..base
+            },
+        }
+    }
+}
+
+impl From<&str> for BallKind {
+    fn from(name: &str) -> Self {
+        BallKind::BUILT_IN
+            .into_iter()
+            .find(|k| k.name() == name)
+            .unwrap_or_else(|| BallKind::Named(name.into()))
+    }
+}
+
+impl From<String> for BallKind {
+    fn from(name: String) -> Self {
+        name.as_str().into()
+    }
+}
+
+impl From<BallKind> for String {
+    fn from(kind: BallKind) -> Self {
+        match kind {
+            BallKind::Named(name) => name,
+            kind => kind.name().into(),
+        }
+    }
+}
+
+impl fmt::Display for BallKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What an impact behavior gets to work with.
+pub struct Impact<'a> {
+    pub entity: Entity,
+    pub pos: Vec3,
+    pub kind: &'a ProjectileKind,
+}
+
+pub type ImpactBehavior = fn(&mut Commands, &Impact);
+
+#[derive(Deserialize)]
+struct ProjectileFile {
+    kind: Vec<ProjectileKind>,
+}
+
+/// Projectile kinds by name, plus the named impact behaviors they can use.
+/// New kinds only need a config entry; new behaviors are registered with
+/// `register_behavior`.
+#[derive(Resource)]
+pub struct ProjectileKinds {
+    kinds: Vec<ProjectileKind>,
+    by_name: HashMap<String, usize>,
+    behaviors: HashMap<String, ImpactBehavior>,
+}
+
+impl Default for ProjectileKinds {
+    fn default() -> Self {
+        let mut kinds = ProjectileKinds {
+            kinds: vec![],
+            by_name: HashMap::new(),
+            behaviors: HashMap::new(),
+        };
+        for kind in BallKind::BUILT_IN {
+            kinds.add(kind.defaults());
+        }
+        kinds.register_behavior("stick", stick);
+        kinds.register_behavior("explode", explode);
+        kinds.register_behavior("crack", crack);
+        kinds
+    }
+}
+
+impl ProjectileKinds {
+    pub fn add(&mut self, kind: ProjectileKind) {
+        match self.by_name.get(&kind.name) {
+            Some(&i) => self.kinds[i] = kind,
+            None => {
+                self.by_name.insert(kind.name.clone(), self.kinds.len());
+                self.kinds.push(kind);
+            }
+        }
+    }
+
+    pub fn register_behavior(&mut self, name: &str, behavior: ImpactBehavior) {
+        self.behaviors.insert(name.to_string(), behavior);
+    }
+
+    pub fn id(&self, kind: &BallKind) -> Option<usize> {
+        self.by_name.get(kind.name()).copied()
+    }
+
+    pub fn get(&self, id: usize) -> &ProjectileKind {
+        &self.kinds[id]
+    }
+
+    pub fn behavior(&self, name: &str) -> Option<ImpactBehavior> {
+        self.behaviors.get(name).copied()
+    }
+
+    /// Adds (or overrides) kinds from a TOML file of `[[kind]]` tables.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<usize, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file: ProjectileFile = toml::from_str(&text).map_err(|e| e.to_string())?;
+        let count = file.kind.len();
+        for kind in file.kind {
+            self.add(kind);
+        }
+        Ok(count)
+    }
+}
+
+/// Tracks which projectile kind an entity is and whether it was touching
+/// anything last frame, so impacts fire once per contact.
+#[derive(Component)]
+pub struct Projectile {
+    pub kind: usize,
+    touching: bool,
+}
+
+impl Projectile {
+    pub fn new(kind: usize) -> Self {
+        Projectile { kind, touching: false }
+    }
+}
+
+pub fn load_projectiles(mut kinds: ResMut<ProjectileKinds>, settings: Res<MarchySettings>) {
+    let path = &settings.projectiles_path;
+    if !path.exists() {
+        return;
+    }
+    match kinds.load(path) {
+        Ok(n) => info!("loaded {n} projectile kinds from {}", path.display()),
+        Err(e) => error!("failed to load {}: {e}", path.display()),
+    }
+}
+
+pub fn projectile_impacts(
+    mut cmds: Commands,
+    kinds: Res<ProjectileKinds>,
+    settings: Res<MarchySettings>,
+    mut projectiles: Query<(
+        Entity,
+        &mut Projectile,
+        &CollidingEntities,
+        &Transform,
+        Option<&LinearVelocity>,
+    )>,
+) {
+    for (entity, mut projectile, colliding, t, velocity) in &mut projectiles {
+        let touching = !colliding.is_empty();
+        let started = touching && !projectile.touching;
+        projectile.touching = touching;
+        if !started {
+            continue;
+        }
+
+        let kind = kinds.get(projectile.kind);
+        // Fast enough hits dent the ground, harder the faster they go.
+        let speed = velocity.map_or(0.0, |v| v.length());
+        if speed > settings.carve_speed {
+            cmds.trigger(Carve {
+                center: t.translation,
+                radius: kind.radius * 2.0,
+                strength: speed - settings.carve_speed,
+                cause: CarveCause::Impact,
+            });
+        }
+        let impact = Impact { entity, pos: t.translation, kind };
+        for name in &kind.on_impact {
+            match kinds.behavior(name) {
+                Some(behavior) => behavior(&mut cmds, &impact),
+                None => warn!("unknown impact behavior {name:?} on {:?}", kind.name),
+            }
+        }
+    }
+}
+
+fn stick(cmds: &mut Commands, impact: &Impact) {
+    cmds.entity(impact.entity).insert(RigidBody::Static);
+}
+
+fn explode(cmds: &mut Commands, impact: &Impact) {
+    let (pos, radius) = (impact.pos, impact.kind.blast_radius);
+    cmds.entity(impact.entity).despawn();
+    // Lands on the floor of the crater, small enough not to poke out of its walls.
+    cmds.trigger(DecalSpawn {
+        pos: pos - Vec3::Y * radius,
+        normal: Vec3::Y,
+        radius: radius * 0.6,
+        kind: DecalKind::Scorch,
+        snap: false,
+    });
+    cmds.trigger(Carve { center: pos, radius, strength: f32::INFINITY, cause: CarveCause::Impact });
+}
+
+fn crack(cmds: &mut Commands, impact: &Impact) {
+    cmds.trigger(DecalSpawn {
+        pos: impact.pos,
+        normal: Vec3::Y,
+        radius: impact.kind.radius * 2.0,
+        kind: DecalKind::Crack,
+        snap: true,
+    });
+}
@@ -0,0 +1,153 @@
+use bevy::{pbr::DirectionalLightShadowMap, platform::time::Instant, prelude::*};
+use std::time::Duration;
+use crate::{
+    mesh::Simplify,
+    mesher::{DensityView, MeshOptions, Meshers},
+    terrain::{self, Generator, TerrainConfig},
+    MarchySettings,
+    VoxelGrid,
+};
+
+/// LOD distances at a bias of 1.
+const LOD_DISTANCES: [f32; 2] = [40.0, 80.0];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Quality {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Ultra,
+}
+
+/// What a `Quality` sets.
+#[derive(Clone, Copy, Debug)]
+pub struct QualityPreset {
+    /// Streaming load radius, in chunks.
+    pub view_distance: f32,
+    /// Sun shadow map resolution; `None` turns sun shadows off.
+    pub shadow_map: Option<usize>,
+    /// Scales the distances chunks drop detail at.
+    pub lod_bias: f32,
+    /// Most particles in one impact burst.
+    pub particles: usize,
+    pub collider_downsample: u32,
+    pub ao_radius: u32,
+    pub simplify: Option<Simplify>,
+}
+
+impl Quality {
+    pub const ALL: [Quality; 4] = [Quality::Low, Quality::Medium, Quality::High, Quality::Ultra];
+
+    pub fn named(name: &str) -> Option<Quality> {
+        Self::ALL.into_iter().find(|q| q.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Quality::Low => "low",
+            Quality::Medium => "medium",
+            Quality::High => "high",
+            Quality::Ultra => "ultra",
+        }
+    }
+
+    pub fn preset(self) -> QualityPreset {
+        match self {
+            Quality::Low => QualityPreset {
+                view_distance: 2.0,
+                shadow_map: None,
+                lod_bias: 0.5,
+                particles: 6,
+                collider_downsample: 1,
+                ao_radius: 0,
+                simplify: Some(Simplify::Error(2.0)),
+            },
+            Quality::Medium => QualityPreset {
+                view_distance: 3.0,
+                shadow_map: Some(2048),
+                lod_bias: 1.0,
+                particles: 16,
+                collider_downsample: 0,
+                ao_radius: 1,
+                simplify: None,
+            },
+            Quality::High => QualityPreset {
+                view_distance: 4.0,
+                shadow_map: Some(4096),
+                lod_bias: 1.5,
+                particles: 24,
+                collider_downsample: 0,
+                ao_radius: 1,
+                simplify: None,
+            },
+            Quality::Ultra => QualityPreset {
+                view_distance: 6.0,
+                shadow_map: Some(8192),
+                lod_bias: 2.5,
+                particles: 32,
+                collider_downsample: 0,
+                ao_radius: 2,
+                simplify: None,
+            },
+        }
+    }
+
+    /// Overwrites the settings the preset covers.
+    pub fn apply(self, settings: &mut MarchySettings) {
+        let p = self.preset();
+        settings.quality = self;
+        if let Some(streaming) = &mut settings.streaming {
+            let margin = streaming.unload_radius - streaming.load_radius;
+            streaming.load_radius = p.view_distance;
+            streaming.unload_radius = p.view_distance + margin;
+        }
+        settings.shadow_map = p.shadow_map;
+        settings.lod_distances = LOD_DISTANCES.iter().map(|d| d * p.lod_bias).collect();
+        settings.max_particles = p.particles;
+        settings.collider_downsample = p.collider_downsample;
+        settings.ao_radius = p.ao_radius;
+        settings.simplify = p.simplify;
+    }
+
+    /// Picks a quality from how long this machine takes to mesh a chunk of
+    /// noise terrain.
+    pub fn detect() -> Quality {
+        let mut grid = VoxelGrid::new(32);
+        let config = TerrainConfig { generator: Generator::named("noise").unwrap_or_default(), ..default() };
+        terrain::generate(&config, &mut grid, IVec3::ZERO, 5.0);
+        let Some(mesher) = Meshers::default().get("dual") else {
+            return Quality::default();
+        };
+        let view = DensityView { storage: &grid, outside: &|_| None, occupancy: None, materials: None };
+        let options = MeshOptions { iso: 5.0, boundary: default(), smooth_normals: true };
+        const RUNS: u32 = 3;
+        let start = Instant::now();
+        for _ in 0..RUNS {
+            mesher.mesh(&view, &options);
+        }
+        let per_run = start.elapsed() / RUNS;
+        let quality = match per_run {
+            t if t < Duration::from_millis(4) => Quality::Ultra,
+            t if t < Duration::from_millis(12) => Quality::High,
+            t if t < Duration::from_millis(40) => Quality::Medium,
+            _ => Quality::Low,
+        };
+        info!("meshed a benchmark chunk in {per_run:?}, picking {} quality", quality.name());
+        quality
+    }
+}
+
+/// Shadow settings can't live in `MarchySettings` alone, so follow it.
+pub fn apply_shadow_quality(
+    settings: Res<MarchySettings>,
+    mut shadow_map: ResMut<DirectionalLightShadowMap>,
+    mut suns: Query<&mut DirectionalLight>,
+) {
+    if let Some(size) = settings.shadow_map {
+        shadow_map.size = size;
+    }
+    for mut sun in &mut suns {
+        sun.shadows_enabled = settings.shadow_map.is_some();
+    }
+}
@@ -0,0 +1,93 @@
+use bevy::{prelude::*, render::mesh::VertexAttributeValues};
+use crate::{chunk::ChunkMesh, terrain::remesh_all, ChunkMap, MarchySettings};
+
+/// Seconds the old surface takes to fade out after a switch.
+const FADE: f32 = 0.8;
+
+/// Switching between smooth and blocky meshing of the same voxels. The old
+/// surface stays on as a fading ghost until the new one has had time to
+/// mesh, so the two can be compared as one becomes the other.
+#[derive(Resource)]
+pub struct Retro {
+    material: Handle<StandardMaterial>,
+    ghosts: Vec<Entity>,
+    fade: Timer,
+}
+
+impl FromWorld for Retro {
+    fn from_world(world: &mut World) -> Self {
+        let color = world.resource::<MarchySettings>().terrain_color;
+        let material = world.resource_mut::<Assets<StandardMaterial>>().add(StandardMaterial {
+            base_color: color,
+            alpha_mode: AlphaMode::Blend,
+            // Drawn over the new surface where the two coincide.
+            depth_bias: 10.0,
+            ..default()
+        });
+        Retro { material, ghosts: vec![], fade: Timer::from_seconds(FADE, TimerMode::Once) }
+    }
+}
+
+/// Backquote swaps between `blocky_mesher` and `smooth_mesher` ("retro
+/// mode") and remeshes everything.
+pub fn retro_key(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<MarchySettings>,
+    mut chunks: ResMut<ChunkMap>,
+    mut retro: ResMut<Retro>,
+    surfaces: Query<(&Mesh3d, &Transform), With<ChunkMesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if !keys.just_pressed(KeyCode::Backquote) {
+        return;
+    }
+    settings.mesher = if settings.mesher == settings.blocky_mesher {
+        settings.smooth_mesher.clone()
+    } else {
+        settings.blocky_mesher.clone()
+    };
+    info!("meshing with {}", settings.mesher);
+    remesh_all(&mut chunks);
+
+    for ghost in retro.ghosts.drain(..) {
+        cmds.entity(ghost).despawn();
+    }
+    for (mesh, transform) in &surfaces {
+        // The vertex alpha is the terrain shader's sway; the ghost needs it
+        // opaque so only the material fades.
+        if let Some(VertexAttributeValues::Float32x4(colors)) =
+            meshes.get_mut(&mesh.0).and_then(|m| m.attribute_mut(Mesh::ATTRIBUTE_COLOR))
+        {
+            colors.iter_mut().for_each(|c| c[3] = 1.0);
+        }
+        let ghost = cmds.spawn((
+            Name::new("retro ghost"),
+            mesh.clone(),
+            MeshMaterial3d(retro.material.clone()),
+            *transform,
+        )).id();
+        retro.ghosts.push(ghost);
+    }
+    retro.fade.reset();
+}
+
+pub fn crossfade(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut retro: ResMut<Retro>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if retro.ghosts.is_empty() {
+        return;
+    }
+    retro.fade.tick(time.delta());
+    if let Some(material) = materials.get_mut(&retro.material) {
+        material.base_color.set_alpha(retro.fade.fraction_remaining());
+    }
+    if retro.fade.finished() {
+        for ghost in retro.ghosts.drain(..) {
+            cmds.entity(ghost).despawn();
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use bevy::prelude::*;
+use rand::RngCore;
+use crate::terrain::splitmix;
+
+/// The one source of randomness for everything but terrain generation
+/// (which hashes its own seed), so a run started with the same
+/// `MarchySettings::rng_seed` places the same balls, bursts and edits and
+/// bug reports can be replayed. `rand::Rng` supplies the usual methods.
+#[derive(Resource, Clone, Debug)]
+pub struct SeededRng {
+    seed: u64,
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng { seed, state: seed }
+    }
+
+    /// What this started from, for reports.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+/// Splitmix rather than one of `rand`'s generators, so a seed means the
+/// same thing across `rand` versions.
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        splitmix(&mut self.state)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
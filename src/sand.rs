@@ -0,0 +1,271 @@
+use bevy::prelude::*;
+use rand::Rng;
+use std::collections::HashSet;
+use crate::{
+    chunk::{ChunkMap, RegionDirty},
+    materials::VoxelMaterial,
+    rng::SeededRng,
+    MarchySettings,
+};
+
+/// Seconds between steps.
+const TICK: f32 = 0.1;
+/// Cells looked at per step; the rest wait for the next one.
+const MAX_ACTIVE: usize = 50_000;
+
+/// What the automaton sees in a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Air,
+    Sand,
+    Water,
+    /// Anything else solid, which never moves.
+    Stone,
+}
+
+impl Cell {
+    pub fn of(val: f32, mat: VoxelMaterial, iso: f32) -> Self {
+        if val > iso {
+            return Cell::Air;
+        }
+        match mat {
+            VoxelMaterial::Sand => Cell::Sand,
+            VoxelMaterial::Water => Cell::Water,
+            _ => Cell::Stone,
+        }
+    }
+
+    pub fn moves(self) -> bool {
+        matches!(self, Cell::Sand | Cell::Water)
+    }
+
+    /// Everything falls into air, and sand sinks through water.
+    fn displaces(self, other: Cell) -> bool {
+        other == Cell::Air || (self == Cell::Sand && other == Cell::Water)
+    }
+}
+
+/// Falling sand over the voxel grid: sand and water cells fall, sand slides
+/// off slopes and water spreads sideways, a step at a time. Moves swap the
+/// two cells' density and material through `ChunkMap::write`, so the
+/// chunks they touch remesh like any other edit.
+#[derive(Resource)]
+pub struct SandSim {
+    pub enabled: bool,
+    timer: Timer,
+    /// Cells that may move next step: ones that just moved, and movable
+    /// cells next to them.
+    active: HashSet<IVec3>,
+    /// Edited boxes to look for movable cells in next step.
+    woken: Vec<(IVec3, IVec3)>,
+}
+
+impl Default for SandSim {
+    fn default() -> Self {
+        SandSim {
+            enabled: false,
+            timer: Timer::from_seconds(TICK, TimerMode::Repeating),
+            active: HashSet::new(),
+            woken: vec![],
+        }
+    }
+}
+
+/// Ctrl+F1 toggles the simulation. Turning it on wakes the whole map.
+pub fn sand_key(keys: Res<ButtonInput<KeyCode>>, mut sim: ResMut<SandSim>, chunks: Res<ChunkMap>) {
+    if !(keys.pressed(KeyCode::ControlLeft) && keys.just_pressed(KeyCode::F1)) {
+        return;
+    }
+    sim.enabled = !sim.enabled;
+    sim.active.clear();
+    sim.woken.clear();
+    if sim.enabled {
+        sim.woken.extend(chunks.extent());
+    }
+    info!("falling sand: {}", sim.enabled);
+}
+
+/// Painting sand or water, digging under it, or streaming in a chunk all
+/// give cells a reason to move.
+pub fn wake_sand(trigger: Trigger<RegionDirty>, mut sim: ResMut<SandSim>) {
+    if sim.enabled {
+        sim.woken.push((trigger.min - 1, trigger.max + 1));
+    }
+}
+
+fn cell(chunks: &ChunkMap, pos: IVec3, iso: f32) -> Option<Cell> {
+    Some(Cell::of(chunks.read(pos)?, chunks.read_material(pos)?, iso))
+}
+
+/// Steps the simulation every `TICK` while it's on.
+pub fn step_sand(
+    time: Res<Time>,
+    mut sim: ResMut<SandSim>,
+    mut chunks: ResMut<ChunkMap>,
+    settings: Res<MarchySettings>,
+    mut rng: ResMut<SeededRng>,
+) {
+    if sim.enabled && sim.timer.tick(time.delta()).just_finished() {
+        sim.step(&mut chunks, settings.iso_level, &mut *rng);
+    }
+}
+
+impl SandSim {
+    /// One step, double-buffered: every move is picked against the map as
+    /// it was at the start of the step, with each cell taking part in at
+    /// most one, and only then are they all applied.
+    fn step(&mut self, chunks: &mut ChunkMap, iso: f32, rng: &mut impl Rng) {
+        for (min, max) in std::mem::take(&mut self.woken) {
+            for z in min.z..max.z {
+                for y in min.y..max.y {
+                    for x in min.x..max.x {
+                        let p = IVec3::new(x, y, z);
+                        if cell(chunks, p, iso).is_some_and(Cell::moves) {
+                            self.active.insert(p);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Sorted so the same world and seed always step the same way.
+        let mut order: Vec<IVec3> = self.active.drain().collect();
+        order.sort_by_key(|p| (p.y, p.z, p.x));
+        let rest = order.split_off(order.len().min(MAX_ACTIVE));
+        let mut claimed = HashSet::new();
+        let mut swaps = vec![];
+        for p in order {
+            let Some(c) = cell(chunks, p, iso).filter(|c| c.moves() && !claimed.contains(&p)) else {
+                continue;
+            };
+            let mut sides = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+            for i in (1..sides.len()).rev() {
+                sides.swap(i, rng.random_range(0..=i));
+            }
+            let down = std::iter::once(IVec3::ZERO).chain(sides).map(|s| s + IVec3::NEG_Y);
+            let level = sides.into_iter().filter(|_| c == Cell::Water);
+            let target = down.chain(level).map(|d| p + d).find(|t| {
+                !claimed.contains(t) && cell(chunks, *t, iso).is_some_and(|o| c.displaces(o))
+            });
+            if let Some(t) = target {
+                claimed.insert(p);
+                claimed.insert(t);
+                swaps.push((p, t));
+            }
+        }
+
+        self.active.extend(rest);
+        for &(a, b) in &swaps {
+            let (Some(va), Some(vb)) = (chunks.read(a), chunks.read(b)) else {
+                continue;
+            };
+            let (ma, mb) = (chunks.read_material(a).unwrap_or_default(), chunks.read_material(b).unwrap_or_default());
+            chunks.write(a, vb);
+            chunks.write(b, va);
+            chunks.write_material(a, mb);
+            chunks.write_material(b, ma);
+        }
+        for (a, b) in swaps {
+            for p in [a, b] {
+                for z in -1..=1 {
+                    for y in -1..=1 {
+                        for x in -1..=1 {
+                            let n = p + IVec3::new(x, y, z);
+                            if cell(chunks, n, iso).is_some_and(Cell::moves) {
+                                self.active.insert(n);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk::ChunkCoord, VoxelGrid};
+
+    const ISO: f32 = 0.0;
+
+    /// One chunk of air over a floor two cells deep.
+    fn world() -> ChunkMap {
+        let mut grid = VoxelGrid::new(16);
+        grid.map(|_, y, _, _| if y < 2 { -1.0 } else { 1.0 });
+        let mut chunks = ChunkMap::new(16);
+        chunks.insert(ChunkCoord(IVec3::ZERO), grid);
+        chunks
+    }
+
+    fn put(chunks: &mut ChunkMap, pos: IVec3, mat: VoxelMaterial) {
+        chunks.write(pos, -1.0);
+        chunks.write_material(pos, mat);
+    }
+
+    fn run(chunks: &mut ChunkMap, steps: usize, seed: u64) {
+        let mut sim = SandSim { enabled: true, ..default() };
+        sim.woken.extend(chunks.extent());
+        let mut rng = SeededRng::new(seed);
+        for _ in 0..steps {
+            sim.step(chunks, ISO, &mut rng);
+        }
+    }
+
+    #[test]
+    fn sand_falls_to_the_floor() {
+        let mut chunks = world();
+        put(&mut chunks, IVec3::new(8, 9, 8), VoxelMaterial::Sand);
+        run(&mut chunks, 10, 1);
+        assert_eq!(cell(&chunks, IVec3::new(8, 9, 8), ISO), Some(Cell::Air));
+        assert_eq!(cell(&chunks, IVec3::new(8, 2, 8), ISO), Some(Cell::Sand));
+    }
+
+    #[test]
+    fn sand_sinks_through_water() {
+        let mut chunks = world();
+        // Water in a pit one cell wide, so it has nowhere to spread.
+        put(&mut chunks, IVec3::new(8, 1, 8), VoxelMaterial::Water);
+        put(&mut chunks, IVec3::new(8, 2, 8), VoxelMaterial::Sand);
+        run(&mut chunks, 1, 1);
+        assert_eq!(cell(&chunks, IVec3::new(8, 1, 8), ISO), Some(Cell::Sand));
+        assert_eq!(cell(&chunks, IVec3::new(8, 2, 8), ISO), Some(Cell::Water));
+    }
+
+    #[test]
+    fn water_spreads_sideways() {
+        let mut chunks = world();
+        put(&mut chunks, IVec3::new(8, 2, 8), VoxelMaterial::Water);
+        run(&mut chunks, 1, 1);
+        assert_eq!(cell(&chunks, IVec3::new(8, 2, 8), ISO), Some(Cell::Air));
+        let wet = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z]
+            .into_iter()
+            .filter(|&d| cell(&chunks, IVec3::new(8, 2, 8) + d, ISO) == Some(Cell::Water))
+            .count();
+        assert_eq!(wet, 1);
+    }
+
+    #[test]
+    fn same_seed_same_steps() {
+        let pile = |seed| {
+            let mut chunks = world();
+            for y in 4..10 {
+                put(&mut chunks, IVec3::new(8, y, 8), VoxelMaterial::Sand);
+                put(&mut chunks, IVec3::new(9, y, 8), VoxelMaterial::Water);
+            }
+            run(&mut chunks, 20, seed);
+            let mut cells = vec![];
+            for z in 0..16 {
+                for y in 0..16 {
+                    for x in 0..16 {
+                        cells.push(cell(&chunks, IVec3::new(x, y, z), ISO));
+                    }
+                }
+            }
+            cells
+        };
+        let cells = pile(3);
+        assert_eq!(cells, pile(3));
+        assert_eq!(cells.iter().filter(|&&c| c == Some(Cell::Sand)).count(), 6);
+    }
+}
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+use crate::{
+    chunk::{ChunkCoord, ChunkMap},
+    grid::read_u32,
+    MarchySettings,
+    VoxelGrid,
+};
+
+const MAGIC: &[u8; 4] = b"MWL2";
+/// Saves from before chunk generations were stored.
+const MAGIC_V1: &[u8; 4] = b"MWLD";
+
+pub struct SavedChunk {
+    pub coord: ChunkCoord,
+    pub generation: u32,
+    pub grid: VoxelGrid,
+    pub materials: Vec<u8>,
+}
+
+/// Every chunk of the world, voxels and materials, as read from disk.
+pub struct WorldSnapshot {
+    pub chunk_size: u32,
+    pub chunks: Vec<SavedChunk>,
+}
+
+/// With `lossy`, grids go through the lossy `codec` at that quality.
+pub fn save_world(chunks: &ChunkMap, path: impl AsRef<Path>, lossy: Option<u8>) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write_world(chunks, &mut w, lossy)?;
+    w.flush()
+}
+
+/// `MWL2`, chunk size, chunk count, then per chunk its coordinate, its
+/// generation, the compressed grid and one material byte per cell.
+pub fn write_world(chunks: &ChunkMap, w: &mut impl Write, lossy: Option<u8>) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&chunks.chunk_size.to_le_bytes())?;
+    w.write_all(&(chunks.iter().count() as u32).to_le_bytes())?;
+    for (coord, chunk) in chunks.iter() {
+        for c in coord.0.to_array() {
+            w.write_all(&c.to_le_bytes())?;
+        }
+        w.write_all(&chunk.generation.to_le_bytes())?;
+        match lossy {
+            Some(quality) => chunk.grid.write_lossy(w, quality)?,
+            None => chunk.grid.write_to(w, true)?,
+        }
+        w.write_all(&chunk.materials)?;
+    }
+    Ok(())
+}
+
+pub fn load_world(path: impl AsRef<Path>) -> io::Result<WorldSnapshot> {
+    read_world(&mut BufReader::new(File::open(path)?))
+}
+
+/// Reads what `write_world` wrote.
+pub fn read_world(r: &mut impl Read) -> io::Result<WorldSnapshot> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC && &magic != MAGIC_V1 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a world save"));
+    }
+    let chunk_size = read_u32(r)?;
+    let count = read_u32(r)?;
+
+    // Grown as chunks arrive rather than sized from a count that may be
+    // corrupt (or, from a network peer, hostile).
+    let mut chunks = vec![];
+    for _ in 0..count {
+        let mut c = [0; 3];
+        for v in &mut c {
+            *v = read_u32(r)? as i32;
+        }
+        let generation = if &magic == MAGIC { read_u32(r)? } else { 0 };
+        let grid = VoxelGrid::read_from(r)?;
+        if grid.dims() != UVec3::splat(chunk_size) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "chunk grid doesn't match the chunk size"));
+        }
+        let mut materials = vec![0; grid.data.len()];
+        r.read_exact(&mut materials)?;
+        chunks.push(SavedChunk {
+            coord: ChunkCoord(IVec3::from_array(c)),
+            generation,
+            grid,
+            materials,
+        });
+    }
+    Ok(WorldSnapshot { chunk_size, chunks })
+}
+
+/// Replaces the world with a snapshot, despawning chunks it doesn't contain.
+pub fn restore_world(
+    cmds: &mut Commands,
+    chunks: &mut ChunkMap,
+    snapshot: WorldSnapshot
+) -> io::Result<()> {
+    if snapshot.chunk_size != chunks.chunk_size {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "chunk size mismatch"));
+    }
+    let keep: Vec<_> = snapshot.chunks.iter().map(|c| c.coord).collect();
+    let stale: Vec<_> = chunks
+        .iter()
+        .map(|(c, _)| *c)
+        .filter(|c| !keep.contains(c))
+        .collect();
+    for coord in stale {
+        if let Some(entity) = chunks.remove(coord).and_then(|c| c.entity) {
+            cmds.entity(entity).despawn();
+        }
+    }
+
+    for saved in snapshot.chunks {
+        chunks.insert(saved.coord, saved.grid);
+        chunks.restore_generation(saved.coord, saved.generation);
+        if let Some(chunk) = chunks.chunk_mut(saved.coord) {
+            chunk.materials = saved.materials;
+        }
+    }
+    Ok(())
+}
+
+/// F5 snapshots the world to `save_path`, F9 restores it. With Ctrl held
+/// these save and load whole levels instead (see `level`), and with Shift
+/// whole scenes (see `scene`).
+pub fn save_load_keys(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut chunks: ResMut<ChunkMap>,
+    settings: Res<MarchySettings>,
+) {
+    if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ShiftLeft]) {
+        return;
+    }
+    let path = &settings.save_path;
+    if keys.just_pressed(KeyCode::F5) {
+        match save_world(&chunks, path, settings.lossy_saves) {
+            Ok(()) => info!("saved world to {}", path.display()),
+            Err(e) => error!("failed to save {}: {e}", path.display()),
+        }
+    }
+    if keys.just_pressed(KeyCode::F9) {
+        let result = load_world(path).and_then(|snap| restore_world(&mut cmds, &mut chunks, snap));
+        match result {
+            Ok(()) => info!("loaded world from {}", path.display()),
+            Err(e) => error!("failed to load {}: {e}", path.display()),
+        }
+    }
+}
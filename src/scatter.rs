@@ -0,0 +1,299 @@
+use bevy::prelude::*;
+use avian3d::prelude::*;
+use std::{collections::HashMap, f32::consts::TAU};
+use crate::{
+    chunk::{ChunkCoord, ChunkMap},
+    layers::Layer,
+    materials::VoxelMaterial,
+    mesh::{BevyMesh, MeshBuffers},
+    terrain::splitmix,
+    MarchySettings,
+};
+
+/// How a scattered prop takes part in physics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScatterCollider {
+    /// Just for looks; balls roll through it.
+    #[default]
+    None,
+    /// A sphere of its own.
+    Primitive,
+    /// Merged with the chunk's other `Compound` props into one collider.
+    Compound,
+}
+
+/// The mesh a prop is drawn with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PropShape {
+    #[default]
+    Sphere,
+    /// A cone standing on the surface.
+    Tuft,
+}
+
+#[derive(Clone, Debug)]
+pub struct ScatterProp {
+    pub name: String,
+    pub shape: PropShape,
+    pub min_radius: f32,
+    pub max_radius: f32,
+    pub color: Color,
+    /// How often this prop is picked relative to the others.
+    pub weight: f32,
+    pub collider: ScatterCollider,
+    /// What it grows on; empty for anything.
+    pub materials: Vec<VoxelMaterial>,
+    /// Steepest surface it sits on, in degrees from flat.
+    pub max_slope: f32,
+    /// World heights it sits between.
+    pub height: (f32, f32),
+}
+
+/// Small rocks, debris and grass strewn over the marched surface. Placement
+/// comes from the terrain seed and where each triangle is, so a world always
+/// gets the same props and an edit only moves the ones it touches.
+#[derive(Clone, Debug)]
+pub struct ScatterSettings {
+    /// Props per unit of surface area, by the material under it.
+    pub density: [f32; VoxelMaterial::ALL.len()],
+    pub props: Vec<ScatterProp>,
+}
+
+impl Default for ScatterSettings {
+    fn default() -> Self {
+        let prop = |name: &str, shape, radius: (f32, f32), color, weight, collider| ScatterProp {
+            name: name.into(),
+            shape,
+            min_radius: radius.0,
+            max_radius: radius.1,
+            color,
+            weight,
+            collider,
+            materials: vec![],
+            max_slope: 40.0,
+            height: (f32::NEG_INFINITY, f32::INFINITY),
+        };
+        let mut density = [0.0; VoxelMaterial::ALL.len()];
+        for (mat, d) in [
+            (VoxelMaterial::Dirt, 0.05),
+            (VoxelMaterial::Sand, 0.02),
+            (VoxelMaterial::Stone, 0.08),
+            (VoxelMaterial::Grass, 0.3),
+        ] {
+            density[mat.id() as usize] = d;
+        }
+        use {PropShape::*, ScatterCollider::{Compound, Primitive}};
+        ScatterSettings {
+            density,
+            props: vec![
+                prop("pebble", Sphere, (0.06, 0.15), Color::srgb(0.55, 0.52, 0.48), 3.0, ScatterCollider::None),
+                prop("rock", Sphere, (0.2, 0.4), Color::srgb(0.45, 0.43, 0.4), 1.0, Compound),
+                ScatterProp {
+                    max_slope: 20.0,
+                    ..prop("boulder", Sphere, (0.5, 0.8), Color::srgb(0.38, 0.36, 0.34), 0.2, Primitive)
+                },
+                ScatterProp {
+                    materials: vec![VoxelMaterial::Grass],
+                    max_slope: 30.0,
+                    ..prop("grass tuft", Tuft, (0.08, 0.14), Color::srgb(0.25, 0.55, 0.15), 6.0, ScatterCollider::None)
+                },
+            ],
+        }
+    }
+}
+
+/// The scatter meshes and materials, one each per prop, and per chunk the
+/// remesh its props were placed after and the entity holding them. Props
+/// sharing both are drawn instanced.
+#[derive(Resource)]
+pub struct Scatter {
+    meshes: Vec<Handle<Mesh>>,
+    materials: Vec<Handle<StandardMaterial>>,
+    placed: HashMap<ChunkCoord, (u32, Entity)>,
+}
+
+impl FromWorld for Scatter {
+    fn from_world(world: &mut World) -> Self {
+        let props: Vec<ScatterProp> = world
+            .resource::<MarchySettings>()
+            .scatter
+            .iter()
+            .flat_map(|s| s.props.clone())
+            .collect();
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let sphere = meshes.add(Sphere::new(1.0).mesh().ico(1).unwrap());
+        let tuft = meshes.add(Cone::new(1.0, 2.0).mesh().resolution(5));
+        let meshes = props
+            .iter()
+            .map(|p| match p.shape {
+                PropShape::Sphere => sphere.clone(),
+                PropShape::Tuft => tuft.clone(),
+            })
+            .collect();
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let materials = props
+            .into_iter()
+            .map(|p| materials.add(StandardMaterial { base_color: p.color, perceptual_roughness: 0.9, ..default() }))
+            .collect();
+        Scatter { meshes, materials, placed: HashMap::new() }
+    }
+}
+
+/// A prop placed in a chunk, in the chunk entity's space.
+struct Placement {
+    prop: usize,
+    transform: Transform,
+}
+
+/// Deterministic props over a chunk's mesh: each triangle gets its area
+/// times the density of the material under it, on average, at random
+/// points, each a prop that can sit on that material, slope and height.
+fn place(
+    coord: ChunkCoord,
+    at: &Transform,
+    surface: &MeshBuffers,
+    materials: &[u8],
+    size: u32,
+    seed: u64,
+    settings: &ScatterSettings,
+) -> Vec<Placement> {
+    let half = size as f32 / 2.0;
+    let mut out = vec![];
+    for tri in surface.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(surface.positions[tri[i] as usize]));
+        let cross = (b - a).cross(c - a);
+        let area = cross.length() / 2.0;
+        if area <= 0.0 {
+            continue;
+        }
+        let mut normal = cross / (area * 2.0);
+        if let Some(n) = surface.normals.get(tri[0] as usize) {
+            if normal.dot(Vec3::from(*n)) < 0.0 {
+                normal = -normal;
+            }
+        }
+        // The solid cell just under the middle of the triangle, with cell
+        // `x` spanning `x - size/2 - 1..x - size/2`.
+        let inside = (a + b + c) / 3.0 - normal * 0.5;
+        let last = IVec3::splat(size as i32 - 1);
+        let cell = (inside + half + 1.0).floor().as_ivec3().clamp(IVec3::ZERO, last).as_uvec3();
+        let mat = VoxelMaterial::from_id(materials[((cell.z * size + cell.y) * size + cell.x) as usize]);
+        let expected = area * settings.density[mat.id() as usize];
+        if expected <= 0.0 {
+            continue;
+        }
+
+        // Seeded by where the triangle is rather than its index, so props
+        // away from an edit stay put when the chunk remeshes.
+        let key = ((a + b + c) * 4.0 / 3.0).round().as_i64vec3() + coord.0.as_i64vec3() * size as i64 * 4;
+        let mut state = seed ^ (key.x as u64).wrapping_mul(0x9e37_79b9) ^ (key.y as u64).wrapping_mul(0x85eb_ca6b)
+            ^ (key.z as u64).wrapping_mul(0xc2b2_ae35);
+        let mut unit = || (splitmix(&mut state) >> 40) as f32 / (1u64 << 24) as f32;
+        let count = expected.floor() as u32 + (unit() < expected.fract()) as u32;
+        let slope = normal.y.clamp(-1.0, 1.0).acos().to_degrees();
+        let candidates: Vec<usize> = (0..settings.props.len())
+            .filter(|&i| {
+                let p = &settings.props[i];
+                (p.materials.is_empty() || p.materials.contains(&mat)) && slope <= p.max_slope
+            })
+            .collect();
+        let total: f32 = candidates.iter().map(|&i| settings.props[i].weight).sum();
+        if total <= 0.0 {
+            continue;
+        }
+        for _ in 0..count {
+            let (u, v, pick, scale, turn, squash) = (unit(), unit(), unit(), unit(), unit(), unit());
+            // Uniform over the triangle.
+            let (u, v) = if u + v > 1.0 { (1.0 - u, 1.0 - v) } else { (u, v) };
+            let pos = a + (b - a) * u + (c - a) * v;
+            let mut pick = pick * total;
+            let prop = candidates.iter().copied().find(|&i| {
+                pick -= settings.props[i].weight;
+                pick < 0.0
+            }).unwrap_or(candidates[candidates.len() - 1]);
+            let p = &settings.props[prop];
+            if !(p.height.0..=p.height.1).contains(&at.transform_point(pos).y) {
+                continue;
+            }
+            let radius = p.min_radius + (p.max_radius - p.min_radius) * scale;
+            let scale = Vec3::new(radius, radius * (0.5 + squash * 0.5), radius);
+            let rotation = Quat::from_rotation_arc(Vec3::Y, normal) * Quat::from_rotation_y(turn * TAU);
+            // Spheres half sunk into the surface, tufts standing on it.
+            let pos = match p.shape {
+                PropShape::Sphere => pos,
+                PropShape::Tuft => pos + normal * scale.y,
+            };
+            out.push(Placement {
+                prop,
+                transform: Transform::from_translation(pos).with_rotation(rotation).with_scale(scale),
+            });
+        }
+    }
+    out
+}
+
+/// Scatters props over each chunk once it has a mesh, and again whenever
+/// it's remeshed so they follow edits to the surface.
+pub fn scatter_props(
+    mut cmds: Commands,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+    mut scatter: ResMut<Scatter>,
+    chunk_meshes: Query<&Mesh3d>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    let Some(scatter_settings) = &settings.scatter else {
+        return;
+    };
+    scatter.placed.retain(|coord, _| chunks.get(*coord).is_some_and(|c| c.entity.is_some()));
+    for (coord, chunk) in chunks.iter() {
+        let (Some(chunk_entity), remeshes) = (chunk.entity, chunk.stats.remeshes) else {
+            continue;
+        };
+        if remeshes == 0 || scatter.placed.get(coord).is_some_and(|(r, _)| *r == remeshes) {
+            continue;
+        }
+        // Bare chunks have no mesh, and nothing to place on.
+        let surface = chunk_meshes.get(chunk_entity).ok().and_then(|m| meshes.get(&m.0));
+        if let Some((_, old)) = scatter.placed.remove(coord) {
+            cmds.entity(old).try_despawn();
+        }
+
+        let placements = surface.map_or(vec![], |surface| {
+            let surface = MeshBuffers::from_mesh(surface);
+            place(*coord, &chunks.chunk_transform(*coord), &surface, &chunk.materials, chunks.chunk_size, settings.terrain.seed, scatter_settings)
+        });
+        let terrain = settings.layers.layers(Layer::Terrain);
+        let group = cmds.spawn((
+            Name::new("scatter"),
+            Transform::default(),
+            Visibility::default(),
+            ChildOf(chunk_entity),
+        )).id();
+        let mut compound = vec![];
+        for Placement { prop, transform } in placements {
+            let mut entity = cmds.spawn((
+                Name::new(scatter_settings.props[prop].name.clone()),
+                Mesh3d(scatter.meshes[prop].clone()),
+                MeshMaterial3d(scatter.materials[prop].clone()),
+                transform,
+                ChildOf(group),
+            ));
+            match scatter_settings.props[prop].collider {
+                ScatterCollider::None => {}
+                // Scaled with the mesh by the transform.
+                ScatterCollider::Primitive => {
+                    entity.insert((Collider::sphere(1.0), terrain));
+                }
+                ScatterCollider::Compound => {
+                    compound.push((transform.translation, transform.rotation, Collider::sphere(transform.scale.x)));
+                }
+            }
+        }
+        if !compound.is_empty() {
+            // Part of the chunk's static body, like its terrain collider.
+            cmds.entity(group).insert((Collider::compound(compound), terrain));
+        }
+        scatter.placed.insert(*coord, (remeshes, group));
+    }
+}
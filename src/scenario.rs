@@ -0,0 +1,193 @@
+use bevy::{app::AppExit, prelude::*};
+use rand::Rng;
+use std::{fs, path::PathBuf, str::FromStr};
+use crate::{
+    ball::BallSpawn,
+    camera::{Cam, CamMode},
+    chunk::{ChunkMap, MeshTasks},
+    edit::apply_sphere,
+    projectile::BallKind,
+    rng::SeededRng,
+    MarchySettings,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScenarioKind {
+    /// A random dig or fill somewhere in the world every frame.
+    Edits,
+    /// Explosive balls raining down.
+    Explosions,
+    /// A fast cinematic orbit that keeps widening and narrowing.
+    FlyThrough,
+    /// All of the above at once.
+    All,
+}
+
+impl FromStr for ScenarioKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "edits" => Ok(ScenarioKind::Edits),
+            "explosions" => Ok(ScenarioKind::Explosions),
+            "flythrough" => Ok(ScenarioKind::FlyThrough),
+            "all" => Ok(ScenarioKind::All),
+            _ => Err(format!("unknown scenario {s:?} (edits, explosions, flythrough, all)")),
+        }
+    }
+}
+
+/// A scripted stress run. The app quits after `duration` seconds and writes
+/// a report to `report`.
+#[derive(Resource)]
+pub struct Scenario {
+    pub kind: ScenarioKind,
+    pub duration: f32,
+    pub report: PathBuf,
+    elapsed: f32,
+    frame_times: Vec<f32>,
+    peak_tasks: usize,
+}
+
+impl Scenario {
+    pub fn new(kind: ScenarioKind, duration: f32) -> Self {
+        Scenario {
+            kind,
+            duration,
+            report: PathBuf::from(format!("scenario-{kind:?}.txt").to_lowercase()),
+            elapsed: 0.0,
+            frame_times: vec![],
+            peak_tasks: 0,
+        }
+    }
+
+    /// `--scenario <kind> [--duration <secs>] [--report <path>]`. `None` when
+    /// no scenario was asked for.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut args = args.into_iter();
+        let (mut kind, mut duration, mut report) = (None, 60.0, None);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--scenario" => kind = Some(value()?.parse()?),
+                "--duration" => {
+                    duration = value()?.parse().map_err(|e| format!("bad --duration: {e}"))?
+                }
+                "--report" => report = Some(PathBuf::from(value()?)),
+                _ => {}
+            }
+        }
+        Ok(kind.map(|kind| {
+            let mut scenario = Scenario::new(kind, duration);
+            if let Some(report) = report {
+                scenario.report = report;
+            }
+            scenario
+        }))
+    }
+
+    fn runs(&self, kind: ScenarioKind) -> bool {
+        self.kind == kind || self.kind == ScenarioKind::All
+    }
+}
+
+/// Resident set size in bytes, where the OS makes it cheap to ask.
+fn resident_memory() -> Option<u64> {
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}
+
+/// Applies this frame's scripted load.
+pub fn drive_scenario(
+    mut cmds: Commands,
+    mut scenario: ResMut<Scenario>,
+    mut chunks: ResMut<ChunkMap>,
+    mut cams: Query<&mut Cam>,
+    time: Res<Time>,
+    settings: Res<MarchySettings>,
+    mut rng: ResMut<SeededRng>,
+) {
+    let dt = time.delta_secs();
+    scenario.elapsed += dt;
+    scenario.frame_times.push(dt);
+
+    // Random points inside the loaded chunks.
+    let coords: Vec<_> = chunks.iter().map(|(c, _)| *c).collect();
+    let size = chunks.chunk_size as f32;
+    let random_point = |chunks: &ChunkMap, rng: &mut SeededRng| {
+        let coord = coords[rng.random::<u32>() as usize % coords.len()];
+        let offset = Vec3::new(rng.random(), rng.random(), rng.random()) * size;
+        chunks.transform.to_world(coord.0.as_vec3() * size + offset)
+    };
+
+    if scenario.runs(ScenarioKind::Edits) && !coords.is_empty() {
+        let center = random_point(&chunks, &mut *rng);
+        let radius = 0.5 + rng.random::<f32>() * 2.0;
+        apply_sphere(&mut chunks, center, radius, settings.iso_level, rng.random());
+    }
+    if scenario.runs(ScenarioKind::Explosions) && !coords.is_empty() && rng.random::<f32>() < 0.2 {
+        cmds.trigger(BallSpawn {
+            pos: random_point(&chunks, &mut *rng) + Vec3::Y * size,
+            kind: BallKind::Explosive,
+            color: None,
+            id: None,
+            respawn: None,
+            velocity: Vec3::ZERO,
+            pose: None,
+        });
+    }
+    if scenario.runs(ScenarioKind::FlyThrough) {
+        for mut cam in &mut cams {
+            cam.mode = CamMode::Cinematic;
+            cam.r = size * (1.0 + (scenario.elapsed * 0.3).sin().abs() * 2.0);
+        }
+    }
+}
+
+/// Tracks peak load and, once the scenario's time is up, writes the report
+/// and quits.
+pub fn finish_scenario(
+    mut scenario: ResMut<Scenario>,
+    chunks: Res<ChunkMap>,
+    tasks: Res<MeshTasks>,
+    entities: Query<()>,
+    mut exit: EventWriter<AppExit>,
+) {
+    scenario.peak_tasks = scenario.peak_tasks.max(tasks.pending());
+    if scenario.elapsed < scenario.duration {
+        return;
+    }
+
+    let mut times = scenario.frame_times.clone();
+    times.sort_by(f32::total_cmp);
+    let frames = times.len().max(1);
+    let pct = |p: f32| times.get(((frames - 1) as f32 * p) as usize).copied().unwrap_or(0.0) * 1000.0;
+    let (triangles, voxel_bytes) = chunks
+        .iter()
+        .fold((0, 0), |(t, m), (_, c)| (t + c.stats.triangles, m + c.memory()));
+
+    let report = format!(
+        "scenario: {:?}\nduration: {:.1}s\nframes: {}\n\
+        frame ms: avg {:.2}  p50 {:.2}  p99 {:.2}  max {:.2}\n\
+        chunks: {}\ntriangles: {triangles}\nvoxel memory: {} KiB\n\
+        peak mesh tasks: {}\nentities: {}\nresident memory: {}\n",
+        scenario.kind,
+        scenario.elapsed,
+        times.len(),
+        scenario.elapsed / frames as f32 * 1000.0,
+        pct(0.5),
+        pct(0.99),
+        pct(1.0),
+        chunks.iter().count(),
+        voxel_bytes / 1024,
+        scenario.peak_tasks,
+        entities.iter().count(),
+        resident_memory().map_or("n/a".into(), |b| format!("{} MiB", b / (1024 * 1024))),
+    );
+    match fs::write(&scenario.report, &report) {
+        Ok(()) => info!("scenario report written to {}\n{report}", scenario.report.display()),
+        Err(e) => error!("failed to write {}: {e}\n{report}", scenario.report.display()),
+    }
+    exit.write(AppExit::Success);
+}
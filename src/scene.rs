@@ -0,0 +1,258 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use avian3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io, path::{Path, PathBuf}};
+use crate::{
+    ball::BallSpawn,
+    camera::{Cam, CamMode},
+    chunk::ChunkMap,
+    config::MarchyConfig,
+    editor::Editable,
+    ids::{StableId, StableIds},
+    level::{write_level, LevelContents, LevelMeta, LoadLevel},
+    projectile::{BallKind, Projectile, ProjectileKinds},
+    stats::SessionStats,
+    MarchySettings,
+};
+
+/// Bumped whenever the manifest changes incompatibly, like `LEVEL_VERSION`.
+pub const SCENE_VERSION: u32 = 1;
+const MANIFEST: &str = "scene.toml";
+
+/// A body in flight: a projectile that isn't a placed prop.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedBody {
+    pub id: StableId,
+    pub kind: BallKind,
+    pub pos: [f32; 3],
+    pub rotation: [f32; 4],
+    pub linear_velocity: [f32; 3],
+    pub angular_velocity: [f32; 3],
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedCamera {
+    pub mode: CamMode,
+    pub r: f32,
+    pub target: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub fly_speed: f32,
+}
+
+/// What the session has done so far, from `SessionStats`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneHistory {
+    pub seconds: f32,
+    pub tools: BTreeMap<String, u32>,
+}
+
+/// `scene.toml`: the rest of a session on top of the level saved beside it
+/// in the same directory, which holds the voxels and placed objects.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneManifest {
+    pub version: u32,
+    /// The startup config the session ran with; `--scene` boots with it.
+    pub config: MarchyConfig,
+    pub camera: Option<SavedCamera>,
+    #[serde(default)]
+    pub bodies: Vec<SavedBody>,
+    #[serde(default)]
+    pub history: SceneHistory,
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+pub fn read_scene(dir: &Path) -> io::Result<SceneManifest> {
+    let text = fs::read_to_string(dir.join(MANIFEST))?;
+    let manifest: SceneManifest = toml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+    if manifest.version > SCENE_VERSION {
+        return Err(invalid(format!(
+            "scene version {} is newer than supported {SCENE_VERSION}",
+            manifest.version
+        )));
+    }
+    Ok(manifest)
+}
+
+/// What a scene keeps of the running session besides its level.
+#[derive(SystemParam)]
+pub struct Session<'w, 's> {
+    config: Option<Res<'w, MarchyConfig>>,
+    kinds: Res<'w, ProjectileKinds>,
+    stats: Res<'w, SessionStats>,
+    time: Res<'w, Time>,
+    cams: Query<'w, 's, &'static Cam>,
+    bodies: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Projectile,
+            &'static Transform,
+            &'static LinearVelocity,
+            &'static AngularVelocity,
+            Option<&'static StableId>,
+        ),
+        Without<Editable>,
+    >,
+}
+
+/// Shift+F5 saves the whole session to `scene_path`: a level, plus every
+/// body in flight with its velocity, the camera, the startup config and the
+/// session stats. Bodies without a `StableId` are given one so a load can
+/// find them again.
+pub fn save_scene(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+    contents: LevelContents,
+    session: Session,
+    mut ids: ResMut<StableIds>,
+) {
+    if !(keys.pressed(KeyCode::ShiftLeft) && keys.just_pressed(KeyCode::F5)) {
+        return;
+    }
+    let dir = &settings.scene_path;
+    let bodies = session.bodies.iter().map(|(entity, p, t, lin, ang, id)| {
+        let id = id.copied().unwrap_or_else(|| {
+            let id = ids.allocate();
+            cmds.entity(entity).insert(id);
+            id
+        });
+        SavedBody {
+            id,
+            kind: session.kinds.get(p.kind).name.as_str().into(),
+            pos: t.translation.to_array(),
+            rotation: t.rotation.to_array(),
+            linear_velocity: lin.0.to_array(),
+            angular_velocity: ang.0.to_array(),
+        }
+    });
+    let manifest = SceneManifest {
+        version: SCENE_VERSION,
+        config: session.config.as_deref().cloned().unwrap_or_default(),
+        // The player isn't saved, so neither is its view.
+        camera: session.cams.single().ok().map(|cam| SavedCamera {
+            mode: if cam.mode == CamMode::FirstPerson { CamMode::Orbit } else { cam.mode },
+            r: cam.r,
+            target: cam.target.to_array(),
+            yaw: cam.yaw,
+            pitch: cam.pitch,
+            fly_speed: cam.fly_speed,
+        }),
+        bodies: bodies.collect(),
+        history: SceneHistory {
+            seconds: session.stats.carried_secs + session.time.elapsed_secs(),
+            tools: session.stats.tools.clone(),
+        },
+    };
+    let name = dir.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let result = write_level(dir, &contents.manifest(LevelMeta { name, ..default() }), &chunks, settings.lossy_saves)
+        .and_then(|()| toml::to_string_pretty(&manifest).map_err(|e| invalid(e.to_string())))
+        .and_then(|text| fs::write(dir.join(MANIFEST), text));
+    match result {
+        Ok(()) => info!("saved scene to {}", dir.display()),
+        Err(e) => error!("failed to save scene {}: {e}", dir.display()),
+    }
+}
+
+/// Replaces the session with the scene in `dir`. The config is kept as the
+/// app's `MarchyConfig` but only takes full effect when booting with
+/// `--scene`, since most of it is read at startup.
+#[derive(Event, Clone, Debug)]
+pub struct LoadScene {
+    pub dir: PathBuf,
+}
+
+/// Motion to give the bodies a scene load spawned, once they exist.
+#[derive(Resource, Default)]
+pub struct PendingBodies(Vec<SavedBody>);
+
+/// Shift+F9 loads the scene in `scene_path`.
+pub fn load_scene_key(mut cmds: Commands, keys: Res<ButtonInput<KeyCode>>, settings: Res<MarchySettings>) {
+    if keys.pressed(KeyCode::ShiftLeft) && keys.just_pressed(KeyCode::F9) {
+        cmds.trigger(LoadScene { dir: settings.scene_path.clone() });
+    }
+}
+
+/// Loads the scene `--scene` named once the app has set itself up.
+pub fn boot_scene(mut cmds: Commands, config: Option<Res<MarchyConfig>>) {
+    if let Some(dir) = config.and_then(|c| c.scene.clone()) {
+        cmds.trigger(LoadScene { dir });
+    }
+}
+
+pub fn load_scene(
+    trigger: Trigger<LoadScene>,
+    mut cmds: Commands,
+    mut settings: ResMut<MarchySettings>,
+    mut stats: ResMut<SessionStats>,
+    mut pending: ResMut<PendingBodies>,
+    mut cams: Query<&mut Cam>,
+    bodies: Query<Entity, (With<Projectile>, Without<Editable>)>,
+) {
+    let dir = trigger.event().dir.clone();
+    let manifest = match read_scene(&dir) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            error!("failed to load scene {}: {e}", dir.display());
+            return;
+        }
+    };
+    cmds.trigger(LoadLevel { dir: dir.clone() });
+
+    for entity in &bodies {
+        cmds.entity(entity).despawn();
+    }
+    for body in &manifest.bodies {
+        cmds.trigger(BallSpawn {
+            pos: Vec3::from(body.pos),
+            kind: body.kind.clone(),
+            color: None,
+            id: Some(body.id),
+            respawn: None,
+            // Up front, so in-flight balls keep their anti-tunneling sweep.
+            velocity: Vec3::from(body.linear_velocity),
+            pose: None,
+        });
+    }
+    pending.0 = manifest.bodies;
+    if let (Some(saved), Ok(mut cam)) = (manifest.camera, cams.single_mut()) {
+        *cam = Cam {
+            mode: saved.mode,
+            r: saved.r,
+            target: Vec3::from(saved.target),
+            yaw: saved.yaw,
+            pitch: saved.pitch,
+            fly_speed: saved.fly_speed,
+        };
+    }
+    stats.tools = manifest.history.tools;
+    stats.carried_secs = manifest.history.seconds;
+    cmds.insert_resource(manifest.config);
+    // Saving again goes back where it came from.
+    settings.scene_path = dir.clone();
+    info!("loaded scene from {}", dir.display());
+}
+
+/// Turns the loaded bodies back the way they were and sets them spinning
+/// again; their linear velocity went in with the spawn.
+pub fn restore_bodies(
+    mut pending: ResMut<PendingBodies>,
+    ids: Res<StableIds>,
+    mut bodies: Query<(&mut Transform, &mut AngularVelocity)>,
+) {
+    for body in std::mem::take(&mut pending.0) {
+        let Some(Ok((mut t, mut ang))) = ids.get(body.id).map(|e| bodies.get_mut(e)) else {
+            warn!("scene body {} wasn't spawned", body.id.0);
+            continue;
+        };
+        t.rotation = Quat::from_array(body.rotation);
+        ang.0 = Vec3::from(body.angular_velocity);
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use crate::{
+    chunk::{ChunkCoord, ChunkMap},
+    mesh::BevyMesh,
+    mesher::{DensityView, MeshOptions, Meshers},
+    MarchySettings,
+};
+
+/// Several iso levels of the same field drawn at once as translucent
+/// shells, for looking at a field's shape away from its surface (like the
+/// test shape's distance field). Shell `k` of `count` marches
+/// `iso_level + spacing * (k - (count - 1) / 2)`, so they're spread evenly
+/// through the surface, colored from red deep inside to blue far out. The
+/// terrain's own mesh is hidden while they show.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IsoShells {
+    pub count: u32,
+    /// Density units between neighboring shells.
+    pub spacing: f32,
+    pub opacity: f32,
+}
+
+impl Default for IsoShells {
+    fn default() -> Self {
+        IsoShells { count: 5, spacing: 1.0, opacity: 0.25 }
+    }
+}
+
+impl IsoShells {
+    pub fn levels(&self, iso: f32) -> impl Iterator<Item = f32> + use<'_> {
+        let mid = (self.count as f32 - 1.0) / 2.0;
+        (0..self.count).map(move |k| iso + self.spacing * (k as f32 - mid))
+    }
+
+    fn color(&self, k: u32) -> Color {
+        let t = if self.count > 1 { k as f32 / (self.count - 1) as f32 } else { 0.5 };
+        Color::hsla(240.0 * t, 0.9, 0.55, self.opacity)
+    }
+}
+
+#[derive(Component)]
+pub struct IsoShell;
+
+/// The shells built so far, and what with.
+#[derive(Resource, Default)]
+pub struct Shells {
+    built: Option<(IsoShells, f32, String)>,
+    materials: Vec<Handle<StandardMaterial>>,
+    /// By chunk: its remesh count when its shells were made, and them.
+    placed: HashMap<ChunkCoord, (u32, Vec<Entity>)>,
+}
+
+/// Marches each chunk's shells once it has a mesh, and again whenever it's
+/// remeshed. Changing the shells, the iso level or the mesher rebuilds
+/// them all.
+pub fn build_shells(
+    mut cmds: Commands,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+    meshers: Res<Meshers>,
+    mut shells: ResMut<Shells>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let wanted = settings.iso_shells.clone().map(|s| (s, settings.iso_level, settings.smooth_mesher.clone()));
+    if shells.built != wanted {
+        for (_, entities) in shells.placed.drain().map(|(_, p)| p) {
+            for entity in entities {
+                cmds.entity(entity).try_despawn();
+            }
+        }
+        shells.materials = wanted.iter().flat_map(|(s, ..)| (0..s.count).map(move |k| s.color(k))).map(|color| {
+            materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            })
+        }).collect();
+        shells.built = wanted;
+    }
+    let Some((config, iso, mesher)) = shells.built.clone() else {
+        return;
+    };
+    // The smooth mesher, since blocky shells would all look alike.
+    let Some(mesher) = meshers.get(&mesher) else {
+        return;
+    };
+
+    shells.placed.retain(|coord, _| chunks.get(*coord).is_some_and(|c| c.entity.is_some()));
+    for (coord, chunk) in chunks.iter() {
+        let (Some(chunk_entity), remeshes) = (chunk.entity, chunk.stats.remeshes) else {
+            continue;
+        };
+        if remeshes == 0 || shells.placed.get(coord).is_some_and(|(r, _)| *r == remeshes) {
+            continue;
+        }
+        for entity in shells.placed.remove(coord).into_iter().flat_map(|(_, e)| e) {
+            cmds.entity(entity).try_despawn();
+        }
+        let border = chunks.border(*coord, settings.boundary);
+        let outside = |p: IVec3| border.get(&p).copied();
+        let view = DensityView { storage: &chunk.grid, outside: &outside, occupancy: None, materials: None };
+        let mut entities = vec![];
+        for (k, level) in config.levels(iso).enumerate() {
+            let options = MeshOptions { iso: level, boundary: settings.boundary, smooth_normals: true };
+            let buffers = mesher.mesh(&view, &options);
+            if buffers.indices.is_empty() {
+                continue;
+            }
+            entities.push(cmds.spawn((
+                Name::new(format!("iso shell {level:.2}")),
+                IsoShell,
+                Mesh3d(meshes.add(buffers.into_mesh())),
+                MeshMaterial3d(shells.materials[k].clone()),
+                // Shown even though the chunk they hang off is hidden.
+                Visibility::Visible,
+                ChildOf(chunk_entity),
+            )).id());
+        }
+        shells.placed.insert(*coord, (remeshes, entities));
+    }
+}
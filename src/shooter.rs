@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use crate::{ball::BallSpawn, camera::Cam, editor::Selection, projectile::BallKind, MarchySettings};
+
+/// What the right mouse button fires.
+#[derive(Clone, Debug)]
+pub struct ShooterSettings {
+    /// The default, `Explosive`, carves a crater wherever it lands.
+    pub kind: BallKind,
+    /// Launch speed, in world units per second.
+    pub speed: f32,
+    /// Seconds between shots while the button is held.
+    pub cooldown: f32,
+}
+
+impl Default for ShooterSettings {
+    fn default() -> Self {
+        ShooterSettings { kind: BallKind::Explosive, speed: 40.0, cooldown: 0.2 }
+    }
+}
+
+/// Holding the right mouse button fires balls from the camera along its
+/// view, for trying out destruction end to end. Right drags belong to the
+/// editor gizmo while it has something selected.
+pub fn shoot(
+    mut cmds: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    time: Res<Time>,
+    settings: Res<MarchySettings>,
+    selection: Res<Selection>,
+    cams: Query<&GlobalTransform, With<Cam>>,
+    mut last: Local<Option<f32>>,
+) {
+    if !mouse.pressed(MouseButton::Right) || selection.entity.is_some() {
+        return;
+    }
+    let shooter = &settings.shooter;
+    let now = time.elapsed_secs();
+    if last.is_some_and(|t| now - t < shooter.cooldown) {
+        return;
+    }
+    let Ok(cam) = cams.single() else {
+        return;
+    };
+    *last = Some(now);
+    let dir = cam.forward();
+    cmds.trigger(BallSpawn {
+        // Clear of the near plane.
+        pos: cam.translation() + *dir,
+        kind: shooter.kind.clone(),
+        color: None,
+        id: None,
+        respawn: None,
+        velocity: *dir * shooter.speed,
+        pose: None,
+    });
+}
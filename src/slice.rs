@@ -0,0 +1,160 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    image::ImageSampler,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use std::f32::consts::FRAC_PI_2;
+use crate::{chunk::ChunkMap, MarchySettings};
+
+/// Density difference from the iso level at which the colors saturate.
+const RANGE: f32 = 4.0;
+
+/// An axis-aligned plane through the world showing the density field on it
+/// as a heatmap: solid red, air blue, fading to white at the iso level.
+#[derive(Resource, Default)]
+pub struct SlicePlane {
+    pub enabled: bool,
+    /// 0 x, 1 y, 2 z: the axis the plane is perpendicular to.
+    pub axis: usize,
+    /// World voxel coordinate of the plane along `axis`.
+    pub at: i32,
+    quad: Option<(Entity, Handle<Image>, Handle<Mesh>)>,
+    /// `ChunkMap::edits` as of the last redraw.
+    drawn: Option<u64>,
+}
+
+/// End toggles the slice plane. While it's on, Shift+Home picks the next
+/// axis and Shift+Page Up/Down move it.
+pub fn slice_keys(keys: Res<ButtonInput<KeyCode>>, mut slice: ResMut<SlicePlane>, chunks: Res<ChunkMap>) {
+    if keys.just_pressed(KeyCode::End) {
+        slice.enabled = !slice.enabled;
+        if slice.enabled {
+            if let Some((min, max)) = chunks.extent() {
+                let axis = slice.axis;
+                slice.at = slice.at.clamp(min[axis], max[axis] - 1);
+            }
+        }
+    }
+    if !slice.enabled || !keys.pressed(KeyCode::ShiftLeft) {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Home) {
+        slice.axis = (slice.axis + 1) % 3;
+    }
+    if keys.just_pressed(KeyCode::PageUp) {
+        slice.at += 1;
+    }
+    if keys.just_pressed(KeyCode::PageDown) {
+        slice.at -= 1;
+    }
+}
+
+/// Redraws the plane's texture whenever it moves or the voxels change.
+pub fn update_slice(
+    mut cmds: Commands,
+    mut slice: ResMut<SlicePlane>,
+    chunks: Res<ChunkMap>,
+    settings: Res<MarchySettings>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !slice.enabled {
+        if let Some((entity, ..)) = slice.quad.take() {
+            cmds.entity(entity).despawn();
+        }
+        return;
+    }
+    // Not `chunks.is_changed()`: remeshing touches the map without changing
+    // a voxel.
+    if !slice.is_changed() && slice.drawn == Some(chunks.edits()) {
+        return;
+    }
+    slice.drawn = Some(chunks.edits());
+    let Some((min, max)) = chunks.extent() else {
+        return;
+    };
+    let (axis, at) = (slice.axis, slice.at);
+    let dims = max - min;
+    let (w, h) = match axis {
+        0 => (dims.z, dims.y),
+        1 => (dims.x, dims.z),
+        _ => (dims.x, dims.y),
+    };
+    // Texel (c, r) from the top left, as the voxel it shows once the quad
+    // is turned to face along `axis`.
+    let voxel = |c: i32, r: i32| match axis {
+        0 => IVec3::new(at, max.y - 1 - r, max.z - 1 - c),
+        1 => IVec3::new(min.x + c, at, min.z + r),
+        _ => IVec3::new(min.x + c, max.y - 1 - r, at),
+    };
+
+    let iso = settings.iso_level;
+    let mut data = Vec::with_capacity((w * h * 4) as usize);
+    for r in 0..h {
+        for c in 0..w {
+            let color = match chunks.read(voxel(c, r)) {
+                Some(val) => {
+                    let t = ((val - iso) / RANGE).clamp(-1.0, 1.0);
+                    let hot = Srgba::rgb(0.9, 0.15, 0.1);
+                    let cold = Srgba::rgb(0.1, 0.3, 0.9);
+                    Srgba::WHITE.mix(if t < 0.0 { &hot } else { &cold }, t.abs())
+                }
+                None => Srgba::BLACK.with_alpha(0.3),
+            };
+            data.extend(color.to_u8_array());
+        }
+    }
+    let mut image = Image::new(
+        Extent3d { width: w as u32, height: h as u32, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    let mesh = Mesh::from(Rectangle::new(w as f32, h as f32));
+
+    // Laid out in voxel space, then moved into the world with the grid.
+    let mut center = (min + max).as_vec3() / 2.0;
+    center[axis] = at as f32 + 0.5;
+    let rotation = match axis {
+        0 => Quat::from_rotation_y(FRAC_PI_2),
+        1 => Quat::from_rotation_x(-FRAC_PI_2),
+        _ => Quat::IDENTITY,
+    };
+    let transform = Transform {
+        translation: chunks.transform.to_world(center),
+        rotation: chunks.transform.rotation * rotation,
+        scale: Vec3::splat(chunks.transform.cell_size),
+    };
+
+    if let Some((entity, image_handle, mesh_handle)) = &slice.quad {
+        if let Some(old) = images.get_mut(image_handle) {
+            *old = image;
+        }
+        if let Some(old) = meshes.get_mut(mesh_handle) {
+            *old = mesh;
+        }
+        cmds.entity(*entity).insert(transform);
+        return;
+    }
+    let image = images.add(image);
+    let mesh = meshes.add(mesh);
+    let material = materials.add(StandardMaterial {
+        base_color_texture: Some(image.clone()),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    });
+    let entity = cmds.spawn((
+        Name::new("slice plane"),
+        Mesh3d(mesh.clone()),
+        MeshMaterial3d(material),
+        transform,
+    )).id();
+    slice.quad = Some((entity, image, mesh));
+}
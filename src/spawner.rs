@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+use rand::Rng;
+use crate::{ball::BallSpawn, bounds::Respawn, projectile::{BallKind, Projectile}, rng::SeededRng, MarchySettings};
+
+/// A box balls are dropped from at random points.
+#[derive(Clone, Debug)]
+pub struct Emitter {
+    pub center: Vec3,
+    pub half_size: Vec3,
+}
+
+impl Emitter {
+    fn point(&self, rng: &mut impl Rng) -> Vec3 {
+        let r = Vec3::new(rng.random(), rng.random(), rng.random()) * 2.0 - 1.0;
+        self.center + r * self.half_size
+    }
+}
+
+/// Keeps balls raining onto the terrain, for stress-testing the chunk
+/// colliders over time.
+#[derive(Clone, Debug)]
+pub struct SpawnerSettings {
+    /// Balls per second, shared between the emitters.
+    pub rate: f32,
+    /// No more spawning while this many projectiles exist.
+    pub cap: usize,
+    pub emitters: Vec<Emitter>,
+    /// Projectile kinds spawned in turn.
+    pub kinds: Vec<BallKind>,
+}
+
+impl Default for SpawnerSettings {
+    fn default() -> Self {
+        SpawnerSettings {
+            rate: 5.0,
+            cap: 300,
+            emitters: vec![Emitter { center: Vec3::Y * 8.0, half_size: Vec3::new(4.0, 0.5, 4.0) }],
+            kinds: vec![BallKind::Ball, BallKind::Bouncy, BallKind::Heavy],
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Spawner {
+    pub enabled: bool,
+    /// Balls due but not yet spawned.
+    owed: f32,
+    next_kind: usize,
+}
+
+/// Ctrl+F starts and stops the spawner.
+pub fn spawner_key(keys: Res<ButtonInput<KeyCode>>, mut spawner: ResMut<Spawner>) {
+    if keys.pressed(KeyCode::ControlLeft) && keys.just_pressed(KeyCode::KeyF) {
+        spawner.enabled = !spawner.enabled;
+        spawner.owed = 0.0;
+        info!("ball spawner: {}", spawner.enabled);
+    }
+}
+
+/// Spawns balls at `rate` up to `cap`. Each one that leaves the world is
+/// put back where it was spawned instead of despawned, so the pool
+/// recycles.
+pub fn run_spawner(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut spawner: ResMut<Spawner>,
+    settings: Res<MarchySettings>,
+    projectiles: Query<(), With<Projectile>>,
+    mut rng: ResMut<SeededRng>,
+) {
+    let config = &settings.spawner;
+    if !spawner.enabled || config.emitters.is_empty() || config.kinds.is_empty() {
+        return;
+    }
+    spawner.owed += config.rate * time.delta_secs();
+    let mut count = projectiles.iter().count();
+    while spawner.owed >= 1.0 && count < config.cap {
+        spawner.owed -= 1.0;
+        count += 1;
+        let i = (rng.random::<f32>() * config.emitters.len() as f32) as usize;
+        let pos = config.emitters[i.min(config.emitters.len() - 1)].point(&mut *rng);
+        let kind = config.kinds[spawner.next_kind % config.kinds.len()].clone();
+        spawner.next_kind += 1;
+        cmds.trigger(BallSpawn { pos, kind, color: None, id: None, respawn: Some(Respawn::At(pos)), velocity: Vec3::ZERO, pose: None });
+    }
+    // At the cap, don't build up a burst for when room frees up.
+    spawner.owed = spawner.owed.min(1.0);
+}
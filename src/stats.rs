@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use crate::{
+    ball::BallSpawn,
+    chunk::ChunkMap,
+    edit::Carve,
+    level::LoadLevel,
+    voxelize::ModelEdit,
+};
+
+/// Keys counted as tool uses, named after what they do.
+const TOOL_KEYS: [(KeyCode, &str); 14] = [
+    (KeyCode::KeyL, "light"),
+    (KeyCode::KeyJ, "marker"),
+    (KeyCode::KeyP, "player"),
+    (KeyCode::KeyV, "vehicle"),
+    (KeyCode::KeyC, "camera mode"),
+    (KeyCode::KeyG, "gizmo translate"),
+    (KeyCode::KeyR, "gizmo rotate"),
+    (KeyCode::KeyT, "gizmo scale"),
+    (KeyCode::KeyN, "reroll"),
+    (KeyCode::KeyM, "generator"),
+    (KeyCode::F5, "save"),
+    (KeyCode::F7, "export"),
+    (KeyCode::F9, "load"),
+    (KeyCode::F10, "petrify"),
+];
+
+/// Counts of what was used this session. Kept in memory only: nothing is
+/// written or sent anywhere, it's just for seeing where time goes.
+#[derive(Resource, Default)]
+pub struct SessionStats {
+    pub tools: BTreeMap<String, u32>,
+    /// Seconds of a loaded scene's session, counted before this one's.
+    pub carried_secs: f32,
+    pub open: bool,
+}
+
+impl SessionStats {
+    pub fn count(&mut self, tool: &str) {
+        *self.tools.entry(tool.into()).or_default() += 1;
+    }
+}
+
+#[derive(Component)]
+pub struct StatsPanel;
+
+/// Session length, tool usage and remesh totals, shown with Shift+F8.
+pub struct SessionStatsPlugin;
+
+impl Plugin for SessionStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SessionStats>()
+            .add_systems(Startup, spawn_stats_panel)
+            .add_systems(Update, (count_inputs, update_stats_panel).chain())
+            .add_systems(Last, log_stats_on_exit)
+            .add_observer(|_: Trigger<Carve>, mut stats: ResMut<SessionStats>| stats.count("carve"))
+            .add_observer(|_: Trigger<BallSpawn>, mut stats: ResMut<SessionStats>| stats.count("ball"))
+            .add_observer(|_: Trigger<ModelEdit>, mut stats: ResMut<SessionStats>| stats.count("import"))
+            .add_observer(|_: Trigger<LoadLevel>, mut stats: ResMut<SessionStats>| stats.count("load level"));
+    }
+}
+
+fn spawn_stats_panel(mut cmds: Commands) {
+    cmds.spawn((
+        StatsPanel,
+        Text::new(""),
+        TextFont { font_size: 13.0, ..default() },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            right: Val::Px(8.0),
+            padding: UiRect::all(Val::Px(6.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        Visibility::Hidden,
+    ));
+}
+
+/// Counts tool keys, and digs and fills per click rather than per frame.
+pub fn count_inputs(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut stats: ResMut<SessionStats>,
+) {
+    for (key, tool) in TOOL_KEYS {
+        if keys.just_pressed(key) {
+            stats.count(tool);
+        }
+    }
+    if mouse.just_pressed(MouseButton::Left) && !keys.pressed(KeyCode::ShiftLeft) {
+        stats.count(if keys.pressed(KeyCode::ControlLeft) { "fill" } else { "dig" });
+    }
+    if keys.pressed(KeyCode::ShiftLeft) && keys.just_pressed(KeyCode::F8) {
+        stats.open = !stats.open;
+    }
+}
+
+fn report(stats: &SessionStats, chunks: &ChunkMap, time: &Time) -> String {
+    let secs = (stats.carried_secs + time.elapsed_secs()) as u32;
+    let remeshes: u32 = chunks.iter().map(|(_, c)| c.stats.remeshes).sum();
+    let triangles: usize = chunks.iter().map(|(_, c)| c.stats.triangles).sum();
+    let mut out = format!(
+        "session {}:{:02}:{:02}\nremeshes {remeshes}\ntriangles {triangles}\n",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+    );
+    let mut tools: Vec<_> = stats.tools.iter().collect();
+    tools.sort_by(|a, b| b.1.cmp(a.1));
+    for (tool, n) in tools {
+        out += &format!("{tool} {n}\n");
+    }
+    out
+}
+
+pub fn update_stats_panel(
+    stats: Res<SessionStats>,
+    chunks: Res<ChunkMap>,
+    time: Res<Time>,
+    mut panel: Query<(&mut Text, &mut Visibility), With<StatsPanel>>,
+) {
+    let Ok((mut text, mut vis)) = panel.single_mut() else {
+        return;
+    };
+    *vis = if stats.open { Visibility::Visible } else { Visibility::Hidden };
+    if stats.open {
+        text.0 = report(&stats, &chunks, &time);
+    }
+}
+
+/// Logs the session summary when the app exits.
+pub fn log_stats_on_exit(
+    mut exits: EventReader<AppExit>,
+    stats: Res<SessionStats>,
+    chunks: Res<ChunkMap>,
+    time: Res<Time>,
+) {
+    if exits.read().next().is_some() {
+        info!("session stats:\n{}", report(&stats, &chunks, &time));
+    }
+}
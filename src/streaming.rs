@@ -0,0 +1,158 @@
+use bevy::{platform::time::Instant, prelude::*};
+use avian3d::prelude::*;
+use crate::{
+    camera::Cam,
+    chunk::{ChunkCoord, ChunkMap, ChunkMesh},
+    materials,
+    player::Player,
+    terrain,
+    MarchySettings,
+    VoxelGrid,
+};
+
+/// Keeps the world loaded around the viewer, generating chunks from the
+/// terrain config as they come into range and dropping them past it.
+#[derive(Clone, Debug)]
+pub struct Streaming {
+    /// Chunks within this many chunk widths (in X/Z) are loaded.
+    pub load_radius: f32,
+    /// Chunks further than this are unloaded. Keep it above `load_radius`
+    /// so a viewer on the edge doesn't load and drop the same chunks.
+    pub unload_radius: f32,
+    /// Chunk layers loaded above and below the viewer's.
+    pub vertical: i32,
+    /// Chunks generated per frame, at most.
+    pub budget: usize,
+    /// Chunks rise into place as they first mesh and sink away as they
+    /// unload, instead of popping in and out at the edge.
+    pub fade: bool,
+}
+
+/// Seconds a chunk takes to rise or sink.
+const FADE_TIME: f32 = 0.8;
+
+/// A streamed chunk easing up to `rest`, or down from it and then away.
+#[derive(Component)]
+pub struct ChunkFade {
+    rest: Vec3,
+    depth: f32,
+    sinking: bool,
+    timer: Timer,
+}
+
+impl ChunkFade {
+    /// With the transform to start it from. `rest` is the chunk's
+    /// `ChunkMap::chunk_transform`; it sinks half a chunk.
+    pub fn rising(rest: Transform, chunk_size: u32) -> (Self, Transform) {
+        let depth = chunk_size as f32 / 2.0 * rest.scale.y;
+        let fade = ChunkFade {
+            rest: rest.translation,
+            depth,
+            sinking: false,
+            timer: Timer::from_seconds(FADE_TIME, TimerMode::Once),
+        };
+        (fade, Transform { translation: rest.translation - Vec3::Y * depth, ..rest })
+    }
+
+    pub fn sinking(rest: Transform, chunk_size: u32) -> Self {
+        let depth = chunk_size as f32 / 2.0 * rest.scale.y;
+        ChunkFade { rest: rest.translation, depth, sinking: true, timer: Timer::from_seconds(FADE_TIME, TimerMode::Once) }
+    }
+}
+
+impl Default for Streaming {
+    fn default() -> Self {
+        Streaming {
+            load_radius: 3.0,
+            unload_radius: 4.5,
+            vertical: 1,
+            budget: 2,
+            fade: true,
+        }
+    }
+}
+
+/// Loads the nearest missing chunks around the player (or the camera when
+/// there's no player), and unloads distant ones along with their entities.
+pub fn stream_chunks(
+    mut cmds: Commands,
+    players: Query<&GlobalTransform, With<Player>>,
+    cams: Query<&GlobalTransform, With<Cam>>,
+    mut chunks: ResMut<ChunkMap>,
+    settings: Res<MarchySettings>,
+) {
+    let Some(stream) = &settings.streaming else {
+        return;
+    };
+    let Some(eye) = players.iter().chain(&cams).next().map(|t| t.translation()) else {
+        return;
+    };
+    let size = chunks.chunk_size;
+    let (center, _) = chunks.locate(chunks.voxel_at(eye));
+    let dist = |c: ChunkCoord| (c.0 - center.0).xz().as_vec2().length();
+
+    let far: Vec<_> = chunks
+        .iter()
+        .map(|(c, _)| *c)
+        .filter(|c| {
+            dist(*c) > stream.unload_radius || (c.0.y - center.0.y).abs() > stream.vertical + 1
+        })
+        .collect();
+    for coord in far {
+        match chunks.remove(coord).and_then(|c| c.entity) {
+            // No longer part of the world, just on its way out of view.
+            Some(entity) if stream.fade => {
+                cmds.entity(entity)
+                    .remove::<(ChunkMesh, Collider)>()
+                    .insert(ChunkFade::sinking(chunks.chunk_transform(coord), size));
+            }
+            Some(entity) => cmds.entity(entity).despawn(),
+            None => {}
+        }
+        debug!(target: "streaming", "unloaded chunk {:?}", coord.0);
+    }
+
+    let r = stream.load_radius.ceil() as i32;
+    let mut missing = vec![];
+    for z in -r..=r {
+        for y in -stream.vertical..=stream.vertical {
+            for x in -r..=r {
+                let coord = ChunkCoord(center.0 + IVec3::new(x, y, z));
+                if dist(coord) <= stream.load_radius && chunks.get(coord).is_none() {
+                    missing.push(coord);
+                }
+            }
+        }
+    }
+    missing.sort_by(|a, b| dist(*a).total_cmp(&dist(*b)));
+    for coord in missing.into_iter().take(stream.budget) {
+        let start = Instant::now();
+        let mut grid = VoxelGrid::new(size);
+        terrain::generate(&settings.terrain, &mut grid, coord.0 * size as i32, settings.iso_level);
+        let layers = materials::layered(&grid, settings.iso_level);
+        chunks.filled.push(start.elapsed());
+        chunks.insert(coord, grid);
+        if let Some(chunk) = chunks.chunk_mut(coord) {
+            chunk.materials = layers;
+        }
+        debug!(target: "streaming", "loaded chunk {:?}", coord.0);
+    }
+}
+
+/// Moves fading chunks along, despawning the ones that have sunk away.
+pub fn fade_chunks(mut cmds: Commands, time: Res<Time>, mut fading: Query<(Entity, &mut Transform, &mut ChunkFade)>) {
+    for (entity, mut transform, mut fade) in &mut fading {
+        let t = fade.timer.tick(time.delta()).fraction();
+        let eased = t * t * (3.0 - 2.0 * t);
+        let down = if fade.sinking { eased } else { 1.0 - eased };
+        transform.translation = fade.rest - Vec3::Y * fade.depth * down;
+        if !fade.timer.finished() {
+            continue;
+        }
+        if fade.sinking {
+            cmds.entity(entity).despawn();
+        } else {
+            cmds.entity(entity).remove::<ChunkFade>();
+        }
+    }
+}
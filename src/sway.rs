@@ -0,0 +1,38 @@
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+};
+
+const SHADER: &str = "shaders/sway.wgsl";
+
+/// The terrain's material: standard PBR with `Sway` moving its vertices.
+pub type TerrainMaterial = ExtendedMaterial<StandardMaterial, Sway>;
+
+/// Slow waves pushing terrain vertices in and out along their normals, so
+/// soft materials breathe without any remeshing. How much a vertex moves
+/// is its material's `VoxelMaterial::sway`, carried in the vertex color's
+/// alpha. Shadows and prepasses see the still surface.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub struct Sway {
+    /// World units a fully soft vertex moves; zero turns the effect off.
+    #[uniform(100)]
+    pub amplitude: f32,
+    /// Waves per world unit.
+    #[uniform(100)]
+    pub frequency: f32,
+    #[uniform(100)]
+    pub speed: f32,
+}
+
+impl Default for Sway {
+    fn default() -> Self {
+        Sway { amplitude: 0.06, frequency: 0.2, speed: 0.8 }
+    }
+}
+
+impl MaterialExtension for Sway {
+    fn vertex_shader() -> ShaderRef {
+        SHADER.into()
+    }
+}
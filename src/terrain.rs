@@ -0,0 +1,146 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    image::{CompressedImageFormats, ImageSampler, ImageType},
+    platform::time::Instant,
+    prelude::*,
+};
+use std::{fs, path::Path};
+use crate::{chunk::{ChunkMap, MeshTasks}, materials, mesh::Boundary, MarchySettings};
+
+pub use marchy_core::generator::*;
+
+/// Regenerates every chunk from the current terrain config.
+pub fn regenerate_all(chunks: &mut ChunkMap, settings: &MarchySettings) {
+    let size = chunks.chunk_size as i32;
+    let coords: Vec<_> = chunks.iter().map(|(c, _)| *c).collect();
+    for coord in coords {
+        let start = Instant::now();
+        if let Some(chunk) = chunks.chunk_mut(coord) {
+            generate(&settings.terrain, &mut chunk.grid, coord.0 * size, settings.iso_level);
+            chunk.materials = materials::layered(&chunk.grid, settings.iso_level);
+        }
+        chunks.filled.push(start.elapsed());
+    }
+}
+
+/// Re-samples an animated generator every `animate_every` frames. Waits for
+/// the last round of meshes to land first, since re-dirtying a chunk cancels
+/// its meshing job and a slow mesher would otherwise never catch up.
+pub fn animate_terrain(
+    time: Res<Time>,
+    tasks: Res<MeshTasks>,
+    settings: Res<MarchySettings>,
+    mut chunks: ResMut<ChunkMap>,
+    mut frames: Local<u32>,
+) {
+    if !settings.terrain.generator.is_animated() {
+        return;
+    }
+    *frames += 1;
+    if *frames < settings.animate_every.max(1) || tasks.pending() > 0 {
+        return;
+    }
+    *frames = 0;
+    let config = TerrainConfig { time: time.elapsed_secs(), ..settings.terrain.clone() };
+    let size = chunks.chunk_size as i32;
+    let coords: Vec<_> = chunks.iter().map(|(c, _)| *c).collect();
+    for coord in coords {
+        let start = Instant::now();
+        if let Some(chunk) = chunks.chunk_mut(coord) {
+            generate(&config, &mut chunk.grid, coord.0 * size, settings.iso_level);
+            chunk.materials = materials::layered(&chunk.grid, settings.iso_level);
+        }
+        chunks.filled.push(start.elapsed());
+    }
+}
+
+/// N rerolls the world seed, M cycles the generator (Shift+M is
+/// `toggle_metaballs`), B cycles the mesher's boundary condition, O toggles
+/// smooth normals, K toggles tiling: terrain that repeats across the loaded
+/// map in X/Z, meshed periodically so exports tile seamlessly.
+pub fn reroll_terrain(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<MarchySettings>,
+    mut chunks: ResMut<ChunkMap>,
+) {
+    if keys.just_pressed(KeyCode::KeyN) {
+        settings.terrain.seed = settings.terrain.seed.wrapping_add(1);
+    } else if keys.just_pressed(KeyCode::KeyM) && !keys.pressed(KeyCode::ShiftLeft) {
+        settings.terrain.generator = match settings.terrain.generator {
+            Generator::Sphere { .. } => Generator::Noise {
+                fbm: Fbm::default(),
+                threshold: 0.1,
+            },
+            Generator::Noise { .. } => Generator::HeightmapCaves {
+                height: 2.0,
+                amplitude: 3.0,
+                fbm: Fbm::default(),
+                caves: Fbm { frequency: 0.15, octaves: 2, ..default() },
+                cave_threshold: 0.3,
+            },
+            Generator::HeightmapCaves { .. } => Generator::metaballs(),
+            Generator::Metaballs { .. } | Generator::Sdf(_) | Generator::Heightmap { .. } => Generator::default(),
+        };
+    } else if keys.just_pressed(KeyCode::KeyK) {
+        let Some((min, max)) = chunks.extent() else {
+            return;
+        };
+        if settings.terrain.tile.is_some() {
+            settings.terrain.tile = None;
+            settings.boundary = Boundary::Open;
+        } else {
+            let span = (max - min).as_uvec3();
+            settings.terrain.tile = Some(UVec2::new(span.x, span.z));
+            settings.boundary = Boundary::Periodic;
+        }
+        info!("tile: {:?}", settings.terrain.tile);
+    } else if keys.just_pressed(KeyCode::KeyB) {
+        settings.boundary = match settings.boundary {
+            Boundary::Open => Boundary::Solid,
+            Boundary::Solid => Boundary::Periodic,
+            Boundary::Periodic => Boundary::Open,
+        };
+        info!("boundary: {:?}", settings.boundary);
+        remesh_all(&mut chunks);
+        return;
+    } else if keys.just_pressed(KeyCode::KeyO) {
+        settings.smooth_normals = !settings.smooth_normals;
+        info!("smooth normals: {}", settings.smooth_normals);
+        remesh_all(&mut chunks);
+        return;
+    } else {
+        return;
+    }
+    regenerate_all(&mut chunks, &settings);
+}
+
+/// Remeshes everything without touching the voxels.
+pub fn remesh_all(chunks: &mut ChunkMap) {
+    let coords: Vec<_> = chunks.iter().map(|(c, _)| *c).collect();
+    for coord in coords {
+        chunks.mark_dirty(coord);
+    }
+}
+
+/// Reads a grayscale image (anything Bevy decodes, 16-bit PNGs included)
+/// into a `Heightmap`, black low and white high, a sample per pixel.
+pub fn load_heightmap(path: &Path) -> Result<Heightmap, String> {
+    let bytes = fs::read(path).map_err(|e| format!("can't read heightmap {}: {e}", path.display()))?;
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    let image = Image::from_buffer(
+        &bytes,
+        ImageType::Extension(ext),
+        CompressedImageFormats::NONE,
+        false,
+        ImageSampler::Default,
+        RenderAssetUsages::MAIN_WORLD,
+    )
+    .map_err(|e| format!("bad heightmap {}: {e}", path.display()))?;
+    let (width, depth) = (image.width(), image.height());
+    let samples = (0..depth)
+        .flat_map(|z| (0..width).map(move |x| (x, z)))
+        .map(|(x, z)| image.get_color_at(x, z).map(|c| c.to_linear().red))
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|e| format!("bad heightmap {}: {e}", path.display()))?;
+    Ok(Heightmap { width, depth, samples })
+}
@@ -0,0 +1,90 @@
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+
+/// Speeds `TimeControl` steps through.
+const SCALES: [f32; 7] = [0.1, 0.25, 0.5, 1.0, 1.25, 1.5, 2.0];
+
+/// Freezes, single-steps and slows game time, for watching bodies meet the
+/// terrain up close. It drives `Time<Virtual>`, so physics (which ticks on
+/// the fixed clock it feeds) and everything animating off `Res<Time>`
+/// follow along; the camera runs on real time and keeps moving.
+#[derive(Resource, Debug)]
+pub struct TimeControl {
+    pub paused: bool,
+    /// Relative speed, from 0.1 to 2.
+    pub scale: f32,
+    /// Physics ticks stepped since the last pause.
+    pub ticks: u32,
+    /// Running one tick's worth of time this frame.
+    stepping: bool,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        TimeControl { paused: false, scale: 1.0, ticks: 0, stepping: false }
+    }
+}
+
+impl TimeControl {
+    /// For the diagnostics overlay.
+    pub fn describe(&self) -> String {
+        if self.paused {
+            format!("paused, {} ticks stepped", self.ticks)
+        } else {
+            format!("{:.2}x", self.scale)
+        }
+    }
+}
+
+/// Alt+P pauses or resumes, Alt+Period steps one physics tick while paused,
+/// and Alt+Minus and Alt+Equal slow down and speed up.
+pub fn time_control_keys(keys: Res<ButtonInput<KeyCode>>, mut control: ResMut<TimeControl>) {
+    if !keys.pressed(KeyCode::AltLeft) {
+        return;
+    }
+    if keys.just_pressed(KeyCode::KeyP) {
+        control.paused = !control.paused;
+        control.ticks = 0;
+        info!(target: "physics", "time: {}", control.describe());
+    }
+    if keys.just_pressed(KeyCode::Period) && control.paused {
+        control.stepping = true;
+        control.ticks += 1;
+    }
+    let at = SCALES.iter().position(|&s| s >= control.scale).unwrap_or(SCALES.len() - 1);
+    let scale = if keys.just_pressed(KeyCode::Minus) {
+        SCALES[at.saturating_sub(1)]
+    } else if keys.just_pressed(KeyCode::Equal) {
+        SCALES[(at + 1).min(SCALES.len() - 1)]
+    } else {
+        return;
+    };
+    control.scale = scale;
+    info!(target: "physics", "time: {}", control.describe());
+}
+
+/// A step runs the virtual clock for exactly one fixed timestep, so the
+/// physics schedule ticks once (the leftover from before the pause is
+/// always less than a tick), then pauses it again the frame after.
+pub fn apply_time_control(
+    mut cmds: Commands,
+    mut control: ResMut<TimeControl>,
+    mut virt: ResMut<Time<Virtual>>,
+    fixed: Res<Time<Fixed>>,
+) {
+    if control.stepping && virt.is_paused() {
+        cmds.insert_resource(TimeUpdateStrategy::ManualDuration(fixed.timestep()));
+        virt.set_relative_speed(1.0);
+        virt.unpause();
+        return;
+    }
+    if control.stepping {
+        control.stepping = false;
+        cmds.insert_resource(TimeUpdateStrategy::Automatic);
+    }
+    virt.set_relative_speed(control.scale);
+    if control.paused {
+        virt.pause();
+    } else {
+        virt.unpause();
+    }
+}
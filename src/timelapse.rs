@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+};
+use crate::{
+    chunk::ChunkMesh,
+    export::merge_chunks,
+    mesh::{BevyMesh, MeshBuffers},
+    MarchyMaterials,
+    MarchySettings,
+};
+
+/// Frames kept before recording stops by itself.
+const MAX_FRAMES: usize = 1000;
+const PLAYBACK_FPS: f32 = 10.0;
+
+/// Snapshots of the whole meshed world, taken every `timelapse_interval`
+/// seconds while recording, for watching slow changes (animated terrain,
+/// long simulations) sped up.
+#[derive(Resource, Default)]
+pub struct Timelapse {
+    pub frames: Vec<MeshBuffers>,
+    pub recording: bool,
+    timer: Timer,
+    playback: Option<Playback>,
+}
+
+struct Playback {
+    entity: Entity,
+    mesh: Handle<Mesh>,
+    frame: usize,
+    timer: Timer,
+}
+
+/// Shift+F7 starts a new recording or stops the current one. Ctrl+F7 plays
+/// the frames back in a loop in place of the live chunks, or stops playing.
+/// Alt+F7 writes them to `timelapse_dir` as numbered `.obj` files.
+pub fn timelapse_keys(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut lapse: ResMut<Timelapse>,
+    mut chunks: Query<&mut Visibility, With<ChunkMesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mats: Res<MarchyMaterials>,
+    settings: Res<MarchySettings>,
+) {
+    if !keys.just_pressed(KeyCode::F7) {
+        return;
+    }
+    if keys.pressed(KeyCode::ShiftLeft) {
+        lapse.recording = !lapse.recording;
+        if lapse.recording {
+            lapse.frames.clear();
+            let mut timer = Timer::from_seconds(settings.timelapse_interval, TimerMode::Repeating);
+            // The first frame is taken straight away.
+            timer.set_elapsed(timer.duration());
+            lapse.timer = timer;
+        }
+        info!("timelapse recording: {} ({} frames)", lapse.recording, lapse.frames.len());
+    } else if keys.pressed(KeyCode::ControlLeft) {
+        let show_chunks = if let Some(playback) = lapse.playback.take() {
+            cmds.entity(playback.entity).despawn();
+            Visibility::Inherited
+        } else if let Some(first) = lapse.frames.first() {
+            let mesh = meshes.add(first.clone().into_mesh());
+            let entity = cmds.spawn((
+                Name::new("timelapse"),
+                Mesh3d(mesh.clone()),
+                MeshMaterial3d(mats.terrain.clone()),
+                Transform::default(),
+            )).id();
+            let timer = Timer::from_seconds(1.0 / PLAYBACK_FPS, TimerMode::Repeating);
+            lapse.playback = Some(Playback { entity, mesh, frame: 0, timer });
+            Visibility::Hidden
+        } else {
+            warn!("no timelapse frames to play");
+            return;
+        };
+        for mut vis in &mut chunks {
+            *vis = show_chunks;
+        }
+    } else if keys.pressed(KeyCode::AltLeft) {
+        let dir = &settings.timelapse_dir;
+        let result = fs::create_dir_all(dir).and_then(|()| {
+            for (i, frame) in lapse.frames.iter().enumerate() {
+                let mut w = BufWriter::new(File::create(dir.join(format!("frame-{i:04}.obj")))?);
+                frame.write_obj(&mut w)?;
+                w.flush()?;
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => info!("exported {} timelapse frames to {}", lapse.frames.len(), dir.display()),
+            Err(e) => error!("failed to export timelapse to {}: {e}", dir.display()),
+        }
+    }
+}
+
+pub fn record_timelapse(
+    time: Res<Time>,
+    mut lapse: ResMut<Timelapse>,
+    chunks: Query<(&Mesh3d, &Transform), With<ChunkMesh>>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    if !lapse.recording || !lapse.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    lapse.frames.push(merge_chunks(&chunks, &meshes));
+    if lapse.frames.len() >= MAX_FRAMES {
+        lapse.recording = false;
+        warn!("timelapse stopped at {MAX_FRAMES} frames");
+    }
+}
+
+pub fn play_timelapse(
+    time: Res<Time>,
+    mut lapse: ResMut<Timelapse>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let count = lapse.frames.len();
+    let Timelapse { frames, playback: Some(playback), .. } = &mut *lapse else {
+        return;
+    };
+    if count == 0 || !playback.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    playback.frame = (playback.frame + 1) % count;
+    if let Some(mesh) = meshes.get_mut(&playback.mesh) {
+        *mesh = frames[playback.frame].clone().into_mesh();
+    }
+}
@@ -0,0 +1,29 @@
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::{mesh::MeshTag, render_resource::{AsBindGroup, ShaderRef}},
+};
+
+const SHADER: &str = "shaders/tint.wgsl";
+
+/// The balls' one shared material: standard PBR, tinted per entity by `Tint`.
+pub type BallMaterial = ExtendedMaterial<StandardMaterial, Tint>;
+
+/// Multiplies a mesh's vertex colors by its entity's `MeshTag`, read as
+/// packed sRGB bytes (see `tag`). Every ball can then share one material
+/// whatever its color, and they all still batch into one instanced draw.
+/// The mesh needs a color attribute, if only a white one.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone, Default)]
+pub struct Tint {}
+
+impl MaterialExtension for Tint {
+    fn vertex_shader() -> ShaderRef {
+        SHADER.into()
+    }
+}
+
+/// A `MeshTag` tinting its entity `color` under `BallMaterial`. Alpha is
+/// carried but the material is opaque.
+pub fn tag(color: Color) -> MeshTag {
+    MeshTag(u32::from_le_bytes(color.to_srgba().to_u8_array()))
+}
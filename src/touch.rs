@@ -0,0 +1,99 @@
+use bevy::{
+    input::{
+        mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll},
+        touch::Touches,
+    },
+    prelude::*,
+};
+use crate::camera::{Cam, CamMode};
+
+/// Seconds a finger has to rest before it starts editing.
+const HOLD: f32 = 0.35;
+/// Pixels a finger can wander and still count as resting.
+const SLOP: f32 = 12.0;
+/// Pixels of pinch per wheel line.
+const PINCH: f32 = 40.0;
+
+#[derive(Clone, Copy, Debug, Default)]
+enum Gesture {
+    #[default]
+    None,
+    /// One finger down and still, for this many seconds.
+    Resting(f32),
+    Dragging,
+    Editing,
+    /// Two fingers were down; nothing more happens until they're all up.
+    Pinching,
+}
+
+/// Touch screens drive the mouse controls, so the web build works on a
+/// phone: dragging a finger is a middle-drag (orbit, or look when flying),
+/// pinching is the wheel, two fingers pan, and a finger held still is the
+/// left button, aimed at `pos`.
+#[derive(Resource, Default)]
+pub struct TouchPointer {
+    /// Where the editing finger is, for `TerrainCursor` to aim at instead
+    /// of the mouse.
+    pub pos: Option<Vec2>,
+    gesture: Gesture,
+}
+
+/// Runs after input is gathered, so everything reading the mouse later in
+/// the frame sees the touches too.
+pub fn touch_input(
+    touches: Res<Touches>,
+    // Real time, so holding to edit works while `TimeControl` has paused.
+    time: Res<Time<Real>>,
+    mut pointer: ResMut<TouchPointer>,
+    mut mouse: ResMut<ButtonInput<MouseButton>>,
+    mut motion: ResMut<AccumulatedMouseMotion>,
+    mut scroll: ResMut<AccumulatedMouseScroll>,
+    mut cams: Query<(&mut Cam, &Transform)>,
+) {
+    let fingers: Vec<_> = touches.iter().collect();
+    let gesture = pointer.gesture;
+    let end = |mouse: &mut ButtonInput<MouseButton>| match gesture {
+        Gesture::Dragging => mouse.release(MouseButton::Middle),
+        Gesture::Editing => mouse.release(MouseButton::Left),
+        _ => {}
+    };
+    match fingers.as_slice() {
+        [] => {
+            end(&mut mouse);
+            *pointer = default();
+        }
+        [finger] => match gesture {
+            Gesture::Pinching => {}
+            Gesture::Editing => pointer.pos = Some(finger.position()),
+            Gesture::Dragging => motion.delta += finger.delta(),
+            _ if finger.distance().length() > SLOP => {
+                mouse.press(MouseButton::Middle);
+                motion.delta += finger.distance();
+                pointer.gesture = Gesture::Dragging;
+            }
+            Gesture::Resting(secs) if secs + time.delta_secs() >= HOLD => {
+                mouse.press(MouseButton::Left);
+                pointer.pos = Some(finger.position());
+                pointer.gesture = Gesture::Editing;
+            }
+            Gesture::Resting(secs) => pointer.gesture = Gesture::Resting(secs + time.delta_secs()),
+            Gesture::None => pointer.gesture = Gesture::Resting(0.0),
+        },
+        [a, b] => {
+            end(&mut mouse);
+            pointer.pos = None;
+            pointer.gesture = Gesture::Pinching;
+            let spread = a.position().distance(b.position()) - a.previous_position().distance(b.previous_position());
+            scroll.delta.y += spread / PINCH;
+            // Two-finger drags pan like Shift + middle-drag.
+            let delta = (a.delta() + b.delta()) / 2.0;
+            for (mut cam, t) in &mut cams {
+                if cam.mode == CamMode::Orbit {
+                    let pan = t.rotation * Vec3::new(-delta.x, delta.y, 0.0) * cam.r * 0.002;
+                    cam.target += pan;
+                }
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,256 @@
+use bevy::prelude::*;
+use crate::{
+    brush::Brush,
+    chunk::ChunkMap,
+    culling::ChunkCulling,
+    mesher::Meshers,
+    quality::Quality,
+    shells::IsoShells,
+    terrain::{regenerate_all, remesh_all, Fbm, Generator},
+    MarchySettings,
+};
+
+const FIELDS: [&str; 19] = [
+    "substeps",
+    "restitution iterations",
+    "gravity",
+    "sleep linear",
+    "sleep angular",
+    "deactivation time",
+    "iso level",
+    "noise frequency",
+    "noise octaves",
+    "brush size",
+    "mesher",
+    "quality",
+    "iso shells",
+    "shell spacing",
+    "draw distance",
+    "time of day",
+    "day length",
+    "shadow cascades",
+    "shadow distance",
+];
+
+#[derive(Component)]
+pub struct TuningPanel;
+
+#[derive(Resource, Default)]
+pub struct Tuning {
+    pub open: bool,
+    pub selected: usize,
+}
+
+/// What a change needs redone for it to show.
+enum Redo {
+    Nothing,
+    Remesh,
+    Regenerate,
+}
+
+/// The noise of generators that have one.
+fn fbm(generator: &mut Generator) -> Option<&mut Fbm> {
+    match generator {
+        Generator::Noise { fbm, .. } | Generator::HeightmapCaves { fbm, .. } => Some(fbm),
+        _ => None,
+    }
+}
+
+fn value(s: &MarchySettings, brush: &Brush, field: usize) -> String {
+    let p = &s.physics;
+    let noise = |f: fn(&Fbm) -> String| match &s.terrain.generator {
+        Generator::Noise { fbm, .. } | Generator::HeightmapCaves { fbm, .. } => f(fbm),
+        _ => "-".into(),
+    };
+    match field {
+        0 => p.substeps.to_string(),
+        1 => p.restitution_iterations.to_string(),
+        2 => format!("{:.2}", p.gravity.y),
+        3 => format!("{:.2}", p.sleep_linear),
+        4 => format!("{:.2}", p.sleep_angular),
+        5 => format!("{:.2}", p.deactivation_time),
+        6 => format!("{:.2}", s.iso_level),
+        7 => noise(|f| format!("{:.3}", f.frequency)),
+        8 => noise(|f| f.octaves.to_string()),
+        9 => format!("{:.1}", brush.radius),
+        10 => s.mesher.clone(),
+        11 => s.quality.name().into(),
+        12 => s.iso_shells.as_ref().map_or("off".into(), |sh| sh.count.to_string()),
+        13 => s.iso_shells.as_ref().map_or("-".into(), |sh| format!("{:.2}", sh.spacing)),
+        14 => match &s.culling {
+            Some(ChunkCulling { draw_distance: Some(d), .. }) => format!("{d:.0}"),
+            Some(_) => "far plane".into(),
+            None => "off".into(),
+        },
+        15 => {
+            let hours = s.day.start * 24.0;
+            format!("{:02}:{:02}", hours as u32, (hours.fract() * 60.0) as u32)
+        }
+        16 if s.day.length > 0.0 => format!("{:.0}s", s.day.length),
+        16 => "held".into(),
+        17 => s.sun_shadows.cascades.to_string(),
+        _ => format!("{:.0}", s.sun_shadows.max_distance),
+    }
+}
+
+fn adjust(s: &mut MarchySettings, brush: &mut Brush, meshers: &Meshers, field: usize, dir: f32) -> Redo {
+    let p = &mut s.physics;
+    match field {
+        0 => p.substeps = (p.substeps as f32 + dir).max(1.0) as u32,
+        1 => p.restitution_iterations = (p.restitution_iterations as f32 + dir).max(0.0) as usize,
+        2 => p.gravity.y += dir * 0.5,
+        3 => p.sleep_linear = (p.sleep_linear + dir * 0.05).max(0.0),
+        4 => p.sleep_angular = (p.sleep_angular + dir * 0.05).max(0.0),
+        5 => p.deactivation_time = (p.deactivation_time + dir * 0.1).max(0.0),
+        6 => {
+            // The stored densities stay put, so this thickens or thins the
+            // surface rather than regenerating it.
+            s.iso_level += dir * 0.25;
+            return Redo::Remesh;
+        }
+        7 | 8 => {
+            let Some(fbm) = fbm(&mut s.terrain.generator) else {
+                return Redo::Nothing;
+            };
+            if field == 7 {
+                fbm.frequency = (fbm.frequency * 1.1f32.powf(dir)).max(0.001);
+            } else {
+                fbm.octaves = (fbm.octaves as f32 + dir).clamp(1.0, 8.0) as u32;
+            }
+            return Redo::Regenerate;
+        }
+        9 => brush.radius = (brush.radius + dir * 0.5).clamp(0.5, 16.0),
+        10 => {
+            let mut names: Vec<_> = meshers.names().collect();
+            names.sort();
+            let i = names.iter().position(|n| *n == s.mesher).unwrap_or(0) as isize;
+            let next = (i + dir as isize).rem_euclid(names.len().max(1) as isize) as usize;
+            if let Some(name) = names.get(next) {
+                s.mesher = name.to_string();
+            }
+            return Redo::Remesh;
+        }
+        11 => {
+            let all = Quality::ALL;
+            let i = all.iter().position(|&q| q == s.quality).unwrap_or(0) as isize;
+            all[(i + dir as isize).rem_euclid(all.len() as isize) as usize].apply(s);
+            return Redo::Remesh;
+        }
+        12 => {
+            // Down to none turns them off.
+            let count = s.iso_shells.as_ref().map_or(0, |sh| sh.count) as f32 + dir;
+            s.iso_shells = (count >= 1.0).then(|| IsoShells {
+                count: count.min(9.0) as u32,
+                ..s.iso_shells.clone().unwrap_or_default()
+            });
+        }
+        13 => {
+            if let Some(shells) = &mut s.iso_shells {
+                shells.spacing = (shells.spacing * 1.25f32.powf(dir)).clamp(0.05, 10.0);
+            }
+        }
+        14 => {
+            // Up from off starts culling at 50; down past 20 stops it.
+            let d = match s.culling.as_ref().and_then(|c| c.draw_distance) {
+                Some(d) => d * 1.25f32.powf(dir),
+                None => 50.0 * dir.max(0.0),
+            };
+            s.culling = (d >= 20.0).then(|| ChunkCulling {
+                draw_distance: Some(d.min(1000.0)),
+                ..s.culling.clone().unwrap_or_default()
+            });
+        }
+        // Half-hour steps, shifting the running clock along with it.
+        15 => s.day.start = (s.day.start + dir / 48.0).rem_euclid(1.0),
+        16 => {
+            // Down past a minute holds the sun still.
+            let length = if s.day.length > 0.0 { s.day.length * 2f32.powf(dir) } else { 60.0 * dir.max(0.0) };
+            s.day.length = if length < 60.0 { 0.0 } else { length.min(3600.0) };
+        }
+        17 => s.sun_shadows.cascades = (s.sun_shadows.cascades as f32 + dir).clamp(1.0, 4.0) as usize,
+        _ => s.sun_shadows.max_distance = (s.sun_shadows.max_distance * 1.25f32.powf(dir)).clamp(20.0, 1000.0),
+    }
+    Redo::Nothing
+}
+
+pub fn spawn_tuning_panel(mut cmds: Commands) {
+    cmds.spawn((
+        TuningPanel,
+        Text::new(""),
+        TextFont { font_size: 14.0, ..default() },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        },
+        Visibility::Hidden,
+    ));
+}
+
+/// F2 toggles the panel, up/down picks a field and left/right changes it.
+/// Terrain fields remesh the world, or regenerate it for the noise, in the
+/// background as they change.
+pub fn tuning_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut tuning: ResMut<Tuning>,
+    mut settings: ResMut<MarchySettings>,
+    mut brush: ResMut<Brush>,
+    meshers: Res<Meshers>,
+    mut chunks: ResMut<ChunkMap>,
+) {
+    if keys.just_pressed(KeyCode::F2) {
+        tuning.open = !tuning.open;
+    }
+    if !tuning.open {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        tuning.selected = (tuning.selected + 1) % FIELDS.len();
+    }
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        tuning.selected = (tuning.selected + FIELDS.len() - 1) % FIELDS.len();
+    }
+    let dir = if keys.just_pressed(KeyCode::ArrowRight) {
+        1.0
+    } else if keys.just_pressed(KeyCode::ArrowLeft) {
+        -1.0
+    } else {
+        return;
+    };
+    match adjust(&mut settings, &mut brush, &meshers, tuning.selected, dir) {
+        Redo::Nothing => {}
+        Redo::Remesh => remesh_all(&mut chunks),
+        Redo::Regenerate => regenerate_all(&mut chunks, &settings),
+    }
+}
+
+pub fn update_tuning_panel(
+    tuning: Res<Tuning>,
+    settings: Res<MarchySettings>,
+    brush: Res<Brush>,
+    mut panel: Query<(&mut Text, &mut Visibility), With<TuningPanel>>,
+) {
+    let Ok((mut text, mut vis)) = panel.single_mut() else {
+        return;
+    };
+    *vis = if tuning.open { Visibility::Visible } else { Visibility::Hidden };
+    if !tuning.open {
+        return;
+    }
+
+    let mut out = String::from("tuning (F2)\nphysics\n");
+    for (i, name) in FIELDS.iter().enumerate() {
+        if i == 6 {
+            out.push_str("terrain\n");
+        } else if i == 11 {
+            out.push_str("display\n");
+        } else if i == 15 {
+            out.push_str("lighting\n");
+        }
+        let cursor = if i == tuning.selected { ">" } else { " " };
+        out.push_str(&format!("{cursor} {name}: {}\n", value(&settings, &brush, i)));
+    }
+    text.0 = out;
+}
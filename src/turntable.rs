@@ -0,0 +1,152 @@
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    render::view::screenshot::{save_to_disk, Screenshot, ScreenshotCaptured},
+    time::TimeUpdateStrategy,
+};
+use std::{f32::consts::TAU, fs, path::PathBuf, time::Duration};
+use crate::{
+    camera::{Cam, CamMode},
+    chunk::{ChunkMap, MeshTasks},
+};
+
+/// A turntable capture: once everything has meshed, the camera makes one
+/// smooth orbit of its target over `frames` frames, each written to `dir`
+/// as a numbered PNG. The world steps a fixed `1 / fps` seconds a frame
+/// however slow capturing is, so runs line up frame for frame across
+/// generators and meshers, and the frames make a GIF as they are.
+#[derive(Resource, Clone)]
+pub struct Turntable {
+    pub frames: u32,
+    pub fps: f32,
+    pub dir: PathBuf,
+    /// Quit once the last frame is on disk, for captures from the command
+    /// line.
+    pub exit: bool,
+    /// The camera as it was before the capture took it over; `None` while
+    /// waiting for the meshes.
+    start: Option<Cam>,
+    frame: u32,
+    saved: u32,
+}
+
+impl Turntable {
+    pub fn new(frames: u32) -> Self {
+        Turntable {
+            frames,
+            fps: 30.0,
+            dir: PathBuf::from("turntable"),
+            exit: false,
+            start: None,
+            frame: 0,
+            saved: 0,
+        }
+    }
+
+    /// `--turntable <frames> [--turntable-fps <fps>] [--turntable-dir <dir>]`,
+    /// quitting when done. `None` when no capture was asked for.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut args = args.into_iter();
+        let (mut frames, mut fps, mut dir) = (None, None, None);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--turntable" => frames = Some(value()?.parse().map_err(|e| format!("bad --turntable: {e}"))?),
+                "--turntable-fps" => fps = Some(value()?.parse().map_err(|e| format!("bad --turntable-fps: {e}"))?),
+                "--turntable-dir" => dir = Some(PathBuf::from(value()?)),
+                _ => {}
+            }
+        }
+        Ok(frames.filter(|&n| n > 0).map(|frames| {
+            let mut turntable = Turntable::new(frames);
+            turntable.fps = fps.unwrap_or(turntable.fps);
+            turntable.dir = dir.unwrap_or(turntable.dir);
+            turntable.exit = true;
+            turntable
+        }))
+    }
+}
+
+/// Gives the camera and the clock back.
+fn stop(cmds: &mut Commands, turntable: &Turntable, cams: &mut Query<&mut Cam>) {
+    if let (Some(start), Ok(mut cam)) = (&turntable.start, cams.single_mut()) {
+        *cam = start.clone();
+    }
+    cmds.insert_resource(TimeUpdateStrategy::Automatic);
+    cmds.remove_resource::<Turntable>();
+}
+
+/// Shift+F12 starts a 120 frame capture, or cancels the one running.
+pub fn turntable_key(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    turntable: Option<Res<Turntable>>,
+    mut cams: Query<&mut Cam>,
+) {
+    if !(keys.pressed(KeyCode::ShiftLeft) && keys.just_pressed(KeyCode::F12)) {
+        return;
+    }
+    match turntable {
+        Some(turntable) => {
+            stop(&mut cmds, &turntable, &mut cams);
+            info!("turntable cancelled after {} frames", turntable.frame);
+        }
+        None => cmds.insert_resource(Turntable::new(120)),
+    }
+}
+
+fn count_saved(_: Trigger<ScreenshotCaptured>, turntable: Option<ResMut<Turntable>>) {
+    if let Some(mut turntable) = turntable {
+        turntable.saved += 1;
+    }
+}
+
+/// Runs between `cam_input` and `cam_follow`, so the orbit overrides any
+/// input and lands in this frame's render.
+pub fn run_turntable(
+    mut cmds: Commands,
+    mut turntable: ResMut<Turntable>,
+    chunks: Res<ChunkMap>,
+    tasks: Res<MeshTasks>,
+    mut cams: Query<&mut Cam>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if turntable.start.is_none() {
+        // Frames taken mid-mesh would show chunks popping in.
+        if chunks.dirty_count() > 0 || tasks.pending() > 0 {
+            return;
+        }
+        let Ok(cam) = cams.single() else {
+            return;
+        };
+        if let Err(e) = fs::create_dir_all(&turntable.dir) {
+            error!("failed to create {}: {e}", turntable.dir.display());
+            stop(&mut cmds, &turntable, &mut cams);
+            return;
+        }
+        turntable.start = Some(cam.clone());
+        let step = Duration::from_secs_f32(1.0 / turntable.fps.max(1.0));
+        cmds.insert_resource(TimeUpdateStrategy::ManualDuration(step));
+        info!("turntable: {} frames to {}", turntable.frames, turntable.dir.display());
+    }
+
+    if turntable.frame == turntable.frames {
+        // Screenshots land a few frames after they're asked for.
+        if turntable.saved >= turntable.frames {
+            info!("turntable: wrote {} frames to {}", turntable.saved, turntable.dir.display());
+            stop(&mut cmds, &turntable, &mut cams);
+            if turntable.exit {
+                exit.write(AppExit::Success);
+            }
+        }
+        return;
+    }
+    let (Some(start), Ok(mut cam)) = (turntable.start.clone(), cams.single_mut()) else {
+        return;
+    };
+    let turn = turntable.frame as f32 / turntable.frames as f32;
+    *cam = Cam { mode: CamMode::Orbit, yaw: start.yaw + turn * TAU, ..start };
+    let path = turntable.dir.join(format!("frame-{:04}.png", turntable.frame));
+    cmds.spawn(Screenshot::primary_window()).observe(save_to_disk(path)).observe(count_saved);
+    turntable.frame += 1;
+}
@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::fs;
+use crate::{camera::Cam, chunk::ChunkMap, lights::PlacedLight, projectile::Projectile, MarchySettings};
+
+/// What a tutorial step waits for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Goal {
+    /// Radians of camera orbit.
+    Orbit,
+    /// Units of camera zoom.
+    Zoom,
+    /// Chunk remeshes, i.e. terrain edits.
+    Edit,
+    /// Balls spawned.
+    Spawn,
+    /// Lights placed.
+    Light,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Step {
+    pub text: String,
+    pub goal: Goal,
+    pub amount: f32,
+    /// A world position to point an arrow at.
+    pub highlight: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+struct TutorialFile {
+    step: Vec<Step>,
+}
+
+/// Steps read from `tutorial_path`, and how far along the current one is.
+#[derive(Resource, Default)]
+pub struct Tutorial {
+    pub steps: Vec<Step>,
+    pub current: usize,
+    pub progress: f32,
+    last_yaw: Option<f32>,
+    last_r: Option<f32>,
+    last_remeshes: Option<u32>,
+}
+
+impl Tutorial {
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file: TutorialFile = toml::from_str(&text).map_err(|e| e.to_string())?;
+        Ok(Tutorial { steps: file.step, ..default() })
+    }
+
+    pub fn step(&self) -> Option<&Step> {
+        self.steps.get(self.current)
+    }
+
+    /// Counts toward the current step if it waits for `goal`.
+    pub fn record(&mut self, goal: Goal, amount: f32) {
+        let Some(step) = self.step() else {
+            return;
+        };
+        if step.goal != goal {
+            return;
+        }
+        self.progress += amount;
+        if self.progress >= step.amount {
+            self.advance();
+        }
+    }
+
+    pub fn advance(&mut self) {
+        self.current += 1;
+        self.progress = 0.0;
+    }
+}
+
+#[derive(Component)]
+pub struct TutorialPanel;
+
+pub fn load_tutorial(mut cmds: Commands, settings: Res<MarchySettings>) {
+    let path = &settings.tutorial_path;
+    if !path.exists() {
+        return;
+    }
+    match Tutorial::load(path) {
+        Ok(tutorial) => cmds.insert_resource(tutorial),
+        Err(e) => error!("failed to load {}: {e}", path.display()),
+    }
+}
+
+pub fn spawn_tutorial_panel(mut cmds: Commands) {
+    cmds.spawn((
+        TutorialPanel,
+        Text::new(""),
+        TextFont { font_size: 18.0, ..default() },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(24.0),
+            left: Val::Percent(25.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        Visibility::Hidden,
+    ));
+}
+
+/// Measures what the player did this frame against the current step.
+/// Tab skips a step.
+pub fn track_tutorial(
+    mut tutorial: ResMut<Tutorial>,
+    keys: Res<ButtonInput<KeyCode>>,
+    cams: Query<&Cam>,
+    chunks: Res<ChunkMap>,
+    spawned: Query<(), Added<Projectile>>,
+    lights: Query<(), Added<PlacedLight>>,
+) {
+    if keys.just_pressed(KeyCode::Tab) {
+        tutorial.advance();
+    }
+    if let Ok(cam) = cams.single() {
+        if let Some(last) = tutorial.last_yaw.replace(cam.yaw) {
+            tutorial.record(Goal::Orbit, (cam.yaw - last).abs());
+        }
+        if let Some(last) = tutorial.last_r.replace(cam.r) {
+            tutorial.record(Goal::Zoom, (cam.r - last).abs());
+        }
+    }
+    let remeshes: u32 = chunks.iter().map(|(_, c)| c.stats.remeshes).sum();
+    if let Some(last) = tutorial.last_remeshes.replace(remeshes) {
+        tutorial.record(Goal::Edit, remeshes.saturating_sub(last) as f32);
+    }
+    tutorial.record(Goal::Spawn, spawned.iter().count() as f32);
+    tutorial.record(Goal::Light, lights.iter().count() as f32);
+}
+
+/// Shows the current step, or hides the panel once the tutorial is done,
+/// and points an arrow down at the step's highlight.
+pub fn update_tutorial_panel(
+    tutorial: Res<Tutorial>,
+    mut panel: Query<(&mut Text, &mut Visibility), With<TutorialPanel>>,
+    mut gizmos: Gizmos,
+) {
+    let Ok((mut text, mut vis)) = panel.single_mut() else {
+        return;
+    };
+    let Some(step) = tutorial.step() else {
+        *vis = Visibility::Hidden;
+        return;
+    };
+    *vis = Visibility::Visible;
+    text.0 = format!(
+        "{}/{}: {} ({:.0}%)  [Tab to skip]",
+        tutorial.current + 1,
+        tutorial.steps.len(),
+        step.text,
+        100.0 * tutorial.progress / step.amount.max(f32::EPSILON),
+    );
+    if let Some(p) = step.highlight {
+        let p = Vec3::from(p);
+        gizmos.arrow(p + Vec3::Y * 3.0, p, Color::srgb(1.0, 0.9, 0.2));
+    }
+}
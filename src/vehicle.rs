@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use avian3d::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+use crate::{bounds::Respawn, edit::TerrainCursor, outliner::Agent};
+
+const WHEEL_RADIUS: f32 = 0.45;
+const WHEEL_WIDTH: f32 = 0.3;
+/// Wheel positions relative to the body: (x, y, z) with x > 0 on the right.
+const WHEELS: [Vec3; 4] = [
+    Vec3::new(-1.15, -0.35, -1.1),
+    Vec3::new(1.15, -0.35, -1.1),
+    Vec3::new(-1.15, -0.35, 1.1),
+    Vec3::new(1.15, -0.35, 1.1),
+];
+
+#[derive(Debug, Event)]
+pub struct VehicleSpawn {
+    pub pos: Vec3,
+}
+
+/// A skid-steered body on four hinged wheels.
+#[derive(Component)]
+pub struct Vehicle {
+    pub wheels: [Entity; 4],
+    pub torque: f32,
+}
+
+#[derive(Component)]
+pub struct Wheel {
+    pub right: bool,
+}
+
+/// The vehicle the arrow keys drive.
+#[derive(Resource, Default)]
+pub struct ActiveVehicle(pub Option<Entity>);
+
+pub fn vehicle_spawn(
+    trigger: Trigger<VehicleSpawn>,
+    mut cmds: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut active: ResMut<ActiveVehicle>,
+) {
+    let pos = trigger.event().pos;
+    let body_mat = materials.add(Color::srgb(0.9, 0.3, 0.1));
+    let wheel_mat = materials.add(Color::srgb(0.1, 0.1, 0.1));
+    let wheel_mesh = meshes.add(
+        Cylinder::new(WHEEL_RADIUS, WHEEL_WIDTH)
+            .mesh()
+            .build()
+            .rotated_by(Quat::from_rotation_z(FRAC_PI_2))
+    );
+
+    let body = cmds.spawn((
+        Name::new("vehicle"),
+        Agent,
+        RigidBody::Dynamic,
+        Collider::cuboid(2.0, 0.5, 3.0),
+        ColliderDensity(2.0),
+        Mesh3d(meshes.add(Cuboid::new(2.0, 0.5, 3.0))),
+        MeshMaterial3d(body_mat),
+        Transform::from_translation(pos),
+        Respawn::At(pos),
+    )).id();
+
+    let wheels = WHEELS.map(|offset| {
+        // The wheel body stays axis-aligned; only its collider and mesh are
+        // turned, so the hinge axis is X in both bodies' frames.
+        let wheel = cmds.spawn((
+            Wheel { right: offset.x > 0.0 },
+            RigidBody::Dynamic,
+            Collider::compound(vec![(
+                Vec3::ZERO,
+                Quat::from_rotation_z(FRAC_PI_2),
+                Collider::cylinder(WHEEL_RADIUS, WHEEL_WIDTH),
+            )]),
+            Friction::new(1.2),
+            ExternalTorque::default(),
+            Mesh3d(wheel_mesh.clone()),
+            MeshMaterial3d(wheel_mat.clone()),
+            Transform::from_translation(pos + offset),
+            // Moved along with the body.
+            Respawn::Ignore,
+        )).id();
+        cmds.spawn(
+            RevoluteJoint::new(body, wheel)
+                .with_local_anchor_1(offset)
+                .with_aligned_axis(Vec3::X)
+        );
+        wheel
+    });
+
+    cmds.entity(body).insert(Vehicle { wheels, torque: 6.0 });
+    active.0 = Some(body);
+}
+
+/// V drops a vehicle onto the terrain under the cursor.
+pub fn spawn_vehicle_key(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    cursor: TerrainCursor,
+) {
+    if keys.just_pressed(KeyCode::KeyV) {
+        let pos = cursor.hit().map_or(Vec3::new(0.0, 8.0, 0.0), |(p, _)| p + Vec3::Y * 2.0);
+        cmds.trigger(VehicleSpawn { pos });
+    }
+}
+
+/// Arrow keys drive the active vehicle: up/down spin all wheels, left/right
+/// spin the two sides against each other.
+pub fn drive(
+    keys: Res<ButtonInput<KeyCode>>,
+    active: Res<ActiveVehicle>,
+    vehicles: Query<(&Vehicle, &GlobalTransform)>,
+    mut wheels: Query<(&Wheel, &mut ExternalTorque)>,
+) {
+    let Some((vehicle, t)) = active.0.and_then(|e| vehicles.get(e).ok()) else {
+        return;
+    };
+    let axis = |pos: KeyCode, neg: KeyCode| {
+        keys.pressed(pos) as i32 as f32 - keys.pressed(neg) as i32 as f32
+    };
+    let throttle = axis(KeyCode::ArrowUp, KeyCode::ArrowDown);
+    let steer = axis(KeyCode::ArrowRight, KeyCode::ArrowLeft);
+    let axle = t.right();
+
+    for &entity in &vehicle.wheels {
+        let Ok((wheel, mut torque)) = wheels.get_mut(entity) else {
+            continue;
+        };
+        let side = if wheel.right { throttle - steer } else { throttle + steer };
+        // Negative about +X rolls the wheel towards -Z, the body's forward.
+        torque.set_torque(-*axle * side * vehicle.torque);
+    }
+}
@@ -0,0 +1,311 @@
+use bevy::prelude::*;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+use crate::{
+    chunk::ChunkMap,
+    edit::TerrainCursor,
+    materials::VoxelMaterial,
+    mesh::{BevyMesh, MeshBuffers},
+    MarchySettings,
+    VoxelGrid,
+};
+
+/// Cells further than this from the surface aren't measured exactly.
+const BAND: f32 = 3.0;
+/// Cells nearer than this block the outside flood fill.
+const WALL: f32 = 1.0;
+
+/// Reads the vertices and faces of a Wavefront `.obj`. Polygons are fanned
+/// into triangles; texture coordinates and normals are ignored.
+pub fn read_obj(r: impl BufRead) -> io::Result<MeshBuffers> {
+    let bad = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let mut out = MeshBuffers::default();
+    for (n, line) in r.lines().enumerate() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("v") => {
+                let v: Vec<f32> = words.take(3).map(str::parse).collect::<Result<_, _>>()
+                    .map_err(|e| bad(format!("line {}: {e}", n + 1)))?;
+                let [x, y, z] = v[..] else {
+                    return Err(bad(format!("line {}: vertex needs 3 coordinates", n + 1)));
+                };
+                out.positions.push([x, y, z]);
+            }
+            Some("f") => {
+                let count = out.positions.len() as i64;
+                let face: Vec<u32> = words
+                    .map(|w| {
+                        let i: i64 = w.split('/').next().unwrap_or("").parse()
+                            .map_err(|e| bad(format!("line {}: {e}", n + 1)))?;
+                        // 1-based, or negative counting back from the end.
+                        let i = if i < 0 { count + i } else { i - 1 };
+                        if (0..count).contains(&i) {
+                            Ok(i as u32)
+                        } else {
+                            Err(bad(format!("line {}: index out of range", n + 1)))
+                        }
+                    })
+                    .collect::<Result<_, _>>()?;
+                for i in 2..face.len() {
+                    out.indices.extend_from_slice(&[face[0], face[i - 1], face[i]]);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+/// Nearest point to `p` on triangle `abc` (Ericson, Real-Time Collision
+/// Detection 5.1.5).
+fn closest_on_triangle(p: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+/// Converts a closed triangle mesh into a signed distance field, scaled and
+/// centered to fit a `dims` grid with a two-cell margin. Stored values are
+/// `iso` plus the distance in cells, like generated terrain.
+///
+/// Distances are exact within a few cells of the surface. The sign comes
+/// from flood-filling the outside from the grid's faces, stopping at the
+/// cells right against the surface; those take their sign from the facing
+/// of the nearest triangle. Meshes with holes come out hollow.
+pub fn voxelize(mesh: &MeshBuffers, dims: UVec3, iso: f32) -> VoxelGrid {
+    let mut grid = VoxelGrid::with_dims(dims);
+    let Some((min, max)) = mesh.positions.iter().map(|&p| Vec3::from(p))
+        .fold(None, |acc: Option<(Vec3, Vec3)>, p| match acc {
+            Some((lo, hi)) => Some((lo.min(p), hi.max(p))),
+            None => Some((p, p)),
+        }) else {
+        grid.data.fill(iso + BAND);
+        return grid;
+    };
+    let room = (dims.min_element() as f32 - 4.0).max(1.0);
+    let scale = room / (max - min).max_element().max(f32::EPSILON);
+    let center = (min + max) / 2.0;
+    // Mesh space to grid space, with cell centers on the integers.
+    let to_grid = |p: [f32; 3]| (Vec3::from(p) - center) * scale + dims.as_vec3() / 2.0 - 0.5;
+
+    let index = |p: UVec3| ((p.z * dims.y + p.y) * dims.x + p.x) as usize;
+    let mut dist = vec![BAND; grid.data.len()];
+    let mut inside = vec![false; grid.data.len()];
+    for tri in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| to_grid(mesh.positions[i as usize]));
+        let normal = (b - a).cross(c - a);
+        let lo = (a.min(b).min(c) - BAND).ceil().max(Vec3::ZERO).as_uvec3();
+        let hi = (a.max(b).max(c) + BAND).floor().as_uvec3().min(dims - 1);
+        for z in lo.z..=hi.z {
+            for y in lo.y..=hi.y {
+                for x in lo.x..=hi.x {
+                    let p = UVec3::new(x, y, z);
+                    let q = p.as_vec3();
+                    let near = closest_on_triangle(q, a, b, c);
+                    let d = q.distance(near);
+                    let i = index(p);
+                    if d < dist[i] {
+                        dist[i] = d;
+                        inside[i] = (q - near).dot(normal) < 0.0;
+                    }
+                }
+            }
+        }
+    }
+
+    // Everything reachable from the faces without crossing the surface is
+    // outside; the rest of the far cells are inside.
+    let mut outside = vec![false; grid.data.len()];
+    let mut queue: VecDeque<UVec3> = VecDeque::new();
+    grid.each(|x, y, z, _| {
+        let p = UVec3::new(x, y, z);
+        let edge = p.cmpeq(UVec3::ZERO).any() || p.cmpeq(dims - 1).any();
+        if edge && dist[index(p)] >= WALL {
+            outside[index(p)] = true;
+            queue.push_back(p);
+        }
+    });
+    while let Some(p) = queue.pop_front() {
+        for step in [IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y, IVec3::Z, IVec3::NEG_Z] {
+            let n = p.as_ivec3() + step;
+            if n.cmplt(IVec3::ZERO).any() || n.cmpge(dims.as_ivec3()).any() {
+                continue;
+            }
+            let n = n.as_uvec3();
+            let i = index(n);
+            if !outside[i] && dist[i] >= WALL {
+                outside[i] = true;
+                queue.push_back(n);
+            }
+        }
+    }
+
+    for (i, val) in grid.data.iter_mut().enumerate() {
+        let solid = if dist[i] < WALL { inside[i] } else { !outside[i] };
+        *val = iso + if solid { -dist[i] } else { dist[i] };
+    }
+    grid
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CsgOp {
+    /// Add the model to the terrain.
+    #[default]
+    Union,
+    /// Carve the model out of the terrain.
+    Subtract,
+}
+
+/// Combines a model with the terrain. `size` is the model's largest extent in
+/// world units, and `center` where the middle of its bounds goes. Trigger it
+/// with `Commands::trigger`, like other world edits.
+#[derive(Event, Clone, Debug)]
+pub struct ModelEdit {
+    pub mesh: MeshBuffers,
+    pub center: Vec3,
+    pub size: f32,
+    pub op: CsgOp,
+}
+
+/// Voxelizes the model at one cell per world unit and merges it into the
+/// chunks: unions keep the lower density, subtractions the higher of the
+/// terrain and the inverted model. Cells a union turns solid are stone.
+pub fn model_edit(
+    trigger: Trigger<ModelEdit>,
+    mut chunks: ResMut<ChunkMap>,
+    settings: Res<MarchySettings>,
+) {
+    let edit = trigger.event();
+    let iso = settings.iso_level;
+    let dims = UVec3::splat(edit.size.max(1.0).ceil() as u32 + 4);
+    let grid = voxelize(&edit.mesh, dims, iso);
+    // The model's center is at the middle of the grid.
+    let half = dims.as_vec3() / 2.0 - 0.5;
+    let min = chunks.voxel_at(edit.center - half - 1.0);
+    let max = chunks.voxel_at(edit.center + half + 1.0);
+    let mut changed = 0;
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let pos = IVec3::new(x, y, z);
+                let Some(old) = chunks.read(pos) else {
+                    continue;
+                };
+                let g = (chunks.voxel_center(pos) - edit.center + half).round();
+                if g.cmplt(Vec3::ZERO).any() {
+                    continue;
+                }
+                let g = g.as_uvec3();
+                let Some(val) = grid.read(g.x, g.y, g.z) else {
+                    continue;
+                };
+                let new = match edit.op {
+                    CsgOp::Union => old.min(val),
+                    CsgOp::Subtract => old.max(2.0 * iso - val),
+                };
+                if new == old {
+                    continue;
+                }
+                chunks.write(pos, new);
+                if old > iso && new <= iso {
+                    chunks.write_material(pos, VoxelMaterial::Stone);
+                }
+                changed += 1;
+            }
+        }
+    }
+    info!("{:?} of {} triangles changed {changed} cells", edit.op, edit.mesh.indices.len() / 3);
+}
+
+/// A glTF model still loading, and the edit to make with it once it arrives.
+#[derive(Resource)]
+pub struct PendingImport {
+    pub mesh: Handle<Mesh>,
+    pub center: Vec3,
+    pub op: CsgOp,
+}
+
+/// F11 unions `import_path` into the terrain where the cursor points (or at
+/// the origin), Shift+F11 carves it out. `.obj` files are read directly
+/// from disk; anything else goes through the asset server as a glTF
+/// (relative to `assets/`), taking its first primitive.
+pub fn import_key(
+    mut cmds: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    assets: Res<AssetServer>,
+    cursor: TerrainCursor,
+    settings: Res<MarchySettings>,
+) {
+    if !keys.just_pressed(KeyCode::F11) {
+        return;
+    }
+    let op = if keys.pressed(KeyCode::ShiftLeft) { CsgOp::Subtract } else { CsgOp::Union };
+    let center = cursor.hit().map_or(Vec3::ZERO, |(p, _)| p);
+    let path = &settings.import_path;
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("obj")) {
+        match load_obj(path) {
+            Ok(mesh) => cmds.trigger(ModelEdit { mesh, center, size: settings.import_size, op }),
+            Err(e) => error!("failed to import {}: {e}", path.display()),
+        }
+    } else {
+        let label = GltfAssetLabel::Primitive { mesh: 0, primitive: 0 };
+        let mesh = assets.load(label.from_asset(path.clone()));
+        cmds.insert_resource(PendingImport { mesh, center, op });
+    }
+}
+
+fn load_obj(path: &Path) -> io::Result<MeshBuffers> {
+    read_obj(BufReader::new(File::open(path)?))
+}
+
+pub fn finish_import(
+    mut cmds: Commands,
+    pending: Res<PendingImport>,
+    assets: Res<AssetServer>,
+    meshes: Res<Assets<Mesh>>,
+    settings: Res<MarchySettings>,
+) {
+    if let Some(mesh) = meshes.get(&pending.mesh) {
+        cmds.trigger(ModelEdit {
+            mesh: MeshBuffers::from_mesh(mesh),
+            center: pending.center,
+            size: settings.import_size,
+            op: pending.op,
+        });
+        cmds.remove_resource::<PendingImport>();
+    } else if assets.load_state(&pending.mesh).is_failed() {
+        error!("failed to import {}", settings.import_path.display());
+        cmds.remove_resource::<PendingImport>();
+    }
+}
@@ -0,0 +1,122 @@
+//! Drops a fixed set of balls on a fixed marched terrain and compares where
+//! they come to rest against a stored snapshot, so avian3d/bevy upgrades that
+//! change the simulation show up as a failing test.
+//!
+//! Run with `UPDATE_SNAPSHOT=1` to (re)write the snapshot; a missing or
+//! unreadable one fails the test. For bit-for-bit results across platforms
+//! build with `--features enhanced-determinism`.
+
+use avian3d::prelude::*;
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use marchy_bevy::{create_mesh, VoxelGrid};
+use std::{fs, path::Path, time::Duration};
+
+const TICKS: usize = 600;
+const TOLERANCE: f32 = 0.01;
+const SNAPSHOT: &str = "tests/snapshots/determinism.txt";
+
+fn terrain() -> Mesh {
+    let mut vox = VoxelGrid::new(10);
+    let hsize = vox.width as f32 / 2.0;
+    vox.map(|x, y, z, _val| {
+        let xo = x as f32 - hsize;
+        let yo = y as f32;
+        let zo = z as f32 - hsize;
+        (xo * xo + yo * yo + zo * zo).sqrt()
+    });
+    create_mesh(&vox, 5.0)
+}
+
+fn simulate() -> Vec<Vec3> {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        AssetPlugin::default(),
+        bevy::scene::ScenePlugin,
+        PhysicsPlugins::default(),
+    ))
+    .init_asset::<Mesh>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / 64.0)));
+
+    let mesh = terrain();
+    app.world_mut().spawn((
+        RigidBody::Static,
+        Collider::trimesh_from_mesh(&mesh).unwrap(),
+        Transform::default(),
+    ));
+    app.world_mut().spawn((
+        RigidBody::Static,
+        Collider::cylinder(10.0, 0.1),
+        Transform::from_xyz(0.0, -5.0, 0.0),
+    ));
+
+    let mut balls = vec![];
+    for i in 0..16 {
+        let x = (i % 4) as f32 * 2.0 - 3.0;
+        let z = (i / 4) as f32 * 2.0 - 3.0;
+        let id = app.world_mut().spawn((
+            RigidBody::Dynamic,
+            Collider::sphere(0.5),
+            Restitution::new(0.8)
+                .with_combine_rule(CoefficientCombine::Max),
+            Transform::from_xyz(x, 3.0 + (i % 3) as f32, z),
+        )).id();
+        balls.push(id);
+    }
+
+    for _ in 0..TICKS {
+        app.update();
+    }
+
+    balls
+        .iter()
+        .map(|&e| app.world().get::<Transform>(e).unwrap().translation)
+        .collect()
+}
+
+fn read_snapshot(path: &Path) -> Option<Vec<Vec3>> {
+    let text = fs::read_to_string(path).ok()?;
+    text.lines()
+        .map(|line| {
+            let v: Vec<f32> = line
+                .split_whitespace()
+                .map(|n| n.parse().ok())
+                .collect::<Option<_>>()?;
+            (v.len() == 3).then(|| Vec3::new(v[0], v[1], v[2]))
+        })
+        .collect()
+}
+
+fn write_snapshot(path: &Path, positions: &[Vec3]) {
+    let text: String = positions
+        .iter()
+        .map(|p| format!("{} {} {}\n", p.x, p.y, p.z))
+        .collect();
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+}
+
+#[test]
+fn ball_pile_matches_snapshot() {
+    let first = simulate();
+    let second = simulate();
+    assert_eq!(first, second, "two runs in the same process diverged");
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(SNAPSHOT);
+    if std::env::var_os("UPDATE_SNAPSHOT").is_some() {
+        write_snapshot(&path, &first);
+        return;
+    }
+    let Some(expected) = read_snapshot(&path) else {
+        panic!("no snapshot at {}; run with UPDATE_SNAPSHOT=1 to write it", path.display());
+    };
+
+    assert_eq!(expected.len(), first.len(), "body count changed");
+    for (i, (want, got)) in expected.iter().zip(&first).enumerate() {
+        assert!(
+            want.distance(*got) < TOLERANCE,
+            "ball {i} ended at {got}, snapshot has {want}"
+        );
+    }
+}